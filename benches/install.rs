@@ -0,0 +1,130 @@
+//! Benchmarks for the install hot paths.
+//!
+//! Run with `cargo bench`. To check for regressions against a saved
+//! baseline, see `cargo xtask check-benchmarks`.
+#![allow(missing_docs)] // for the functions generated by criterion_group!()
+
+use std::{path::Path, time::Duration, time::Instant};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use takecrate::{
+    inst::{InstallConfig, PackageFileEntry, PackageManifest},
+    manifest::{AppId, DiskFileEntry, DiskManifest, FileType},
+    os::AccessScope,
+    path::AppPathPrefix,
+};
+
+const LARGE_FILE_SIZE: usize = 16 * 1024 * 1024;
+const MANY_FILES_COUNT: usize = 10_000;
+
+fn bench_checksum(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("large.dat");
+    std::fs::write(&path, vec![0xa5u8; LARGE_FILE_SIZE]).unwrap();
+
+    let mut group = c.benchmark_group("checksum");
+    group.throughput(Throughput::Bytes(LARGE_FILE_SIZE as u64));
+    group.bench_function("file_checksum_16mib", |b| {
+        b.iter(|| takecrate::os::file_checksum(&path).unwrap())
+    });
+    group.finish();
+}
+
+fn make_disk_manifest() -> DiskManifest {
+    let mut manifest = DiskManifest::default();
+    manifest.app_id = AppId::new("io.crates.takecrate-bench").unwrap();
+    manifest.app_name = "Takecrate Bench".to_string();
+
+    for index in 0..MANY_FILES_COUNT {
+        let mut entry = DiskFileEntry::default();
+        entry.path = Path::new("/opt/takecrate-bench/share").join(format!("file_{index:05}.dat"));
+        entry.len = index as u64;
+        entry.crc32c = index as u32;
+        manifest.files.push(entry);
+    }
+
+    manifest
+}
+
+fn bench_manifest(c: &mut Criterion) {
+    let manifest = make_disk_manifest();
+    let mut buf = Vec::new();
+    manifest.to_writer(&mut buf).unwrap();
+
+    let mut group = c.benchmark_group("manifest");
+    group.throughput(Throughput::Elements(MANY_FILES_COUNT as u64));
+    group.bench_function("serialize_10k_files", |b| {
+        b.iter_batched_ref(
+            || Vec::with_capacity(buf.len()),
+            |output| manifest.to_writer(output).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("deserialize_10k_files", |b| {
+        b.iter(|| DiskManifest::from_reader(buf.as_slice()).unwrap())
+    });
+    group.finish();
+}
+
+fn make_package(source_dir: &Path, app_id: &AppId) -> PackageManifest {
+    let mut manifest = PackageManifest::new(app_id);
+
+    std::fs::write(source_dir.join("bench-exe"), b"exe").unwrap();
+    manifest.files.push(
+        PackageFileEntry::new_main_exe("bench-exe", "bench-exe", FileType::Executable).unwrap(),
+    );
+
+    for index in 0..MANY_FILES_COUNT {
+        let name = format!("file_{index:05}.dat");
+        std::fs::write(source_dir.join(&name), index.to_le_bytes()).unwrap();
+        manifest = manifest.with_file_entry(&name, FileType::Data).unwrap();
+    }
+
+    manifest
+}
+
+fn bench_install(c: &mut Criterion) {
+    let state_dir = tempfile::tempdir().unwrap();
+    let source_dir = tempfile::tempdir().unwrap();
+
+    // Keep the disk manifest out of the real user account.
+    std::env::set_var("XDG_CONFIG_HOME", state_dir.path());
+    std::env::set_var("LocalAppData", state_dir.path());
+
+    let app_id = AppId::new("io.crates.takecrate-bench").unwrap();
+    let package = make_package(source_dir.path(), &app_id);
+
+    let mut group = c.benchmark_group("install");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(20));
+    group.throughput(Throughput::Elements(MANY_FILES_COUNT as u64));
+    group.bench_function("plan_and_copy_10k_files", |b| {
+        b.iter_custom(|iterations| {
+            let mut elapsed = Duration::ZERO;
+
+            for _ in 0..iterations {
+                let dest_dir = tempfile::tempdir().unwrap();
+
+                let mut config = InstallConfig::default();
+                config.access_scope = AccessScope::User;
+                config.source_dir = source_dir.path().to_path_buf();
+                config.destination = AppPathPrefix::SingleDir(dest_dir.path().to_path_buf());
+                config.modify_os_search_path = false;
+
+                let start = Instant::now();
+                takecrate::install(&package, &config).unwrap();
+                elapsed += start.elapsed();
+
+                let manifest_path =
+                    takecrate::manifest::manifest_path(&app_id, AccessScope::User).unwrap();
+                std::fs::remove_file(manifest_path).unwrap();
+            }
+
+            elapsed
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_checksum, bench_manifest, bench_install);
+criterion_main!(benches);
@@ -2,10 +2,10 @@
 use std::fs::File;
 
 use clap::{Parser, Subcommand};
-use regex_lite::Regex;
 use takecrate::{
-    inst::{InstallConfig, PackageManifest},
+    inst::{InstallConfig, Installer, PackageManifest},
     manifest::{AppId, FileType},
+    uninst::Uninstaller,
 };
 use tracing::level_filters::LevelFilter;
 
@@ -27,8 +27,11 @@ pub fn main() -> anyhow::Result<()> {
 fn main2() -> anyhow::Result<()> {
     let manifest = create_app_package_manifest()?;
 
+    // Record installs and uninstalls for all users in the Windows Event Log
+    takecrate::os::set_windows_event_log(true);
+
     // First, check if we should behave as an automatic guided installer:
-    if is_installer() {
+    if takecrate::is_installer_invocation() {
         takecrate::install_interactive(&manifest)?;
 
         return Ok(());
@@ -46,8 +49,13 @@ fn main2() -> anyhow::Result<()> {
             println!("test.txt: {}", content);
         }
         Command::Self_(self_args) => match self_args.command {
-            SelfCommand::Install { quiet } => {
-                if quiet {
+            SelfCommand::Install { quiet, dry_run } => {
+                if dry_run {
+                    // Show the changes without installing
+                    let config = InstallConfig::new_user()?;
+                    let preview = Installer::new(&manifest).preview(&config)?;
+                    print!("{}", takecrate::ui::format_install_preview_system(&preview));
+                } else if quiet {
                     // Automatically install to user account by default
                     let config = InstallConfig::new_user()?;
                     takecrate::install(&manifest, &config)?;
@@ -56,13 +64,56 @@ fn main2() -> anyhow::Result<()> {
                     takecrate::install_interactive(&manifest)?;
                 }
             }
-            SelfCommand::Uninstall { quiet } => {
+            SelfCommand::Repair => {
+                // Copy missing or damaged files again from the package
+                let report = takecrate::repair(&manifest)?;
+
+                for item in report.missing.iter().chain(&report.modified) {
+                    println!("Could not repair {:?}", item);
+                }
+            }
+            SelfCommand::Uninstall {
+                quiet,
+                purge,
+                keep_data,
+                backup,
+                trash,
+            } => {
+                let mut uninstaller = Uninstaller::new(&manifest.app_id)
+                    .with_purge(purge)
+                    .with_keep_user_data(keep_data)
+                    .with_backup_user_data(backup)
+                    .with_trash(trash)
+                    .with_feedback_hook(|feedback| {
+                        // Only shown, no network requests are made
+                        let message =
+                            "Sorry to see you go! Let us know why at https://example.com/feedback";
+
+                        if feedback.interactive {
+                            Some(message.to_string())
+                        } else {
+                            println!("{}", message);
+                            None
+                        }
+                    });
+
                 if quiet {
                     // Automatically uninstall
-                    takecrate::uninstall(&manifest.app_id)?;
+                    uninstaller.run()?;
+
+                    if let Some(path) = uninstaller.backup_path() {
+                        println!(
+                            "Saved a backup of settings and data files to {}",
+                            path.display()
+                        );
+                    }
+
+                    for dir in uninstaller.kept_user_data() {
+                        println!("Kept settings and data files in {}", dir.display());
+                    }
                 } else {
                     // Otherwise, prompt the user to confirm
-                    takecrate::uninstall_interactive(&manifest.app_id)?;
+                    uninstaller.run_interactive()?;
                 }
             }
         },
@@ -71,31 +122,6 @@ fn main2() -> anyhow::Result<()> {
     Ok(())
 }
 
-/// Returns whether the executable's file name is suffixed with "installer" and
-/// no command line arguments have been provided by the user.
-fn is_installer() -> bool {
-    // We ignore the first argument because it is usually the executable path
-    // or some other string.
-    // We check if any user supplied arguments are provided after the first
-    // argument.
-    if std::env::args_os().len() > 1 {
-        return false;
-    }
-
-    // Remove file extension, if any, and check if it ends with the
-    // installer prefix (separated by dot, space, underscore, or hyphen).
-    let name = std::env::current_exe().unwrap_or_default();
-    let name = if !std::env::consts::EXE_SUFFIX.is_empty() {
-        name.file_stem().unwrap_or(name.as_os_str())
-    } else {
-        name.as_os_str()
-    };
-    let name = name.to_string_lossy();
-
-    let pattern = Regex::new(r"(?i:[. _-]installer)$").unwrap();
-    pattern.is_match(&name)
-}
-
 /// Initialize logging for debugging
 fn init_logging() -> anyhow::Result<()> {
     let log_filename = format!("takecrate_example_installer_{}.log", whoami::username());
@@ -165,11 +191,28 @@ enum SelfCommand {
         /// Install without prompting the user
         #[arg(long)]
         quiet: bool,
+        /// Show the changes without installing
+        #[arg(long)]
+        dry_run: bool,
     },
+    /// Restores missing or damaged files of the installation
+    Repair,
     /// Uninstaller
     Uninstall {
         /// Uninstall without prompting the user
         #[arg(long)]
         quiet: bool,
+        /// Also remove settings and data files
+        #[arg(long)]
+        purge: bool,
+        /// Keep settings and data files for a later reinstall
+        #[arg(long, conflicts_with = "purge")]
+        keep_data: bool,
+        /// Archive settings and data files to the home directory first
+        #[arg(long)]
+        backup: bool,
+        /// Move removed files to the trash instead of deleting them
+        #[arg(long)]
+        trash: bool,
     },
 }
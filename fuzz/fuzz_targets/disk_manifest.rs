@@ -0,0 +1,17 @@
+//! Fuzz target for parsing disk manifests.
+//!
+//! Run with `cargo +nightly fuzz run disk_manifest`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use takecrate::manifest::DiskManifest;
+
+fuzz_target!(|data: &[u8]| {
+    // The uninstaller loads manifests from disk, so any input must either
+    // parse or return an error without panicking.
+    if let Ok(manifest) = DiskManifest::from_reader(data) {
+        let mut buf = Vec::new();
+        manifest.to_writer(&mut buf).unwrap();
+        DiskManifest::from_reader(buf.as_slice()).unwrap();
+    }
+});
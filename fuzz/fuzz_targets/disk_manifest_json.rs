@@ -0,0 +1,16 @@
+//! Fuzz target for importing disk manifests from JSON.
+//!
+//! Run with `cargo +nightly fuzz run disk_manifest_json`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use takecrate::manifest::DiskManifest;
+
+fuzz_target!(|data: &str| {
+    // Deployment tools may pass any JSON, so it must either import or
+    // return an error without panicking.
+    if let Ok(manifest) = DiskManifest::from_json(data) {
+        let text = manifest.to_json().unwrap();
+        DiskManifest::from_json(&text).unwrap();
+    }
+});
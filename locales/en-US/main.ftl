@@ -3,6 +3,10 @@ powered-by-library = Powered by { -library-name }
 button-ok = OK
 button-exit = ❌ Exit
 button-next = ✅ Next
+button-back = ⬅️ Back
+button-yes = Yes
+button-no = No
+exit-confirm = Are you sure you want to exit?
 
 installer-title = 📦 { $app_name } Installer
 installer-intro =
@@ -10,25 +14,89 @@ installer-intro =
 
     Please close any other installers before continuing.
 
+installer-intro-publisher = Publisher: { $publisher }
+installer-intro-homepage = Website: { $url }
+installer-intro-license = License: { $license }
 installer-confirm = The application will be installed on the next step.
+install-summary-prompt = Review the changes before installing:
+install-size = Install size: { $size }
+install-size-with-data = Install size: { $size } (up to ~{ $estimated_size } with caches)
+byte-unit-b = B
+byte-unit-kb = KB
+byte-unit-mb = MB
+byte-unit-gb = GB
+byte-unit-tb = TB
 installer-conclusion =
     { $app_name } was installed successfully.
 
     For all changes to take effect, you may need to restart your terminal or login session.
 
+install-duration = Completed in { $duration }.
+install-reboot-required = Some files were in use and will be replaced when the computer restarts.
 access-scope-prompt = Install the application to this user account or for all users?
 for-this-user = 👤 For this user only
 for-all-users = 👥 For all users
-
+for-all-users-requires-elevation = Installing for all users requires administrator privileges which will be requested before installing.
+destination-prompt = Choose where to install the application:
+default-destination = Default location: { $path }
+custom-destination = Custom location…
+custom-destination-relative = The location { $path } needs to be a full path.
+custom-destination-non-empty = The folder { $path } is not empty. Choose an empty or new folder.
+custom-destination-unwritable = The folder { $path } cannot be written to.
+
+install-profile-prompt = Choose the type of installation:
+install-profile-minimal = Minimal: only copy the files
+install-profile-developer = Developer: copy the files and modify the search path
+install-profile-full = Full: include everything
+install-profile-custom = Custom: choose each option
 modify-search-path-prompt = Modify the search path ("PATH") so the command is accessible everywhere?
 modify-search-path = Yes, if needed
 do-not-modify-search-path = No, do not change it
 
+shortcuts-prompt = Create shortcuts to the application?
+start-menu-shortcut = Start Menu
+desktop-shortcut = Desktop
+
+scheduled-tasks-prompt = Allow the application to run periodically in the background?
+register-scheduled-tasks = Yes, schedule background tasks
+do-not-register-scheduled-tasks = No, do not schedule them
+
 app-already-installed = The application is already installed.
 
 removing-existing-before-install = An existing installation was found. It will be replaced.
+upgrading-existing = { $app_name } will be upgraded from { $existing_version } to { $app_version }.
+downgrade-existing-warning =
+    A newer version of { $app_name }, { $existing_version }, is already installed. It will be replaced with the older version { $app_version }.
+
+    Settings and data files from the newer version may not work with the older version.
+
+read-only-system-root-prompt =
+    { $location } is on a read-only file system, so { $app_name } cannot be installed for all users. This is normal on immutable systems such as Fedora Silverblue.
+
+    Continue to install for only you instead, or go back to choose again.
+
+wsl-search-path-info =
+    This installer is running in the Windows Subsystem for Linux.
+
+    { $app_name } will be available in Linux shells only. Windows terminals, such as PowerShell and Command Prompt, will not find it. To use it from Windows, install the Windows version instead.
+
+newer-system-install-warning =
+    { $app_name } { $system_version } is already installed for all users.
+
+    Installing this older version for only you will hide the newer version. Go back to install for all users instead, or continue anyway.
+
+interrupted-install-prompt = A previous installation of { $app_name } did not complete.
+resume-interrupted-install = Resume the installation
+roll-back-interrupted-install = Undo the incomplete installation
+interrupted-install-rolled-back = The incomplete installation was undone.
 
 installing = Installing...
+cancelling = Cancelling...
+cancel-install-confirm = Cancel the installation? Files that were already copied will be removed.
+install-progress-detail = Copied { $size } of { $total_size } ({ $count }/{ $total_count } { $total_count ->
+        [one] file
+       *[other] files
+    })
 
 uninstaller-title = 🗑️ { $app_name } Uninstaller
 uninstaller-intro =
@@ -36,7 +104,13 @@ uninstaller-intro =
 
     Please close any other installers, instances of the application, or related application files or folders before continuing.
 
+uninstall-purge = Also remove settings and data files
+uninstall-backup = Back up settings and data files to the home folder
+uninstall-trash = Move removed files to the trash instead of deleting them
+uninstall-backup-written = A backup of your settings and data files was saved to { $path }
 uninstaller-conclusion = { $app_name } was uninstalled successfully.
+uninstall-kept-user-data = Your settings and data files were kept in:
+uninstall-reboot-required = Some files were in use and will be removed when the computer restarts.
 
 uninstalling = Uninstalling...
 
@@ -44,3 +118,15 @@ app-not-installed = The application is not installed.
 
 error-occurred = 🙁 Sorry, an error occurred.
 error-details = Error details:
+
+dry-run-title = Changes to install { $app_name } { $app_version }:
+dry-run-existing-installation = Existing installation to be removed
+dry-run-files = Files ({ $size })
+dry-run-links = Links
+dry-run-shortcuts = Shortcuts
+dry-run-search-path = Search path (PATH)
+dry-run-modified-files = Modified files
+dry-run-registry-keys = Registry keys
+dry-run-env-vars = Environment variables
+dry-run-scheduled-tasks = Scheduled tasks
+dry-run-manifest = Disk manifest
@@ -1,5 +1,6 @@
 button-ok = Aceptar
 button-exit = ❌ Salir
 button-next = ✅ Siguiente
+button-back = ⬅️ Atrás
 
 installer-title = 📦 Instalador { $app_name }
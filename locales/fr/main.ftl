@@ -1,5 +1,12 @@
 button-ok = OK
 button-exit = ❌ Quitter
 button-next = ✅ Suivant
+button-back = ⬅️ Retour
 
 installer-title = 📦 Programme d'installation de { $app_name }
+
+byte-unit-b = o
+byte-unit-kb = Ko
+byte-unit-mb = Mo
+byte-unit-gb = Go
+byte-unit-tb = To
@@ -1,5 +1,12 @@
 button-ok = OK
 button-exit = ❌ Выход
 button-next = ✅ Далее
+button-back = ⬅️ Назад
 
 installer-title = 📦 Установщик { $app_name }
+
+byte-unit-b = Б
+byte-unit-kb = КБ
+byte-unit-mb = МБ
+byte-unit-gb = ГБ
+byte-unit-tb = ТБ
@@ -1,5 +1,6 @@
 button-ok = 确定
 button-exit = ❌ 退出
 button-next = ✅ 下一页
+button-back = ⬅️ 上一页
 
 installer-title = 📦 { $app_name }安装程序
@@ -1,5 +1,6 @@
 button-ok = 確定
 button-exit = ❌ 結束
 button-next = ✅ 下一頁
+button-back = ⬅️ 上一頁
 
 installer-title = 📦 { $app_name }安裝程式
@@ -68,6 +68,15 @@ impl InstallerError {
         }
         None
     }
+
+    /// Returns an iterator over this error and its source errors.
+    ///
+    /// The first item is this error, followed by each source error in order.
+    /// This is intended for rendering structured error messages; the
+    /// [`Display`] output of each item does not include its sources.
+    pub fn chain(&self) -> ErrorChain<'_> {
+        ErrorChain { next: Some(self) }
+    }
 }
 
 impl Display for InstallerError {
@@ -101,6 +110,48 @@ impl From<AdditionalContext> for InstallerError {
     }
 }
 
+/// Iterator over an error and its sources returned by [`InstallerError::chain()`].
+#[derive(Debug, Clone)]
+pub struct ErrorChain<'a> {
+    next: Option<&'a (dyn std::error::Error + 'static)>,
+}
+
+impl<'a> Iterator for ErrorChain<'a> {
+    type Item = ErrorChainItem<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let error = self.next.take()?;
+        self.next = error.source();
+
+        Some(ErrorChainItem { error })
+    }
+}
+
+/// A single level of an error chain.
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorChainItem<'a> {
+    error: &'a (dyn std::error::Error + 'static),
+}
+
+impl<'a> ErrorChainItem<'a> {
+    /// Returns the error kind if the error at this level is an [`InstallerError`].
+    pub fn kind(&self) -> Option<&'a InstallerErrorKind> {
+        self.error
+            .downcast_ref::<InstallerError>()
+            .map(|error| error.kind())
+    }
+
+    /// Returns the message of the error at this level without its sources.
+    pub fn message(&self) -> String {
+        self.error.to_string()
+    }
+
+    /// Returns the error at this level.
+    pub fn error(&self) -> &'a (dyn std::error::Error + 'static) {
+        self.error
+    }
+}
+
 /// Error category for [`InstallerError`].
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
@@ -121,6 +172,49 @@ pub enum InstallerErrorKind {
     #[error("unsupported OS family")]
     UnsupportedOsFamily,
 
+    /// A feature required by the package is not available on this system.
+    ///
+    /// For example, scheduled tasks without a `crontab` program. The error
+    /// context names the feature. See [`OsCapabilities`](crate::os::OsCapabilities).
+    #[error("feature not supported on this operating system")]
+    UnsupportedOsFeature,
+
+    /// The access scope or destination is not supported on this system.
+    ///
+    /// For example, system installs on NixOS.
+    #[error("unsupported access scope")]
+    UnsupportedAccessScope,
+
+    /// A system location is on a read-only file system.
+    ///
+    /// For example, `/usr/local` on immutable systems such as NixOS or
+    /// Fedora Silverblue. Installing for the current user works instead,
+    /// which the interactive installer offers. The error context contains
+    /// the location.
+    #[error("system location is on a read-only file system")]
+    ReadOnlySystemRoot,
+
+    /// A location modified by the installation cannot be written to.
+    ///
+    /// The error context contains the location.
+    #[error("location not writable")]
+    NotWritable,
+
+    /// A file or registry key was repeatedly in use by another process.
+    ///
+    /// On Windows, this is usually caused by antivirus software scanning
+    /// the newly installed files. Trying again later or excluding the
+    /// destination directory from real-time scanning may help.
+    #[error("file or registry key in use by another process, such as antivirus software")]
+    SharingViolation,
+
+    /// The process does not have the privileges needed for the access scope.
+    ///
+    /// For example, system installs when not running as root or an
+    /// administrator.
+    #[error("insufficient privileges")]
+    InsufficientPrivileges,
+
     /// Environment variable was missing or malformed.
     #[error("invalid environment variable")]
     InvalidEnvironmentVariable,
@@ -143,6 +237,22 @@ pub enum InstallerErrorKind {
     #[error("malformed disk manifest")]
     MalformedDiskManifest,
 
+    /// [`DiskManifest`](crate::manifest::DiskManifest) was written by a newer
+    /// version of this crate in a format that cannot be read.
+    ///
+    /// See [`DISK_MANIFEST_VERSION`](crate::manifest::DISK_MANIFEST_VERSION).
+    #[error("unsupported disk manifest version")]
+    UnsupportedDiskManifestVersion,
+
+    /// [`DiskManifest`](crate::manifest::DiskManifest) does not match its
+    /// integrity digest.
+    ///
+    /// It may have been modified by another program or corrupted, so the
+    /// files listed in it are not removed. See
+    /// [`ManifestIntegrity`](crate::manifest::ManifestIntegrity).
+    #[error("disk manifest integrity check failed")]
+    TamperedDiskManifest,
+
     /// [`DiskManifest`](crate::manifest::DiskManifest) has an invalid value.
     #[error("invalid disk manifest")]
     InvalidDiskManifest,
@@ -161,6 +271,13 @@ pub enum InstallerErrorKind {
     #[error("console/terminal error")]
     Terminal,
 
+    /// The terminal cannot display the interactive interface.
+    ///
+    /// For example, `TERM` is `dumb` or there is no terminal attached.
+    /// The non-interactive installer or uninstaller can be used instead.
+    #[error("terminal not supported by the interactive interface")]
+    UnsupportedTerminal,
+
     /// Indicates an installation attempt when the application is (likely) already installed.
     ///
     /// It may return a false positive on a prior failed install/uninstall.
@@ -173,6 +290,35 @@ pub enum InstallerErrorKind {
     #[error("application is not installed")]
     NotInstalled,
 
+    /// The installed universal binary would be replaced by a binary missing
+    /// some of its architectures.
+    ///
+    /// Only applies to macOS. See
+    /// [`InstallConfig::allow_architecture_downgrade`](crate::inst::InstallConfig::allow_architecture_downgrade).
+    #[error("installed universal binary would be replaced by one with fewer architectures")]
+    ArchitectureDowngrade,
+
+    /// The installed version is newer than the version being installed.
+    ///
+    /// See
+    /// [`InstallConfig::allow_downgrade`](crate::inst::InstallConfig::allow_downgrade).
+    #[error("installed version is newer")]
+    Downgrade,
+
+    /// A newer version is installed for all users and installing for the
+    /// current user would shadow it with an older copy.
+    ///
+    /// See
+    /// [`InstallConfig::allow_older_user_install`](crate::inst::InstallConfig::allow_older_user_install).
+    #[error("a newer version is installed for all users")]
+    NewerSystemInstall,
+
+    /// Another installer or uninstaller for the same application is running.
+    ///
+    /// Only one process at a time may modify an installation.
+    #[error("another installer or uninstaller is running")]
+    AlreadyRunning,
+
     /// Indicates a guided interactive session was aborted by the user.
     #[error("interrupted by user")]
     InterruptedByUser,
@@ -285,13 +431,40 @@ where
 {
     let mut buf = error.to_string();
 
-    let mut error: Box<&dyn std::error::Error> = Box::new(&error);
+    let mut error: &dyn std::error::Error = &error;
 
     while let Some(source) = error.source() {
-        error = Box::new(source);
+        error = source;
         buf.push_str("\n↳");
         buf.push_str(&error.to_string());
     }
 
     buf
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain() {
+        let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "no file");
+        let error = InstallerError::from(AdditionalContext::new(
+            "failed to copy".to_string(),
+            InstallerError::from(io_error).with_context("file a"),
+        ))
+        .with_context("installing");
+
+        let items = error.chain().collect::<Vec<_>>();
+
+        assert_eq!(items.len(), 4);
+        assert!(matches!(items[0].kind(), Some(InstallerErrorKind::Other)));
+        assert_eq!(items[0].message(), "installing: other");
+        assert!(items[1].kind().is_none());
+        assert_eq!(items[1].message(), "failed to copy");
+        assert!(matches!(items[2].kind(), Some(InstallerErrorKind::Io)));
+        assert_eq!(items[2].message(), "file a: input/output error");
+        assert!(items[3].kind().is_none());
+        assert_eq!(items[3].message(), "no file");
+    }
+}
@@ -1,12 +1,124 @@
 //! Internationalization and localization (globalization) support.
 
+#[cfg(feature = "i18n-custom")]
+use std::path::Path;
+
 #[cfg(feature = "i18n-custom")]
 pub use fluent_bundle;
 #[cfg(feature = "i18n-custom")]
 pub use fluent_templates;
 
+#[cfg(feature = "i18n-custom")]
+use crate::error::{InstallerError, InstallerErrorKind};
+
+/// Language used when a translation is missing from the selected language.
+#[cfg(feature = "i18n-custom")]
+pub const FALLBACK_LANGUAGE: &str = "en-US";
+
+/// Sets the languages tried in order when a translation is missing from
+/// the requested language.
+///
+/// Values are BCP 47 language tags, such as `["pt", "es"]` for an
+/// installer run in `pt-BR`. Regional variants of the requested language,
+/// such as `pt` for `pt-BR`, are tried before the fallback languages, and
+/// `en-US` is always tried last.
+///
+/// Only applies to installers and uninstallers created afterwards.
+pub fn set_fallback_languages(lang_tags: &[&str]) {
+    crate::locale::set_fallback_languages(lang_tags);
+}
+
+/// Returns the BCP 47 language tag of the translations used for the given
+/// language tag.
+///
+/// An empty tag is the system's language. The result may be a fallback
+/// language, such as `fr` for `fr-CA`, or `en-US` for a language without
+/// translations.
+pub fn selected_language(lang_tag: &str) -> String {
+    let locale = if lang_tag.is_empty() {
+        crate::locale::Locale::with_system()
+    } else {
+        crate::locale::Locale::with_language_tag(lang_tag)
+    };
+
+    locale.selected_language_tag()
+}
+
 #[cfg(feature = "i18n-custom")]
 /// Sets a global language translation loader.
 pub fn set_custom_loader(loader: fluent_templates::ArcLoader) {
     crate::locale::set_custom_loader(loader);
 }
+
+#[cfg(feature = "i18n-custom")]
+/// Loads translation files from a directory and sets it as the global
+/// language translation loader.
+///
+/// The directory has the same layout as the `locales` directory of this
+/// crate: a subdirectory of `.ftl` files for each language tag, such as
+/// `fr/main.ftl`, and an optional `core.ftl` shared by all languages.
+/// The directory should contain translations for all the text used by
+/// this crate, not only the ones that were changed.
+///
+/// A relative path is resolved against the directory of the current
+/// executable, so translations can be shipped next to the binary.
+/// Text missing from a language is looked up in [`FALLBACK_LANGUAGE`].
+pub fn load_locales_dir<P: AsRef<Path>>(path: P) -> Result<(), InstallerError> {
+    let path = path.as_ref();
+    let path = if path.is_relative() {
+        crate::os::current_exe_dir()?.join(path)
+    } else {
+        path.to_path_buf()
+    };
+
+    let loader = build_locales_dir_loader(&path)?;
+    set_custom_loader(loader);
+
+    Ok(())
+}
+
+#[cfg(feature = "i18n-custom")]
+fn build_locales_dir_loader(path: &Path) -> Result<fluent_templates::ArcLoader, InstallerError> {
+    tracing::debug!(?path, "loading locales directory");
+
+    if !path.is_dir() {
+        return Err(InstallerError::new(InstallerErrorKind::InvalidInput)
+            .with_context(format!("locales directory {:?} not found", path)));
+    }
+
+    let core_path = path.join("core.ftl");
+    let shared = [core_path.clone()];
+    let fallback = fluent_templates::langid!("en-US");
+
+    fluent_templates::ArcLoader::builder(path, fallback)
+        .shared_resources(core_path.is_file().then_some(shared.as_slice()))
+        .build()
+        .map_err(|error| {
+            InstallerError::new(InstallerErrorKind::InvalidData).with_context(format!(
+                "could not load locales directory {:?}: {}",
+                path, error
+            ))
+        })
+}
+
+#[cfg(all(test, feature = "i18n-custom"))]
+mod tests {
+    use fluent_templates::Loader;
+
+    use super::*;
+
+    #[test]
+    fn test_build_locales_dir_loader() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("locales");
+        let loader = build_locales_dir_loader(&path).unwrap();
+
+        assert_eq!(
+            loader.lookup(&fluent_templates::langid!("fr"), "button-ok"),
+            "OK"
+        );
+        assert!(loader
+            .try_lookup(&fluent_templates::langid!("fr-CA"), "button-next")
+            .is_some());
+        assert!(build_locales_dir_loader(&path.join("nonexistent")).is_err());
+    }
+}
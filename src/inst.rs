@@ -1,22 +1,46 @@
 //! Installer functionality.
 use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::Path;
 use std::rc::Rc;
 
+#[cfg(feature = "ui")]
+use choices::SavedChoices;
+#[cfg(feature = "ui")]
+pub(crate) use exec::CopyProgress;
 use exec::Executor;
 use plan::{InstallPlan, Planner};
+use repair::Repairer;
 
 use crate::error::{InstallerError, InstallerErrorKind};
+use crate::manifest::{DiskManifest, FileType, JournalOperation, ManifestIntegrity};
 use crate::os::AccessScope;
 #[cfg(feature = "ui")]
 use crate::tui::Tui;
+use crate::verify::VerifyReport;
 
 pub use self::config::*;
+pub use self::invocation::*;
 pub use self::package::*;
+pub use self::page::*;
+pub use self::preview::*;
+pub use self::report::*;
 
+#[cfg(feature = "ui")]
+mod choices;
 mod config;
+#[cfg(feature = "ui")]
+mod elevate;
 mod exec;
+mod invocation;
 mod package;
+mod page;
 mod plan;
+mod preflight;
+mod preview;
+mod repair;
+mod report;
+mod stage;
 
 /// The installer interface.
 #[derive(Debug)]
@@ -25,7 +49,22 @@ pub struct Installer {
     #[cfg(feature = "ui")]
     tui: Rc<RefCell<Tui>>,
     lang_tag: String,
+    #[cfg(feature = "ui")]
+    remember_choices: bool,
+    #[cfg(feature = "ui")]
+    install_profiles: Vec<InstallProfile>,
+    custom_pages: Vec<CustomPage>,
+    custom_values: BTreeMap<String, String>,
+    #[cfg(feature = "ui")]
+    custom_destination: bool,
+    #[cfg(feature = "ui")]
+    omit_installer_identity: bool,
+    allow_architecture_downgrade: bool,
+    #[cfg(feature = "ui")]
+    progress_weight: ProgressWeight,
     plan: Option<InstallPlan>,
+    report: Option<InstallReport>,
+    reboot_required: bool,
 }
 
 impl Installer {
@@ -36,7 +75,22 @@ impl Installer {
             #[cfg(feature = "ui")]
             tui: Rc::new(RefCell::new(Tui::new())),
             lang_tag: String::new(),
+            #[cfg(feature = "ui")]
+            remember_choices: true,
+            #[cfg(feature = "ui")]
+            install_profiles: Vec::new(),
+            custom_pages: Vec::new(),
+            custom_values: BTreeMap::new(),
+            #[cfg(feature = "ui")]
+            custom_destination: false,
+            #[cfg(feature = "ui")]
+            omit_installer_identity: false,
+            allow_architecture_downgrade: false,
+            #[cfg(feature = "ui")]
+            progress_weight: ProgressWeight::default(),
             plan: None,
+            report: None,
+            reboot_required: false,
         }
     }
 
@@ -48,6 +102,100 @@ impl Installer {
         self
     }
 
+    /// Sets whether the answers in the UI are saved and pre-selected the
+    /// next time the installer runs.
+    ///
+    /// The answers are saved in a small file in the user's configuration
+    /// directory which is not removed by the uninstaller.
+    ///
+    /// Default is `true`.
+    #[cfg(feature = "ui")]
+    pub fn with_remember_choices(mut self, value: bool) -> Self {
+        self.remember_choices = value;
+        self
+    }
+
+    /// Sets the install profiles offered in the UI.
+    ///
+    /// The user chooses one of the profiles or to choose each option
+    /// individually. The first profile is selected by default.
+    ///
+    /// Default is empty which does not show the choice.
+    #[cfg(feature = "ui")]
+    pub fn with_install_profiles(mut self, profiles: &[InstallProfile]) -> Self {
+        self.install_profiles = profiles.to_vec();
+        self
+    }
+
+    /// Sets whether the UI offers installing to a directory chosen by the
+    /// user instead of the default location.
+    ///
+    /// The directory needs to be empty or not exist. It is installed as
+    /// [`AppPathPrefix::SingleDir`](crate::path::AppPathPrefix::SingleDir).
+    ///
+    /// Default is `false`.
+    #[cfg(feature = "ui")]
+    pub fn with_custom_destination(mut self, value: bool) -> Self {
+        self.custom_destination = value;
+        self
+    }
+
+    /// Sets the application's pages shown in the UI after the built-in
+    /// options.
+    ///
+    /// When installing non-interactively, the answers are taken from
+    /// [`InstallConfig::custom_values`] or the pages' default values.
+    /// See [`Self::custom_values()`] to read the answers.
+    ///
+    /// Default is empty.
+    pub fn with_custom_pages(mut self, pages: &[CustomPage]) -> Self {
+        self.custom_pages = pages.to_vec();
+        self
+    }
+
+    /// Returns the answers to the custom pages of the last installation.
+    ///
+    /// The answers are deserialized into a struct with a `String` field
+    /// named after each page ID. Returns an error if there was no
+    /// installation or a field is missing.
+    pub fn custom_values<T: serde::de::DeserializeOwned>(&self) -> Result<T, InstallerError> {
+        page::deserialize_values(&self.custom_values)
+    }
+
+    /// Sets whether to leave out who performed the installation from the
+    /// disk manifest when the user chooses a system install.
+    ///
+    /// See [`InstallConfig::omit_installer_identity`].
+    ///
+    /// Default is `false`.
+    #[cfg(feature = "ui")]
+    pub fn with_omit_installer_identity(mut self, value: bool) -> Self {
+        self.omit_installer_identity = value;
+        self
+    }
+
+    /// Sets whether an installed universal binary may be replaced with a
+    /// binary missing some of its architectures.
+    ///
+    /// This applies to the interactive installer and to
+    /// [`Self::check_replacement_binary()`].
+    /// See [`InstallConfig::allow_architecture_downgrade`].
+    ///
+    /// Default is `false`.
+    pub fn with_allow_architecture_downgrade(mut self, value: bool) -> Self {
+        self.allow_architecture_downgrade = value;
+        self
+    }
+
+    /// Sets how the progress bar of the interactive installer is computed.
+    ///
+    /// See [`InstallConfig::progress_weight`].
+    #[cfg(feature = "ui")]
+    pub fn with_progress_weight(mut self, value: ProgressWeight) -> Self {
+        self.progress_weight = value;
+        self
+    }
+
     /// Sets the theme for the UI.
     #[cfg(feature = "ui-theme")]
     pub fn with_theme(self, value: cursive::theme::Theme) -> Self {
@@ -65,6 +213,17 @@ impl Installer {
     }
 
     /// Install with a TUI.
+    ///
+    /// If the user chooses to install for all users but the process is not
+    /// running as root or an administrator, the binary is run again with
+    /// the same arguments using `sudo` (or `pkexec` if unavailable) on Unix
+    /// or a UAC prompt on Windows. The elevated installer continues with the
+    /// user's answers and this function returns once it completes.
+    ///
+    /// On Windows, the elevated installer is given an additional
+    /// `--takecrate-elevated-choices=` argument, which the application's
+    /// command line parser should ignore.
+    /// [`InstallerNameMatcher::is_invocation()`] ignores it.
     #[cfg(feature = "ui")]
     pub fn run_interactive(&mut self) -> Result<(), InstallerError> {
         self.tui.borrow_mut().set_name(
@@ -73,10 +232,21 @@ impl Installer {
                 .get_display_name(&self.detect_lang_tag()),
             &self.package_manifest.app_metadata.display_version,
         );
-        self.tui.borrow_mut().run_background();
+        self.tui.borrow_mut().set_details(crate::tui::AppDetails {
+            publisher: self.package_manifest.app_metadata.publisher.clone(),
+            description: self.package_manifest.app_metadata.description.clone(),
+            homepage_url: self.package_manifest.app_metadata.homepage_url.clone(),
+            license: self.package_manifest.app_metadata.license.clone(),
+        });
+        self.tui.borrow_mut().run_background()?;
 
         let result = self.run_interactive_impl();
 
+        // The TUI is already stopped if the installer was run again elevated.
+        if !self.tui.borrow().is_running() {
+            return result;
+        }
+
         if let Err(error) = &result {
             match error.kind() {
                 InstallerErrorKind::AlreadyInstalled => {
@@ -94,6 +264,15 @@ impl Installer {
         result
     }
 
+    /// Returns the BCP 47 language tag of the translations used in the UI.
+    ///
+    /// This may be a fallback of the requested language.
+    /// See [`crate::i18n::selected_language()`].
+    #[cfg(feature = "i18n")]
+    pub fn selected_lang_tag(&self) -> String {
+        crate::i18n::selected_language(&self.lang_tag)
+    }
+
     fn detect_lang_tag(&self) -> String {
         if !self.lang_tag.is_empty() {
             return self.lang_tag.clone();
@@ -113,44 +292,130 @@ impl Installer {
     fn run_interactive_impl(&mut self) -> Result<(), InstallerError> {
         use std::time::Duration;
 
-        let mut config = InstallConfig {
-            source_dir: crate::os::current_exe_dir()?,
-            ..Default::default()
-        };
+        let source_dir = crate::os::current_exe_dir()?;
 
         {
             let tui = self.tui.borrow_mut();
 
             tui.set_up_background_text(false)?;
 
-            self.package_manifest.verify(&config.source_dir)?;
-            tui.installation_intro()?.unwrap_button()?;
-            config.access_scope = tui.prompt_access_scope()?.unwrap_button()?;
-            config.destination = config.access_scope.into();
+            self.package_manifest.verify(&source_dir)?;
+        }
 
-            // Modifying system search path on Unix not supported and likely
-            // not necessary.
-            if cfg!(windows) || config.access_scope == AccessScope::User {
-                config.modify_os_search_path = tui.prompt_modify_search_path()?.unwrap_button()?;
+        if let Some(operation) =
+            crate::manifest::interrupted_operation(&self.package_manifest.app_id)?
+        {
+            if operation.operation == JournalOperation::Install
+                && !self
+                    .tui
+                    .borrow()
+                    .prompt_resume_interrupted()?
+                    .unwrap_button()?
+            {
+                return self.roll_back_interrupted_interactive(&operation.manifest);
             }
         }
 
-        self.run_planner(&config)?;
-        let uninstall_required = self.plan.as_ref().unwrap().manifest_path.exists();
+        // Answers are kept so they are shown again when the user goes back.
+        let mut config = InstallConfig {
+            source_dir,
+            modify_os_search_path: true,
+            start_menu_shortcut: true,
+            register_scheduled_tasks: true,
+            omit_installer_identity: self.omit_installer_identity,
+            allow_architecture_downgrade: self.allow_architecture_downgrade,
+            // Shown as warnings instead
+            allow_downgrade: true,
+            allow_older_user_install: true,
+            progress_weight: self.progress_weight,
+            ..Default::default()
+        };
 
-        {
-            let tui = self.tui.borrow_mut();
+        let saved_choices = if self.remember_choices {
+            SavedChoices::load(&self.package_manifest.app_id)
+        } else {
+            None
+        };
 
-            if uninstall_required {
-                tui.prompt_uninstall_existing()?.unwrap_button()?;
+        match saved_choices {
+            Some(choices) => choices.apply(&mut config),
+            None => {
+                if let Some(profile) = self.install_profiles.first() {
+                    config.apply_profile(*profile);
+                }
             }
+        }
+
+        page::apply_default_values(&self.custom_pages, &mut config.custom_values);
+
+        #[cfg(unix)]
+        if crate::os::unix::is_nixos() {
+            config.access_scope = AccessScope::User;
+            config.destination = AccessScope::User.into();
+        }
+
+        let mut step_index = 0;
 
-            tui.prompt_install_confirm()?.unwrap_button()?;
+        if let Some(choices) = elevate::take_choices(&self.package_manifest.app_id) {
+            choices.apply(&mut config);
+            step_index = InteractiveStep::ALL.len() - 1;
         }
 
-        self.run_uninstaller_interactive()?;
+        let (uninstall_required, mut timer) = loop {
+            let step = InteractiveStep::ALL[step_index];
+
+            if !self.is_interactive_step_applicable(step, &config) {
+                step_index += 1;
+                continue;
+            }
+
+            let next = match step {
+                InteractiveStep::Confirm => {
+                    if self.prompt_read_only_system_root(&mut config)? {
+                        if config.access_scope == AccessScope::System
+                            && !crate::os::has_system_access()
+                        {
+                            return self.run_interactive_elevated(&config);
+                        }
+
+                        // Only the plan that is confirmed is included in the report
+                        let mut timer = StepTimer::default();
+                        timer.time(InstallStep::Plan, || self.run_planner(&config))?;
+                        let uninstall_required =
+                            self.plan.as_ref().unwrap().existing_manifest_path.is_some();
+
+                        if self.prompt_install_confirm(uninstall_required)? {
+                            break (uninstall_required, timer);
+                        }
+                    }
+
+                    false
+                }
+                _ => self.prompt_interactive_step(step, &mut config)?,
+            };
+
+            if next {
+                step_index += 1;
+            } else {
+                // The introduction has no Back button, so this won't underflow
+                step_index -= 1;
+
+                while !self
+                    .is_interactive_step_applicable(InteractiveStep::ALL[step_index], &config)
+                {
+                    step_index -= 1;
+                }
+            }
+        };
+
+        self.run_preflight(&mut timer)?;
+        let _staged_dir = self.run_staging(&mut timer)?;
+        let _lock = self.acquire_lock()?;
+        self.recover_interrupted()?;
+        self.run_uninstaller_interactive(&mut timer)?;
 
         self.tui.borrow_mut().show_install_progress_dialog()?;
+        crate::os::interrupt::begin_interruptible();
 
         if uninstall_required {
             // Because it happens so fast, the user might be confused if they
@@ -160,7 +425,32 @@ impl Installer {
             std::thread::sleep(Duration::from_millis(500));
         }
 
-        self.run_executor()?;
+        let result = self.run_executor(&mut timer);
+
+        if result
+            .as_ref()
+            .is_err_and(|error| matches!(error.kind(), InstallerErrorKind::InterruptedByUser))
+        {
+            self.roll_back_cancelled();
+        }
+
+        crate::os::interrupt::end_interruptible();
+        result?;
+
+        let mut report = timer.into_report();
+        report.reboot_required = self.reboot_required;
+        report.wsl = self.plan.as_ref().is_some_and(|plan| plan.wsl);
+        let duration = report.duration;
+        self.report = Some(report);
+
+        if self.remember_choices {
+            if let Err(error) = self
+                .remembered_choices(&config)
+                .save(&self.package_manifest.app_id)
+            {
+                tracing::warn!(?error, "could not save choices");
+            }
+        }
 
         // As described above, pause briefly so the user can see we did something.
         std::thread::sleep(Duration::from_millis(500));
@@ -168,71 +458,849 @@ impl Installer {
         let tui = self.tui.borrow_mut();
 
         tui.hide_install_progress_dialog()?;
-        tui.installation_conclusion()?;
+        tui.installation_conclusion(duration, self.reboot_required)?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "ui")]
+    fn roll_back_interrupted_interactive(
+        &mut self,
+        manifest: &crate::manifest::DiskManifest,
+    ) -> Result<(), InstallerError> {
+        tracing::info!(manifest_path = ?manifest.manifest_path, "rolling back interrupted installation");
+
+        let mut uninstaller = crate::uninst::Uninstaller::new(&manifest.app_id)
+            .with_manifest(manifest)
+            .with_tui(self.tui.clone());
+
+        uninstaller.run_from_installer_interactive()?;
+        self.tui.borrow().show_interrupted_rolled_back()
+    }
+
+    /// Removes the files that were copied before the user cancelled or
+    /// closed the terminal.
+    ///
+    /// This is best effort and doesn't use the TUI since the terminal may be
+    /// gone. Anything left over is recovered from the journal the next time.
+    #[cfg(feature = "ui")]
+    fn roll_back_cancelled(&mut self) {
+        let _ = self.tui.borrow_mut().hide_install_progress_dialog();
+
+        tracing::info!("rolling back cancelled installation");
+
+        if let Err(error) = self.roll_back_cancelled_impl() {
+            tracing::warn!(?error, "could not roll back cancelled installation");
+        }
+    }
+
+    #[cfg(feature = "ui")]
+    fn roll_back_cancelled_impl(&mut self) -> Result<(), InstallerError> {
+        let app_id = &self.package_manifest.app_id;
+        let plan = self.plan.as_ref().unwrap();
+        let Some(operation) = crate::manifest::stale_journal(&plan.manifest_path, app_id)? else {
+            return Ok(());
+        };
+
+        let mut uninstaller = crate::uninst::Uninstaller::new(app_id)
+            .with_manifest(&operation.manifest)
+            .with_app_lock(false);
+        uninstaller.run()?;
+        self.reboot_required |= uninstaller.reboot_required();
+
+        Ok(())
+    }
+
+    /// Runs the installer again with privileges needed for a system install.
+    ///
+    /// The TUI is stopped so the elevated process can use the terminal.
+    #[cfg(feature = "ui")]
+    fn run_interactive_elevated(&mut self, config: &InstallConfig) -> Result<(), InstallerError> {
+        let choices = SavedChoices::from_config(config);
+
+        self.tui.borrow_mut().stop()?;
+
+        elevate::run_elevated(&self.package_manifest.app_id, &choices)?;
+        self.custom_values = config.custom_values.clone();
+
+        if self.remember_choices {
+            if let Err(error) = self
+                .remembered_choices(config)
+                .save(&self.package_manifest.app_id)
+            {
+                tracing::warn!(?error, "could not save choices");
+            }
+        }
 
         Ok(())
     }
 
+    /// Returns the choices saved for the next run, leaving out the answers
+    /// to the custom pages that are not persisted.
+    #[cfg(feature = "ui")]
+    fn remembered_choices(&self, config: &InstallConfig) -> SavedChoices {
+        let mut choices = SavedChoices::from_config(config);
+        choices.custom_values = page::persisted_values(&self.custom_pages, &choices.custom_values);
+        choices
+    }
+
+    #[cfg(feature = "ui")]
+    fn is_interactive_step_applicable(
+        &self,
+        step: InteractiveStep,
+        config: &InstallConfig,
+    ) -> bool {
+        match step {
+            InteractiveStep::Intro | InteractiveStep::Confirm => true,
+            // Only user installs are possible on NixOS
+            InteractiveStep::AccessScope => {
+                #[cfg(unix)]
+                {
+                    !crate::os::unix::is_nixos()
+                }
+                #[cfg(not(unix))]
+                {
+                    true
+                }
+            }
+            InteractiveStep::Profile => !self.install_profiles.is_empty(),
+            // Modifying system search path on Unix (except macOS) not
+            // supported and likely not necessary.
+            InteractiveStep::SearchPath => {
+                config.profile.is_none()
+                    && (cfg!(any(windows, target_os = "macos"))
+                        || config.access_scope == AccessScope::User)
+            }
+            InteractiveStep::Shortcuts => config.profile.is_none() && cfg!(windows),
+            InteractiveStep::ScheduledTasks => {
+                config.profile.is_none() && !self.package_manifest.scheduled_tasks.is_empty()
+            }
+            InteractiveStep::Destination => self.custom_destination,
+            InteractiveStep::CustomPages => !self.custom_pages.is_empty(),
+        }
+    }
+
+    /// Shows the prompt for the step and returns `false` if the user went back.
+    #[cfg(feature = "ui")]
+    fn prompt_interactive_step(
+        &self,
+        step: InteractiveStep,
+        config: &mut InstallConfig,
+    ) -> Result<bool, InstallerError> {
+        let tui = self.tui.borrow();
+
+        match step {
+            InteractiveStep::Intro => {
+                tui.installation_intro()?.unwrap_button()?;
+            }
+            InteractiveStep::AccessScope => {
+                let Some(value) = tui
+                    .prompt_access_scope(config.access_scope, crate::os::has_system_access())?
+                    .unwrap_step()?
+                else {
+                    return Ok(false);
+                };
+                if value != config.access_scope {
+                    config.access_scope = value;
+                    config.destination = value.into();
+                }
+            }
+            InteractiveStep::Profile => {
+                let Some(value) = tui
+                    .prompt_install_profile(&self.install_profiles, config.profile)?
+                    .unwrap_step()?
+                else {
+                    return Ok(false);
+                };
+                match value {
+                    Some(profile) => config.apply_profile(profile),
+                    None => config.profile = None,
+                }
+            }
+            InteractiveStep::SearchPath => {
+                let Some(value) = tui
+                    .prompt_modify_search_path(config.modify_os_search_path)?
+                    .unwrap_step()?
+                else {
+                    return Ok(false);
+                };
+                config.modify_os_search_path = value;
+            }
+            InteractiveStep::Shortcuts => {
+                let current = (config.start_menu_shortcut, config.desktop_shortcut);
+                let Some(value) = tui.prompt_shortcuts(current)?.unwrap_step()? else {
+                    return Ok(false);
+                };
+                (config.start_menu_shortcut, config.desktop_shortcut) = value;
+            }
+            InteractiveStep::ScheduledTasks => {
+                let Some(value) = tui
+                    .prompt_scheduled_tasks(config.register_scheduled_tasks)?
+                    .unwrap_step()?
+                else {
+                    return Ok(false);
+                };
+                config.register_scheduled_tasks = value;
+            }
+            InteractiveStep::Destination => return self.prompt_destination(config),
+            InteractiveStep::CustomPages => return self.prompt_custom_pages(config),
+            InteractiveStep::Confirm => unreachable!(),
+        }
+
+        Ok(true)
+    }
+
+    /// Shows the destination choice until a valid directory is entered and
+    /// returns `false` if the user went back.
+    #[cfg(feature = "ui")]
+    fn prompt_destination(&self, config: &mut InstallConfig) -> Result<bool, InstallerError> {
+        use crate::path::{AppPathPrefix, PathResolver};
+
+        let tui = self.tui.borrow();
+        let app_id = &self.package_manifest.app_id;
+        let default_dir =
+            PathResolver::new(app_id.plain_id(), &config.access_scope.into())?.data_dir();
+        let existing_dir = crate::manifest::discover_manifest_in_scope(app_id, config.access_scope)
+            .ok()
+            .and_then(|manifest| match manifest.app_paths.prefix {
+                AppPathPrefix::SingleDir(dir) => Some(dir),
+                _ => None,
+            });
+        let mut current = match &config.destination {
+            AppPathPrefix::SingleDir(dir) => Some(dir.clone()),
+            _ => None,
+        };
+
+        loop {
+            let Some(value) = tui
+                .prompt_destination(&default_dir, current.as_deref())?
+                .unwrap_step()?
+            else {
+                return Ok(false);
+            };
+
+            let Some(dir) = value else {
+                config.destination = config.access_scope.into();
+                return Ok(true);
+            };
+
+            let allow_existing = existing_dir.as_ref() == Some(&dir);
+
+            match preflight::check_custom_destination(&dir, allow_existing) {
+                Ok(()) => {
+                    config.destination = AppPathPrefix::SingleDir(dir);
+                    return Ok(true);
+                }
+                Err(problem) => {
+                    tracing::info!(?dir, ?problem, "invalid custom destination");
+                    tui.show_invalid_destination(&dir, problem.text_id())?;
+                    current = Some(dir);
+                }
+            }
+        }
+    }
+
+    /// Shows the custom pages in order and returns `false` if the user went
+    /// back from the first page.
+    #[cfg(feature = "ui")]
+    fn prompt_custom_pages(&self, config: &mut InstallConfig) -> Result<bool, InstallerError> {
+        let tui = self.tui.borrow();
+        let mut index = 0;
+
+        while let Some(page) = self.custom_pages.get(index) {
+            let current = config
+                .custom_values
+                .get(&page.id)
+                .cloned()
+                .unwrap_or_default();
+
+            match tui.prompt_custom_page(page, &current)?.unwrap_step()? {
+                Some(value) => {
+                    config.custom_values.insert(page.id.clone(), value);
+                    index += 1;
+                }
+                None if index == 0 => return Ok(false),
+                None => index -= 1,
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Offers to install for the current user if the system locations are
+    /// read-only.
+    ///
+    /// Returns `false` if the user went back.
+    #[cfg(feature = "ui")]
+    fn prompt_read_only_system_root(
+        &self,
+        config: &mut InstallConfig,
+    ) -> Result<bool, InstallerError> {
+        let Some(location) = preflight::read_only_system_location(&self.package_manifest, config)?
+        else {
+            return Ok(true);
+        };
+
+        tracing::warn!(?location, "system location is read-only");
+
+        if self
+            .tui
+            .borrow()
+            .prompt_read_only_system_root(&location)?
+            .unwrap_step()?
+            .is_none()
+        {
+            return Ok(false);
+        }
+
+        config.access_scope = AccessScope::User;
+        config.destination = AccessScope::User.into();
+
+        Ok(true)
+    }
+
+    /// Shows the confirmation prompts and returns `false` if the user went back.
+    #[cfg(feature = "ui")]
+    fn prompt_install_confirm(&self, uninstall_required: bool) -> Result<bool, InstallerError> {
+        let tui = self.tui.borrow();
+        let plan = self.plan.as_ref().unwrap();
+
+        if let Some(version) = &plan.newer_system_version {
+            if tui
+                .prompt_newer_system_install(version)?
+                .unwrap_step()?
+                .is_none()
+            {
+                return Ok(false);
+            }
+        }
+
+        if plan.wsl
+            && (plan.search_path.is_some() || !plan.env_vars.is_empty())
+            && tui.show_wsl_search_path_info()?.unwrap_step()?.is_none()
+        {
+            return Ok(false);
+        }
+
+        if uninstall_required
+            && tui
+                .prompt_uninstall_existing(plan.existing_version.as_deref(), plan.downgrade)?
+                .unwrap_step()?
+                .is_none()
+        {
+            return Ok(false);
+        }
+
+        if tui
+            .show_install_summary(&InstallPreview::from_plan(plan))?
+            .unwrap_step()?
+            .is_none()
+        {
+            return Ok(false);
+        }
+
+        Ok(tui
+            .prompt_install_confirm(plan.total_file_size(), plan.estimated_size())?
+            .unwrap_step()?
+            .is_some())
+    }
+
+    /// Returns the timings of the last successful installation.
+    ///
+    /// Returns `None` if the installation was done by an elevated installer
+    /// process.
+    pub fn report(&self) -> Option<&InstallReport> {
+        self.report.as_ref()
+    }
+
     /// Install automatically.
+    ///
+    /// Returns [`InstallerErrorKind::InsufficientPrivileges`] if the access
+    /// scope is system but the process is not running as root or an
+    /// administrator. Returns [`InstallerErrorKind::AlreadyRunning`] if
+    /// another installer or uninstaller for the application is running.
     pub fn run(&mut self, config: &InstallConfig) -> Result<(), InstallerError> {
+        let mut timer = StepTimer::default();
+
+        if config.access_scope == AccessScope::System && !crate::os::has_system_access() {
+            return Err(
+                InstallerError::new(InstallerErrorKind::InsufficientPrivileges).with_context(
+                    "installing for all users requires running as root or an administrator",
+                ),
+            );
+        }
+
+        self.package_manifest.verify(&config.source_dir)?;
+        timer.time(InstallStep::Plan, || self.run_planner(config))?;
+        self.run_preflight(&mut timer)?;
+        let _staged_dir = self.run_staging(&mut timer)?;
+        let _lock = self.acquire_lock()?;
+        self.recover_interrupted()?;
+        self.run_uninstaller(&mut timer)?;
+        self.run_executor(&mut timer)?;
+
+        let mut report = timer.into_report();
+        report.reboot_required = self.reboot_required;
+        report.wsl = self.plan.as_ref().is_some_and(|plan| plan.wsl);
+        self.report = Some(report);
+
+        Ok(())
+    }
+
+    /// Returns the changes that [`Self::run()`] would make without
+    /// modifying anything.
+    ///
+    /// This is intended for a "dry run" option. To display the changes,
+    /// see [`crate::ui::format_install_preview()`].
+    pub fn preview(&mut self, config: &InstallConfig) -> Result<InstallPreview, InstallerError> {
         self.package_manifest.verify(&config.source_dir)?;
         self.run_planner(config)?;
-        self.run_uninstaller()?;
-        self.run_executor()?;
+
+        Ok(InstallPreview::from_plan(self.plan.as_ref().unwrap()))
+    }
+
+    /// Updates the details of the existing installation shown by the OS
+    /// without reinstalling.
+    ///
+    /// This is intended to be called after the binary updated itself in
+    /// place. The name and version are taken from the package manifest and
+    /// the sizes and checksums of the installed binaries are computed again.
+    /// Other files keep their recorded checksums so files modified by the
+    /// user are still kept when uninstalling. The disk manifest and receipt
+    /// are updated to match.
+    ///
+    /// On Windows, the entry in the OS's list of installed applications is
+    /// also updated.
+    ///
+    /// If the disk manifest was modified outside of this crate, the error
+    /// kind [`InstallerErrorKind::TamperedDiskManifest`] is returned.
+    pub fn refresh_os_metadata(&self) -> Result<(), InstallerError> {
+        use crate::error::AddContext;
+
+        let app_id = &self.package_manifest.app_id;
+        let exe_path = crate::os::current_exe()?;
+        let mut disk_manifest = crate::manifest::discover_manifest(&exe_path, app_id)?;
+        let app_metadata = &self.package_manifest.app_metadata;
+
+        Self::verify_manifest_integrity(&disk_manifest)?;
+
+        tracing::info!(manifest_path = ?disk_manifest.manifest_path, "refreshing OS metadata");
+
+        let _lock = crate::os::AppLock::acquire(
+            app_id,
+            disk_manifest
+                .manifest_path
+                .parent()
+                .unwrap_or(Path::new("")),
+        )?;
+
+        disk_manifest.app_name = app_metadata.display_name.clone();
+        disk_manifest.app_version = app_metadata.display_version.clone();
+        disk_manifest.version = app_metadata.comparable_version();
+
+        // The receipt is one of the installed files, so it is updated first.
+        let receipt_path =
+            crate::manifest::receipt_path_beside(&disk_manifest.manifest_path, app_id);
+
+        if receipt_path.exists() {
+            let mut receipt = crate::manifest::Receipt::load(&receipt_path)?;
+            receipt.app_name = disk_manifest.app_name.clone();
+            receipt.app_version = disk_manifest.app_version.clone();
+
+            let mut contents = Vec::new();
+            receipt.to_writer(&mut contents)?;
+            crate::os::write_file_atomic(&receipt_path, &contents, true)
+                .with_contextc(|_e| format!("could not write file {:?}", receipt_path))?;
+        }
+
+        if cfg!(target_os = "macos") {
+            if let Some(entry) = disk_manifest.main_executable() {
+                disk_manifest.main_executable_architectures =
+                    crate::os::executable_architectures(&entry.path)
+                        .with_contextc(|_e| format!("could not read file {:?}", entry.path))?;
+            }
+        }
+
+        for entry in &mut disk_manifest.files {
+            // Data files may have been modified by the user
+            if entry.file_type != FileType::Executable && entry.path != receipt_path {
+                continue;
+            }
+
+            match crate::os::file_checksum(&entry.path) {
+                Ok(checksum) => {
+                    entry.len = checksum.len;
+                    entry.crc32c = checksum.crc32c;
+                    entry.digest = checksum.digest;
+                }
+                Err(error) => {
+                    tracing::warn!(path = ?entry.path, ?error, "could not read installed file");
+                }
+            }
+        }
+
+        let mut contents = Vec::new();
+        disk_manifest.to_writer(&mut contents)?;
+        crate::os::write_file_atomic(&disk_manifest.manifest_path, &contents, true).with_contextc(
+            |_e| format!("could not write file {:?}", disk_manifest.manifest_path),
+        )?;
+
+        #[cfg(windows)]
+        {
+            let config = crate::os::windows::UninstallEntryConfig {
+                manifest_path: disk_manifest.manifest_path.clone(),
+                display_name: app_metadata
+                    .get_display_name(&self.detect_lang_tag())
+                    .to_string(),
+                display_version: disk_manifest.app_version.clone(),
+                publisher: app_metadata.publisher.clone(),
+                homepage_url: app_metadata.homepage_url.clone(),
+                comments: app_metadata
+                    .get_description(&self.detect_lang_tag())
+                    .to_string(),
+                estimated_size: disk_manifest
+                    .files
+                    .iter()
+                    .map(|entry| {
+                        std::fs::metadata(&entry.path)
+                            .map(|metadata| metadata.len())
+                            .unwrap_or(entry.len)
+                    })
+                    .sum::<u64>()
+                    .saturating_add(disk_manifest.estimated_data_size),
+                ..Default::default()
+            };
+
+            crate::os::windows::update_uninstall_entry(
+                disk_manifest.access_scope,
+                app_id,
+                &config,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Installs over the existing installation with the same options.
+    ///
+    /// This is intended for shipping a new version without asking the
+    /// user to go through the interactive installer again. The existing
+    /// disk manifest is found as in [`crate::manifest()`] and the options
+    /// are taken from it as in [`InstallConfig::from_manifest()`]. The files
+    /// are installed from `source_dir`, which is usually the directory of
+    /// the running binary.
+    ///
+    /// The existing installation is removed and the new one installed as in
+    /// [`Self::run()`], so settings and data files modified by the user are
+    /// kept. If the application is not installed, the error kind
+    /// [`InstallerErrorKind::NotInstalled`] is returned.
+    pub fn upgrade(&mut self, source_dir: &Path) -> Result<(), InstallerError> {
+        let app_id = &self.package_manifest.app_id;
+        let exe_path = crate::os::current_exe()?;
+        let disk_manifest = match crate::manifest::discover_manifest(&exe_path, app_id) {
+            Ok(manifest) => manifest,
+            Err(error) if matches!(error.kind(), InstallerErrorKind::DiskManifestNotFound) => {
+                return Err(InstallerError::new(InstallerErrorKind::NotInstalled).with_source(error))
+            }
+            Err(error) => return Err(error),
+        };
+
+        tracing::info!(manifest_path = ?disk_manifest.manifest_path, "upgrading");
+
+        let mut config = InstallConfig::from_manifest(&disk_manifest)?;
+        config.source_dir = source_dir.to_path_buf();
+
+        self.run(&config)
+    }
+
+    /// Checks that the binary may replace the installed main binary.
+    ///
+    /// This is intended to be called by a binary that updates itself in
+    /// place before it replaces itself with the downloaded binary. On macOS,
+    /// replacing a universal binary with a binary missing some of its
+    /// architectures returns the error kind
+    /// [`InstallerErrorKind::ArchitectureDowngrade`] unless allowed with
+    /// [`Self::with_allow_architecture_downgrade()`]. Otherwise, nothing is
+    /// checked.
+    pub fn check_replacement_binary(&self, path: &Path) -> Result<(), InstallerError> {
+        use crate::error::AddContext;
+
+        if self.allow_architecture_downgrade || !cfg!(target_os = "macos") {
+            return Ok(());
+        }
+
+        let exe_path = crate::os::current_exe()?;
+        let disk_manifest =
+            crate::manifest::discover_manifest(&exe_path, &self.package_manifest.app_id)?;
+        let architectures = crate::os::executable_architectures(path)
+            .with_contextc(|_e| format!("could not read file {:?}", path))?;
+
+        plan::check_architectures(&disk_manifest.main_executable_architectures, &architectures)
+    }
+
+    /// Restores the missing or damaged parts of the existing installation
+    /// without reinstalling.
+    ///
+    /// The installation is checked as in [`crate::verify()`]. Missing files
+    /// and modified binaries are copied again from the package in
+    /// `source_dir`, which is usually the directory of the running binary
+    /// as in [`InstallConfig::source_dir`]. Modified data files are kept since
+    /// the user may have edited them. Missing search path (PATH) and App
+    /// Paths entries are added again. The disk manifest is updated with the
+    /// checksums of the restored files.
+    ///
+    /// Returns the result of checking the installation again after
+    /// repairing. Items that could not be restored, such as files that are
+    /// not in the package, are still in the report. If the disk manifest was
+    /// modified outside of this crate, the error kind
+    /// [`InstallerErrorKind::TamperedDiskManifest`] is returned.
+    pub fn repair(&self, source_dir: &Path) -> Result<VerifyReport, InstallerError> {
+        let app_id = &self.package_manifest.app_id;
+        let exe_path = crate::os::current_exe()?;
+        let disk_manifest = crate::manifest::discover_manifest(&exe_path, app_id)?;
+
+        Self::verify_manifest_integrity(&disk_manifest)?;
+
+        if disk_manifest.access_scope == AccessScope::System && !crate::os::has_system_access() {
+            return Err(
+                InstallerError::new(InstallerErrorKind::InsufficientPrivileges).with_context(
+                    "repairing an installation for all users requires running as root or an administrator",
+                ),
+            );
+        }
+
+        tracing::info!(manifest_path = ?disk_manifest.manifest_path, "repairing installation");
+
+        let _lock = crate::os::AppLock::acquire(
+            app_id,
+            disk_manifest
+                .manifest_path
+                .parent()
+                .unwrap_or(Path::new("")),
+        )?;
+
+        let report = crate::verify::verify_manifest(&disk_manifest)?;
+
+        if report.is_intact() {
+            return Ok(report);
+        }
+
+        let mut repairer = Repairer::new(&self.package_manifest, source_dir, disk_manifest);
+        repairer.run(&report)?;
+
+        if repairer.reboot_required() {
+            tracing::warn!("some files will be replaced when the computer restarts");
+        }
+
+        crate::verify::verify_manifest(repairer.manifest())
+    }
+
+    /// Removes the changes of an installation that was interrupted, such as
+    /// by a crash or power loss, instead of resuming it.
+    ///
+    /// An interrupted installation is otherwise resumed by installing again.
+    /// See [`crate::manifest::interrupted_operation()`]. Returns
+    /// [`InstallerErrorKind::NotInstalled`] if there is no interrupted
+    /// installation.
+    pub fn roll_back_interrupted(&self) -> Result<(), InstallerError> {
+        let app_id = &self.package_manifest.app_id;
+        let operation = crate::manifest::interrupted_operation(app_id)?
+            .filter(|operation| operation.operation == JournalOperation::Install)
+            .ok_or_else(|| {
+                InstallerError::new(InstallerErrorKind::NotInstalled)
+                    .with_context("no interrupted installation")
+            })?;
+
+        tracing::info!(manifest_path = ?operation.manifest.manifest_path, "rolling back interrupted installation");
+
+        let mut uninstaller =
+            crate::uninst::Uninstaller::new(app_id).with_manifest(&operation.manifest);
+        uninstaller.run()?;
+
+        if uninstaller.reboot_required() {
+            tracing::warn!("some files will be removed when the computer restarts");
+        }
+
+        Ok(())
+    }
+
+    /// Refuses to modify an installation whose disk manifest was modified or
+    /// corrupted.
+    fn verify_manifest_integrity(disk_manifest: &DiskManifest) -> Result<(), InstallerError> {
+        if disk_manifest.integrity == ManifestIntegrity::Invalid {
+            tracing::error!(manifest_path = ?disk_manifest.manifest_path, "disk manifest integrity mismatch");
+
+            return Err(
+                InstallerError::new(InstallerErrorKind::TamperedDiskManifest).with_context(
+                    format!(
+                        "refusing to modify the installation of {:?}",
+                        disk_manifest.manifest_path
+                    ),
+                ),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Finishes an interrupted operation found after acquiring the lock.
+    ///
+    /// If the disk manifest exists, the uninstaller for the existing
+    /// installation also recovers. Otherwise, the changes recorded in the
+    /// journal are removed here before installing again.
+    fn recover_interrupted(&mut self) -> Result<(), InstallerError> {
+        let app_id = &self.package_manifest.app_id;
+        let plan = self.plan.as_ref().unwrap();
+        let Some(operation) = crate::manifest::stale_journal(&plan.manifest_path, app_id)? else {
+            return Ok(());
+        };
+
+        tracing::warn!(operation = ?operation.operation, "found interrupted operation");
+
+        if plan.existing_manifest_path.is_some() {
+            return Ok(());
+        }
+
+        let mut uninstaller = crate::uninst::Uninstaller::new(app_id)
+            .with_manifest(&operation.manifest)
+            .with_app_lock(false);
+        uninstaller.run()?;
+        self.reboot_required |= uninstaller.reboot_required();
+
         Ok(())
     }
 
     fn run_planner(&mut self, config: &InstallConfig) -> Result<(), InstallerError> {
         tracing::debug!(package_manifest = ?self.package_manifest, ?config, "running planner");
 
-        let mut planner = Planner::new(&self.package_manifest, config);
-        let plan = planner.run()?;
+        let mut planner =
+            Planner::new(&self.package_manifest, config).with_lang_tag(&self.detect_lang_tag());
+        let mut plan = planner.run()?;
+
+        let mut custom_values = config.custom_values.clone();
+        page::apply_default_values(&self.custom_pages, &mut custom_values);
+        plan.custom_values = page::persisted_values(&self.custom_pages, &custom_values);
+        self.custom_values = custom_values;
 
         tracing::debug!(?plan, "created plan");
         self.plan = Some(plan);
+        self.reboot_required = false;
 
         Ok(())
     }
 
+    fn run_preflight(&self, timer: &mut StepTimer) -> Result<(), InstallerError> {
+        let plan = self.plan.as_ref().unwrap();
+
+        timer.time(InstallStep::Preflight, || preflight::check_writable(plan))
+    }
+
+    /// Copies the files to a temporary directory if the plan requires it.
+    ///
+    /// The returned directory needs to be kept until the files are installed.
+    fn run_staging(
+        &mut self,
+        timer: &mut StepTimer,
+    ) -> Result<Option<tempfile::TempDir>, InstallerError> {
+        let plan = self.plan.as_mut().unwrap();
+
+        if !plan.stage_source_files {
+            return Ok(None);
+        }
+
+        timer
+            .time(InstallStep::Stage, || stage::stage_files(plan))
+            .map(Some)
+    }
+
     #[cfg(feature = "ui")]
-    fn run_uninstaller_interactive(&mut self) -> Result<(), InstallerError> {
-        let manifest_path = &self.plan.as_ref().unwrap().manifest_path;
-        let uninstall_required = manifest_path.exists();
+    fn run_uninstaller_interactive(&mut self, timer: &mut StepTimer) -> Result<(), InstallerError> {
+        let uninstall_required = self.plan.as_ref().unwrap().existing_manifest_path.is_some();
 
         if !uninstall_required {
             return Ok(());
         }
 
-        let manifest = crate::manifest::DiskManifest::load(manifest_path)?;
+        timer.time(InstallStep::UninstallExisting, || {
+            self.run_uninstaller_interactive_impl()
+        })
+    }
+
+    #[cfg(feature = "ui")]
+    fn run_uninstaller_interactive_impl(&mut self) -> Result<(), InstallerError> {
+        let manifest_path = self.plan.as_ref().unwrap().existing_manifest_path.as_ref();
+        let manifest = crate::manifest::DiskManifest::load(manifest_path.unwrap())?;
 
         let mut uninstaller = crate::uninst::Uninstaller::new(&manifest.app_id)
             .with_manifest(&manifest)
+            .with_keep_main_executable(self.plan.as_ref().unwrap().overwrite_main_executable)
+            .with_progress_weight(self.plan.as_ref().unwrap().progress_weight)
+            .with_app_lock(false)
             .with_tui(self.tui.clone());
 
         uninstaller.run_from_installer_interactive()?;
+        self.add_replaceable_main_executable(&manifest, &uninstaller);
+        self.reboot_required |= uninstaller.reboot_required();
 
         Ok(())
     }
 
-    fn run_uninstaller(&mut self) -> Result<(), InstallerError> {
-        let manifest_path = &self.plan.as_ref().unwrap().manifest_path;
-        let uninstall_required = manifest_path.exists();
+    fn acquire_lock(&self) -> Result<crate::os::AppLock, InstallerError> {
+        let plan = self.plan.as_ref().unwrap();
+
+        crate::os::AppLock::acquire(
+            &self.package_manifest.app_id,
+            plan.manifest_path.parent().unwrap_or(Path::new("")),
+        )
+    }
+
+    fn run_uninstaller(&mut self, timer: &mut StepTimer) -> Result<(), InstallerError> {
+        let uninstall_required = self.plan.as_ref().unwrap().existing_manifest_path.is_some();
 
         if !uninstall_required {
             return Ok(());
         }
 
-        let manifest = crate::manifest::DiskManifest::load(manifest_path)?;
+        timer.time(InstallStep::UninstallExisting, || {
+            self.run_uninstaller_impl()
+        })
+    }
 
-        let mut uninstaller =
-            crate::uninst::Uninstaller::new(&manifest.app_id).with_manifest(&manifest);
+    fn run_uninstaller_impl(&mut self) -> Result<(), InstallerError> {
+        let manifest_path = self.plan.as_ref().unwrap().existing_manifest_path.as_ref();
+        let manifest = crate::manifest::DiskManifest::load(manifest_path.unwrap())?;
+
+        let mut uninstaller = crate::uninst::Uninstaller::new(&manifest.app_id)
+            .with_manifest(&manifest)
+            .with_keep_main_executable(self.plan.as_ref().unwrap().overwrite_main_executable)
+            .with_progress_weight(self.plan.as_ref().unwrap().progress_weight)
+            .with_app_lock(false);
 
         uninstaller.run()?;
+        self.add_replaceable_main_executable(&manifest, &uninstaller);
+        self.reboot_required |= uninstaller.reboot_required();
 
         Ok(())
     }
 
-    fn run_executor(&mut self) -> Result<(), InstallerError> {
+    /// Allows the executor to overwrite the main binary left by the uninstaller.
+    fn add_replaceable_main_executable(
+        &mut self,
+        manifest: &crate::manifest::DiskManifest,
+        uninstaller: &crate::uninst::Uninstaller,
+    ) {
+        let plan = self.plan.as_mut().unwrap();
+
+        if let (Some(path), Some(entry)) = (
+            uninstaller.kept_main_executable(),
+            manifest.main_executable(),
+        ) {
+            plan.replaceable_files
+                .push((path.to_path_buf(), entry.checksum()));
+        }
+    }
+
+    fn run_executor(&mut self, timer: &mut StepTimer) -> Result<(), InstallerError> {
         let plan = self.plan.as_ref().unwrap();
         let mut executor = Executor::new(&self.package_manifest.app_id, plan);
 
@@ -240,14 +1308,46 @@ impl Installer {
         if self.tui.borrow().is_running() {
             let tui = self.tui.clone();
             if tui.borrow().is_running() {
-                executor = executor.with_progress_callback(move |current, total| {
-                    let _ = tui.borrow_mut().update_install_progress(current, total);
+                executor = executor.with_progress_callback(move |progress| {
+                    let _ = tui.borrow_mut().update_install_progress(progress);
                 });
             }
         }
 
-        executor.run()?;
+        let result = executor.run();
+        self.reboot_required |= executor.reboot_required();
+        timer.extend(executor.into_timer());
 
-        Ok(())
+        result
     }
 }
+
+/// Pages of the interactive installer, in order.
+#[cfg(feature = "ui")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InteractiveStep {
+    Intro,
+    AccessScope,
+    Destination,
+    Profile,
+    SearchPath,
+    Shortcuts,
+    ScheduledTasks,
+    CustomPages,
+    Confirm,
+}
+
+#[cfg(feature = "ui")]
+impl InteractiveStep {
+    const ALL: [Self; 9] = [
+        Self::Intro,
+        Self::AccessScope,
+        Self::Destination,
+        Self::Profile,
+        Self::SearchPath,
+        Self::Shortcuts,
+        Self::ScheduledTasks,
+        Self::CustomPages,
+        Self::Confirm,
+    ];
+}
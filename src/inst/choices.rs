@@ -0,0 +1,143 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{AddContext, InstallerError, InstallerErrorKind},
+    manifest::AppId,
+    os::AccessScope,
+    path::AppPathPrefix,
+};
+
+use super::{InstallConfig, InstallProfile};
+
+/// Answers from the interactive installer saved for the next run.
+///
+/// The file is stored per user beside the user scope disk manifest and is
+/// kept after uninstalling so a reinstall pre-selects the same answers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SavedChoices {
+    pub access_scope: AccessScope,
+    pub destination: AppPathPrefix,
+    pub modify_os_search_path: bool,
+    pub start_menu_shortcut: bool,
+    pub desktop_shortcut: bool,
+    pub register_scheduled_tasks: bool,
+    #[serde(default)]
+    pub profile: Option<InstallProfile>,
+    #[serde(default)]
+    pub custom_values: BTreeMap<String, String>,
+}
+
+impl SavedChoices {
+    pub fn from_config(config: &InstallConfig) -> Self {
+        Self {
+            access_scope: config.access_scope,
+            destination: config.destination.clone(),
+            modify_os_search_path: config.modify_os_search_path,
+            start_menu_shortcut: config.start_menu_shortcut,
+            desktop_shortcut: config.desktop_shortcut,
+            register_scheduled_tasks: config.register_scheduled_tasks,
+            profile: config.profile,
+            custom_values: config.custom_values.clone(),
+        }
+    }
+
+    pub fn apply(&self, config: &mut InstallConfig) {
+        config.access_scope = self.access_scope;
+        config.destination = self.destination.clone();
+        config.modify_os_search_path = self.modify_os_search_path;
+        config.start_menu_shortcut = self.start_menu_shortcut;
+        config.desktop_shortcut = self.desktop_shortcut;
+        config.register_scheduled_tasks = self.register_scheduled_tasks;
+        config.profile = self.profile;
+        config.custom_values.extend(
+            self.custom_values
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+    }
+
+    pub fn path(app_id: &AppId) -> Result<PathBuf, InstallerError> {
+        let manifest_path = crate::manifest::manifest_path(app_id, AccessScope::User)?;
+
+        Ok(manifest_path
+            .with_file_name(format!("takecrate-choices__{}.ron", app_id.namespaced_id())))
+    }
+
+    /// Returns the saved choices, or `None` if there are none or they can't be read.
+    pub fn load(app_id: &AppId) -> Option<Self> {
+        let path = Self::path(app_id).ok()?;
+
+        if !path.exists() {
+            return None;
+        }
+
+        tracing::debug!(?path, "loading saved choices");
+
+        match std::fs::read_to_string(&path)
+            .map_err(InstallerError::from)
+            .and_then(|text| {
+                ron::from_str(&text).map_err(|error| {
+                    InstallerError::new(InstallerErrorKind::Other).with_source(error)
+                })
+            }) {
+            Ok(choices) => Some(choices),
+            Err(error) => {
+                tracing::warn!(?path, ?error, "could not load saved choices");
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, app_id: &AppId) -> Result<(), InstallerError> {
+        let path = Self::path(app_id)?;
+
+        tracing::debug!(?path, "saving choices");
+
+        if let Some(parent) = path.parent() {
+            crate::manifest::create_manifest_dir(parent, AccessScope::User)?;
+        }
+
+        let text = ron::ser::to_string_pretty(self, Default::default())
+            .map_err(|error| InstallerError::new(InstallerErrorKind::Other).with_source(error))?;
+
+        crate::os::write_file_atomic(&path, text.as_bytes(), true)
+            .with_contextc(|_error| format!("could not write file {:?}", path))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_round_trip() {
+        let config = InstallConfig {
+            access_scope: AccessScope::System,
+            destination: AppPathPrefix::System,
+            modify_os_search_path: false,
+            desktop_shortcut: true,
+            ..Default::default()
+        };
+        let choices = SavedChoices::from_config(&config);
+
+        let text = ron::to_string(&choices).unwrap();
+        let choices2 = ron::from_str::<SavedChoices>(&text).unwrap();
+        assert_eq!(choices2, choices);
+
+        let mut config2 = InstallConfig {
+            modify_os_search_path: true,
+            ..Default::default()
+        };
+        choices2.apply(&mut config2);
+
+        assert_eq!(config2.access_scope, AccessScope::System);
+        assert!(matches!(config2.destination, AppPathPrefix::System));
+        assert!(!config2.modify_os_search_path);
+        assert!(config2.desktop_shortcut);
+        assert!(!config2.start_menu_shortcut);
+    }
+}
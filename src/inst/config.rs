@@ -1,12 +1,22 @@
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
-use crate::{error::InstallerError, os::AccessScope, path::AppPathPrefix};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::InstallerError,
+    manifest::{DiskInstallOptions, DiskManifest},
+    os::AccessScope,
+    path::AppPathPrefix,
+};
 
 /// Parameters that control how the binary is installed.
 #[derive(Debug, Clone, Default)]
 #[non_exhaustive]
 pub struct InstallConfig {
     /// Access scope.
+    ///
+    /// On NixOS, system scope is refused with
+    /// [`InstallerErrorKind::UnsupportedAccessScope`](crate::error::InstallerErrorKind::UnsupportedAccessScope).
     pub access_scope: AccessScope,
     /// Where the files will be installed.
     pub destination: AppPathPrefix,
@@ -15,15 +25,130 @@ pub struct InstallConfig {
     /// Whether to modify the search path (PATH).
     ///
     /// On Windows, this will modify the environment variable and App Paths
-    /// in the registry.
+    /// in the registry. See [`Self::search_path_strategy`] for an alternative.
     ///
     /// On Unix with user scope, this will modify the user's shell profile
     /// config. The SHELL variable and the existence of
     /// the `.bash_profile`, `.zprofile`, or `.profile` will be used to select
     /// the appropriate file. If the file already contains the path, it will
-    /// not be modified.
-    /// For system scope, it's not supported.
+    /// not be modified. If the SHELL is Nushell, the `env.nu` file in the
+    /// Nushell config directory will be used instead. If the profile is a
+    /// read-only file in the Nix store (such as one managed by Home
+    /// Manager), it is not modified and a warning is logged.
+    /// For system scope on macOS, a file named after the application ID is
+    /// written to `/etc/paths.d` instead. Otherwise, system scope is not
+    /// supported.
     pub modify_os_search_path: bool,
+    /// How the search path is modified.
+    ///
+    /// See [`SearchPathStrategy`] for the supported platforms and scopes.
+    pub search_path_strategy: SearchPathStrategy,
+    /// Whether to create a shortcut to the main binary in the Start Menu.
+    ///
+    /// The shortcut is placed in a folder named after the application.
+    /// Only supported on Windows.
+    pub start_menu_shortcut: bool,
+    /// Whether to create a shortcut to the main binary on the Desktop.
+    ///
+    /// Only supported on Windows.
+    pub desktop_shortcut: bool,
+    /// Whether to register the package's [`ScheduledTask`](crate::inst::ScheduledTask)s.
+    pub register_scheduled_tasks: bool,
+    /// Whether to overwrite the main binary of an existing installation
+    /// instead of deleting it first.
+    ///
+    /// By default, the existing installation is uninstalled before installing,
+    /// which deletes the main binary using self-deletion. If enabled, the
+    /// main binary is kept during the uninstall and replaced by copying over it.
+    /// This is intended for platforms or security software setups where
+    /// self-deletion is not possible.
+    pub overwrite_main_executable: bool,
+    /// Whether to allow replacing an installed universal binary with a
+    /// binary that is missing some of its architectures.
+    ///
+    /// On macOS, the architectures of the main binary are recorded in the
+    /// disk manifest. By default, installing over a universal binary with,
+    /// for example, an `arm64` only binary returns the error kind
+    /// [`InstallerErrorKind::ArchitectureDowngrade`](crate::error::InstallerErrorKind::ArchitectureDowngrade)
+    /// since the application would stop working on the other machines
+    /// sharing the installation.
+    pub allow_architecture_downgrade: bool,
+    /// Whether to replace an existing installation with an older version.
+    ///
+    /// Versions are compared by
+    /// [`AppMetadata::comparable_version()`](crate::manifest::AppMetadata::comparable_version).
+    /// By default, a downgrade returns the error kind
+    /// [`InstallerErrorKind::Downgrade`](crate::error::InstallerErrorKind::Downgrade).
+    /// The interactive installer shows a warning instead.
+    pub allow_downgrade: bool,
+    /// Whether to install for the current user when a newer version is
+    /// installed for all users.
+    ///
+    /// The user's copy is found first in the search path, so the user
+    /// would be running an outdated version. By default, this returns the
+    /// error kind
+    /// [`InstallerErrorKind::NewerSystemInstall`](crate::error::InstallerErrorKind::NewerSystemInstall).
+    /// The interactive installer shows a warning instead.
+    pub allow_older_user_install: bool,
+    /// Whether to keep the settings and data files of an existing
+    /// installation that were modified by the user.
+    ///
+    /// By default, a modified file where the package has a file returns
+    /// the error kind
+    /// [`InstallerErrorKind::UnknownFileInDestination`](crate::error::InstallerErrorKind::UnknownFileInDestination).
+    /// If enabled, the user's file is kept instead of the package's file.
+    /// This is enabled by [`Self::from_manifest()`].
+    pub keep_modified_user_files: bool,
+    /// Whether to hard link the files from the source directory instead of
+    /// copying them.
+    ///
+    /// This makes installing large packages nearly instant and avoids
+    /// doubling the disk usage, which is intended for local development
+    /// installs. Files that cannot be linked, such as those on a different
+    /// file system, are copied instead. Linked files keep the permissions
+    /// of the source files.
+    ///
+    /// Modifying a source file in place also modifies the installed file.
+    pub hardlink_files: bool,
+    /// Whether the files are copied to a temporary directory before
+    /// installing.
+    ///
+    /// See [`SourceStaging`].
+    pub source_staging: SourceStaging,
+    /// Whether to leave out who performed the installation from the disk
+    /// manifest for privacy.
+    ///
+    /// For system scope, the username, hostname, and elevation method are
+    /// recorded in [`DiskManifest::installed_by`](crate::manifest::DiskManifest::installed_by)
+    /// for auditing on shared machines. They are never recorded for user
+    /// scope.
+    pub omit_installer_identity: bool,
+    /// How the progress of copying files is computed.
+    pub progress_weight: ProgressWeight,
+    /// Directory where the disk manifest and other state files are stored
+    /// instead of the default location.
+    ///
+    /// The disk manifest is stored in the `user` or `system` subdirectory.
+    /// This is intended for containerized test environments and machines
+    /// where the default location is not usable. The uninstaller needs the
+    /// same directory with
+    /// [`Uninstaller::with_state_dir()`](crate::uninst::Uninstaller::with_state_dir).
+    /// To relocate the directory for the whole process, set
+    /// [`STATE_DIR_ENV_VAR`](crate::manifest::STATE_DIR_ENV_VAR) instead.
+    ///
+    /// If `None`, [`STATE_DIR_ENV_VAR`](crate::manifest::STATE_DIR_ENV_VAR)
+    /// or the default location is used.
+    pub state_dir: Option<PathBuf>,
+    /// The preset the options were taken from, if any.
+    ///
+    /// This is set by [`Self::apply_profile()`] and is `None` if the options
+    /// were chosen individually.
+    pub profile: Option<InstallProfile>,
+    /// Answers to the [`CustomPage`](crate::inst::CustomPage)s keyed by
+    /// page ID.
+    ///
+    /// Pages without an answer use their default value.
+    pub custom_values: BTreeMap<String, String>,
 }
 
 impl InstallConfig {
@@ -34,6 +159,22 @@ impl InstallConfig {
             destination: AppPathPrefix::User,
             source_dir: crate::os::current_exe_dir()?,
             modify_os_search_path: true,
+            search_path_strategy: SearchPathStrategy::Environment,
+            start_menu_shortcut: false,
+            desktop_shortcut: false,
+            register_scheduled_tasks: false,
+            overwrite_main_executable: false,
+            allow_architecture_downgrade: false,
+            allow_downgrade: false,
+            allow_older_user_install: false,
+            keep_modified_user_files: false,
+            hardlink_files: false,
+            source_staging: SourceStaging::default(),
+            omit_installer_identity: false,
+            progress_weight: ProgressWeight::default(),
+            state_dir: None,
+            profile: None,
+            custom_values: BTreeMap::new(),
         })
     }
 
@@ -44,6 +185,298 @@ impl InstallConfig {
             destination: AppPathPrefix::System,
             source_dir: crate::os::current_exe_dir()?,
             modify_os_search_path: true,
+            search_path_strategy: SearchPathStrategy::Environment,
+            start_menu_shortcut: false,
+            desktop_shortcut: false,
+            register_scheduled_tasks: false,
+            overwrite_main_executable: false,
+            allow_architecture_downgrade: false,
+            allow_downgrade: false,
+            allow_older_user_install: false,
+            keep_modified_user_files: false,
+            hardlink_files: false,
+            source_staging: SourceStaging::default(),
+            omit_installer_identity: false,
+            progress_weight: ProgressWeight::default(),
+            state_dir: None,
+            profile: None,
+            custom_values: BTreeMap::new(),
         })
     }
+
+    /// Create a new config that installs again with the options recorded in
+    /// the disk manifest of an existing installation.
+    ///
+    /// The access scope, destination, search path (PATH) strategy,
+    /// shortcuts, and scheduled tasks are taken from the disk manifest.
+    /// For installations by previous versions that didn't record
+    /// [`DiskManifest::install_options`], they are inferred from the
+    /// installed items. Other options are the defaults of
+    /// [`Self::new_user()`] or [`Self::new_system()`].
+    pub fn from_manifest(manifest: &DiskManifest) -> Result<Self, InstallerError> {
+        let mut config = match manifest.access_scope {
+            AccessScope::User => Self::new_user()?,
+            AccessScope::System => Self::new_system()?,
+        };
+
+        config.destination = manifest.app_paths.prefix.clone();
+        config.keep_modified_user_files = true;
+        config.omit_installer_identity =
+            manifest.access_scope == AccessScope::System && manifest.installed_by.is_none();
+
+        match &manifest.install_options {
+            Some(options) => {
+                config.modify_os_search_path = options.modify_os_search_path;
+                config.search_path_strategy = options.search_path_strategy;
+                config.start_menu_shortcut = options.start_menu_shortcut;
+                config.desktop_shortcut = options.desktop_shortcut;
+                config.register_scheduled_tasks = options.register_scheduled_tasks;
+                config.profile = options.profile;
+            }
+            None => config.infer_options(manifest)?,
+        }
+
+        config.state_dir = relocated_state_dir(manifest);
+        config.custom_values = manifest.custom_values.clone();
+
+        Ok(config)
+    }
+
+    /// Returns the choices recorded in the disk manifest.
+    pub(crate) fn install_options(&self) -> DiskInstallOptions {
+        DiskInstallOptions {
+            modify_os_search_path: self.modify_os_search_path,
+            search_path_strategy: self.search_path_strategy,
+            start_menu_shortcut: self.start_menu_shortcut,
+            desktop_shortcut: self.desktop_shortcut,
+            register_scheduled_tasks: self.register_scheduled_tasks,
+            profile: self.profile,
+        }
+    }
+
+    fn infer_options(&mut self, manifest: &DiskManifest) -> Result<(), InstallerError> {
+        self.modify_os_search_path = manifest.search_path.is_some();
+        self.register_scheduled_tasks = !manifest.scheduled_tasks.is_empty();
+
+        #[cfg(windows)]
+        {
+            let strategy = if !manifest.powershell_profile_paths.is_empty() {
+                Some(SearchPathStrategy::PowerShellProfile)
+            } else if manifest.shims_dir.is_some() {
+                Some(SearchPathStrategy::Shims)
+            } else if !manifest.execution_aliases.is_empty() {
+                Some(SearchPathStrategy::ExecutionAlias)
+            } else {
+                None
+            };
+
+            if let Some(strategy) = strategy {
+                self.modify_os_search_path = true;
+                self.search_path_strategy = strategy;
+            }
+
+            let start_menu_dir =
+                crate::os::windows::start_menu_programs_dir(manifest.access_scope)?;
+            let desktop_dir = crate::os::windows::desktop_dir(manifest.access_scope)?;
+
+            self.start_menu_shortcut = manifest
+                .shortcuts
+                .iter()
+                .any(|path| path.starts_with(&start_menu_dir));
+            self.desktop_shortcut = manifest
+                .shortcuts
+                .iter()
+                .any(|path| path.parent() == Some(&desktop_dir));
+        }
+
+        #[cfg(unix)]
+        if manifest.search_path.is_none() {
+            let link_dir = crate::path::PathResolver::new(
+                manifest.app_id.plain_id(),
+                &manifest.access_scope.into(),
+            )?
+            .bin_dir();
+
+            if manifest
+                .symlinks
+                .iter()
+                .any(|path| path.parent() == Some(&link_dir))
+                && manifest.app_paths.executable != link_dir
+            {
+                self.modify_os_search_path = true;
+                self.search_path_strategy = SearchPathStrategy::Symlink;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets the options bundled by the profile.
+    ///
+    /// The access scope, destination, and other options not covered by the
+    /// profile are unchanged.
+    pub fn apply_profile(&mut self, profile: InstallProfile) {
+        let (search_path, shortcuts, scheduled_tasks) = match profile {
+            InstallProfile::Minimal => (false, false, false),
+            InstallProfile::Developer => (true, false, false),
+            InstallProfile::Full => (true, true, true),
+        };
+
+        self.modify_os_search_path = search_path;
+        self.search_path_strategy = SearchPathStrategy::Environment;
+        self.start_menu_shortcut = shortcuts;
+        self.desktop_shortcut = shortcuts;
+        self.register_scheduled_tasks = scheduled_tasks;
+        self.profile = Some(profile);
+    }
+}
+
+/// How progress is computed from the number and sizes of files.
+///
+/// Progress computed purely by bytes shows nearly complete while thousands
+/// of small files remain since creating each file has a fixed cost. Instead,
+/// each file counts as [`Self::file_cost`] bytes in addition to its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ProgressWeight {
+    /// Number of bytes that creating or removing a file is equivalent to.
+    ///
+    /// Zero computes progress by bytes only.
+    pub file_cost: u64,
+}
+
+impl ProgressWeight {
+    /// Default cost of a file in bytes.
+    pub const DEFAULT_FILE_COST: u64 = 64 * 1024;
+
+    /// Creates a weight with the given cost of a file in bytes.
+    pub fn new(file_cost: u64) -> Self {
+        Self { file_cost }
+    }
+
+    /// Returns the progress units of a file with the given size.
+    pub fn cost(&self, len: u64) -> u64 {
+        len.saturating_add(self.file_cost)
+    }
+}
+
+impl Default for ProgressWeight {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_FILE_COST)
+    }
+}
+
+/// Whether the files are copied to a temporary directory before installing.
+///
+/// When the installer runs from a mounted disk image, optical disc, or
+/// network share, the medium may be slow, ejected, or disconnected while
+/// installing. Staging reads every file once into a temporary directory on
+/// a local disk and checks it against the checksum computed when planning
+/// before anything is modified, so a failed read does not leave a partial
+/// installation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SourceStaging {
+    /// Stage the files only if the source directory is on a read-only
+    /// medium.
+    ///
+    /// See [`crate::os::is_read_only_medium()`].
+    #[default]
+    Auto,
+    /// Always stage the files.
+    Always,
+    /// Never stage the files.
+    Never,
+}
+
+/// Named preset of install options.
+///
+/// A profile is selected using [`InstallConfig::apply_profile()`] or by the
+/// user in the interactive installer when offered with
+/// [`Installer::with_install_profiles()`](crate::inst::Installer::with_install_profiles).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum InstallProfile {
+    /// Only copy the files.
+    ///
+    /// The search path is not modified and no shortcuts or scheduled tasks
+    /// are added.
+    Minimal,
+    /// Copy the files and modify the search path.
+    ///
+    /// This is intended for command line usage.
+    Developer,
+    /// Copy the files, modify the search path, and add shortcuts and
+    /// scheduled tasks.
+    Full,
+}
+
+/// Method of modifying the search path (PATH).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum SearchPathStrategy {
+    /// Modify the `Path` environment variable in the registry on Windows or
+    /// the shell profile on Unix.
+    #[default]
+    Environment,
+    /// Append a snippet to the user's PowerShell profiles.
+    ///
+    /// This is intended for environments where modifying the environment
+    /// variables in the registry is blocked. The snippet is added to the
+    /// "current user, all hosts" `profile.ps1` of both Windows PowerShell and
+    /// PowerShell (Core). The search path is modified only in PowerShell
+    /// sessions and only when the execution policy allows running the profile.
+    ///
+    /// Only supported on Windows with user scope. Otherwise, it is the same
+    /// as [`Self::Environment`].
+    PowerShellProfile,
+    /// Create a symlink to the main binary in a directory that is usually
+    /// already in the search path.
+    ///
+    /// The directory is `~/.local/bin` for user scope and `/usr/local/bin`
+    /// for system scope. This is intended for installs to a custom
+    /// destination where modifying the shell profile is undesirable.
+    ///
+    /// Only supported on Unix. Otherwise, it is the same as
+    /// [`Self::Environment`].
+    Symlink,
+    /// Create shims for the commands in a directory shared by all
+    /// applications.
+    ///
+    /// A `.cmd` and a `.ps1` script that run the command are written to the
+    /// `shims` directory beside the disk manifests. The directory is added
+    /// to the `Path` environment variable only once, so installing other
+    /// applications does not modify it again. The directory is removed from
+    /// the `Path` when the last shim is uninstalled.
+    ///
+    /// Only supported on Windows. Otherwise, it is the same as
+    /// [`Self::Environment`].
+    Shims,
+    /// Create a link to each command in the app execution alias directory
+    /// (`%LOCALAPPDATA%\Microsoft\WindowsApps`).
+    ///
+    /// The directory is already in the user's `Path` on Windows 10 and
+    /// later, so the environment variable is not modified. The links are
+    /// hard links, so the destination needs to be on the same drive.
+    ///
+    /// Only supported on Windows with user scope. Otherwise, or if the
+    /// directory does not exist, it is the same as [`Self::Environment`].
+    ExecutionAlias,
+}
+
+/// Returns the state directory of a disk manifest stored outside of the
+/// default location, so an upgrade keeps it in place.
+fn relocated_state_dir(manifest: &DiskManifest) -> Option<PathBuf> {
+    let manifest_dir = manifest.manifest_path.parent()?;
+    let state_dir = manifest_dir.parent()?;
+    let expected_dir = crate::manifest::state_manifest_dir(state_dir, manifest.access_scope);
+
+    if manifest_dir != expected_dir {
+        return None;
+    }
+
+    match crate::manifest::manifest_path(&manifest.app_id, manifest.access_scope) {
+        Ok(default_path) if default_path == manifest.manifest_path => None,
+        _ => Some(state_dir.to_path_buf()),
+    }
 }
@@ -0,0 +1,136 @@
+//! Running the interactive installer again with root or administrator
+//! privileges.
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{InstallerError, InstallerErrorKind},
+    manifest::AppId,
+};
+
+use super::choices::SavedChoices;
+
+/// Environment variable containing the path of the file with the answers
+/// for the elevated installer.
+#[cfg(unix)]
+const HANDOFF_ENV_VAR: &str = "TAKECRATE_ELEVATED_CHOICES_PATH";
+
+/// Prefix of the command line argument containing the path of the file with
+/// the answers for the elevated installer.
+#[cfg(windows)]
+const HANDOFF_ARG_PREFIX: &str = "--takecrate-elevated-choices=";
+
+/// File name prefix of the file with the answers.
+const HANDOFF_FILE_PREFIX: &str = "takecrate-elevate__";
+
+/// Answers passed from the unelevated installer to the elevated installer.
+#[derive(Debug, Serialize, Deserialize)]
+struct Handoff {
+    app_id: AppId,
+    choices: SavedChoices,
+}
+
+/// Runs the installer again elevated and waits for it to complete.
+///
+/// The answers are passed along so the elevated installer continues at
+/// the confirmation page.
+pub fn run_elevated(app_id: &AppId, choices: &SavedChoices) -> Result<(), InstallerError> {
+    let handoff = Handoff {
+        app_id: app_id.clone(),
+        choices: choices.clone(),
+    };
+    let text = ron::to_string(&handoff)
+        .map_err(|error| InstallerError::new(InstallerErrorKind::Other).with_source(error))?;
+
+    tracing::info!("running installer elevated");
+
+    // The command line is visible in the process list and logged, so the
+    // answers are passed in a file only readable by the user (and
+    // administrators) instead. The file has a random name that is passed on
+    // the command line so the elevated installer only reads this file.
+    let mut file = tempfile::Builder::new()
+        .prefix(HANDOFF_FILE_PREFIX)
+        .suffix(".ron")
+        .tempfile()?;
+    {
+        use std::io::Write;
+
+        file.write_all(text.as_bytes())?;
+        file.flush()?;
+    }
+    let path = file.path().to_string_lossy();
+
+    // sudo and pkexec clear the environment, so the path is passed using `env`.
+    #[cfg(unix)]
+    let result = crate::os::unix::run_current_exe_elevated(&[(HANDOFF_ENV_VAR, &path)]);
+
+    // UAC starts the process with a new environment, possibly as another
+    // user with a different temporary directory, so the path is passed as an
+    // argument instead.
+    #[cfg(windows)]
+    let result =
+        crate::os::windows::run_current_exe_elevated(&[&format!("{}{}", HANDOFF_ARG_PREFIX, path)]);
+
+    drop(file);
+
+    let success = result.map_err(|error| {
+        InstallerError::new(InstallerErrorKind::InsufficientPrivileges)
+            .with_source(error)
+            .with_context("could not run installer elevated")
+    })?;
+
+    if success {
+        Ok(())
+    } else {
+        Err(InstallerError::new(InstallerErrorKind::Other)
+            .with_context("elevated installer did not complete"))
+    }
+}
+
+/// Returns the answers passed by the unelevated installer, if any.
+pub fn take_choices(app_id: &AppId) -> Option<SavedChoices> {
+    if !crate::os::is_elevated() {
+        return None;
+    }
+
+    #[cfg(unix)]
+    let path = std::path::PathBuf::from(std::env::var_os(HANDOFF_ENV_VAR)?);
+
+    #[cfg(windows)]
+    let path = std::env::args_os().find_map(|arg| {
+        arg.to_str()?
+            .strip_prefix(HANDOFF_ARG_PREFIX)
+            .map(std::path::PathBuf::from)
+    })?;
+
+    if !path
+        .file_name()?
+        .to_string_lossy()
+        .starts_with(HANDOFF_FILE_PREFIX)
+    {
+        tracing::warn!(?path, "ignoring unexpected answers file");
+        return None;
+    }
+
+    // The file is removed by the unelevated installer
+    let text = std::fs::read_to_string(path).ok()?;
+
+    match ron::from_str::<Handoff>(&text) {
+        Ok(handoff) if handoff.app_id == *app_id => {
+            tracing::debug!(choices = ?handoff.choices, "using answers from unelevated installer");
+            Some(handoff.choices)
+        }
+        Ok(_) => None,
+        Err(error) => {
+            tracing::warn!(?error, "could not read answers from unelevated installer");
+            None
+        }
+    }
+}
+
+/// Returns whether the command line argument was added for passing the
+/// answers to the elevated installer.
+#[cfg(windows)]
+pub(crate) fn is_handoff_arg(arg: &std::ffi::OsStr) -> bool {
+    arg.to_str()
+        .is_some_and(|arg| arg.starts_with(HANDOFF_ARG_PREFIX))
+}
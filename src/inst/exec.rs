@@ -1,17 +1,78 @@
-use std::{io::Write, path::Path};
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::Path,
+};
 
 use crate::{
-    error::{AddInstallerContext, InstallerError, InstallerErrorKind},
-    manifest::{AppId, DiskDirEntry, DiskFileEntry, DiskManifest, DiskPaths, FileType},
-    os::FileChecksum,
+    error::{AddContext, AddInstallerContext, InstallerError, InstallerErrorKind},
+    manifest::{
+        AppId, DiskDirEntry, DiskFileEntry, DiskManifest, DiskPaths, FileType, JournalOperation,
+    },
+    os::{EventLogEvent, FileChecksum},
+};
+
+use super::{
+    plan::{InstallPlan, PlanFileEntry},
+    report::{InstallStep, StepTimer},
 };
 
-use super::plan::{InstallPlan, PlanFileEntry};
+/// Number of bytes copied between progress updates.
+const PROGRESS_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
 
 pub struct Executor {
     app_id: AppId,
     plan: InstallPlan,
-    progress_callback: Box<dyn FnMut(u64, u64)>,
+    progress_callback: Box<dyn FnMut(&CopyProgress)>,
+    timer: StepTimer,
+    reboot_required: bool,
+    #[cfg(windows)]
+    registry_changes: Vec<RegistryChange>,
+}
+
+/// Registry change that is undone if a later step fails.
+#[cfg(windows)]
+#[derive(Debug)]
+enum RegistryChange {
+    PathEnvVar(std::path::PathBuf),
+    AppPath(String),
+    FileAssociation {
+        prog_id: String,
+        extensions: Vec<String>,
+    },
+    UrlScheme(String),
+    EnvVar {
+        name: String,
+        value: String,
+    },
+    UninstallEntry,
+}
+
+/// Progress of copying the files.
+// The totals are only shown by the TUI
+#[cfg_attr(not(feature = "ui"), allow(dead_code))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CopyProgress {
+    /// Completed progress units weighted by the install config's progress weight.
+    pub current: u64,
+    /// Progress units of all the files.
+    pub total: u64,
+    /// Bytes copied.
+    pub bytes: u64,
+    /// Bytes of all the files.
+    pub total_bytes: u64,
+    /// Number of files completed.
+    pub files: usize,
+    /// Number of all the files.
+    pub total_files: usize,
+}
+
+/// Result of copying a single file.
+pub(super) struct CopiedFile {
+    /// Whether the file was hard linked instead of copied.
+    pub linked: bool,
+    /// Whether the destination was in use and is replaced on restart.
+    pub pending_reboot: bool,
 }
 
 impl Executor {
@@ -19,53 +80,232 @@ impl Executor {
         Self {
             app_id: app_id.clone(),
             plan: plan.clone(),
-            progress_callback: Box::new(|_, _| {}),
+            progress_callback: Box::new(|_| {}),
+            timer: StepTimer::default(),
+            reboot_required: false,
+            #[cfg(windows)]
+            registry_changes: Vec::new(),
         }
     }
 
     pub fn with_progress_callback<F>(mut self, progress_callback: F) -> Self
     where
-        F: FnMut(u64, u64) + 'static,
+        F: FnMut(&CopyProgress) + 'static,
     {
         self.progress_callback = Box::new(progress_callback);
         self
     }
 
+    /// Returns whether files in use will be replaced when the computer
+    /// restarts.
+    pub fn reboot_required(&self) -> bool {
+        self.reboot_required
+    }
+
+    /// Returns the recorded step durations.
+    pub fn into_timer(self) -> StepTimer {
+        self.timer
+    }
+
     pub fn run(&mut self) -> Result<(), InstallerError> {
-        let disk_manifest = self.populate_disk_manifest();
-
-        self.check_existing_manifest()?;
-        self.persist_disk_manifest(&disk_manifest)
-            .inst_context("failed to persist disk manifest")?;
-        self.copy_files()?;
-        self.add_path_env_var()
-            .inst_context("failed to add PATH environment variable")?;
-        self.add_app_path().inst_context("failed to add App Path")?;
-        self.add_uninstall_entry()
-            .inst_context("failed to add uninstall entry")?;
+        let name = format!("{} {}", self.plan.display_name, self.plan.display_version);
+        self.report_event(
+            EventLogEvent::InstallStarted,
+            &format!("Installing {}.", name),
+        );
+
+        let mut timer = std::mem::take(&mut self.timer);
+        let result = self.run_impl(&mut timer);
+        self.timer = timer;
+
+        match &result {
+            Ok(()) => self.report_event(
+                EventLogEvent::InstallSucceeded,
+                &format!("Installed {}.", name),
+            ),
+            Err(error) => {
+                self.undo_registry_changes();
+                self.report_event(
+                    EventLogEvent::InstallFailed,
+                    &format!("Installation of {} failed: {}", name, error),
+                )
+            }
+        }
+
+        result
+    }
+
+    fn report_event(&self, event: EventLogEvent, message: &str) {
+        let source = if self.plan.display_name.is_empty() {
+            self.app_id.namespaced_id()
+        } else {
+            &self.plan.display_name
+        };
+
+        crate::os::report_event(self.plan.access_scope, source, event, message);
+    }
+
+    fn run_impl(&mut self, timer: &mut StepTimer) -> Result<(), InstallerError> {
+        timer.time(InstallStep::Manifest, || {
+            let disk_manifest = self.populate_disk_manifest();
+
+            self.check_existing_manifest()?;
+            self.write_journal(&disk_manifest)?;
+            self.persist_disk_manifest(&disk_manifest)
+                .inst_context("failed to persist disk manifest")
+        })?;
+        timer.time(InstallStep::Files, || {
+            self.copy_files()?;
+            self.write_generated_files()
+        })?;
+        timer.time(InstallStep::SearchPath, || {
+            self.add_path_env_var()
+                .inst_context("failed to add PATH environment variable")?;
+            self.add_symlinks().inst_context("failed to add symlinks")?;
+            self.add_execution_aliases()
+                .inst_context("failed to add execution aliases")?;
+            self.add_app_path().inst_context("failed to add App Path")
+        })?;
+        timer.time(InstallStep::Shortcuts, || {
+            self.add_shortcuts().inst_context("failed to add shortcuts")
+        })?;
+        timer.time(InstallStep::Associations, || {
+            self.update_desktop_database();
+            self.add_file_associations()
+                .inst_context("failed to add file associations")?;
+            self.add_url_schemes()
+                .inst_context("failed to add URL schemes")
+        })?;
+        timer.time(InstallStep::ScheduledTasks, || {
+            self.add_scheduled_tasks()
+                .inst_context("failed to add scheduled tasks")
+        })?;
+        timer.time(InstallStep::EnvVars, || {
+            self.add_env_vars()
+                .inst_context("failed to add environment variables")
+        })?;
+        timer.time(InstallStep::UninstallEntry, || {
+            self.add_uninstall_entry()
+                .inst_context("failed to add uninstall entry")
+        })?;
+
+        crate::manifest::remove_kept_user_data(&self.plan.manifest_path, &self.app_id)?;
+        crate::manifest::remove_journal(&self.plan.manifest_path, &self.app_id)?;
 
         Ok(())
     }
 
     fn populate_disk_manifest(&self) -> DiskManifest {
         let mut disk_manifest = DiskManifest {
-            manifest_version: 0,
+            manifest_version: crate::manifest::DISK_MANIFEST_VERSION,
             manifest_path: Default::default(),
             app_id: self.app_id.clone(),
             app_name: self.plan.display_name.clone(),
             app_version: self.plan.display_version.clone(),
+            version: self.plan.version.clone(),
             access_scope: self.plan.access_scope,
             app_paths: DiskPaths {
                 prefix: self.plan.destination.clone(),
+                program_files_dir: self.plan.program_files_dir.clone(),
                 ..Default::default()
             },
             dirs: Default::default(),
             files: Default::default(),
             search_path: self.plan.search_path.clone(),
+            estimated_data_size: self.plan.estimated_data_size,
+            scheduled_tasks: self
+                .plan
+                .scheduled_tasks
+                .iter()
+                .map(|task| task.id.clone())
+                .collect(),
+            env_vars: self.plan.env_vars.clone(),
+            installed_by: self.plan.installed_by.clone(),
+            installed_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .ok()
+                .map(|duration| duration.as_secs()),
+            installer_version: env!("CARGO_PKG_VERSION").to_string(),
+            install_options: Some(self.plan.install_options.clone()),
+            main_executable_architectures: self.plan.main_executable_architectures.clone(),
+            custom_values: self.plan.custom_values.clone(),
+            #[cfg(windows)]
+            app_path_exe_name: self
+                .plan
+                .app_paths
+                .iter()
+                .find(|item| item.is_main_executable)
+                .map(|item| item.exe_name.clone()),
+            #[cfg(windows)]
+            command_app_path_exe_names: self
+                .plan
+                .app_paths
+                .iter()
+                .filter(|item| !item.is_main_executable)
+                .map(|item| item.exe_name.clone())
+                .collect(),
             #[cfg(windows)]
-            app_path_exe_name: self.plan.app_path.clone().map(|item| item.exe_name),
+            shortcuts: self
+                .plan
+                .shortcuts
+                .iter()
+                .map(|item| item.path.clone())
+                .collect(),
+            #[cfg(windows)]
+            file_associations: self
+                .plan
+                .file_associations
+                .iter()
+                .map(|item| crate::manifest::DiskFileAssociation {
+                    prog_id: item.prog_id.clone(),
+                    extensions: item.extensions.clone(),
+                })
+                .collect(),
+            #[cfg(windows)]
+            url_schemes: self
+                .plan
+                .url_schemes
+                .iter()
+                .map(|item| item.scheme.clone())
+                .collect(),
+            #[cfg(windows)]
+            powershell_profile_paths: self.plan.powershell_profile_paths.clone(),
+            #[cfg(windows)]
+            shims_dir: self.plan.shims_dir.clone(),
+            #[cfg(windows)]
+            execution_aliases: self
+                .plan
+                .execution_aliases
+                .iter()
+                .map(|item| item.link_path.clone())
+                .collect(),
+            #[cfg(windows)]
+            interactive_uninstall_args: self.plan.interactive_uninstall_args.clone(),
+            // The entry is not added without the interactive arguments
+            #[cfg(windows)]
+            quiet_uninstall_args: if self.plan.interactive_uninstall_args.is_empty() {
+                Vec::new()
+            } else {
+                self.plan.quiet_uninstall_args.clone()
+            },
+            #[cfg(windows)]
+            modify_args: if self.plan.interactive_uninstall_args.is_empty() {
+                Vec::new()
+            } else {
+                self.plan.modify_args.clone()
+            },
             #[cfg(unix)]
             shell_profile_path: self.plan.shell_profile_path.clone(),
+            #[cfg(unix)]
+            symlinks: self
+                .plan
+                .symlinks
+                .iter()
+                .map(|item| item.link_path.clone())
+                .collect(),
+            #[cfg(unix)]
+            desktop_data_dir: self.plan.desktop_data_dir.clone(),
+            ..Default::default()
         };
 
         for entry in &self.plan.dirs {
@@ -93,6 +333,18 @@ impl Executor {
                 crc32c: entry.crc32c,
                 file_type: entry.file_type,
                 is_main_executable: entry.is_main_executable,
+                digest: entry.digest.clone(),
+            });
+        }
+
+        for entry in &self.plan.generated_files {
+            disk_manifest.files.push(DiskFileEntry {
+                path: entry.destination_path.clone(),
+                len: entry.len,
+                crc32c: entry.crc32c,
+                file_type: FileType::Data,
+                is_main_executable: false,
+                digest: entry.digest.clone(),
             });
         }
 
@@ -107,23 +359,44 @@ impl Executor {
         }
     }
 
+    /// Records the installation as in progress until all steps complete.
+    ///
+    /// The journal is left if the installation fails so it can be resumed
+    /// or rolled back.
+    fn write_journal(&self, disk_manifest: &DiskManifest) -> Result<(), InstallerError> {
+        if let Some(parent) = self.plan.manifest_path.parent() {
+            crate::manifest::create_manifest_dir(parent, self.plan.access_scope)?;
+        }
+
+        let disk_manifest = DiskManifest {
+            manifest_path: self.plan.manifest_path.clone(),
+            ..disk_manifest.clone()
+        };
+
+        crate::manifest::write_journal(JournalOperation::Install, &disk_manifest)
+            .inst_context("failed to write journal")
+    }
+
     fn persist_disk_manifest(&self, disk_manifest: &DiskManifest) -> Result<(), InstallerError> {
         tracing::debug!("persist disk manifest");
 
-        let mut manifest_temp_file = tempfile::NamedTempFile::new()?;
-        disk_manifest.to_writer(&mut manifest_temp_file)?;
-        manifest_temp_file.flush()?;
+        let mut contents = Vec::new();
+        disk_manifest.to_writer(&mut contents)?;
 
-        let manifest_checksum = crate::os::file_checksum(manifest_temp_file.path())?;
+        if let Some(parent) = self.plan.manifest_path.parent() {
+            crate::manifest::create_manifest_dir(parent, self.plan.access_scope)?;
+        }
 
-        self.copy_file(
-            manifest_temp_file.path(),
-            &manifest_checksum,
-            &self.plan.manifest_path,
-        )?;
+        // Another installer may be racing us for the same application
+        match crate::os::write_file_atomic(&self.plan.manifest_path, &contents, false) {
+            Ok(_) => {}
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Err(InstallerErrorKind::AlreadyInstalled.into());
+            }
+            Err(error) => return Err(error.into()),
+        }
         #[cfg(unix)]
         {
-            use crate::error::AddContext;
             let mode = crate::os::unix::get_effective_posix_permission(FileType::Data);
             crate::os::unix::set_posix_permission(&self.plan.manifest_path, mode)
                 .with_context("failed to set disk manifest file permissions")?;
@@ -133,71 +406,252 @@ impl Executor {
     }
 
     fn copy_files(&mut self) -> Result<(), InstallerError> {
-        let mut current = 0;
-        let total = self.plan.total_file_size();
+        let mut progress = CopyProgress {
+            total: self.plan.total_progress(),
+            total_bytes: self.plan.total_file_size(),
+            total_files: self.plan.files.len(),
+            ..Default::default()
+        };
+        let progress_weight = self.plan.progress_weight;
+        let progress_callback = &mut self.progress_callback;
+        let mut reboot_required = false;
 
         for entry in &self.plan.files {
             let span =
                 tracing::debug_span!("executor file entry", source_path = ?entry.source_path);
             let _guard = span.enter();
 
+            if crate::os::interrupt::is_interrupt_requested() {
+                return Err(InstallerError::new(InstallerErrorKind::InterruptedByUser)
+                    .with_context("installation cancelled while copying files"));
+            }
+
+            if self.plan.kept_files.contains(&entry.destination_path) {
+                tracing::info!(destination = ?entry.destination_path, "keeping user's file");
+                progress.current += progress_weight.cost(entry.len);
+                progress.bytes += entry.len;
+                progress.files += 1;
+                progress_callback(&progress);
+                continue;
+            }
+
             let checksum = FileChecksum {
                 crc32c: entry.crc32c,
                 len: entry.len,
+                digest: entry.digest.clone(),
             };
-            self.copy_file(&entry.source_path, &checksum, &entry.destination_path)
-                .inst_contextc(|| {
+            let previous_checksum = self
+                .plan
+                .replaceable_files
+                .iter()
+                .find(|(path, _)| path == &entry.destination_path)
+                .map(|(_, checksum)| checksum);
+            let copied = Self::copy_file(
+                &entry.source_path,
+                &checksum,
+                previous_checksum,
+                &entry.destination_path,
+                self.plan.hardlink_files,
+                &mut |file_current| {
+                    progress_callback(&CopyProgress {
+                        current: progress.current + file_current,
+                        bytes: progress.bytes + file_current,
+                        ..progress
+                    })
+                },
+            )
+            .inst_contextc(|| {
+                format!(
+                    "failed to copy file {:?} {:?}",
+                    entry.source_path, entry.destination_path
+                )
+            })?;
+
+            reboot_required |= copied.pending_reboot;
+
+            // Linked files share permissions with the source file
+            if !copied.linked {
+                Self::apply_posix_permission(entry).inst_contextc(|| {
                     format!(
-                        "failed to copy file {:?} {:?}",
-                        entry.source_path, entry.destination_path
+                        "failed to set file permissions {:?}",
+                        entry.destination_path
                     )
                 })?;
-            self.apply_posix_permission(entry).inst_contextc(|| {
-                format!(
-                    "failed to set file permissions {:?}",
-                    entry.destination_path
+            }
+
+            progress.current += progress_weight.cost(entry.len);
+            progress.bytes += entry.len;
+            progress.files += 1;
+            progress_callback(&progress);
+        }
+
+        self.reboot_required |= reboot_required;
+
+        Ok(())
+    }
+
+    fn write_generated_files(&self) -> Result<(), InstallerError> {
+        for entry in &self.plan.generated_files {
+            let checksum = entry.checksum();
+
+            if Self::is_existing_destination(&checksum, None, &entry.destination_path)? {
+                continue;
+            }
+
+            tracing::info!(destination = ?entry.destination_path, "writing generated file");
+
+            if let Some(parent) = entry.destination_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            crate::os::retry_sharing_violation(|| {
+                Ok(
+                    crate::os::write_file_atomic(&entry.destination_path, &entry.contents, true)
+                        .with_contextc(|_e| {
+                            format!("failed to write file {:?}", entry.destination_path)
+                        })?,
                 )
             })?;
 
-            current += entry.len;
-            (self.progress_callback)(current, total);
+            #[cfg(unix)]
+            {
+                let mode = crate::os::unix::get_effective_posix_permission(FileType::Data);
+                crate::os::unix::set_posix_permission(&entry.destination_path, mode)?;
+            }
         }
 
         Ok(())
     }
 
-    fn copy_file(
-        &self,
-        source: &Path,
+    fn update_desktop_database(&self) {
+        #[cfg(unix)]
+        if let Some(dir) = &self.plan.desktop_data_dir {
+            tracing::info!(?dir, "updating desktop database");
+            crate::os::unix::update_desktop_database(dir);
+        }
+    }
+
+    /// Returns whether the destination already contains the file.
+    ///
+    /// Returns an error if the destination contains a different file
+    /// that isn't the previous version of the file.
+    fn is_existing_destination(
         source_checksum: &FileChecksum,
+        previous_checksum: Option<&FileChecksum>,
         destination: &Path,
-    ) -> Result<(), InstallerError> {
+    ) -> Result<bool, InstallerError> {
         if destination.exists() {
             let checksum = crate::os::file_checksum(destination)?;
 
             if source_checksum == &checksum {
                 tracing::info!(?destination, "destination file already exists");
 
-                return Ok(());
+                Ok(true)
+            } else if previous_checksum == Some(&checksum) {
+                tracing::info!(?destination, "replacing previous version of file");
+
+                Ok(false)
             } else {
                 tracing::error!(?destination, "unknown file in destination");
-                return Err(InstallerErrorKind::UnknownFileInDestination.into());
+                Err(InstallerErrorKind::UnknownFileInDestination.into())
             }
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Copies or hard links the file and returns whether it was linked.
+    pub(super) fn copy_file(
+        source: &Path,
+        source_checksum: &FileChecksum,
+        previous_checksum: Option<&FileChecksum>,
+        destination: &Path,
+        hardlink: bool,
+        progress_callback: &mut dyn FnMut(u64),
+    ) -> Result<CopiedFile, InstallerError> {
+        let source = &crate::os::extended_length_path(source);
+        let destination = &crate::os::extended_length_path(destination);
+
+        if Self::is_existing_destination(source_checksum, previous_checksum, destination)? {
+            return Ok(CopiedFile {
+                linked: false,
+                pending_reboot: false,
+            });
         }
 
-        tracing::info!(?source, ?destination, "copying file");
+        tracing::info!(?source, ?destination, hardlink, "copying file");
 
         if let Some(parent) = destination.parent() {
             tracing::debug!(dir = ?parent, "creating directories");
             std::fs::create_dir_all(parent)?;
         }
 
-        std::fs::copy(source, destination)?;
+        if destination.exists() {
+            // Copy beside the file and rename over it because the previous
+            // version may be running.
+            let mut temp_name = destination.file_name().unwrap_or_default().to_os_string();
+            temp_name.push(".takecrate-tmp");
+            let temp_path = destination.with_file_name(temp_name);
+
+            // A leftover file may be a link to the source which must not be
+            // truncated.
+            if temp_path.exists() {
+                std::fs::remove_file(&temp_path)?;
+            }
 
-        Ok(())
+            let linked = crate::os::retry_sharing_violation(|| {
+                Ok(link_or_copy_file(
+                    source,
+                    &temp_path,
+                    hardlink,
+                    progress_callback,
+                )?)
+            })?;
+            let result = crate::os::retry_sharing_violation(|| {
+                Ok(std::fs::rename(&temp_path, destination)?)
+            });
+
+            match result {
+                Ok(()) => Ok(CopiedFile {
+                    linked,
+                    pending_reboot: false,
+                }),
+                // The existing file is still running, so it is replaced on restart.
+                #[cfg(windows)]
+                Err(error) if crate::os::is_file_in_use(&error) => {
+                    tracing::warn!(?destination, "file in use, replacing it on restart");
+
+                    crate::os::windows::move_file_on_reboot(&temp_path, Some(destination))
+                        .map_err(|move_error| {
+                            tracing::warn!(?move_error, "could not schedule file replacement");
+                            error
+                        })?;
+
+                    Ok(CopiedFile {
+                        linked,
+                        pending_reboot: true,
+                    })
+                }
+                Err(error) => Err(error),
+            }
+        } else {
+            let linked = crate::os::retry_sharing_violation(|| {
+                Ok(link_or_copy_file(
+                    source,
+                    destination,
+                    hardlink,
+                    progress_callback,
+                )?)
+            })?;
+
+            Ok(CopiedFile {
+                linked,
+                pending_reboot: false,
+            })
+        }
     }
 
-    fn apply_posix_permission(&self, entry: &PlanFileEntry) -> Result<(), InstallerError> {
+    fn apply_posix_permission(entry: &PlanFileEntry) -> Result<(), InstallerError> {
         #[cfg(unix)]
         {
             let mode = entry.posix_permissions;
@@ -211,11 +665,56 @@ impl Executor {
         Ok(())
     }
 
-    fn add_path_env_var(&self) -> Result<(), InstallerError> {
+    fn add_path_env_var(&mut self) -> Result<(), InstallerError> {
         #[cfg(windows)]
         if let Some(part) = &self.plan.search_path {
-            tracing::info!(?part, "modifying Path environment variable");
-            crate::os::windows::add_path_env_var(self.plan.access_scope, part.as_os_str())?;
+            if self.plan.powershell_profile_paths.is_empty() {
+                // Directories added by the user are kept when undoing
+                let existing = crate::os::windows::is_in_path_env_var(
+                    self.plan.access_scope,
+                    part.as_os_str(),
+                )
+                .unwrap_or_default();
+
+                tracing::info!(?part, "modifying Path environment variable");
+                crate::os::retry_sharing_violation(|| {
+                    Ok(crate::os::windows::add_path_env_var(
+                        self.plan.access_scope,
+                        part.as_os_str(),
+                    )?)
+                })?;
+
+                if !existing {
+                    self.registry_changes
+                        .push(RegistryChange::PathEnvVar(part.clone()));
+                }
+            }
+
+            for profile in &self.plan.powershell_profile_paths {
+                tracing::info!(?part, ?profile, "modifying PowerShell profile");
+                crate::os::retry_sharing_violation(|| {
+                    Ok(crate::os::windows::add_powershell_profile_path(
+                        profile,
+                        part.as_os_str(),
+                    )?)
+                })?;
+            }
+        }
+
+        // The shims directory is shared, so it is only added once
+        #[cfg(windows)]
+        if let Some(dir) = &self.plan.shims_dir {
+            if !crate::os::windows::is_in_path_env_var(self.plan.access_scope, dir.as_os_str())? {
+                tracing::info!(?dir, "adding shims directory to Path environment variable");
+                crate::os::retry_sharing_violation(|| {
+                    Ok(crate::os::windows::add_path_env_var(
+                        self.plan.access_scope,
+                        dir.as_os_str(),
+                    )?)
+                })?;
+                self.registry_changes
+                    .push(RegistryChange::PathEnvVar(dir.clone()));
+            }
         }
 
         #[cfg(unix)]
@@ -233,23 +732,162 @@ impl Executor {
         Ok(())
     }
 
-    fn add_app_path(&self) -> Result<(), InstallerError> {
+    fn add_symlinks(&self) -> Result<(), InstallerError> {
+        #[cfg(unix)]
+        for symlink in &self.plan.symlinks {
+            tracing::info!(link_path = ?symlink.link_path, "adding symlink to executable");
+            crate::os::unix::add_symlink(&symlink.link_path, &symlink.target_path)?;
+        }
+
+        Ok(())
+    }
+
+    fn add_execution_aliases(&self) -> Result<(), InstallerError> {
+        #[cfg(windows)]
+        for alias in &self.plan.execution_aliases {
+            tracing::info!(link_path = ?alias.link_path, "adding execution alias");
+            crate::os::retry_sharing_violation(|| {
+                Ok(crate::os::windows::add_execution_alias(
+                    &alias.link_path,
+                    &alias.target_path,
+                )?)
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn add_app_path(&mut self) -> Result<(), InstallerError> {
         #[cfg(windows)]
-        if let Some(app_path) = &self.plan.app_path {
+        for app_path in &self.plan.app_paths {
             tracing::info!(name = ?app_path.exe_name, "modifying App Paths");
             let config = crate::os::windows::AppPathConfig::default();
-            crate::os::windows::add_app_path(
-                self.plan.access_scope,
-                &app_path.exe_name,
-                app_path.exe_path.as_os_str(),
-                &config,
-            )?;
+            crate::os::retry_sharing_violation(|| {
+                Ok(crate::os::windows::add_app_path(
+                    self.plan.access_scope,
+                    &app_path.exe_name,
+                    app_path.exe_path.as_os_str(),
+                    &config,
+                )?)
+            })?;
+            self.registry_changes
+                .push(RegistryChange::AppPath(app_path.exe_name.clone()));
+        }
+
+        Ok(())
+    }
+
+    fn add_shortcuts(&self) -> Result<(), InstallerError> {
+        #[cfg(windows)]
+        for shortcut in &self.plan.shortcuts {
+            tracing::info!(path = ?shortcut.path, "adding shortcut");
+
+            if let Some(parent) = shortcut.path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            crate::os::retry_sharing_violation(|| {
+                Ok(crate::os::windows::create_shortcut(
+                    &shortcut.path,
+                    &shortcut.target_path,
+                    &self.plan.display_name,
+                )?)
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn add_file_associations(&mut self) -> Result<(), InstallerError> {
+        #[cfg(windows)]
+        for association in &self.plan.file_associations {
+            tracing::info!(prog_id = association.prog_id, "adding file association");
+
+            crate::os::retry_sharing_violation(|| {
+                Ok(crate::os::windows::add_file_association(
+                    self.plan.access_scope,
+                    association,
+                )?)
+            })?;
+            self.registry_changes.push(RegistryChange::FileAssociation {
+                prog_id: association.prog_id.clone(),
+                extensions: association.extensions.clone(),
+            });
         }
 
         Ok(())
     }
 
-    fn add_uninstall_entry(&self) -> Result<(), InstallerError> {
+    fn add_url_schemes(&mut self) -> Result<(), InstallerError> {
+        #[cfg(windows)]
+        for url_scheme in &self.plan.url_schemes {
+            tracing::info!(scheme = url_scheme.scheme, "adding URL scheme");
+
+            crate::os::retry_sharing_violation(|| {
+                Ok(crate::os::windows::add_url_scheme(
+                    self.plan.access_scope,
+                    url_scheme,
+                )?)
+            })?;
+            self.registry_changes
+                .push(RegistryChange::UrlScheme(url_scheme.scheme.clone()));
+        }
+
+        Ok(())
+    }
+
+    fn add_env_vars(&mut self) -> Result<(), InstallerError> {
+        #[cfg(windows)]
+        for env_var in &self.plan.env_vars {
+            tracing::info!(name = env_var.name, "adding environment variable");
+
+            crate::os::retry_sharing_violation(|| {
+                Ok(crate::os::windows::add_env_var(
+                    self.plan.access_scope,
+                    &env_var.name,
+                    &env_var.value,
+                )?)
+            })?;
+            self.registry_changes.push(RegistryChange::EnvVar {
+                name: env_var.name.clone(),
+                value: env_var.value.clone(),
+            });
+        }
+
+        // System scope uses a generated script instead
+        #[cfg(unix)]
+        if !self.plan.env_vars.is_empty() && self.plan.access_scope == crate::os::AccessScope::User
+        {
+            if let Some(profile) = &self.plan.shell_profile_path {
+                tracing::info!(?profile, "adding environment variables");
+
+                let env_vars = self
+                    .plan
+                    .env_vars
+                    .iter()
+                    .map(|item| (item.name.clone(), item.value.clone()))
+                    .collect::<Vec<_>>();
+                crate::os::unix::add_env_vars(profile, self.app_id.namespaced_id(), &env_vars)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_scheduled_tasks(&self) -> Result<(), InstallerError> {
+        for task in &self.plan.scheduled_tasks {
+            tracing::info!(id = task.id, "adding scheduled task");
+
+            #[cfg(unix)]
+            crate::os::unix::add_scheduled_task(self.plan.access_scope, task)?;
+            #[cfg(windows)]
+            crate::os::windows::add_scheduled_task(self.plan.access_scope, task)?;
+        }
+
+        Ok(())
+    }
+
+    fn add_uninstall_entry(&mut self) -> Result<(), InstallerError> {
         #[cfg(windows)]
         {
             if self.plan.interactive_uninstall_args.is_empty() {
@@ -262,20 +900,33 @@ impl Executor {
 
                 let config = crate::os::windows::UninstallEntryConfig {
                     manifest_path: self.plan.manifest_path.clone(),
-                    display_name: self.plan.display_name.clone(),
+                    display_name: self.plan.localized_display_name.clone(),
                     display_version: self.plan.display_version.clone(),
-                    publisher: String::new(),
-                    estimated_size: self.plan.total_file_size(),
+                    publisher: self.plan.publisher.clone(),
+                    homepage_url: self.plan.homepage_url.clone(),
+                    comments: self.plan.localized_description.clone(),
+                    display_icon: self
+                        .plan
+                        .icon_path
+                        .clone()
+                        .unwrap_or_else(|| entry.destination_path.clone())
+                        .into_os_string(),
+                    estimated_size: self.plan.estimated_size(),
                     quiet_exe_args: self.plan.quiet_uninstall_args.clone(),
+                    modify_exe_args: self.plan.modify_args.clone(),
+                    repairable: self.plan.repairable,
                 };
 
-                crate::os::windows::add_uninstall_entry(
-                    self.plan.access_scope,
-                    &self.app_id,
-                    entry.destination_path.as_os_str(),
-                    &self.plan.interactive_uninstall_args,
-                    &config,
-                )?;
+                crate::os::retry_sharing_violation(|| {
+                    Ok(crate::os::windows::add_uninstall_entry(
+                        self.plan.access_scope,
+                        &self.app_id,
+                        entry.destination_path.as_os_str(),
+                        &self.plan.interactive_uninstall_args,
+                        &config,
+                    )?)
+                })?;
+                self.registry_changes.push(RegistryChange::UninstallEntry);
             }
         }
         #[cfg(unix)]
@@ -285,4 +936,121 @@ impl Executor {
 
         Ok(())
     }
+
+    /// Removes the registry changes made so far in reverse order so a failed
+    /// installation doesn't leave entries pointing to missing files.
+    ///
+    /// Errors are logged because the installation error is reported instead.
+    fn undo_registry_changes(&mut self) {
+        #[cfg(windows)]
+        while let Some(change) = self.registry_changes.pop() {
+            tracing::info!(?change, "undoing registry change");
+
+            let access_scope = self.plan.access_scope;
+            let result = match &change {
+                RegistryChange::PathEnvVar(dir) => {
+                    crate::os::windows::remove_path_env_var(access_scope, dir.as_os_str())
+                }
+                RegistryChange::AppPath(exe_name) => {
+                    crate::os::windows::remove_app_path(access_scope, exe_name)
+                }
+                RegistryChange::FileAssociation {
+                    prog_id,
+                    extensions,
+                } => crate::os::windows::remove_file_association(access_scope, prog_id, extensions),
+                RegistryChange::UrlScheme(scheme) => {
+                    crate::os::windows::remove_url_scheme(access_scope, scheme)
+                }
+                RegistryChange::EnvVar { name, value } => {
+                    crate::os::windows::remove_env_var(access_scope, name, value)
+                }
+                RegistryChange::UninstallEntry => {
+                    crate::os::windows::remove_uninstall_entry(access_scope, &self.app_id)
+                }
+            };
+
+            if let Err(error) = result {
+                tracing::warn!(?error, ?change, "could not undo registry change");
+            }
+        }
+    }
+}
+
+/// Hard links the file if requested, falling back to copying, and returns
+/// whether it was linked.
+fn link_or_copy_file(
+    source: &Path,
+    destination: &Path,
+    hardlink: bool,
+    progress_callback: &mut dyn FnMut(u64),
+) -> std::io::Result<bool> {
+    if hardlink {
+        match std::fs::hard_link(source, destination) {
+            Ok(_) => return Ok(true),
+            Err(error) => {
+                // Usually the source and destination are on different file systems
+                tracing::debug!(?error, "hard link failed, copying instead");
+            }
+        }
+    }
+
+    copy_file_contents(source, destination, progress_callback)?;
+
+    Ok(false)
+}
+
+/// Copies a file in chunks while reporting the number of bytes copied so far.
+fn copy_file_contents(
+    source: &Path,
+    destination: &Path,
+    progress_callback: &mut dyn FnMut(u64),
+) -> std::io::Result<u64> {
+    let mut source_file = File::open(source)?;
+    let mut destination_file = File::create(destination)?;
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut current = 0;
+    let mut last_reported = 0;
+
+    loop {
+        let amount = source_file.read(&mut buf)?;
+
+        if amount == 0 {
+            break;
+        }
+
+        destination_file.write_all(&buf[0..amount])?;
+        current += amount as u64;
+
+        if current - last_reported >= PROGRESS_CHUNK_SIZE {
+            progress_callback(current);
+            last_reported = current;
+        }
+    }
+
+    destination_file.flush()?;
+
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_file_contents_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source.dat");
+        let destination = dir.path().join("destination.dat");
+        let len = PROGRESS_CHUNK_SIZE * 2 + 100;
+
+        std::fs::write(&source, vec![1u8; len as usize]).unwrap();
+
+        let mut reports = Vec::new();
+        let copied =
+            copy_file_contents(&source, &destination, &mut |value| reports.push(value)).unwrap();
+
+        assert_eq!(copied, len);
+        assert_eq!(reports, [PROGRESS_CHUNK_SIZE, PROGRESS_CHUNK_SIZE * 2]);
+        assert_eq!(std::fs::metadata(&destination).unwrap().len(), len);
+    }
 }
@@ -0,0 +1,172 @@
+//! Detecting whether the binary was started as an installer.
+use std::ffi::OsStr;
+
+/// Default file name suffixes that indicate the binary is an installer.
+///
+/// Includes English and common localized words for "installer" and "setup".
+pub const DEFAULT_INSTALLER_SUFFIXES: &[&str] = &[
+    "installer",
+    "setup",
+    "installateur",
+    "installationsprogramm",
+    "installatore",
+    "instalador",
+    "instalator",
+    "installatie",
+    "установщик",
+    "インストーラー",
+    "安装程序",
+    "安裝程式",
+    "설치프로그램",
+];
+
+/// Characters that separate the application name from the suffix.
+const SEPARATORS: [char; 4] = ['.', ' ', '_', '-'];
+
+/// Checks whether the binary's file name indicates it is an installer, such
+/// as `my-app_installer.exe` or `my-app-setup`.
+///
+/// The suffix is matched case-insensitively and needs to be separated from
+/// the rest of the name by a dot, space, underscore, or hyphen. On Windows,
+/// the `.exe` extension is ignored. A copy number added by web browsers to
+/// duplicate downloads, such as `my-app_installer (1).exe`, is also ignored.
+#[derive(Debug, Clone)]
+pub struct InstallerNameMatcher {
+    suffixes: Vec<String>,
+}
+
+impl InstallerNameMatcher {
+    /// Creates a matcher with [`DEFAULT_INSTALLER_SUFFIXES`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the file name suffixes that indicate an installer.
+    pub fn with_suffixes(mut self, suffixes: &[&str]) -> Self {
+        self.suffixes = suffixes
+            .iter()
+            .map(|suffix| suffix.to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Returns whether the file name ends with one of the suffixes.
+    pub fn is_match<S: AsRef<OsStr>>(&self, file_name: S) -> bool {
+        let name = file_name.as_ref().to_string_lossy().to_lowercase();
+        let name = strip_exe_suffix(&name);
+        let name = strip_copy_number(name);
+
+        self.suffixes.iter().any(|suffix| {
+            name.strip_suffix(suffix.as_str())
+                .is_some_and(|rest| rest.ends_with(SEPARATORS))
+        })
+    }
+
+    /// Returns whether the current binary's file name is an installer name
+    /// and no command line arguments were given.
+    ///
+    /// Arguments indicate the user intends to run the application normally.
+    pub fn is_invocation(&self) -> bool {
+        // The first argument is usually the executable path or some other string
+        let args = std::env::args_os().skip(1);
+
+        // Added when the installer runs itself elevated
+        #[cfg(all(windows, feature = "ui"))]
+        let args = args.filter(|arg| !super::elevate::is_handoff_arg(arg));
+
+        if args.count() > 0 {
+            return false;
+        }
+
+        match crate::os::current_exe_name() {
+            Ok(name) => self.is_match(name),
+            Err(error) => {
+                tracing::warn!(?error, "could not get executable name");
+                false
+            }
+        }
+    }
+}
+
+impl Default for InstallerNameMatcher {
+    fn default() -> Self {
+        Self {
+            suffixes: DEFAULT_INSTALLER_SUFFIXES
+                .iter()
+                .map(|suffix| suffix.to_string())
+                .collect(),
+        }
+    }
+}
+
+fn strip_exe_suffix(name: &str) -> &str {
+    let exe_suffix = std::env::consts::EXE_SUFFIX;
+
+    if exe_suffix.is_empty() {
+        name
+    } else {
+        name.strip_suffix(exe_suffix).unwrap_or(name)
+    }
+}
+
+/// Removes a suffix like ` (1)`.
+fn strip_copy_number(name: &str) -> &str {
+    let Some(rest) = name.strip_suffix(')') else {
+        return name;
+    };
+    let Some((rest, number)) = rest.rsplit_once(" (") else {
+        return name;
+    };
+
+    if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) {
+        rest
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_match() {
+        let matcher = InstallerNameMatcher::new();
+        let exe = std::env::consts::EXE_SUFFIX;
+
+        for name in [
+            "my-app_installer",
+            "my-app-setup",
+            "my-app.installer",
+            "My App Installer",
+            "MY-APP_SETUP",
+            "my-app_installer (1)",
+            "my-app_instalador",
+            "my-app_установщик",
+            "my-app_インストーラー",
+        ] {
+            assert!(matcher.is_match(name), "{}", name);
+            assert!(matcher.is_match(format!("{}{}", name, exe)), "{}", name);
+        }
+
+        for name in [
+            "my-app",
+            "installer",
+            "setup",
+            "my-appinstaller",
+            "my-app_installer_v2",
+            "my-app_installer (a)",
+            "my-app_installer ()",
+        ] {
+            assert!(!matcher.is_match(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_is_match_custom_suffixes() {
+        let matcher = InstallerNameMatcher::new().with_suffixes(&["Bootstrap"]);
+
+        assert!(matcher.is_match("my-app-bootstrap"));
+        assert!(!matcher.is_match("my-app-installer"));
+    }
+}
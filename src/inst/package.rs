@@ -42,13 +42,63 @@ pub struct PackageManifest {
 
     /// Additional arguments passed to the binary to start the interactive uninstaller.
     ///
-    /// This may be called by the OS application settings.
+    /// This may be called by the OS application settings. Each item is a
+    /// single argument and is quoted as needed.
     pub interactive_uninstall_args: Vec<String>,
 
     /// Additional arguments passed to the binary to start the automatic uninstaller.
     ///
     /// This may be called by shell scripts.
     pub quiet_uninstall_args: Vec<String>,
+
+    /// Additional arguments passed to the binary to start the interactive
+    /// maintenance mode, such as for changing the installed components.
+    ///
+    /// If not empty, the OS application settings on Windows show a Modify
+    /// button. Each item is a single argument and is quoted as needed.
+    pub modify_args: Vec<String>,
+
+    /// Whether the OS application settings on Windows show a Repair button.
+    ///
+    /// The button starts the binary with [`Self::modify_args`], so it is
+    /// only shown if they are not empty.
+    pub repairable: bool,
+
+    /// Expected size in bytes of data written by the application after installation.
+    ///
+    /// This includes data such as caches that grow when the application is
+    /// used. It is added to the installed file size when displaying an
+    /// estimated size to the user.
+    pub estimated_data_size: u64,
+
+    /// File types to be associated with the main binary.
+    ///
+    /// Supported on Windows and on Unix systems using freedesktop.org
+    /// desktop entries (such as Linux).
+    pub file_associations: Vec<FileAssociation>,
+
+    /// URL schemes to be handled by the main binary.
+    ///
+    /// Supported on Windows and on Unix systems using freedesktop.org
+    /// desktop entries (such as Linux).
+    pub url_schemes: Vec<UrlScheme>,
+
+    /// Tasks that periodically run the main binary.
+    ///
+    /// The tasks are only registered if
+    /// [`InstallConfig::register_scheduled_tasks`](crate::inst::InstallConfig::register_scheduled_tasks)
+    /// is enabled.
+    pub scheduled_tasks: Vec<ScheduledTask>,
+
+    /// Environment variables to be set persistently.
+    ///
+    /// See [`EnvVar`] for details.
+    pub env_vars: Vec<EnvVar>,
+
+    /// Additional names for the commands.
+    ///
+    /// See [`CommandAlias`] for details.
+    pub aliases: Vec<CommandAlias>,
 }
 
 impl PackageManifest {
@@ -63,6 +113,14 @@ impl PackageManifest {
             files: Vec::new(),
             interactive_uninstall_args: Vec::new(),
             quiet_uninstall_args: Vec::new(),
+            modify_args: Vec::new(),
+            repairable: false,
+            estimated_data_size: 0,
+            file_associations: Vec::new(),
+            url_schemes: Vec::new(),
+            scheduled_tasks: Vec::new(),
+            env_vars: Vec::new(),
+            aliases: Vec::new(),
         }
     }
 
@@ -111,6 +169,62 @@ impl PackageManifest {
         self
     }
 
+    /// Sets the `modify_args` field.
+    pub fn with_modify_args(mut self, args: &[&str]) -> Self {
+        self.modify_args = args.iter().map(|arg| arg.to_string()).collect();
+
+        self
+    }
+
+    /// Sets the `repairable` field.
+    pub fn with_repairable(mut self, value: bool) -> Self {
+        self.repairable = value;
+
+        self
+    }
+
+    /// Sets the `estimated_data_size` field.
+    pub fn with_estimated_data_size(mut self, value: u64) -> Self {
+        self.estimated_data_size = value;
+
+        self
+    }
+
+    /// Adds a file association.
+    pub fn with_file_association(mut self, value: FileAssociation) -> Self {
+        self.file_associations.push(value);
+
+        self
+    }
+
+    /// Adds a URL scheme handler.
+    pub fn with_url_scheme(mut self, value: UrlScheme) -> Self {
+        self.url_schemes.push(value);
+
+        self
+    }
+
+    /// Adds a scheduled task.
+    pub fn with_scheduled_task(mut self, value: ScheduledTask) -> Self {
+        self.scheduled_tasks.push(value);
+
+        self
+    }
+
+    /// Adds an environment variable.
+    pub fn with_env_var(mut self, name: &str, value: &str) -> Self {
+        self.env_vars.push(EnvVar::new(name, value));
+
+        self
+    }
+
+    /// Adds an alias for the main binary.
+    pub fn with_alias(mut self, name: &str) -> Self {
+        self.aliases.push(CommandAlias::new(name));
+
+        self
+    }
+
     /// Adds a file entry.
     pub fn with_file_entry<P: AsRef<Path>>(
         mut self,
@@ -137,11 +251,44 @@ impl PackageManifest {
         Ok(self)
     }
 
+    /// Adds a file entry for an additional binary.
+    ///
+    /// See [`PackageFileEntry::is_command()`].
+    pub fn with_command_entry<P: AsRef<Path>>(
+        mut self,
+        package_path: P,
+    ) -> Result<Self, InstallerError> {
+        self.files.push(PackageFileEntry::new_command(
+            package_path.as_ref(),
+            package_path.as_ref(),
+        )?);
+        Ok(self)
+    }
+
+    /// Adds a file entry for an additional binary with a destination name.
+    ///
+    /// You need to append [`std::env::consts::EXE_SUFFIX`] yourself.
+    pub fn with_command_entry_renamed<P: AsRef<Path>>(
+        mut self,
+        package_path: P,
+        target_path: P,
+    ) -> Result<Self, InstallerError> {
+        self.files
+            .push(PackageFileEntry::new_command(package_path, target_path)?);
+        Ok(self)
+    }
+
     /// Returns the file entry containing the binary.
     pub fn main_executable(&self) -> Option<&PackageFileEntry> {
         self.files.iter().find(|entry| entry.is_main_executable)
     }
 
+    /// Returns the file entries of binaries run by the user, including the
+    /// main binary.
+    pub fn commands(&self) -> impl Iterator<Item = &PackageFileEntry> {
+        self.files.iter().filter(|entry| entry.is_command())
+    }
+
     /// Checks if the files can be read.
     ///
     /// This is intended for a quick test for basic errors.
@@ -160,6 +307,65 @@ impl PackageManifest {
             })?;
         }
 
+        for url_scheme in &self.url_schemes {
+            if !url_scheme.is_valid() {
+                return Err(PackageVerifyError::InvalidUrlScheme {
+                    scheme: url_scheme.scheme.clone(),
+                });
+            }
+        }
+
+        for task in &self.scheduled_tasks {
+            if !task.is_valid() {
+                return Err(PackageVerifyError::InvalidScheduledTask {
+                    name: task.name.clone(),
+                });
+            }
+        }
+
+        for env_var in &self.env_vars {
+            if !env_var.is_valid() {
+                return Err(PackageVerifyError::InvalidEnvVar {
+                    name: env_var.name.clone(),
+                });
+            }
+        }
+
+        let mut command_names = std::collections::HashSet::new();
+
+        for entry in self.commands() {
+            let name = entry.target_path().file_name().unwrap_or_default();
+
+            if !command_names.insert(name.to_string_lossy().to_string()) {
+                return Err(PackageVerifyError::DuplicateCommand {
+                    name: name.to_string_lossy().to_string(),
+                });
+            }
+        }
+
+        for alias in &self.aliases {
+            let is_known_target = match &alias.target_path {
+                Some(target_path) => self
+                    .commands()
+                    .any(|entry| entry.target_path() == target_path),
+                None => true,
+            };
+
+            if !alias.is_valid() || !is_known_target {
+                return Err(PackageVerifyError::InvalidAlias {
+                    name: alias.name.clone(),
+                });
+            }
+
+            let name = format!("{}{}", alias.name, std::env::consts::EXE_SUFFIX);
+
+            if !command_names.insert(name) {
+                return Err(PackageVerifyError::DuplicateCommand {
+                    name: alias.name.clone(),
+                });
+            }
+        }
+
         Ok(())
     }
 }
@@ -172,6 +378,7 @@ pub struct PackageFileEntry {
     target_path: PathBuf,
     file_type: FileType,
     is_main_executable: bool,
+    is_command: bool,
 }
 
 impl PackageFileEntry {
@@ -193,6 +400,19 @@ impl PackageFileEntry {
         Self::new_impl(package_path, target_path, file_type, true)
     }
 
+    /// Creates a file entry for an additional binary that is run by the user.
+    ///
+    /// See [`Self::is_command()`].
+    pub fn new_command<P: AsRef<Path>>(
+        package_path: P,
+        target_path: P,
+    ) -> Result<Self, PackagePathError> {
+        let mut entry = Self::new_impl(package_path, target_path, FileType::Executable, false)?;
+        entry.is_command = true;
+
+        Ok(entry)
+    }
+
     fn new_impl<P: AsRef<Path>>(
         package_path: P,
         target_path: P,
@@ -207,6 +427,7 @@ impl PackageFileEntry {
             target_path: target_path.as_ref().to_owned(),
             file_type,
             is_main_executable,
+            is_command: false,
         })
     }
 
@@ -240,6 +461,378 @@ impl PackageFileEntry {
     pub fn is_main_executable(&self) -> bool {
         self.is_main_executable
     }
+
+    /// Returns whether the file is a binary that is run by the user.
+    ///
+    /// Commands are made available in the search path: an App Paths entry
+    /// on Windows, or a symlink when using
+    /// [`SearchPathStrategy::Symlink`](crate::inst::SearchPathStrategy::Symlink).
+    /// The main binary is always a command.
+    pub fn is_command(&self) -> bool {
+        self.is_command || self.is_main_executable
+    }
+}
+
+/// A file type associated with the main binary.
+///
+/// On Windows, this registers a programmatic identifier (ProgID) with an
+/// icon and "open" verb, and adds it to the "Open with" list of
+/// each file extension.
+///
+/// On Unix systems using freedesktop.org specifications (such as Linux),
+/// this installs a shared-mime-info definition for the MIME type and a
+/// desktop entry that lists the binary as a handler of the MIME type.
+/// The MIME type database is updated with `update-mime-database` if
+/// it is available.
+///
+/// # Example
+///
+/// ```
+/// # use takecrate::inst::FileAssociation;
+/// let association = FileAssociation::new("Example.MyApp.Document.1")
+///     .with_extension(".myapp")
+///     .with_mime_type("application/x-myapp")
+///     .with_description("My App Document");
+/// ```
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct FileAssociation {
+    /// Programmatic identifier in the form of `Vendor.Application.Type.Version`.
+    pub prog_id: String,
+
+    /// File extensions including the leading dot.
+    pub extensions: Vec<String>,
+
+    /// MIME type such as `application/x-myapp`.
+    ///
+    /// Required for freedesktop.org systems.
+    pub mime_type: String,
+
+    /// Description of the file type shown to the user.
+    pub description: String,
+
+    /// Relative destination path of a [`FileType::Data`] file used as the icon.
+    ///
+    /// If not specified, the icon of the main binary is used.
+    pub icon_path: Option<PathBuf>,
+
+    /// Arguments passed to the binary when a file is opened.
+    ///
+    /// `%1` is replaced with the path of the file. Default is `"%1"` (quoted).
+    pub open_args: Vec<String>,
+}
+
+impl FileAssociation {
+    /// Creates a new file association with the given ProgID.
+    pub fn new(prog_id: &str) -> Self {
+        Self {
+            prog_id: prog_id.to_string(),
+            extensions: Vec::new(),
+            mime_type: String::new(),
+            description: String::new(),
+            icon_path: None,
+            open_args: vec!["\"%1\"".to_string()],
+        }
+    }
+
+    /// Adds a file extension.
+    ///
+    /// A leading dot is added if it is missing.
+    pub fn with_extension(mut self, extension: &str) -> Self {
+        if extension.starts_with('.') {
+            self.extensions.push(extension.to_string());
+        } else {
+            self.extensions.push(format!(".{}", extension));
+        }
+
+        self
+    }
+
+    /// Sets the `mime_type` field.
+    pub fn with_mime_type(mut self, mime_type: &str) -> Self {
+        self.mime_type = mime_type.to_string();
+
+        self
+    }
+
+    /// Sets the `description` field.
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+
+        self
+    }
+
+    /// Sets the `icon_path` field.
+    pub fn with_icon_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.icon_path = Some(path.as_ref().to_path_buf());
+
+        self
+    }
+
+    /// Sets the `open_args` field.
+    pub fn with_open_args(mut self, args: &[&str]) -> Self {
+        self.open_args = args.iter().map(|arg| arg.to_string()).collect();
+
+        self
+    }
+}
+
+/// Registers the main binary as the handler of a custom URL scheme.
+///
+/// On Windows, this registers the scheme as a URL protocol with an
+/// "open" verb.
+///
+/// On Unix systems using freedesktop.org specifications (such as Linux),
+/// this adds `x-scheme-handler/<scheme>` to the desktop entry of the binary.
+///
+/// Example:
+///
+/// ```
+/// # use takecrate::inst::UrlScheme;
+/// let scheme = UrlScheme::new("myapp://").with_description("My App Link");
+///
+/// assert_eq!(scheme.scheme, "myapp");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UrlScheme {
+    /// Scheme name without the colon, such as `myapp` for `myapp://`.
+    pub scheme: String,
+
+    /// Description of the URL protocol shown to the user.
+    pub description: String,
+
+    /// Arguments passed to the binary when a URL is opened.
+    ///
+    /// Only used on Windows. `%1` is replaced with the URL.
+    /// Default is `"%1"` (quoted).
+    pub open_args: Vec<String>,
+}
+
+impl UrlScheme {
+    /// Creates a new URL scheme handler for the given scheme.
+    ///
+    /// A trailing `:` or `://` is removed.
+    pub fn new(scheme: &str) -> Self {
+        let scheme = scheme.trim_end_matches('/').trim_end_matches(':');
+
+        Self {
+            scheme: scheme.to_ascii_lowercase(),
+            description: String::new(),
+            open_args: vec!["\"%1\"".to_string()],
+        }
+    }
+
+    /// Sets the `description` field.
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+
+        self
+    }
+
+    /// Sets the `open_args` field.
+    pub fn with_open_args(mut self, args: &[&str]) -> Self {
+        self.open_args = args.iter().map(|arg| arg.to_string()).collect();
+
+        self
+    }
+
+    /// Returns whether the scheme name is valid according to RFC 3986.
+    pub fn is_valid(&self) -> bool {
+        let mut chars = self.scheme.chars();
+
+        chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    }
+}
+
+/// A task that periodically runs the main binary, such as a background sync.
+///
+/// On Unix, this adds an entry to the user's crontab. For system scope,
+/// a file is placed in `/etc/cron.d` if the directory exists.
+///
+/// On Windows, this creates a task in the Task Scheduler under a `takecrate`
+/// folder. For system scope, the task runs as the SYSTEM account.
+///
+/// Example:
+///
+/// ```
+/// # use takecrate::inst::{ScheduledTask, TaskSchedule};
+/// let task = ScheduledTask::new("sync", TaskSchedule::Daily).with_args(&["sync", "--quiet"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ScheduledTask {
+    /// Name unique to the application.
+    ///
+    /// Only ASCII letters, digits, `-`, and `_` are allowed.
+    pub name: String,
+
+    /// How often the task runs.
+    pub schedule: TaskSchedule,
+
+    /// Arguments passed to the binary.
+    pub args: Vec<String>,
+}
+
+impl ScheduledTask {
+    /// Creates a new task with the given name and schedule.
+    pub fn new(name: &str, schedule: TaskSchedule) -> Self {
+        Self {
+            name: name.to_string(),
+            schedule,
+            args: Vec::new(),
+        }
+    }
+
+    /// Sets the `args` field.
+    pub fn with_args(mut self, args: &[&str]) -> Self {
+        self.args = args.iter().map(|arg| arg.to_string()).collect();
+
+        self
+    }
+
+    /// Returns whether the task name is valid.
+    pub fn is_valid(&self) -> bool {
+        !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+    }
+}
+
+/// An environment variable set persistently for the user or all users.
+///
+/// The value may contain the following placeholders which are replaced
+/// with the installed locations:
+///
+/// * `{bin_dir}`: directory of the executable files
+/// * `{data_dir}`: directory of the data files
+/// * `{exe_path}`: path of the main binary
+///
+/// On Windows, the variable is stored in the registry `Environment` key.
+///
+/// On Unix with user scope, an `export` statement is added to the shell
+/// profile selected for the search path. With system scope, a script is
+/// placed in `/etc/profile.d` if the directory exists.
+///
+/// When uninstalling, the variable is removed. On Windows, it's only removed
+/// if the value is unchanged.
+///
+/// Example:
+///
+/// ```
+/// # use takecrate::inst::EnvVar;
+/// let env_var = EnvVar::new("MYAPP_HOME", "{data_dir}");
+/// assert!(env_var.is_valid());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EnvVar {
+    /// Name of the variable.
+    ///
+    /// Only ASCII letters, digits, and `_` are allowed and it must not
+    /// start with a digit. `PATH` is not allowed.
+    pub name: String,
+
+    /// Value of the variable.
+    pub value: String,
+}
+
+impl EnvVar {
+    /// Creates a new environment variable.
+    pub fn new(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    /// Returns whether the name and value are valid.
+    pub fn is_valid(&self) -> bool {
+        let mut chars = self.name.chars();
+
+        chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !self.name.eq_ignore_ascii_case("PATH")
+            && !self.value.chars().any(|c| c.is_control())
+    }
+
+    /// Returns the value with the placeholders replaced.
+    pub fn resolve_value(&self, bin_dir: &Path, data_dir: &Path, exe_path: &Path) -> String {
+        self.value
+            .replace("{bin_dir}", &bin_dir.to_string_lossy())
+            .replace("{data_dir}", &data_dir.to_string_lossy())
+            .replace("{exe_path}", &exe_path.to_string_lossy())
+    }
+}
+
+/// An additional name for a command.
+///
+/// On Unix, the alias is a symlink beside the command. On Windows, it is a
+/// `.cmd` script beside the command and an App Paths entry.
+///
+/// Example:
+///
+/// ```
+/// # use takecrate::inst::CommandAlias;
+/// let alias = CommandAlias::new("ma");
+/// assert!(alias.is_valid());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CommandAlias {
+    /// Name of the alias without a file extension.
+    ///
+    /// Only ASCII letters, digits, `-`, `_`, and `.` are allowed and it must
+    /// start with a letter or digit.
+    pub name: String,
+
+    /// Target path of the command the alias runs.
+    ///
+    /// This is the same as [`PackageFileEntry::target_path()`]. If `None`,
+    /// it is the main binary.
+    pub target_path: Option<PathBuf>,
+}
+
+impl CommandAlias {
+    /// Creates a new alias for the main binary.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            target_path: None,
+        }
+    }
+
+    /// Sets the `target_path` field.
+    pub fn with_target_path<P: Into<PathBuf>>(mut self, value: P) -> Self {
+        self.target_path = Some(value.into());
+        self
+    }
+
+    /// Returns whether the name is valid.
+    pub fn is_valid(&self) -> bool {
+        let mut chars = self.name.chars();
+
+        chars.next().is_some_and(|c| c.is_ascii_alphanumeric())
+            && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    }
+}
+
+/// How often a [`ScheduledTask`] runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum TaskSchedule {
+    /// Once every hour.
+    Hourly,
+    /// Once every day.
+    #[default]
+    Daily,
+    /// Once every week.
+    Weekly,
 }
 
 /// Error for a invalid path to a file in a package.
@@ -288,6 +881,41 @@ pub enum PackageVerifyError {
         #[source]
         source: std::io::Error,
     },
+
+    /// Invalid URL scheme name.
+    #[error("invalid URL scheme {scheme:?}")]
+    InvalidUrlScheme {
+        /// The scheme name.
+        scheme: String,
+    },
+
+    /// Invalid scheduled task name.
+    #[error("invalid scheduled task {name:?}")]
+    InvalidScheduledTask {
+        /// The task name.
+        name: String,
+    },
+
+    /// Invalid environment variable name or value.
+    #[error("invalid environment variable {name:?}")]
+    InvalidEnvVar {
+        /// The variable name.
+        name: String,
+    },
+
+    /// Invalid alias name or target.
+    #[error("invalid alias {name:?}")]
+    InvalidAlias {
+        /// The alias name.
+        name: String,
+    },
+
+    /// More than one command has the same filename.
+    #[error("duplicate command {name:?}")]
+    DuplicateCommand {
+        /// The filename.
+        name: String,
+    },
 }
 
 impl From<PackageVerifyError> for InstallerError {
@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+
+use serde::de::DeserializeOwned;
+
+use crate::error::{InstallerError, InstallerErrorKind};
+
+/// Page added by the application to the interactive installer.
+///
+/// The pages are shown in order after the built-in options and before the
+/// confirmation. Each answer is stored under the page's ID in
+/// [`InstallConfig::custom_values`](crate::inst::InstallConfig::custom_values)
+/// and can be read back with
+/// [`Installer::custom_values()`](crate::inst::Installer::custom_values).
+///
+/// The text is shown as is, so it should already be localized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CustomPage {
+    /// Key of the answer, such as `default_editor`.
+    pub id: String,
+    /// Question or instructions shown to the user.
+    pub text: String,
+    /// How the user answers.
+    pub kind: CustomPageKind,
+    /// Answer used when the user didn't change it or when installing
+    /// non-interactively.
+    pub default_value: String,
+    /// Whether the answer is recorded in
+    /// [`DiskManifest::custom_values`](crate::manifest::DiskManifest::custom_values)
+    /// and remembered for the next run.
+    ///
+    /// Leave this disabled for secrets such as license keys.
+    pub persist: bool,
+}
+
+impl CustomPage {
+    /// Creates a page where the user selects one of the options.
+    ///
+    /// Each option is a value and the label shown for it. The first option
+    /// is the default.
+    pub fn choice(id: &str, text: &str, options: &[(&str, &str)]) -> Self {
+        Self {
+            id: id.to_string(),
+            text: text.to_string(),
+            kind: CustomPageKind::Choice(
+                options
+                    .iter()
+                    .map(|(value, label)| CustomPageOption {
+                        value: value.to_string(),
+                        label: label.to_string(),
+                    })
+                    .collect(),
+            ),
+            default_value: options
+                .first()
+                .map(|(value, _)| value.to_string())
+                .unwrap_or_default(),
+            persist: false,
+        }
+    }
+
+    /// Creates a page where the user enters text.
+    pub fn text_input(id: &str, text: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            text: text.to_string(),
+            kind: CustomPageKind::TextInput,
+            default_value: String::new(),
+            persist: false,
+        }
+    }
+
+    /// Sets the default answer.
+    pub fn with_default_value(mut self, value: &str) -> Self {
+        self.default_value = value.to_string();
+        self
+    }
+
+    /// Sets whether the answer is recorded in the disk manifest.
+    pub fn with_persist(mut self, value: bool) -> Self {
+        self.persist = value;
+        self
+    }
+}
+
+/// How the user answers a [`CustomPage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CustomPageKind {
+    /// Select one of the options.
+    Choice(Vec<CustomPageOption>),
+    /// Enter a single line of text.
+    TextInput,
+}
+
+/// Option of a [`CustomPageKind::Choice`] page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CustomPageOption {
+    /// Value stored as the answer.
+    pub value: String,
+    /// Text shown to the user.
+    pub label: String,
+}
+
+/// Adds the default answers of the pages that have no answer.
+pub(crate) fn apply_default_values(pages: &[CustomPage], values: &mut BTreeMap<String, String>) {
+    for page in pages {
+        values
+            .entry(page.id.clone())
+            .or_insert_with(|| page.default_value.clone());
+    }
+}
+
+/// Returns the answers of the pages that are persisted.
+pub(crate) fn persisted_values(
+    pages: &[CustomPage],
+    values: &BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    values
+        .iter()
+        .filter(|(id, _)| pages.iter().any(|page| page.persist && &page.id == *id))
+        .map(|(id, value)| (id.clone(), value.clone()))
+        .collect()
+}
+
+/// Deserializes the answers into a struct with a `String` field for each ID.
+pub(crate) fn deserialize_values<T: DeserializeOwned>(
+    values: &BTreeMap<String, String>,
+) -> Result<T, InstallerError> {
+    let value = serde_json::to_value(values)
+        .map_err(|error| InstallerError::new(InstallerErrorKind::InvalidData).with_source(error))?;
+
+    serde_json::from_value(value).map_err(|error| {
+        InstallerError::new(InstallerErrorKind::InvalidData)
+            .with_context("could not deserialize custom page values")
+            .with_source(error)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Answers {
+        editor: String,
+        license_key: String,
+    }
+
+    #[test]
+    fn test_values() {
+        let pages = [
+            CustomPage::choice(
+                "editor",
+                "Default editor",
+                &[("vim", "Vim"), ("nano", "nano")],
+            )
+            .with_persist(true),
+            CustomPage::text_input("license_key", "License key"),
+        ];
+        let mut values = BTreeMap::from([("license_key".to_string(), "ABC-123".to_string())]);
+
+        apply_default_values(&pages, &mut values);
+
+        let answers = deserialize_values::<Answers>(&values).unwrap();
+        assert_eq!(answers.editor, "vim");
+        assert_eq!(answers.license_key, "ABC-123");
+
+        let persisted = persisted_values(&pages, &values);
+        assert_eq!(persisted.len(), 1);
+        assert_eq!(persisted["editor"], "vim");
+
+        assert!(deserialize_values::<Answers>(&BTreeMap::new()).is_err());
+    }
+}
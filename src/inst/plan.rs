@@ -1,35 +1,122 @@
-use std::path::PathBuf;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 #[cfg(windows)]
 use std::ffi::OsString;
 
 use crate::{
-    error::{AddContext, InstallerError},
-    manifest::FileType,
-    os::AccessScope,
+    error::{AddContext, InstallerError, InstallerErrorKind},
+    manifest::{
+        AppVersion, DiskEnvVar, DiskInstallOptions, FileType, Receipt, RECEIPT_SCHEMA_VERSION,
+    },
+    os::{
+        AccessScope, FileChecksum, FileDigest, InstallerIdentity, OsCapabilities,
+        ScheduledTaskConfig,
+    },
     path::{AppPathPrefix, PathResolver},
 };
 
-use super::{InstallConfig, PackageManifest};
+use super::{InstallConfig, PackageManifest, ProgressWeight, SourceStaging};
 
 #[derive(Debug, Clone, Default)]
 pub struct InstallPlan {
     pub display_name: String,
     pub display_version: String,
+    #[cfg(windows)]
+    pub publisher: String,
+    #[cfg(windows)]
+    pub homepage_url: String,
+    /// Display name in the installer's language.
+    #[cfg(windows)]
+    pub localized_display_name: String,
+    /// Description in the installer's language.
+    #[cfg(windows)]
+    pub localized_description: String,
+    /// Installed path of the application icon.
+    pub icon_path: Option<PathBuf>,
+    pub version: Option<AppVersion>,
     pub access_scope: AccessScope,
     pub manifest_path: PathBuf,
+    /// Manifest of an existing installation that needs to be uninstalled.
+    pub existing_manifest_path: Option<PathBuf>,
+    /// Displayed version of the existing installation if it is a different
+    /// version.
+    pub existing_version: Option<String>,
+    /// Whether the existing installation is a newer version.
+    pub downgrade: bool,
     pub destination: AppPathPrefix,
     pub dirs: Vec<PlanDirEntry>,
     pub files: Vec<PlanFileEntry>,
+    pub generated_files: Vec<PlanGeneratedFile>,
+    pub scheduled_tasks: Vec<ScheduledTaskConfig>,
+    pub env_vars: Vec<DiskEnvVar>,
+    /// Whether to keep the previous main binary when uninstalling an
+    /// existing installation and overwrite it instead.
+    pub overwrite_main_executable: bool,
+    pub hardlink_files: bool,
+    /// Whether to copy the files to a temporary directory before modifying
+    /// anything.
+    pub stage_source_files: bool,
+    pub progress_weight: ProgressWeight,
+    /// Existing files with the given checksum that may be overwritten.
+    pub replaceable_files: Vec<(PathBuf, FileChecksum)>,
+    /// Existing files modified by the user that are kept instead of
+    /// overwritten.
+    pub kept_files: Vec<PathBuf>,
+    pub installed_by: Option<InstallerIdentity>,
+    pub install_options: DiskInstallOptions,
+    pub main_executable_architectures: Vec<String>,
+    /// Answers to the custom pages recorded in the disk manifest.
+    pub custom_values: BTreeMap<String, String>,
+    /// Version installed for all users that is newer than the package
+    /// being installed for the current user.
+    pub newer_system_version: Option<String>,
     pub search_path: Option<PathBuf>,
+    /// Program Files directory of the destination on Windows.
+    pub program_files_dir: Option<PathBuf>,
+    /// Whether running inside the Windows Subsystem for Linux.
+    pub wsl: bool,
+    pub estimated_data_size: u64,
+    #[cfg(windows)]
+    pub app_paths: Vec<PlanAppPath>,
+    #[cfg(windows)]
+    pub shortcuts: Vec<PlanShortcut>,
+    #[cfg(windows)]
+    pub file_associations: Vec<crate::os::windows::FileAssociationConfig>,
+    #[cfg(windows)]
+    pub url_schemes: Vec<crate::os::windows::UrlSchemeConfig>,
+    /// PowerShell profiles to modify instead of the Path environment variable.
+    #[cfg(windows)]
+    pub powershell_profile_paths: Vec<PathBuf>,
+    /// Shared directory for shims which is added to the Path environment
+    /// variable instead of the bin directory.
+    #[cfg(windows)]
+    pub shims_dir: Option<PathBuf>,
+    /// Directory of app execution aliases used instead of the Path
+    /// environment variable.
+    #[cfg(windows)]
+    pub execution_alias_dir: Option<PathBuf>,
+    /// Hard links to the commands in the app execution alias directory.
     #[cfg(windows)]
-    pub app_path: Option<PlanAppPath>,
+    pub execution_aliases: Vec<PlanExecutionAlias>,
     #[cfg(unix)]
     pub shell_profile_path: Option<PathBuf>,
+    /// Symlinks to the commands for aliases or used instead of modifying
+    /// the profile.
+    #[cfg(unix)]
+    pub symlinks: Vec<PlanSymlink>,
+    #[cfg(unix)]
+    pub desktop_data_dir: Option<PathBuf>,
+    #[cfg(windows)]
+    pub interactive_uninstall_args: Vec<String>,
     #[cfg(windows)]
-    pub interactive_uninstall_args: OsString,
+    pub quiet_uninstall_args: Vec<String>,
     #[cfg(windows)]
-    pub quiet_uninstall_args: OsString,
+    pub modify_args: Vec<String>,
+    #[cfg(windows)]
+    pub repairable: bool,
 }
 
 impl InstallPlan {
@@ -40,6 +127,20 @@ impl InstallPlan {
     pub fn total_file_size(&self) -> u64 {
         self.files.iter().map(|entry| entry.len).sum()
     }
+
+    /// Returns the progress units of copying all the files.
+    pub fn total_progress(&self) -> u64 {
+        self.files
+            .iter()
+            .map(|entry| self.progress_weight.cost(entry.len))
+            .sum()
+    }
+
+    #[cfg(any(windows, feature = "ui"))]
+    pub fn estimated_size(&self) -> u64 {
+        self.total_file_size()
+            .saturating_add(self.estimated_data_size)
+    }
 }
 
 #[cfg(windows)]
@@ -47,6 +148,21 @@ impl InstallPlan {
 pub struct PlanAppPath {
     pub exe_name: String,
     pub exe_path: PathBuf,
+    pub is_main_executable: bool,
+}
+
+#[cfg(windows)]
+#[derive(Debug, Clone, Default)]
+pub struct PlanExecutionAlias {
+    pub link_path: PathBuf,
+    pub target_path: PathBuf,
+}
+
+#[cfg(windows)]
+#[derive(Debug, Clone, Default)]
+pub struct PlanShortcut {
+    pub path: PathBuf,
+    pub target_path: PathBuf,
 }
 
 #[derive(Debug, Clone)]
@@ -64,14 +180,84 @@ pub struct PlanFileEntry {
     pub is_main_executable: bool,
     pub len: u64,
     pub crc32c: u32,
+    pub digest: Option<FileDigest>,
     #[cfg(unix)]
     pub posix_permissions: u32,
 }
 
+/// A file with contents created by the installer instead of the package.
+#[derive(Debug, Clone)]
+pub struct PlanGeneratedFile {
+    pub destination_path: PathBuf,
+    pub contents: Vec<u8>,
+    pub len: u64,
+    pub crc32c: u32,
+    pub digest: Option<FileDigest>,
+}
+
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub struct PlanSymlink {
+    pub link_path: PathBuf,
+    pub target_path: PathBuf,
+}
+
+impl PlanGeneratedFile {
+    pub fn new(destination_path: PathBuf, contents: Vec<u8>) -> Self {
+        let digest = crate::os::bytes_checksum(&contents)
+            .map(|checksum| checksum.digest)
+            .unwrap_or_else(|error| {
+                tracing::warn!(?destination_path, ?error, "could not compute digest");
+                None
+            });
+
+        Self {
+            destination_path,
+            len: contents.len() as u64,
+            crc32c: crc32c::crc32c(&contents),
+            digest,
+            contents,
+        }
+    }
+
+    pub fn checksum(&self) -> FileChecksum {
+        FileChecksum {
+            crc32c: self.crc32c,
+            len: self.len,
+            digest: self.digest.clone(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Planner {
     package_manifest: PackageManifest,
     config: InstallConfig,
+    capabilities: OsCapabilities,
+    lang_tag: String,
+}
+
+/// Returns an error if the existing binary is a universal binary and the
+/// new binary is missing some of its architectures.
+pub(super) fn check_architectures(
+    existing: &[String],
+    new: &[String],
+) -> Result<(), InstallerError> {
+    let missing = existing
+        .iter()
+        .filter(|arch| !new.contains(arch))
+        .collect::<Vec<_>>();
+
+    if existing.len() > 1 && !missing.is_empty() {
+        tracing::error!(?existing, ?new, "architecture downgrade");
+
+        return Err(
+            InstallerError::new(InstallerErrorKind::ArchitectureDowngrade)
+                .with_context(format!("new binary is missing architectures {:?}", missing)),
+        );
+    }
+
+    Ok(())
 }
 
 impl Planner {
@@ -79,44 +265,124 @@ impl Planner {
         Self {
             package_manifest: package_manifest.clone(),
             config: config.clone(),
+            capabilities: OsCapabilities::detect(),
+            lang_tag: String::new(),
         }
     }
 
+    /// Sets the BCP 47 language tag used for localized metadata written to
+    /// the OS.
+    pub fn with_lang_tag(mut self, lang_tag: &str) -> Self {
+        self.lang_tag = lang_tag.to_string();
+        self
+    }
+
     pub fn run(&mut self) -> Result<InstallPlan, InstallerError> {
+        #[cfg(unix)]
+        self.check_nixos()?;
+        self.check_read_only_system_root()?;
+
         let display_name = self.package_manifest.app_metadata.display_name.clone();
         let display_version = self.package_manifest.app_metadata.display_version.clone();
-        let manifest_path = crate::manifest::manifest_path(
+        let state_dir = self.config.state_dir.as_deref();
+        let manifest_path = crate::manifest::manifest_path_in(
+            state_dir,
             &self.package_manifest.app_id,
             self.config.access_scope,
         )?;
 
+        #[cfg(windows)]
+        let app_metadata = &self.package_manifest.app_metadata;
         let mut plan = InstallPlan {
             display_name,
             display_version,
+            #[cfg(windows)]
+            publisher: app_metadata.publisher.clone(),
+            #[cfg(windows)]
+            homepage_url: app_metadata.homepage_url.clone(),
+            #[cfg(windows)]
+            localized_display_name: app_metadata.get_display_name(&self.lang_tag).to_string(),
+            #[cfg(windows)]
+            localized_description: app_metadata.get_description(&self.lang_tag).to_string(),
+            icon_path: None,
+            version: self.package_manifest.app_metadata.comparable_version(),
             access_scope: self.config.access_scope,
             manifest_path,
+            existing_manifest_path: crate::manifest::existing_manifest_path_in(
+                state_dir,
+                &self.package_manifest.app_id,
+                self.config.access_scope,
+            )?,
+            existing_version: None,
+            downgrade: false,
             destination: self.config.destination.clone(),
             dirs: Default::default(),
             files: Default::default(),
+            generated_files: Default::default(),
+            scheduled_tasks: Default::default(),
+            env_vars: Default::default(),
+            overwrite_main_executable: self.config.overwrite_main_executable,
+            hardlink_files: self.config.hardlink_files,
+            stage_source_files: self.is_staging_required(),
+            progress_weight: self.config.progress_weight,
+            replaceable_files: Default::default(),
+            kept_files: Default::default(),
+            installed_by: self.installer_identity(),
+            install_options: self.config.install_options(),
+            main_executable_architectures: Vec::new(),
+            custom_values: BTreeMap::new(),
+            newer_system_version: None,
             search_path: None,
+            program_files_dir: None,
+            wsl: false,
+            estimated_data_size: self.package_manifest.estimated_data_size,
+            #[cfg(windows)]
+            app_paths: Vec::new(),
+            #[cfg(windows)]
+            shortcuts: Vec::new(),
+            #[cfg(windows)]
+            file_associations: Vec::new(),
             #[cfg(windows)]
-            app_path: None,
+            url_schemes: Vec::new(),
+            #[cfg(windows)]
+            powershell_profile_paths: Vec::new(),
+            #[cfg(windows)]
+            shims_dir: None,
+            #[cfg(windows)]
+            execution_alias_dir: None,
+            #[cfg(windows)]
+            execution_aliases: Vec::new(),
             #[cfg(unix)]
             shell_profile_path: None,
+            #[cfg(unix)]
+            symlinks: Vec::new(),
+            #[cfg(unix)]
+            desktop_data_dir: None,
+            #[cfg(windows)]
+            interactive_uninstall_args: self.package_manifest.interactive_uninstall_args.clone(),
+            #[cfg(windows)]
+            quiet_uninstall_args: self.package_manifest.quiet_uninstall_args.clone(),
             #[cfg(windows)]
-            interactive_uninstall_args: OsString::from(
-                self.package_manifest.interactive_uninstall_args.join(" "),
-            ),
+            modify_args: self.package_manifest.modify_args.clone(),
             #[cfg(windows)]
-            quiet_uninstall_args: OsString::from(
-                self.package_manifest.quiet_uninstall_args.join(" "),
-            ),
+            repairable: self.package_manifest.repairable,
         };
+
+        #[cfg(unix)]
+        if crate::os::unix::is_wsl() {
+            tracing::info!("running in WSL; search path changes only apply to Linux shells");
+            plan.wsl = true;
+        }
+
         let path_resolver = PathResolver::new(
             self.package_manifest.app_id.plain_id(),
             &self.config.destination,
         )?;
 
+        plan.program_files_dir = path_resolver
+            .program_files_dir()
+            .map(|dir| dir.to_path_buf());
+
         let dest_bin_dir = path_resolver.bin_dir();
         let dest_data_dir = path_resolver.data_dir();
 
@@ -124,8 +390,62 @@ impl Planner {
             plan.search_path = Some(dest_bin_dir.clone());
 
             #[cfg(unix)]
+            if self.config.search_path_strategy == super::SearchPathStrategy::Symlink {
+                // Planned with the main executable instead
+                plan.search_path = None;
+            } else if !self.plan_paths_d(&mut plan, &dest_bin_dir)? {
+                plan.shell_profile_path = self.user_shell_profile()?;
+
+                if plan.shell_profile_path.is_none() {
+                    tracing::warn!(
+                        path = ?dest_bin_dir,
+                        "shell profile is managed by Nix; add the directory to home.sessionPath instead"
+                    );
+                    plan.search_path = None;
+                }
+            }
+
+            #[cfg(windows)]
+            if self.config.search_path_strategy == super::SearchPathStrategy::PowerShellProfile {
+                match self.config.access_scope {
+                    AccessScope::User => {
+                        plan.powershell_profile_paths =
+                            crate::os::windows::powershell_profile_paths()?;
+                    }
+                    AccessScope::System => {
+                        tracing::warn!(
+                            "PowerShell profile strategy not supported for system scope"
+                        );
+                    }
+                }
+            }
+
+            #[cfg(windows)]
+            if self.config.search_path_strategy == super::SearchPathStrategy::Shims {
+                // Planned with the commands instead
+                plan.search_path = None;
+                plan.shims_dir = Some(plan.manifest_path.with_file_name("shims"));
+            }
+
+            #[cfg(windows)]
+            if self.config.search_path_strategy == super::SearchPathStrategy::ExecutionAlias {
+                self.plan_execution_alias_dir(&mut plan)?;
+            }
+
+            #[cfg(windows)]
+            if plan.search_path.is_some()
+                && plan.powershell_profile_paths.is_empty()
+                && !crate::os::windows::path_env_var_has_room(
+                    self.config.access_scope,
+                    dest_bin_dir.as_os_str(),
+                )
             {
-                plan.shell_profile_path = Some(crate::os::unix::get_current_shell_profile()?);
+                // Commands are still found using App Paths
+                tracing::warn!(
+                    path = ?dest_bin_dir,
+                    "Path environment variable too long; not adding directory"
+                );
+                plan.search_path = None;
             }
         }
 
@@ -141,6 +461,8 @@ impl Planner {
             content_file_type: Some(FileType::Data),
         });
 
+        plan.icon_path = self.icon_destination_path(&dest_bin_dir, &dest_data_dir);
+
         for entry in &self.package_manifest.files {
             let span =
                 tracing::debug_span!("planner file entry", package_path = ?entry.package_path());
@@ -172,12 +494,20 @@ impl Planner {
                 is_main_executable: entry.is_main_executable(),
                 len: checksum.len,
                 crc32c: checksum.crc32c,
+                digest: checksum.digest,
                 #[cfg(unix)]
                 posix_permissions,
             });
 
             #[cfg(windows)]
-            if entry.is_main_executable() && self.config.modify_os_search_path {
+            if entry.is_command() {
+                let name = destination_path.file_stem().unwrap_or_default();
+                self.plan_shim(&mut plan, &name.to_string_lossy(), &destination_path)?;
+                self.plan_execution_alias(&mut plan, &name.to_string_lossy(), &destination_path)?;
+            }
+
+            #[cfg(windows)]
+            if entry.is_command() && self.config.modify_os_search_path {
                 let exe_name = entry
                     .target_path()
                     .file_name()
@@ -185,13 +515,912 @@ impl Planner {
                     .to_string_lossy()
                     .to_string();
 
-                plan.app_path = Some(PlanAppPath {
+                plan.app_paths.push(PlanAppPath {
                     exe_name,
                     exe_path: destination_path.clone(),
+                    is_main_executable: entry.is_main_executable(),
                 });
             }
+
+            if entry.is_main_executable() && self.config.register_scheduled_tasks {
+                self.plan_scheduled_tasks(&mut plan, &destination_path)?;
+            }
+
+            #[cfg(windows)]
+            if entry.is_main_executable() {
+                self.plan_shortcuts(&mut plan, &destination_path)?;
+                self.plan_file_associations(&mut plan, &destination_path, &dest_data_dir);
+                self.plan_url_schemes(&mut plan, &destination_path);
+            }
+
+            #[cfg(unix)]
+            if entry.is_command()
+                && self.config.modify_os_search_path
+                && self.config.search_path_strategy == super::SearchPathStrategy::Symlink
+            {
+                let name = destination_path.file_name().unwrap_or_default();
+                self.plan_search_path_symlink(&mut plan, name, &destination_path)?;
+            }
+
+            #[cfg(unix)]
+            if entry.is_main_executable() {
+                self.plan_desktop_entry(&mut plan, &destination_path, &path_resolver);
+            }
+
+            if entry.is_main_executable() && cfg!(target_os = "macos") {
+                plan.main_executable_architectures =
+                    crate::os::executable_architectures(&plan.files.last().unwrap().source_path)
+                        .with_contextc(|_| {
+                            format!("could not read file {:?}", entry.package_path())
+                        })?;
+            }
+
+            if entry.is_main_executable() {
+                self.plan_env_vars(&mut plan, &dest_bin_dir, &dest_data_dir, &destination_path)?;
+                self.plan_receipt(&mut plan, &dest_bin_dir, &dest_data_dir, &destination_path)?;
+            }
         }
 
+        self.plan_aliases(&mut plan, &dest_bin_dir)?;
+        self.plan_kept_user_data(&mut plan)?;
+        self.plan_modified_user_files(&mut plan)?;
+        self.check_architecture_downgrade(&plan)?;
+        self.check_downgrade(&mut plan)?;
+        self.check_newer_system_install(&mut plan)?;
+
         Ok(plan)
     }
+
+    fn installer_identity(&self) -> Option<InstallerIdentity> {
+        if self.config.access_scope == AccessScope::System && !self.config.omit_installer_identity {
+            Some(crate::os::installer_identity())
+        } else {
+            None
+        }
+    }
+
+    /// Refuses to replace a universal binary with one missing some of its
+    /// architectures unless allowed.
+    fn check_architecture_downgrade(&self, plan: &InstallPlan) -> Result<(), InstallerError> {
+        let Some(existing_manifest_path) = &plan.existing_manifest_path else {
+            return Ok(());
+        };
+
+        if self.config.allow_architecture_downgrade {
+            return Ok(());
+        }
+
+        let existing = match crate::manifest::DiskManifest::load(existing_manifest_path) {
+            Ok(manifest) => manifest.main_executable_architectures,
+            Err(error) => {
+                tracing::warn!(
+                    ?error,
+                    "could not read architectures of existing installation"
+                );
+                return Ok(());
+            }
+        };
+
+        check_architectures(&existing, &plan.main_executable_architectures)
+    }
+
+    /// Refuses to replace an existing installation with an older version
+    /// unless allowed.
+    fn check_downgrade(&self, plan: &mut InstallPlan) -> Result<(), InstallerError> {
+        let Some(existing_manifest_path) = &plan.existing_manifest_path else {
+            return Ok(());
+        };
+
+        let existing = match crate::manifest::DiskManifest::load(existing_manifest_path) {
+            Ok(manifest) => manifest,
+            Err(error) => {
+                tracing::warn!(?error, "could not read version of existing installation");
+                return Ok(());
+            }
+        };
+
+        let (Some(existing_version), Some(version)) =
+            (existing.comparable_version(), &plan.version)
+        else {
+            return Ok(());
+        };
+
+        if existing_version == *version {
+            return Ok(());
+        }
+
+        plan.existing_version = Some(existing.app_version);
+        plan.downgrade = existing_version > *version;
+
+        if !plan.downgrade {
+            tracing::info!(%existing_version, %version, "upgrading existing installation");
+            return Ok(());
+        }
+
+        tracing::warn!(%existing_version, %version, "downgrading existing installation");
+
+        if self.config.allow_downgrade {
+            return Ok(());
+        }
+
+        Err(InstallerError::new(InstallerErrorKind::Downgrade)
+            .with_context(format!("version {} is installed", existing_version)))
+    }
+
+    /// Refuses to install for the current user when a newer version is
+    /// installed for all users unless allowed.
+    fn check_newer_system_install(&self, plan: &mut InstallPlan) -> Result<(), InstallerError> {
+        if self.config.access_scope != AccessScope::User {
+            return Ok(());
+        }
+
+        let system_manifest = match crate::manifest::existing_manifest_path_in(
+            self.config.state_dir.as_deref(),
+            &self.package_manifest.app_id,
+            AccessScope::System,
+        )
+        .and_then(|path| {
+            path.map(|path| crate::manifest::DiskManifest::load(&path))
+                .transpose()
+        }) {
+            Ok(Some(manifest)) => manifest,
+            Ok(None) => return Ok(()),
+            Err(error) => {
+                tracing::warn!(?error, "could not read system installation");
+                return Ok(());
+            }
+        };
+
+        match (system_manifest.comparable_version(), &plan.version) {
+            (Some(system_version), Some(version)) if system_version > *version => {}
+            _ => return Ok(()),
+        }
+
+        tracing::warn!(
+            system_version = system_manifest.app_version,
+            version = plan.display_version,
+            "newer version installed for all users"
+        );
+        plan.newer_system_version = Some(system_manifest.app_version);
+
+        if self.config.allow_older_user_install {
+            return Ok(());
+        }
+
+        Err(
+            InstallerError::new(InstallerErrorKind::NewerSystemInstall).with_context(format!(
+                "version {} is installed for all users",
+                plan.newer_system_version.as_ref().unwrap()
+            )),
+        )
+    }
+
+    fn is_staging_required(&self) -> bool {
+        match self.config.source_staging {
+            SourceStaging::Always => true,
+            SourceStaging::Never => false,
+            _ => match crate::os::is_read_only_medium(&self.config.source_dir) {
+                Ok(read_only) => {
+                    tracing::debug!(source_dir = ?self.config.source_dir, read_only, "checked source medium");
+                    read_only
+                }
+                Err(error) => {
+                    tracing::warn!(?error, "could not check source medium");
+                    false
+                }
+            },
+        }
+    }
+
+    /// Restores the settings and data files kept by a previous uninstallation.
+    fn plan_kept_user_data(&self, plan: &mut InstallPlan) -> Result<(), InstallerError> {
+        let Some(kept) = crate::manifest::kept_user_data_beside(
+            &plan.manifest_path,
+            &self.package_manifest.app_id,
+        )?
+        else {
+            return Ok(());
+        };
+
+        tracing::info!(path = ?kept.record_path, "found kept user data");
+
+        for entry in &mut plan.dirs {
+            if let Some(kept_dir) = kept
+                .dirs
+                .iter()
+                .find(|kept_dir| kept_dir.path == entry.destination_path)
+            {
+                entry.preserve = kept_dir.preserve;
+            }
+        }
+
+        for entry in &plan.files {
+            let Some(kept_file) = kept
+                .files
+                .iter()
+                .find(|kept_file| kept_file.path == entry.destination_path)
+            else {
+                continue;
+            };
+            let Ok(checksum) = crate::os::file_checksum(&entry.destination_path) else {
+                continue;
+            };
+
+            if checksum == kept_file.checksum() {
+                plan.replaceable_files
+                    .push((entry.destination_path.clone(), checksum));
+            } else {
+                tracing::info!(path = ?entry.destination_path, "keeping file modified by user");
+                plan.kept_files.push(entry.destination_path.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Keeps the settings and data files of the existing installation that
+    /// were modified by the user.
+    fn plan_modified_user_files(&self, plan: &mut InstallPlan) -> Result<(), InstallerError> {
+        if !self.config.keep_modified_user_files {
+            return Ok(());
+        }
+
+        let Some(existing_manifest_path) = &plan.existing_manifest_path else {
+            return Ok(());
+        };
+
+        let existing = crate::manifest::DiskManifest::load(existing_manifest_path)?;
+
+        for entry in &plan.files {
+            if !matches!(entry.file_type, FileType::Data | FileType::Configuration)
+                || plan.kept_files.contains(&entry.destination_path)
+            {
+                continue;
+            }
+
+            let Some(existing_file) = existing.file_by_path(&entry.destination_path) else {
+                continue;
+            };
+            let Ok(checksum) = existing_file.current_checksum() else {
+                continue;
+            };
+
+            if checksum != existing_file.checksum() {
+                tracing::info!(path = ?entry.destination_path, "keeping file modified by user");
+                plan.kept_files.push(entry.destination_path.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn plan_aliases(
+        &self,
+        plan: &mut InstallPlan,
+        bin_dir: &std::path::Path,
+    ) -> Result<(), InstallerError> {
+        for alias in &self.package_manifest.aliases {
+            let target = match &alias.target_path {
+                Some(target_path) => self
+                    .package_manifest
+                    .commands()
+                    .find(|entry| entry.target_path() == target_path),
+                None => self.package_manifest.main_executable(),
+            };
+            let exe_path = target
+                .and_then(|target| {
+                    plan.files
+                        .iter()
+                        .find(|entry| entry.source_path.ends_with(target.package_path()))
+                })
+                .map(|entry| entry.destination_path.clone())
+                .ok_or_else(|| {
+                    InstallerError::new(InstallerErrorKind::InvalidPackageManifest)
+                        .with_context(format!("unknown alias target {:?}", alias.name))
+                })?;
+
+            #[cfg(unix)]
+            {
+                self.plan_symlink(plan, bin_dir.join(&alias.name), &exe_path)?;
+
+                if self.config.modify_os_search_path
+                    && self.config.search_path_strategy == super::SearchPathStrategy::Symlink
+                {
+                    self.plan_search_path_symlink(plan, alias.name.as_ref(), &exe_path)?;
+                }
+            }
+
+            #[cfg(windows)]
+            {
+                let exe_name = exe_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                let script = crate::os::windows::alias_script(&exe_name);
+
+                plan.generated_files.push(PlanGeneratedFile::new(
+                    bin_dir.join(format!("{}.cmd", alias.name)),
+                    script.into_bytes(),
+                ));
+
+                if self.config.modify_os_search_path {
+                    plan.app_paths.push(PlanAppPath {
+                        exe_name: format!("{}.exe", alias.name),
+                        exe_path: exe_path.clone(),
+                        is_main_executable: false,
+                    });
+                }
+
+                self.plan_shim(plan, &alias.name, &exe_path)?;
+                self.plan_execution_alias(plan, &alias.name, &exe_path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the `.cmd` and `.ps1` shims for the command if the shims
+    /// strategy is used.
+    #[cfg(windows)]
+    fn plan_shim(
+        &self,
+        plan: &mut InstallPlan,
+        name: &str,
+        exe_path: &std::path::Path,
+    ) -> Result<(), InstallerError> {
+        let Some(shims_dir) = &plan.shims_dir else {
+            return Ok(());
+        };
+
+        let (cmd_script, ps1_script) = crate::os::windows::shim_scripts(exe_path)?;
+        let cmd_path = shims_dir.join(format!("{}.cmd", name));
+        let ps1_path = shims_dir.join(format!("{}.ps1", name));
+
+        plan.generated_files
+            .push(PlanGeneratedFile::new(cmd_path, cmd_script.into_bytes()));
+        plan.generated_files
+            .push(PlanGeneratedFile::new(ps1_path, ps1_script.into_bytes()));
+
+        Ok(())
+    }
+
+    /// Uses the app execution alias directory instead of the Path
+    /// environment variable if it is supported.
+    #[cfg(windows)]
+    fn plan_execution_alias_dir(&self, plan: &mut InstallPlan) -> Result<(), InstallerError> {
+        if self.config.access_scope != AccessScope::User {
+            tracing::warn!("execution alias strategy not supported for system scope");
+            return Ok(());
+        }
+
+        let dir = crate::os::windows::windows_apps_dir()?;
+
+        if dir.is_dir() {
+            // Planned with the commands instead
+            plan.search_path = None;
+            plan.execution_alias_dir = Some(dir);
+        } else {
+            tracing::warn!(?dir, "execution alias directory not found");
+        }
+
+        Ok(())
+    }
+
+    /// Adds the link for the command if the execution alias strategy is used.
+    #[cfg(windows)]
+    fn plan_execution_alias(
+        &self,
+        plan: &mut InstallPlan,
+        name: &str,
+        exe_path: &std::path::Path,
+    ) -> Result<(), InstallerError> {
+        let Some(dir) = &plan.execution_alias_dir else {
+            return Ok(());
+        };
+
+        let link_path = dir.join(format!("{}.exe", name));
+
+        // A link from a previous installation has the same contents as the
+        // installed file.
+        if link_path.exists() {
+            let is_replaceable = matches!(
+                (crate::os::file_checksum(&link_path), crate::os::file_checksum(exe_path)),
+                (Ok(link_checksum), Ok(exe_checksum)) if link_checksum == exe_checksum
+            );
+
+            if !is_replaceable {
+                return Err(
+                    InstallerError::new(InstallerErrorKind::UnknownFileInDestination)
+                        .with_context(format!("{:?}", link_path)),
+                );
+            }
+        }
+
+        plan.execution_aliases.push(PlanExecutionAlias {
+            link_path,
+            target_path: exe_path.to_path_buf(),
+        });
+
+        Ok(())
+    }
+
+    fn plan_env_vars(
+        &self,
+        plan: &mut InstallPlan,
+        bin_dir: &std::path::Path,
+        data_dir: &std::path::Path,
+        exe_path: &std::path::Path,
+    ) -> Result<(), InstallerError> {
+        if self.package_manifest.env_vars.is_empty() {
+            return Ok(());
+        }
+
+        for env_var in &self.package_manifest.env_vars {
+            plan.env_vars.push(DiskEnvVar {
+                name: env_var.name.clone(),
+                value: env_var.resolve_value(bin_dir, data_dir, exe_path),
+            });
+        }
+
+        #[cfg(unix)]
+        match self.config.access_scope {
+            AccessScope::User => {
+                if plan.shell_profile_path.is_none() {
+                    plan.shell_profile_path = self.user_shell_profile()?;
+                }
+
+                if plan.shell_profile_path.is_none() {
+                    tracing::warn!(
+                        "shell profile is managed by Nix; add the variables to home.sessionVariables instead"
+                    );
+                    plan.env_vars.clear();
+                }
+            }
+            AccessScope::System => {
+                let app_id = self.package_manifest.app_id.namespaced_id();
+
+                if let Some(script_path) = crate::os::unix::system_profile_script_path(app_id) {
+                    let env_vars = plan
+                        .env_vars
+                        .iter()
+                        .map(|item| (item.name.clone(), item.value.clone()))
+                        .collect::<Vec<_>>();
+                    let script = crate::os::unix::env_vars_script(&env_vars)?;
+
+                    plan.dirs.push(PlanDirEntry {
+                        destination_path: script_path.parent().unwrap().to_path_buf(),
+                        preserve: true,
+                        content_file_type: None,
+                    });
+                    plan.generated_files
+                        .push(PlanGeneratedFile::new(script_path, script.into_bytes()));
+                } else {
+                    tracing::warn!("no system profile directory for environment variables");
+                    plan.env_vars.clear();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Refuses system installs on NixOS instead of failing later on the
+    /// read-only file system.
+    #[cfg(unix)]
+    fn check_nixos(&self) -> Result<(), InstallerError> {
+        let is_system = self.config.access_scope == AccessScope::System
+            || self.config.destination == AppPathPrefix::System;
+
+        if is_system && crate::os::unix::is_nixos() {
+            return Err(InstallerError::new(InstallerErrorKind::UnsupportedAccessScope)
+                .with_context(
+                    "NixOS system configuration is managed declaratively; install for the current user instead",
+                ));
+        }
+
+        Ok(())
+    }
+
+    /// Refuses system installs on a read-only file system instead of
+    /// failing partway.
+    fn check_read_only_system_root(&self) -> Result<(), InstallerError> {
+        let location =
+            super::preflight::read_only_system_location(&self.package_manifest, &self.config)?;
+
+        match location {
+            Some(location) => Err(InstallerError::new(InstallerErrorKind::ReadOnlySystemRoot)
+                .with_context(format!(
+                    "{:?} is on a read-only file system; install for the current user instead",
+                    location
+                ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the user's shell profile, or `None` if it is a read-only
+    /// file managed by Nix (Home Manager).
+    #[cfg(unix)]
+    fn user_shell_profile(&self) -> Result<Option<PathBuf>, InstallerError> {
+        let profile = crate::os::unix::get_current_shell_profile()?;
+
+        if crate::os::unix::is_nix_store_path(&profile) {
+            Ok(None)
+        } else {
+            Ok(Some(profile))
+        }
+    }
+
+    #[cfg(unix)]
+    fn plan_search_path_symlink(
+        &self,
+        plan: &mut InstallPlan,
+        name: &std::ffi::OsStr,
+        exe_path: &std::path::Path,
+    ) -> Result<(), InstallerError> {
+        let link_dir = PathResolver::new(
+            self.package_manifest.app_id.plain_id(),
+            &self.config.access_scope.into(),
+        )?
+        .bin_dir();
+        let link_path = link_dir.join(name);
+
+        if link_dir == exe_path.parent().unwrap_or(exe_path) {
+            tracing::debug!(?link_path, "executable already in bin directory");
+            return Ok(());
+        }
+
+        self.plan_symlink(plan, link_path, exe_path)
+    }
+
+    #[cfg(unix)]
+    fn plan_symlink(
+        &self,
+        plan: &mut InstallPlan,
+        link_path: PathBuf,
+        exe_path: &std::path::Path,
+    ) -> Result<(), InstallerError> {
+        if !crate::os::unix::is_replaceable_symlink(&link_path, exe_path) {
+            return Err(
+                InstallerError::new(InstallerErrorKind::UnknownFileInDestination)
+                    .with_context(format!("{:?}", link_path)),
+            );
+        }
+
+        plan.symlinks.push(PlanSymlink {
+            link_path,
+            target_path: exe_path.to_path_buf(),
+        });
+
+        Ok(())
+    }
+
+    /// Adds a `/etc/paths.d` file for system installs on macOS.
+    ///
+    /// Returns whether the file is used instead of a shell profile.
+    #[cfg(unix)]
+    fn plan_paths_d(
+        &self,
+        plan: &mut InstallPlan,
+        bin_dir: &std::path::Path,
+    ) -> Result<bool, InstallerError> {
+        if !cfg!(target_os = "macos") || self.config.access_scope != AccessScope::System {
+            return Ok(false);
+        }
+
+        let app_id = self.package_manifest.app_id.namespaced_id();
+
+        let Some(path) = crate::os::unix::system_paths_d_path(app_id) else {
+            tracing::warn!("no paths.d directory for search path");
+            return Ok(false);
+        };
+
+        let contents = crate::os::unix::paths_d_contents(bin_dir.as_os_str())?;
+
+        plan.dirs.push(PlanDirEntry {
+            destination_path: path.parent().unwrap().to_path_buf(),
+            preserve: true,
+            content_file_type: None,
+        });
+        plan.generated_files
+            .push(PlanGeneratedFile::new(path, contents.into_bytes()));
+
+        Ok(true)
+    }
+
+    fn plan_receipt(
+        &self,
+        plan: &mut InstallPlan,
+        bin_dir: &std::path::Path,
+        data_dir: &std::path::Path,
+        exe_path: &std::path::Path,
+    ) -> Result<(), InstallerError> {
+        let app_id = &self.package_manifest.app_id;
+        let receipt = Receipt {
+            schema_version: RECEIPT_SCHEMA_VERSION,
+            app_id: app_id.namespaced_id().to_string(),
+            app_name: plan.display_name.clone(),
+            app_version: plan.display_version.clone(),
+            access_scope: plan.access_scope,
+            executable: exe_path.to_path_buf(),
+            bin_dir: bin_dir.to_path_buf(),
+            data_dir: data_dir.to_path_buf(),
+            manifest_path: plan.manifest_path.clone(),
+        };
+        let mut contents = Vec::new();
+        receipt.to_writer(&mut contents)?;
+
+        let receipt_path = crate::manifest::receipt_path_beside(&plan.manifest_path, app_id);
+        plan.generated_files
+            .push(PlanGeneratedFile::new(receipt_path, contents));
+
+        Ok(())
+    }
+
+    fn plan_scheduled_tasks(
+        &self,
+        plan: &mut InstallPlan,
+        exe_path: &std::path::Path,
+    ) -> Result<(), InstallerError> {
+        if !self.package_manifest.scheduled_tasks.is_empty() && !self.capabilities.scheduled_tasks {
+            return Err(
+                InstallerError::new(InstallerErrorKind::UnsupportedOsFeature).with_context(
+                    format!(
+                        "scheduled tasks are not supported on {:?} without crontab",
+                        self.capabilities.os
+                    ),
+                ),
+            );
+        }
+
+        for task in &self.package_manifest.scheduled_tasks {
+            plan.scheduled_tasks.push(ScheduledTaskConfig {
+                id: format!(
+                    "{}.{}",
+                    self.package_manifest.app_id.namespaced_id(),
+                    task.name
+                ),
+                schedule: task.schedule,
+                exe_path: exe_path.to_path_buf(),
+                args: task.args.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the installed path of the file that is the application icon.
+    fn icon_destination_path(&self, dest_bin_dir: &Path, dest_data_dir: &Path) -> Option<PathBuf> {
+        let icon = self.package_manifest.app_metadata.icon.as_ref()?;
+        let entry = self
+            .package_manifest
+            .files
+            .iter()
+            .find(|entry| entry.target_path() == icon);
+
+        match entry.map(|entry| entry.file_type()) {
+            Some(FileType::Executable) => Some(dest_bin_dir.join(icon)),
+            Some(FileType::Data) => Some(dest_data_dir.join(icon)),
+            _ => {
+                tracing::warn!(?icon, "icon is not one of the package's files");
+                None
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn plan_desktop_entry(
+        &self,
+        plan: &mut InstallPlan,
+        exe_path: &std::path::Path,
+        path_resolver: &PathResolver,
+    ) {
+        use crate::os::unix::MimeTypeInfo;
+
+        if !self.capabilities.desktop_entries {
+            tracing::debug!(os = ?self.capabilities.os, "desktop entries not supported");
+            return;
+        }
+
+        let Some(share_dir) = path_resolver.share_dir() else {
+            return;
+        };
+
+        let mime_types = self
+            .package_manifest
+            .file_associations
+            .iter()
+            .filter(|association| !association.mime_type.is_empty())
+            .map(|association| MimeTypeInfo {
+                mime_type: association.mime_type.clone(),
+                comment: association.description.clone(),
+                globs: association
+                    .extensions
+                    .iter()
+                    .map(|extension| format!("*{}", extension))
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let url_scheme_mime_types = self
+            .package_manifest
+            .url_schemes
+            .iter()
+            .map(|url_scheme| {
+                format!(
+                    "{}{}",
+                    crate::os::unix::URL_SCHEME_MIME_TYPE_PREFIX,
+                    url_scheme.scheme
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if mime_types.is_empty() && url_scheme_mime_types.is_empty() {
+            return;
+        }
+
+        let app_id = self.package_manifest.app_id.namespaced_id();
+        let mime_dir = share_dir.join("mime").join("packages");
+        let applications_dir = share_dir.join("applications");
+
+        if !mime_types.is_empty() {
+            self.plan_desktop_dir(plan, &mime_dir);
+
+            let xml = crate::os::unix::mime_info_xml(&mime_types);
+            plan.generated_files.push(PlanGeneratedFile::new(
+                mime_dir.join(format!("{}.xml", app_id)),
+                xml.into_bytes(),
+            ));
+        }
+
+        self.plan_desktop_dir(plan, &applications_dir);
+
+        let name = if plan.display_name.is_empty() {
+            self.package_manifest.app_id.plain_id()
+        } else {
+            &plan.display_name
+        };
+        let app_metadata = &self.package_manifest.app_metadata;
+        let config = crate::os::unix::DesktopEntryConfig {
+            name: name.to_string(),
+            locale_names: app_metadata
+                .locale_display_name
+                .iter()
+                .map(|(tag, value)| (tag.clone(), value.clone()))
+                .collect(),
+            comment: app_metadata.description.clone(),
+            locale_comments: app_metadata
+                .locale_description
+                .iter()
+                .map(|(tag, value)| (tag.clone(), value.clone()))
+                .collect(),
+            icon: plan.icon_path.clone(),
+            exe_path: exe_path.to_path_buf(),
+            mime_types: mime_types
+                .into_iter()
+                .map(|info| info.mime_type)
+                .chain(url_scheme_mime_types)
+                .collect(),
+        };
+        let entry = crate::os::unix::desktop_entry(&config);
+        plan.generated_files.push(PlanGeneratedFile::new(
+            applications_dir.join(format!("{}.desktop", app_id)),
+            entry.into_bytes(),
+        ));
+
+        plan.desktop_data_dir = Some(share_dir);
+    }
+
+    #[cfg(unix)]
+    fn plan_desktop_dir(&self, plan: &mut InstallPlan, dir: &std::path::Path) {
+        plan.dirs.push(PlanDirEntry {
+            destination_path: dir.to_path_buf(),
+            preserve: dir.exists(),
+            content_file_type: None,
+        });
+    }
+
+    #[cfg(windows)]
+    fn plan_url_schemes(&self, plan: &mut InstallPlan, exe_path: &std::path::Path) {
+        for url_scheme in &self.package_manifest.url_schemes {
+            let mut icon = OsString::from("\"");
+            icon.push(exe_path);
+            icon.push("\",0");
+
+            let mut command = OsString::from("\"");
+            command.push(exe_path);
+            command.push("\"");
+
+            for arg in &url_scheme.open_args {
+                command.push(" ");
+                command.push(arg);
+            }
+
+            plan.url_schemes.push(crate::os::windows::UrlSchemeConfig {
+                scheme: url_scheme.scheme.clone(),
+                description: url_scheme.description.clone(),
+                icon,
+                command,
+            });
+        }
+    }
+
+    #[cfg(windows)]
+    fn plan_file_associations(
+        &self,
+        plan: &mut InstallPlan,
+        exe_path: &std::path::Path,
+        data_dir: &std::path::Path,
+    ) {
+        for association in &self.package_manifest.file_associations {
+            let icon = match &association.icon_path {
+                Some(icon_path) => data_dir.join(icon_path).into_os_string(),
+                None => {
+                    let mut icon = OsString::from("\"");
+                    icon.push(exe_path);
+                    icon.push("\",0");
+                    icon
+                }
+            };
+
+            let mut command = OsString::from("\"");
+            command.push(exe_path);
+            command.push("\"");
+
+            for arg in &association.open_args {
+                command.push(" ");
+                command.push(arg);
+            }
+
+            plan.file_associations
+                .push(crate::os::windows::FileAssociationConfig {
+                    prog_id: association.prog_id.clone(),
+                    extensions: association.extensions.clone(),
+                    description: association.description.clone(),
+                    icon,
+                    command,
+                });
+        }
+    }
+
+    #[cfg(windows)]
+    fn plan_shortcuts(
+        &self,
+        plan: &mut InstallPlan,
+        target_path: &std::path::Path,
+    ) -> Result<(), InstallerError> {
+        let display_name = if plan.display_name.is_empty() {
+            self.package_manifest.app_id.plain_id()
+        } else {
+            &plan.display_name
+        };
+        let file_stem = crate::os::windows::shortcut_file_stem(display_name);
+        let filename = format!("{}.lnk", file_stem);
+
+        if self.config.start_menu_shortcut {
+            let dir = crate::os::windows::start_menu_programs_dir(self.config.access_scope)?
+                .join(&file_stem);
+
+            plan.dirs.push(PlanDirEntry {
+                destination_path: dir.clone(),
+                preserve: dir.exists(),
+                content_file_type: None,
+            });
+            plan.shortcuts.push(PlanShortcut {
+                path: dir.join(&filename),
+                target_path: target_path.to_path_buf(),
+            });
+        }
+
+        if self.config.desktop_shortcut {
+            let dir = crate::os::windows::desktop_dir(self.config.access_scope)?;
+
+            plan.shortcuts.push(PlanShortcut {
+                path: dir.join(&filename),
+                target_path: target_path.to_path_buf(),
+            });
+        }
+
+        Ok(())
+    }
 }
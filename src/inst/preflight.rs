@@ -0,0 +1,344 @@
+//! Checks that the locations modified by the installation are writable.
+use std::path::{Path, PathBuf};
+
+use crate::{
+    error::{InstallerError, InstallerErrorKind},
+    os::AccessScope,
+    path::{AppPathPrefix, PathResolver},
+};
+
+use super::{plan::InstallPlan, InstallConfig, PackageManifest};
+
+/// Checks the locations before anything is modified so that a blocked
+/// location is reported up front instead of leaving a partial installation.
+pub fn check_writable(plan: &InstallPlan) -> Result<(), InstallerError> {
+    for dir in plan_dirs(plan) {
+        tracing::debug!(?dir, "checking directory is writable");
+
+        check_dir_writable(&dir).map_err(|error| location_error(&dir, error))?;
+    }
+
+    for path in plan_modified_files(plan) {
+        tracing::debug!(?path, "checking file is writable");
+
+        check_file_writable(&path).map_err(|error| location_error(&path, error))?;
+    }
+
+    #[cfg(windows)]
+    for key_path in plan_registry_keys(plan) {
+        tracing::debug!(key_path, "checking registry key is writable");
+
+        crate::os::windows::check_registry_key_writable(plan.access_scope, key_path).map_err(
+            |error| {
+                let permission_denied = crate::os::windows::is_access_denied(&error);
+                location_error_impl(key_path, error, permission_denied)
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Returns the first location of a system installation that is on a
+/// read-only file system, such as `/usr/local` on NixOS or `/usr` on
+/// Fedora Silverblue.
+///
+/// This only needs the config, so it can be checked before the plan is
+/// created or the installer is elevated.
+pub(super) fn read_only_system_location(
+    package_manifest: &PackageManifest,
+    config: &InstallConfig,
+) -> Result<Option<PathBuf>, InstallerError> {
+    // Read-only or remote volumes are not an immutable OS on Windows
+    if !cfg!(unix)
+        || (config.access_scope != AccessScope::System
+            && config.destination != AppPathPrefix::System)
+    {
+        return Ok(None);
+    }
+
+    let app_id = &package_manifest.app_id;
+    let path_resolver = PathResolver::new(app_id.plain_id(), &config.destination)?;
+    let mut locations = vec![path_resolver.bin_dir(), path_resolver.data_dir()];
+
+    if config.access_scope == AccessScope::System {
+        let manifest_path = crate::manifest::manifest_path_in(
+            config.state_dir.as_deref(),
+            app_id,
+            AccessScope::System,
+        )?;
+        locations.extend(manifest_path.parent().map(Path::to_path_buf));
+    }
+
+    for location in locations {
+        match crate::os::is_read_only_medium(closest_existing_dir(&location)) {
+            Ok(true) => return Ok(Some(location)),
+            Ok(false) => {}
+            Err(error) => {
+                tracing::debug!(?location, ?error, "could not check file system");
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Reason a directory entered by the user can't be the destination.
+#[cfg(feature = "ui")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum CustomDestinationProblem {
+    Relative,
+    NonEmpty,
+    Unwritable,
+}
+
+#[cfg(feature = "ui")]
+impl CustomDestinationProblem {
+    /// Returns the ID of the message shown to the user.
+    pub fn text_id(self) -> &'static str {
+        match self {
+            Self::Relative => "custom-destination-relative",
+            Self::NonEmpty => "custom-destination-non-empty",
+            Self::Unwritable => "custom-destination-unwritable",
+        }
+    }
+}
+
+/// Checks a directory entered by the user can be the destination.
+///
+/// The directory needs to be an absolute path and writable. If it exists,
+/// it needs to be empty so that the uninstaller doesn't remove unrelated
+/// files, unless `allow_existing` is set for the directory of the
+/// existing installation.
+#[cfg(feature = "ui")]
+pub(super) fn check_custom_destination(
+    dir: &Path,
+    allow_existing: bool,
+) -> Result<(), CustomDestinationProblem> {
+    if !dir.is_absolute() {
+        return Err(CustomDestinationProblem::Relative);
+    }
+
+    if !allow_existing && dir.exists() {
+        let is_empty = std::fs::read_dir(dir)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(false);
+
+        if !is_empty {
+            return Err(CustomDestinationProblem::NonEmpty);
+        }
+    }
+
+    if let Err(error) = check_dir_writable(dir) {
+        tracing::debug!(?dir, ?error, "custom destination not writable");
+        return Err(CustomDestinationProblem::Unwritable);
+    }
+
+    Ok(())
+}
+
+/// Returns the directories that files will be written to.
+fn plan_dirs(plan: &InstallPlan) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    dirs.extend(plan.dirs.iter().map(|entry| entry.destination_path.clone()));
+    dirs.extend(plan.manifest_path.parent().map(Path::to_path_buf));
+    dirs.extend(
+        plan.files
+            .iter()
+            .filter_map(|entry| entry.destination_path.parent().map(Path::to_path_buf)),
+    );
+    dirs.extend(
+        plan.generated_files
+            .iter()
+            .filter_map(|entry| entry.destination_path.parent().map(Path::to_path_buf)),
+    );
+
+    #[cfg(unix)]
+    dirs.extend(
+        plan.symlinks
+            .iter()
+            .filter_map(|entry| entry.link_path.parent().map(Path::to_path_buf)),
+    );
+
+    #[cfg(windows)]
+    dirs.extend(plan.execution_alias_dir.clone());
+
+    #[cfg(windows)]
+    dirs.extend(
+        plan.shortcuts
+            .iter()
+            .filter_map(|entry| entry.path.parent().map(Path::to_path_buf)),
+    );
+
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+/// Returns the existing files that will be modified in place.
+fn plan_modified_files(plan: &InstallPlan) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    #[cfg(unix)]
+    files.extend(plan.shell_profile_path.clone());
+
+    #[cfg(windows)]
+    files.extend(plan.powershell_profile_paths.iter().cloned());
+
+    files
+}
+
+/// Returns the registry keys modified under the predefined key of the
+/// access scope.
+#[cfg(windows)]
+pub(super) fn plan_registry_keys(plan: &InstallPlan) -> Vec<&'static str> {
+    use crate::os::{windows, AccessScope};
+
+    let mut keys = Vec::new();
+
+    if plan.search_path.is_some() || plan.shims_dir.is_some() || !plan.env_vars.is_empty() {
+        keys.push(match plan.access_scope {
+            AccessScope::User => windows::REGISTRY_ENV_USER_KEY,
+            AccessScope::System => windows::REGISTRY_ENV_SYSTEM_KEY,
+        });
+    }
+
+    if !plan.app_paths.is_empty() {
+        keys.push(windows::REGISTRY_APP_PATHS_KEY);
+    }
+
+    if !plan.file_associations.is_empty() || !plan.url_schemes.is_empty() {
+        keys.push(windows::REGISTRY_CLASSES_KEY);
+    }
+
+    if !plan.interactive_uninstall_args.is_empty() {
+        keys.push(windows::REGISTRY_UNINSTALL_KEY);
+    }
+
+    keys
+}
+
+/// Checks a file can be created in the directory or, if it doesn't exist,
+/// in the closest existing parent directory.
+fn check_dir_writable(dir: &Path) -> std::io::Result<()> {
+    let existing = closest_existing_dir(dir);
+
+    if !existing.is_dir() {
+        return Err(std::io::Error::other(format!(
+            "{:?} is not a directory",
+            existing
+        )));
+    }
+
+    tempfile::Builder::new()
+        .prefix(".takecrate-probe")
+        .tempfile_in(existing)?;
+
+    Ok(())
+}
+
+/// Returns the directory or, if it doesn't exist, the closest existing
+/// parent directory.
+fn closest_existing_dir(dir: &Path) -> &Path {
+    let mut existing = dir;
+
+    while !existing.exists() {
+        match existing.parent() {
+            Some(parent) => existing = parent,
+            None => break,
+        }
+    }
+
+    existing
+}
+
+fn check_file_writable(path: &Path) -> std::io::Result<()> {
+    if path.exists() {
+        std::fs::File::options().append(true).open(path)?;
+
+        Ok(())
+    } else {
+        check_dir_writable(path.parent().unwrap_or(Path::new("")))
+    }
+}
+
+fn location_error(path: &Path, error: std::io::Error) -> InstallerError {
+    let permission_denied = error.kind() == std::io::ErrorKind::PermissionDenied;
+
+    location_error_impl(&format!("{:?}", path), error, permission_denied)
+}
+
+fn location_error_impl<E>(location: &str, error: E, permission_denied: bool) -> InstallerError
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    if permission_denied && !crate::os::is_elevated() {
+        InstallerError::new(InstallerErrorKind::InsufficientPrivileges)
+            .with_source(error)
+            .with_context(format!(
+                "cannot write to {}; running as root or an administrator may fix this",
+                location
+            ))
+    } else {
+        InstallerError::new(InstallerErrorKind::NotWritable)
+            .with_source(error)
+            .with_context(format!("cannot write to {}", location))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_dir_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("file.txt");
+        std::fs::write(&file_path, b"").unwrap();
+
+        check_dir_writable(dir.path()).unwrap();
+        check_dir_writable(&dir.path().join("a").join("b")).unwrap();
+        assert!(check_dir_writable(&file_path.join("a")).is_err());
+
+        // The probe file is removed
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(feature = "ui")]
+    #[test]
+    fn test_check_custom_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("file.txt");
+
+        check_custom_destination(dir.path(), false).unwrap();
+        check_custom_destination(&dir.path().join("a"), false).unwrap();
+        assert_eq!(
+            check_custom_destination(Path::new("a"), false),
+            Err(CustomDestinationProblem::Relative)
+        );
+
+        std::fs::write(&file_path, b"").unwrap();
+
+        assert_eq!(
+            check_custom_destination(dir.path(), false),
+            Err(CustomDestinationProblem::NonEmpty)
+        );
+        check_custom_destination(dir.path(), true).unwrap();
+        assert_eq!(
+            check_custom_destination(&file_path, true),
+            Err(CustomDestinationProblem::Unwritable)
+        );
+    }
+
+    #[test]
+    fn test_closest_existing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert_eq!(closest_existing_dir(dir.path()), dir.path());
+        assert_eq!(
+            closest_existing_dir(&dir.path().join("a").join("b")),
+            dir.path()
+        );
+    }
+}
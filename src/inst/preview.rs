@@ -0,0 +1,137 @@
+use std::path::PathBuf;
+
+use crate::os::AccessScope;
+
+use super::plan::InstallPlan;
+
+/// Changes that an installation would make.
+///
+/// Returned by [`Installer::preview()`](crate::inst::Installer::preview).
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct InstallPreview {
+    /// Application name displayed to the user.
+    pub app_name: String,
+    /// Application version displayed to the user.
+    pub app_version: String,
+    /// Access scope of the installation.
+    pub access_scope: AccessScope,
+    /// Disk manifest of an existing installation that will be uninstalled first.
+    pub existing_manifest_path: Option<PathBuf>,
+    /// Disk manifest that will be written.
+    pub manifest_path: PathBuf,
+    /// Directories that will be created if they don't exist.
+    pub dirs: Vec<PathBuf>,
+    /// Files that will be copied or written.
+    pub files: Vec<PathBuf>,
+    /// Sizes in bytes of the files in the same order as `files`.
+    pub file_sizes: Vec<u64>,
+    /// Total size in bytes of the files.
+    pub files_size: u64,
+    /// Links to the commands, such as aliases, symlinks, and execution aliases.
+    pub links: Vec<PathBuf>,
+    /// Shortcut files that will be created.
+    pub shortcuts: Vec<PathBuf>,
+    /// Directory that will be added to the search path (PATH).
+    pub search_path: Option<PathBuf>,
+    /// Existing files that will be modified, such as shell profiles.
+    pub modified_files: Vec<PathBuf>,
+    /// Registry keys that will be modified, including the predefined key.
+    pub registry_keys: Vec<String>,
+    /// Environment variables that will be set persistently.
+    pub env_vars: Vec<(String, String)>,
+    /// Identifiers of the scheduled tasks that will be registered.
+    pub scheduled_tasks: Vec<String>,
+}
+
+impl InstallPreview {
+    pub(super) fn from_plan(plan: &InstallPlan) -> Self {
+        let mut preview = Self {
+            app_name: plan.display_name.clone(),
+            app_version: plan.display_version.clone(),
+            access_scope: plan.access_scope,
+            existing_manifest_path: plan.existing_manifest_path.clone(),
+            manifest_path: plan.manifest_path.clone(),
+            dirs: plan
+                .dirs
+                .iter()
+                .map(|entry| entry.destination_path.clone())
+                .collect(),
+            files: plan
+                .files
+                .iter()
+                .map(|entry| entry.destination_path.clone())
+                .chain(
+                    plan.generated_files
+                        .iter()
+                        .map(|entry| entry.destination_path.clone()),
+                )
+                .collect(),
+            file_sizes: plan
+                .files
+                .iter()
+                .map(|entry| entry.len)
+                .chain(plan.generated_files.iter().map(|entry| entry.len))
+                .collect(),
+            files_size: plan.total_file_size().saturating_add(
+                plan.generated_files
+                    .iter()
+                    .map(|entry| entry.len)
+                    .sum::<u64>(),
+            ),
+            search_path: plan.search_path.clone(),
+            env_vars: plan
+                .env_vars
+                .iter()
+                .map(|item| (item.name.clone(), item.value.clone()))
+                .collect(),
+            scheduled_tasks: plan
+                .scheduled_tasks
+                .iter()
+                .map(|task| task.id.clone())
+                .collect(),
+            ..Default::default()
+        };
+
+        #[cfg(unix)]
+        {
+            preview
+                .links
+                .extend(plan.symlinks.iter().map(|item| item.link_path.clone()));
+            preview
+                .modified_files
+                .extend(plan.shell_profile_path.clone());
+        }
+
+        #[cfg(windows)]
+        {
+            let predefined_key = match plan.access_scope {
+                AccessScope::User => "HKEY_CURRENT_USER",
+                AccessScope::System => "HKEY_LOCAL_MACHINE",
+            };
+
+            preview.links.extend(
+                plan.execution_aliases
+                    .iter()
+                    .map(|item| item.link_path.clone()),
+            );
+            preview
+                .shortcuts
+                .extend(plan.shortcuts.iter().map(|item| item.path.clone()));
+            preview
+                .modified_files
+                .extend(plan.powershell_profile_paths.iter().cloned());
+            preview.registry_keys.extend(
+                super::preflight::plan_registry_keys(plan)
+                    .into_iter()
+                    .map(|key| format!(r"{}\{}", predefined_key, key)),
+            );
+
+            if preview.search_path.is_none() {
+                preview.search_path.clone_from(&plan.shims_dir);
+            }
+        }
+
+        preview
+    }
+}
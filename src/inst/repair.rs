@@ -0,0 +1,249 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    error::{AddContext, AddInstallerContext, InstallerError},
+    manifest::{DiskFileEntry, DiskManifest, FileType},
+    verify::{VerifyItem, VerifyReport},
+};
+
+use super::{exec::Executor, PackageManifest};
+
+/// Restores the missing and damaged parts of an existing installation.
+pub(super) struct Repairer<'a> {
+    package_manifest: &'a PackageManifest,
+    source_dir: PathBuf,
+    manifest: DiskManifest,
+    reboot_required: bool,
+}
+
+impl<'a> Repairer<'a> {
+    pub fn new(
+        package_manifest: &'a PackageManifest,
+        source_dir: &Path,
+        manifest: DiskManifest,
+    ) -> Self {
+        Self {
+            package_manifest,
+            source_dir: source_dir.to_path_buf(),
+            manifest,
+            reboot_required: false,
+        }
+    }
+
+    /// Returns whether files in use will be replaced when the computer
+    /// restarts.
+    pub fn reboot_required(&self) -> bool {
+        self.reboot_required
+    }
+
+    /// Returns the disk manifest updated with the restored files.
+    pub fn manifest(&self) -> &DiskManifest {
+        &self.manifest
+    }
+
+    /// Repairs the items in the report.
+    pub fn run(&mut self, report: &VerifyReport) -> Result<(), InstallerError> {
+        for item in &report.missing {
+            match item {
+                VerifyItem::File(path) => self
+                    .restore_file(path, false)
+                    .inst_contextc(|| format!("failed to restore file {:?}", path))?,
+                VerifyItem::Dir(path) => {
+                    tracing::info!(?path, "restoring directory");
+                    std::fs::create_dir_all(path)
+                        .with_contextc(|_e| format!("could not create directory {:?}", path))?;
+                }
+                VerifyItem::SearchPath { dir, profile } => self
+                    .restore_search_path(dir, profile.as_deref())
+                    .inst_context("failed to add PATH environment variable")?,
+                VerifyItem::AppPath(exe_name) => self
+                    .restore_app_path(exe_name)
+                    .inst_context("failed to add App Path")?,
+                _ => {
+                    tracing::warn!(?item, "cannot repair item");
+                }
+            }
+        }
+
+        for item in &report.modified {
+            match item {
+                VerifyItem::File(path) => self
+                    .restore_file(path, true)
+                    .inst_contextc(|| format!("failed to restore file {:?}", path))?,
+                _ => {
+                    tracing::warn!(?item, "cannot repair item");
+                }
+            }
+        }
+
+        self.save_manifest()
+    }
+
+    fn restore_file(&mut self, path: &Path, modified: bool) -> Result<(), InstallerError> {
+        let Some(entry) = self.manifest.file_by_path(path).cloned() else {
+            return Ok(());
+        };
+
+        if modified && is_user_editable(&entry) {
+            tracing::info!(?path, "keeping user's file");
+            return Ok(());
+        }
+
+        let Some(source) = self.source_path(&entry) else {
+            tracing::warn!(?path, "file is not in the package");
+            return Ok(());
+        };
+
+        if source == entry.path || !source.is_file() {
+            tracing::warn!(?source, ?path, "no source file to restore from");
+            return Ok(());
+        }
+
+        tracing::info!(?source, ?path, "restoring file");
+
+        let source_checksum = crate::os::file_checksum(&source)
+            .with_contextc(|_e| format!("could not read file {:?}", source))?;
+        let destination_checksum = if modified {
+            Some(crate::os::file_checksum(path)?)
+        } else {
+            None
+        };
+
+        let copied = Executor::copy_file(
+            &source,
+            &source_checksum,
+            destination_checksum.as_ref(),
+            path,
+            false,
+            &mut |_| {},
+        )?;
+        self.reboot_required |= copied.pending_reboot;
+
+        #[cfg(unix)]
+        crate::os::unix::set_posix_permission(
+            path,
+            crate::os::unix::get_effective_posix_permission(entry.file_type),
+        )?;
+
+        // The package may be a different version than the one installed
+        if let Some(entry) = self
+            .manifest
+            .files
+            .iter_mut()
+            .find(|item| item.path == path)
+        {
+            entry.len = source_checksum.len;
+            entry.crc32c = source_checksum.crc32c;
+            entry.digest = source_checksum.digest;
+        }
+        self.manifest.reset_file_index();
+
+        Ok(())
+    }
+
+    /// Returns the path of the file in the package that was installed to
+    /// the entry's path.
+    fn source_path(&self, entry: &DiskFileEntry) -> Option<PathBuf> {
+        let app_paths = &self.manifest.app_paths;
+
+        self.package_manifest
+            .files
+            .iter()
+            .find(|package_entry| {
+                let destination = match package_entry.file_type() {
+                    FileType::Executable => app_paths.executable.join(package_entry.target_path()),
+                    FileType::Data => app_paths.data.join(package_entry.target_path()),
+                    _ => return false,
+                };
+
+                destination == entry.path
+            })
+            .map(|package_entry| self.source_dir.join(package_entry.package_path()))
+    }
+
+    fn restore_search_path(
+        &self,
+        dir: &Path,
+        profile: Option<&Path>,
+    ) -> Result<(), InstallerError> {
+        tracing::info!(?dir, ?profile, "restoring search path");
+
+        #[cfg(windows)]
+        match profile {
+            Some(profile) => {
+                crate::os::windows::add_powershell_profile_path(profile, dir.as_os_str())?;
+            }
+            None => {
+                crate::os::retry_sharing_violation(|| {
+                    Ok(crate::os::windows::add_path_env_var(
+                        self.manifest.access_scope,
+                        dir.as_os_str(),
+                    )?)
+                })?;
+            }
+        }
+        #[cfg(unix)]
+        if let Some(profile) = profile {
+            if self.manifest.access_scope == crate::os::AccessScope::User {
+                crate::os::unix::add_path_env_var(
+                    self.manifest.access_scope,
+                    dir.as_os_str(),
+                    profile,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn restore_app_path(&self, exe_name: &str) -> Result<(), InstallerError> {
+        #[cfg(windows)]
+        {
+            let exe_path = self
+                .manifest
+                .files
+                .iter()
+                .find(|entry| {
+                    entry.file_type == FileType::Executable
+                        && entry.path.file_name() == Some(std::ffi::OsStr::new(exe_name))
+                })
+                .map(|entry| entry.path.clone());
+
+            if let Some(exe_path) = exe_path {
+                tracing::info!(exe_name, "restoring App Paths");
+
+                crate::os::retry_sharing_violation(|| {
+                    Ok(crate::os::windows::add_app_path(
+                        self.manifest.access_scope,
+                        exe_name,
+                        exe_path.as_os_str(),
+                        &crate::os::windows::AppPathConfig::default(),
+                    )?)
+                })?;
+            }
+        }
+
+        let _ = exe_name;
+
+        Ok(())
+    }
+
+    fn save_manifest(&self) -> Result<(), InstallerError> {
+        let path = &self.manifest.manifest_path;
+
+        tracing::debug!(?path, "saving disk manifest");
+
+        let mut contents = Vec::new();
+        self.manifest.to_writer(&mut contents)?;
+        crate::os::write_file_atomic(path, &contents, true)
+            .with_contextc(|_e| format!("could not write file {:?}", path))?;
+
+        Ok(())
+    }
+}
+
+/// Returns whether the file may have been edited by the user, such as
+/// settings, so it is not replaced when modified.
+fn is_user_editable(entry: &DiskFileEntry) -> bool {
+    matches!(entry.file_type, FileType::Data | FileType::Configuration)
+}
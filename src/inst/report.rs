@@ -0,0 +1,129 @@
+use std::time::{Duration, Instant};
+
+/// Summary of a completed installation.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct InstallReport {
+    /// Total wall-clock time spent installing.
+    ///
+    /// For interactive installs, this excludes time waiting for the user.
+    pub duration: Duration,
+    /// Wall-clock time of each step in the order they were run.
+    ///
+    /// Steps that were not needed are not included.
+    pub step_durations: Vec<(InstallStep, Duration)>,
+    /// Whether files in use, such as a running binary, will be replaced or
+    /// removed when the computer restarts.
+    ///
+    /// Only applies to Windows.
+    pub reboot_required: bool,
+    /// Whether the installation was made inside the Windows Subsystem for
+    /// Linux.
+    ///
+    /// The search path and environment variables are only added to the
+    /// Linux shell profile, so they don't apply to Windows terminals.
+    pub wsl: bool,
+}
+
+impl InstallReport {
+    /// Returns the duration of the given step if it was run.
+    pub fn step_duration(&self, step: InstallStep) -> Option<Duration> {
+        self.step_durations
+            .iter()
+            .find(|(item, _)| *item == step)
+            .map(|(_, duration)| *duration)
+    }
+}
+
+/// A step of the installation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InstallStep {
+    /// Computing the list of changes.
+    Plan,
+    /// Checking that the locations to be modified are writable.
+    Preflight,
+    /// Copying the files to a temporary directory when installing from a
+    /// read-only medium.
+    Stage,
+    /// Removing an existing installation.
+    UninstallExisting,
+    /// Writing the disk manifest.
+    Manifest,
+    /// Copying and writing the files.
+    Files,
+    /// Modifying the search path (PATH) and App Paths.
+    SearchPath,
+    /// Creating shortcuts.
+    Shortcuts,
+    /// Registering file associations, URL schemes, and desktop entries.
+    Associations,
+    /// Registering scheduled tasks.
+    ScheduledTasks,
+    /// Setting environment variables.
+    EnvVars,
+    /// Adding the entry to the OS's list of installed applications.
+    UninstallEntry,
+}
+
+/// Records the durations of steps.
+#[derive(Debug, Default)]
+pub(crate) struct StepTimer {
+    step_durations: Vec<(InstallStep, Duration)>,
+}
+
+impl StepTimer {
+    /// Runs the function and records its duration.
+    pub fn time<T, F>(&mut self, step: InstallStep, func: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let start = Instant::now();
+        let result = func();
+        let duration = start.elapsed();
+
+        tracing::info!(?step, duration_ms = duration.as_millis(), "step completed");
+        self.step_durations.push((step, duration));
+
+        result
+    }
+
+    pub fn extend(&mut self, other: StepTimer) {
+        self.step_durations.extend(other.step_durations);
+    }
+
+    pub fn into_report(self) -> InstallReport {
+        let duration: Duration = self.step_durations.iter().map(|(_, item)| *item).sum();
+
+        tracing::info!(duration_ms = duration.as_millis(), "install completed");
+
+        InstallReport {
+            duration,
+            step_durations: self.step_durations,
+            reboot_required: false,
+            wsl: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_timer() {
+        let mut timer = StepTimer::default();
+        let value = timer.time(InstallStep::Plan, || 123);
+        timer.time(InstallStep::Files, || {
+            std::thread::sleep(Duration::from_millis(10));
+        });
+
+        let report = timer.into_report();
+
+        assert_eq!(value, 123);
+        assert_eq!(report.step_durations.len(), 2);
+        assert!(report.step_duration(InstallStep::Files).unwrap() >= Duration::from_millis(10));
+        assert!(report.step_duration(InstallStep::Shortcuts).is_none());
+        assert!(report.duration >= report.step_duration(InstallStep::Files).unwrap());
+    }
+}
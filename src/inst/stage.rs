@@ -0,0 +1,84 @@
+//! Copying the package files to a temporary directory before installing.
+use std::{fs::File, path::Path};
+
+use tempfile::TempDir;
+
+use crate::{
+    error::{AddContext, InstallerError, InstallerErrorKind},
+    os::FileChecksum,
+};
+
+use super::plan::InstallPlan;
+
+/// Copies the files to a temporary directory and changes the plan to
+/// install from the copies.
+///
+/// Each copy is checked against the checksum computed when planning, so a
+/// file that changed or could not be read reliably is reported before
+/// anything is modified. The directory is removed when the returned value
+/// is dropped, so it needs to be kept until the files are installed.
+pub fn stage_files(plan: &mut InstallPlan) -> Result<TempDir, InstallerError> {
+    let dir = tempfile::Builder::new()
+        .prefix(".takecrate-stage")
+        .tempdir()
+        .with_context("could not create temporary directory")?;
+
+    tracing::info!(dir = ?dir.path(), "staging files");
+
+    // Linked files would keep the permissions of the copies
+    plan.hardlink_files = false;
+
+    for (index, entry) in plan.files.iter_mut().enumerate() {
+        if plan.kept_files.contains(&entry.destination_path) {
+            continue;
+        }
+
+        let mut name = format!("{}-", index);
+        name.push_str(
+            &entry
+                .source_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy(),
+        );
+        let staged_path = dir.path().join(name);
+
+        tracing::debug!(source = ?entry.source_path, ?staged_path, "staging file");
+
+        copy_file_contents(&entry.source_path, &staged_path)
+            .with_contextc(|_e| format!("could not copy file {:?}", entry.source_path))?;
+
+        let expected = FileChecksum {
+            crc32c: entry.crc32c,
+            len: entry.len,
+            digest: entry.digest.clone(),
+        };
+        let checksum = crate::os::file_checksum(&staged_path)
+            .with_contextc(|_e| format!("could not read file {:?}", staged_path))?;
+
+        if checksum != expected {
+            return Err(
+                InstallerError::new(InstallerErrorKind::InvalidData).with_context(format!(
+                    "file {:?} changed or could not be read reliably",
+                    entry.source_path
+                )),
+            );
+        }
+
+        entry.source_path = staged_path;
+    }
+
+    Ok(dir)
+}
+
+/// Copies only the contents so that attributes of the medium, such as
+/// read-only, are not copied.
+fn copy_file_contents(source: &Path, destination: &Path) -> std::io::Result<()> {
+    let mut source_file = File::open(source)?;
+    let mut destination_file = File::create(destination)?;
+
+    std::io::copy(&mut source_file, &mut destination_file)?;
+    destination_file.sync_all()?;
+
+    Ok(())
+}
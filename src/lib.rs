@@ -9,7 +9,7 @@
 //!
 //! 1. Create a unique ID for your application using [`AppId`].
 //! 2. Create the listing of input files using [`PackageManifest`].
-//! 3. Based on context, run [`install_interactive()`] or [`uninstall_interactive`], or continue normally in your binary.
+//! 3. Based on context, such as [`is_installer_invocation()`], run [`install_interactive()`] or [`uninstall_interactive`], or continue normally in your binary.
 //! 4. If you need a included data file in a installation, use [`manifest()`] to get a [`DiskManifest`].
 //!
 //! ## Example
@@ -19,11 +19,10 @@
 //! # use takecrate::manifest::AppId;
 //! # use takecrate::inst::PackageManifest;
 //! # fn main() -> Result<(), Box<dyn Error>> {
-//! # let exe_name = "";
 //! let app_id = AppId::new("com.example.my-app")?;
 //! let manifest = PackageManifest::new(&app_id).with_self_exe()?;
 //!
-//! if exe_name.ends_with("_installer") {
+//! if takecrate::is_installer_invocation() {
 //!     takecrate::install_interactive(&manifest)?;
 //! }
 //! # Ok(())
@@ -36,10 +35,12 @@
 
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
-use error::InstallerError;
-use inst::{InstallConfig, Installer, PackageManifest};
-use manifest::{AppId, DiskManifest};
+use error::{InstallerError, InstallerErrorKind};
+use inst::{InstallConfig, Installer, InstallerNameMatcher, PackageManifest};
+use manifest::{AppId, DiskManifest, InstalledApp, Receipt};
+use os::AccessScope;
 use uninst::Uninstaller;
+use verify::VerifyReport;
 
 #[cfg(all(feature = "ui", not(feature = "i18n")))]
 compile_error!("feature 'ui' depends on feature 'i18n'");
@@ -57,7 +58,10 @@ pub mod os;
 pub mod path;
 #[cfg(feature = "ui")]
 mod tui;
+#[cfg(feature = "i18n")]
+pub mod ui;
 pub mod uninst;
+pub mod verify;
 
 /// Starts the installer with a interactive interface.
 ///
@@ -65,23 +69,60 @@ pub mod uninst;
 /// options and perform the installation.
 ///
 /// If the user cancels the guide, the error kind [`InterruptedByUser`](crate::error::InstallerErrorKind::InterruptedByUser)
-/// will be returned. If an error occurs, an appropriate error kind will be
-/// returned.
+/// will be returned. If the terminal can't display the interface, such as
+/// when `TERM` is `dumb`, the error kind [`UnsupportedTerminal`](crate::error::InstallerErrorKind::UnsupportedTerminal)
+/// is returned and [`install()`] can be used instead. If an error occurs, an
+/// appropriate error kind will be returned.
 #[cfg(feature = "ui")]
 pub fn install_interactive(manifest: &PackageManifest) -> Result<(), InstallerError> {
     let mut installer = Installer::new(manifest);
     installer.run_interactive()
 }
 
+/// Returns whether the binary was started as an installer.
+///
+/// This is `true` when the binary's file name ends with an installer suffix,
+/// such as `my-app_installer.exe` or `my-app-setup`, and no command line
+/// arguments were given. See [`InstallerNameMatcher`](crate::inst::InstallerNameMatcher)
+/// for details and for customizing the suffixes.
+pub fn is_installer_invocation() -> bool {
+    InstallerNameMatcher::new().is_invocation()
+}
+
 /// Installs the binary to the device with the given configuration.
 ///
 /// This function is intended for "quiet" installs where the installation
 /// occurs automatically, such as, a shell script.
+///
+/// A system install requires running as root or an administrator, except on
+/// Termux; otherwise, the error kind [`InsufficientPrivileges`](crate::error::InstallerErrorKind::InsufficientPrivileges)
+/// is returned. See [`os::has_system_access()`].
 pub fn install(manifest: &PackageManifest, config: &InstallConfig) -> Result<(), InstallerError> {
     let mut installer = Installer::new(manifest);
     installer.run(config)
 }
 
+/// Installs the new version over the existing installation with the same
+/// options.
+///
+/// The binary is expected to be run from the package, such as the
+/// extracted archive, as in [`install()`]. See [`Installer::upgrade()`]
+/// for details.
+pub fn upgrade(manifest: &PackageManifest) -> Result<(), InstallerError> {
+    let mut installer = Installer::new(manifest);
+    installer.upgrade(&os::current_exe_dir()?)
+}
+
+/// Updates the details of the existing installation, such as the version
+/// and size shown by the OS, without reinstalling.
+///
+/// This is intended for binaries that update themselves in place.
+/// See [`Installer::refresh_os_metadata()`] for details.
+pub fn refresh_os_metadata(manifest: &PackageManifest) -> Result<(), InstallerError> {
+    let installer = Installer::new(manifest);
+    installer.refresh_os_metadata()
+}
+
 /// Starts the uninstaller with a interactive interface.
 ///
 /// A terminal user interface (TUI) will prompt for a confirmation before
@@ -107,6 +148,22 @@ pub fn uninstall(app_id: &AppId) -> Result<(), InstallerError> {
     uninstaller.run()
 }
 
+/// Uninstalls the installation of the given access scope.
+///
+/// This is the same as [`uninstall()`] except that the User installation is
+/// not preferred. For example, an administrator can remove the System
+/// version while a User version is also installed. If the application is
+/// not installed in the scope, the error kind
+/// [`NotInstalled`](crate::error::InstallerErrorKind::NotInstalled) is
+/// returned.
+pub fn uninstall_with_scope(
+    app_id: &AppId,
+    access_scope: AccessScope,
+) -> Result<(), InstallerError> {
+    let mut uninstaller = Uninstaller::new(app_id).with_scope(access_scope);
+    uninstaller.run()
+}
+
 /// Returns the disk manifest when the binary is installed.
 ///
 /// If there is both a User and System installation, the User version will
@@ -115,3 +172,89 @@ pub fn manifest(app_id: &AppId) -> Result<DiskManifest, InstallerError> {
     let exe_path = std::env::current_exe()?;
     crate::manifest::discover_manifest(&exe_path, app_id)
 }
+
+/// Returns the disk manifest of the installation in the given access scope.
+///
+/// Unlike [`manifest()`], the User installation is not preferred. For
+/// example, a binary installed for all users can find its own data
+/// directory even if a stale User installation exists.
+pub fn manifest_in_scope(
+    app_id: &AppId,
+    access_scope: AccessScope,
+) -> Result<DiskManifest, InstallerError> {
+    crate::manifest::discover_manifest_in_scope(app_id, access_scope)
+}
+
+/// Returns the disk manifests of all the installations of the binary.
+///
+/// The installations are in the order [`manifest()`] searches them, so
+/// the first one is the one returned by [`manifest()`]. To find the
+/// installation of the running binary, compare the path of
+/// [`DiskManifest::main_executable()`] with [`std::env::current_exe()`].
+pub fn manifests(app_id: &AppId) -> Result<Vec<DiskManifest>, InstallerError> {
+    let exe_path = std::env::current_exe()?;
+    crate::manifest::discover_manifests(&exe_path, app_id)
+}
+
+/// Returns whether the binary is installed.
+///
+/// Only the existence of the disk manifest is checked, so this is
+/// inexpensive to call on every start up. Use [`installed_version()`] for
+/// the scope and version of the installation.
+pub fn is_installed(app_id: &AppId) -> Result<bool, InstallerError> {
+    let exe_path = std::env::current_exe()?;
+    Ok(crate::manifest::discover_manifest_path(&exe_path, app_id)?.is_some())
+}
+
+/// Returns the access scope and version of the installation, or `None` if
+/// the binary is not installed.
+///
+/// The disk manifest is loaded as in [`manifest()`], so if there is both a
+/// User and System installation, the User version will be returned.
+pub fn installed_version(app_id: &AppId) -> Result<Option<InstalledApp>, InstallerError> {
+    match manifest(app_id) {
+        Ok(manifest) => Ok(Some(InstalledApp::from(manifest))),
+        Err(error) if matches!(error.kind(), InstallerErrorKind::DiskManifestNotFound) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Checks whether the installed files and OS entries are intact.
+///
+/// The disk manifest is loaded as in [`manifest()`] and every installed file
+/// is checked. Missing and modified items, such as a deleted file or a
+/// removed search path (PATH) entry, are returned in the report.
+/// See [`verify::verify_manifest()`] for details.
+pub fn verify(app_id: &AppId) -> Result<VerifyReport, InstallerError> {
+    let manifest = manifest(app_id)?;
+    crate::verify::verify_manifest(&manifest)
+}
+
+/// Restores missing or damaged files and OS entries of the installation.
+///
+/// The binary is expected to be run from the package, such as the
+/// extracted archive, so the files can be copied again.
+/// See [`Installer::repair()`] for details.
+pub fn repair(manifest: &PackageManifest) -> Result<VerifyReport, InstallerError> {
+    let installer = Installer::new(manifest);
+    installer.repair(&os::current_exe_dir()?)
+}
+
+/// Returns the applications installed by this crate on the machine.
+///
+/// This is intended for an overview of a suite of tools. See
+/// [`manifest::installed_apps()`] for details.
+pub fn list_installed() -> Result<Vec<InstalledApp>, InstallerError> {
+    crate::manifest::installed_apps()
+}
+
+/// Returns the install receipt when the binary is installed.
+///
+/// The receipt is a stable JSON format intended for scripts.
+/// See [`Receipt`] for details.
+///
+/// If there is both a User and System installation, the User version will
+/// be returned.
+pub fn receipt(app_id: &AppId) -> Result<Receipt, InstallerError> {
+    crate::manifest::discover_receipt(app_id)
+}
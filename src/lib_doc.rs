@@ -22,6 +22,10 @@
 //! can still bundle your files into a zip or tar.gz file and instruct the
 //! user to extract them.
 //!
+//! The `package` command in this repository's xtask can serve as a
+//! reference: it bundles a binary, renamed with the `_installer` suffix,
+//! and its data files into a zip (Windows) or tar.gz (Unix) file.
+//!
 //! See [`PackageManifest`](crate::PackageManifest) for details on how to
 //! configure the installer to recognize files beside your binary.
 //!
@@ -43,6 +47,53 @@
 //! installer operating. On Windows, some paths might not be preserved.
 //! On Unix, the user's .profile file might be corrupted.
 //!
+//! ### Self-deletion
+//!
+//! The uninstaller removes the main binary even if it is currently running.
+//! On Windows, this uses the `self-replace` crate which spawns a helper
+//! process to delete the file after the binary exits.
+//!
+//! On platforms where this isn't possible (such as a read-only store or
+//! security software that blocks the helper), disable the `self-replace`
+//! feature, use [`Uninstaller::with_keep_main_executable()`](crate::uninst::Uninstaller::with_keep_main_executable)
+//! to leave the binary in place, and enable
+//! [`InstallConfig::overwrite_main_executable`](crate::inst::InstallConfig::overwrite_main_executable)
+//! so reinstalls copy over the binary instead of deleting it.
+//!
+//! ### Antivirus software
+//!
+//! On Windows, antivirus software commonly scans new files and registry
+//! values as they are written, which can briefly lock them. The installer
+//! retries such operations with increasing delays. If they are still locked,
+//! the error kind [`SharingViolation`](crate::error::InstallerErrorKind::SharingViolation)
+//! is returned so the user can be told to try again later or to exclude
+//! the destination directory from real-time scanning.
+//!
+//! ### Files in use
+//!
+//! On Windows, a file that is in use, such as a running binary, cannot be
+//! replaced or removed. For system installs, the installer and uninstaller
+//! schedule the file to be replaced or removed when the computer restarts
+//! and report it with [`InstallReport::reboot_required`](crate::inst::InstallReport::reboot_required)
+//! or [`Uninstaller::reboot_required()`](crate::uninst::Uninstaller::reboot_required).
+//! Scheduling requires administrator privileges, so user installs return
+//! an error instead.
+//!
+//! ### 32-bit binaries on 64-bit Windows
+//!
+//! The App Paths and installed applications entries are always written to
+//! the 64-bit registry view, including for x86 binaries on x64 and ARM64
+//! Windows, so they are found regardless of the binary's architecture.
+//!
+//! ### Interrupted installations
+//!
+//! If the installer or uninstaller is interrupted, such as by a crash or
+//! power loss, a journal is left beside the disk manifest. The next
+//! installation resumes it and the next uninstallation completes it even if
+//! the disk manifest was already removed. The interactive installer offers
+//! to undo an interrupted installation instead.
+//! See [`interrupted_operation()`](crate::manifest::interrupted_operation).
+//!
 //! ### Terminal security
 //!
 //! If a GUI terminal is launched with administrator-level permissions by the OS,
@@ -57,7 +108,8 @@
 //! and will automatically pick a language for the current locale.
 //! Translation files are embedded into the binary.
 //! Use feature `i18n-static` and `i18n-custom` to customize this behavior.
+//! With `i18n-custom`, `takecrate::i18n::load_locales_dir()` loads translation files shipped next to the binary at runtime.
 //!
 //! The translation files are located in the `locales` directory of
 //! this crate's source code. If you want to contribute a localization,
-//! please see the contributing note in the source repository.
\ No newline at end of file
+//! please see the contributing note in the source repository.
@@ -17,6 +17,9 @@ fluent_templates::static_loader! {
 }
 
 static CUSTOM_LOADER: Mutex<Option<Arc<ArcLoader>>> = Mutex::new(None);
+static FALLBACK_LANGUAGES: Mutex<Vec<LanguageIdentifier>> = Mutex::new(Vec::new());
+
+const DEFAULT_FALLBACK_LANGUAGE: LanguageIdentifier = fluent_templates::langid!("en-US");
 
 fn current_lang_id() -> &'static LanguageIdentifier {
     static LANG_ID: LazyLock<LanguageIdentifier> = LazyLock::new(|| {
@@ -41,51 +44,138 @@ pub fn set_custom_loader(loader: ArcLoader) {
     guard.replace(Arc::new(loader));
 }
 
+pub fn set_fallback_languages(values: &[&str]) {
+    let values = values
+        .iter()
+        .filter_map(|value| match LanguageIdentifier::from_str(value) {
+            Ok(value) => Some(value),
+            Err(_) => {
+                tracing::warn!(value, "invalid fallback language tag");
+                None
+            }
+        })
+        .collect();
+
+    *FALLBACK_LANGUAGES.lock().unwrap() = values;
+}
+
 pub struct Locale {
     lang_id: LanguageIdentifier,
     custom_loader: Option<Arc<ArcLoader>>,
+    fallbacks: Vec<LanguageIdentifier>,
+    /// Available languages tried in order for lookups.
+    languages: Vec<LanguageIdentifier>,
 }
 
 impl Locale {
     fn new(id: LanguageIdentifier) -> Self {
-        Self {
+        let mut locale = Self {
             lang_id: id,
             custom_loader: CUSTOM_LOADER.lock().unwrap().clone(),
-        }
+            fallbacks: FALLBACK_LANGUAGES.lock().unwrap().clone(),
+            languages: Vec::new(),
+        };
+        locale.resolve_languages();
+        locale
     }
 
     pub fn with_system() -> Self {
         Self::new(current_lang_id().clone())
     }
 
-    // pub fn with_language_tag(value: &str) -> Self {
-    //     let lang_id = match LanguageIdentifier::from_str(value) {
-    //         Ok(value) => value,
-    //         Err(_) => fluent_templates::langid!("en-US"),
-    //     };
-    //     Self::new(lang_id)
-    // }
+    pub fn with_language_tag(value: &str) -> Self {
+        let lang_id = match LanguageIdentifier::from_str(value) {
+            Ok(value) => value,
+            Err(_) => fluent_templates::langid!("en-US"),
+        };
+        Self::new(lang_id)
+    }
+
+    pub fn language(&self) -> &str {
+        self.lang_id.language.as_str()
+    }
+
+    /// Returns the language tag of the translations that are used, which
+    /// may be a fallback of the requested language.
+    pub fn selected_language_tag(&self) -> String {
+        self.languages
+            .first()
+            .unwrap_or(&DEFAULT_FALLBACK_LANGUAGE)
+            .to_string()
+    }
 
     pub fn set_language_tag(&mut self, value: &str) {
         self.lang_id = match LanguageIdentifier::from_str(value) {
             Ok(value) => value,
             Err(_) => fluent_templates::langid!("en-US"),
         };
+        self.resolve_languages();
     }
 
-    pub fn text(&self, text_id: &str) -> String {
-        if let Some(loader) = &self.custom_loader {
-            loader.lookup(&self.lang_id, text_id)
+    fn resolve_languages(&mut self) {
+        let available = self.available_languages();
+        self.languages = resolve_languages(&self.lang_id, &self.fallbacks, &available);
+
+        tracing::debug!(lang_id = %self.lang_id, languages = ?self.languages, "resolved languages");
+    }
+
+    fn available_languages(&self) -> Vec<LanguageIdentifier> {
+        let mut languages: Vec<LanguageIdentifier> = if let Some(loader) = &self.custom_loader {
+            loader.locales().cloned().collect()
         } else {
             #[cfg(feature = "i18n-static")]
             {
-                LOCALES.lookup(&self.lang_id, text_id)
+                LOCALES.locales().cloned().collect()
             }
             #[cfg(not(feature = "i18n-static"))]
             {
-                text_id.to_string()
+                Vec::new()
+            }
+        };
+
+        // Sorted since loaders store them in hash maps
+        languages.sort_by_key(|value| value.to_string());
+        languages
+    }
+
+    fn lookup(
+        &self,
+        text_id: &str,
+        args: Option<&HashMap<&str, FluentValue<'_>>>,
+    ) -> Option<String> {
+        for lang_id in &self.languages {
+            let result = if let Some(loader) = &self.custom_loader {
+                loader.lookup_single_language(lang_id, text_id, args)
+            } else {
+                #[cfg(feature = "i18n-static")]
+                {
+                    LOCALES.lookup_single_language(lang_id, text_id, args)
+                }
+                #[cfg(not(feature = "i18n-static"))]
+                {
+                    return None;
+                }
+            };
+
+            match result {
+                Ok(value) => return Some(value),
+                Err(error) => {
+                    tracing::trace!(%lang_id, text_id, %error, "lookup failed");
+                }
             }
         }
+
+        None
+    }
+
+    pub fn text(&self, text_id: &str) -> String {
+        self.lookup(text_id, None)
+            .unwrap_or_else(|| text_id.to_string())
+    }
+
+    /// Like [`Self::text()`] but returns `None` if there is no translation.
+    pub fn try_text(&self, text_id: &str) -> Option<String> {
+        self.lookup(text_id, None)
     }
 
     pub fn text_args<'a, A>(&self, text_id: &str, args: A) -> String
@@ -94,17 +184,120 @@ impl Locale {
     {
         let args: HashMap<&str, FluentValue<'_>> = args.into();
 
-        if let Some(loader) = &self.custom_loader {
-            loader.lookup_with_args(&self.lang_id, text_id, &args)
-        } else {
-            #[cfg(feature = "i18n-static")]
-            {
-                LOCALES.lookup_with_args(&self.lang_id, text_id, &args)
-            }
-            #[cfg(not(feature = "i18n-static"))]
-            {
-                text_id.to_string()
+        self.lookup(text_id, Some(&args))
+            .unwrap_or_else(|| text_id.to_string())
+    }
+}
+
+/// Returns the available languages to try in order for the requested
+/// language followed by each fallback language and finally `en-US`.
+fn resolve_languages(
+    requested: &LanguageIdentifier,
+    fallbacks: &[LanguageIdentifier],
+    available: &[LanguageIdentifier],
+) -> Vec<LanguageIdentifier> {
+    let mut languages = Vec::new();
+
+    for wanted in std::iter::once(requested)
+        .chain(fallbacks)
+        .chain(std::iter::once(&DEFAULT_FALLBACK_LANGUAGE))
+    {
+        for lang_id in matching_languages(wanted, available) {
+            if !languages.contains(lang_id) {
+                languages.push(lang_id.clone());
             }
         }
     }
+
+    languages
+}
+
+/// Returns the available languages matching the wanted language from the
+/// most to least specific.
+///
+/// For example, `zh-Hant-TW` matches `zh-Hant-TW`, then `zh-TW`, then
+/// `zh`. A wanted language without a region, such as `pt`, also matches
+/// regional variants, such as `pt-BR`.
+fn matching_languages<'a>(
+    wanted: &LanguageIdentifier,
+    available: &'a [LanguageIdentifier],
+) -> Vec<&'a LanguageIdentifier> {
+    let same_language = |lang_id: &&LanguageIdentifier| lang_id.language == wanted.language;
+    let exact = available.iter().filter(|lang_id| *lang_id == wanted);
+    let same_region = available
+        .iter()
+        .filter(same_language)
+        .filter(|lang_id| wanted.region.is_some() && lang_id.region == wanted.region);
+    let language_only = available
+        .iter()
+        .filter(same_language)
+        .filter(|lang_id| lang_id.region.is_none());
+    let any_region = available
+        .iter()
+        .filter(same_language)
+        .filter(|_| wanted.region.is_none());
+
+    let mut matches = Vec::new();
+
+    for lang_id in exact
+        .chain(same_region)
+        .chain(language_only)
+        .chain(any_region)
+    {
+        if !matches.contains(&lang_id) {
+            matches.push(lang_id);
+        }
+    }
+
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use fluent_templates::langid;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve_languages() {
+        let available = [
+            langid!("en-US"),
+            langid!("es"),
+            langid!("pt"),
+            langid!("pt-BR"),
+            langid!("zh-CN"),
+            langid!("zh-TW"),
+        ];
+
+        assert_eq!(
+            resolve_languages(&langid!("pt-BR"), &[], &available),
+            [langid!("pt-BR"), langid!("pt"), langid!("en-US")]
+        );
+        assert_eq!(
+            resolve_languages(&langid!("zh-Hant-TW"), &[], &available),
+            [langid!("zh-TW"), langid!("en-US")]
+        );
+        assert_eq!(
+            resolve_languages(&langid!("gl"), &[langid!("es")], &available),
+            [langid!("es"), langid!("en-US")]
+        );
+        assert_eq!(
+            resolve_languages(&langid!("de"), &[], &available),
+            [langid!("en-US")]
+        );
+        assert!(resolve_languages(&langid!("de"), &[], &[]).is_empty());
+    }
+
+    #[cfg(feature = "i18n-static")]
+    #[test]
+    fn test_selected_language_tag() {
+        assert_eq!(
+            Locale::with_language_tag("fr-CA").selected_language_tag(),
+            "fr"
+        );
+        assert_eq!(
+            Locale::with_language_tag("de").selected_language_tag(),
+            "en-US"
+        );
+    }
 }
@@ -7,19 +7,55 @@
 //!
 //! Disk manifests will be located in:
 //!
-//! * `$HOME/.config/io.crates.takecrate/`
-//! * `/var/local/lib/io.crates.takecrate/`
+//! * `$XDG_STATE_HOME/io.crates.takecrate/` (or `$HOME/.local/state` if
+//!   `XDG_STATE_HOME` is not set)
+//! * `/var/lib/takecrate/` (see [`set_unix_system_manifest_dir()`])
 //! * `%LocalAppData%/io.crates.takecrate/`
 //! * `%ProgramData%/io.crates.takecrate/`
 //!
+//! On macOS with [`MacOsPathLayout::Native`](crate::path::MacOsPathLayout::Native),
+//! `~/Library/Application Support/io.crates.takecrate/` and
+//! `/Library/Application Support/io.crates.takecrate/` are used instead.
+//!
 //! Disk manifest files are named `takecrate-manifest__[app-id].ron`
-//! where `[app-id`] is the namespaced ID format.
+//! where `[app-id`] is the namespaced ID format. The directory and file name
+//! are returned by [`manifest_dir()`] and [`manifest_file_name()`] for tools
+//! that need to locate them, such as backup programs. The installed
+//! applications can be listed with [`installed_apps()`].
+//!
+//! A [`Receipt`] intended for scripts is stored beside the disk manifest.
+//!
+//! While installing or uninstalling, a journal named
+//! `takecrate-journal__[app-id].ron` is also stored beside the disk manifest
+//! so an interrupted operation can be detected.
+//! See [`interrupted_operation()`].
+//!
+//! When an uninstallation keeps the user's data, the locations of the kept
+//! files are recorded in `takecrate-kept-data__[app-id].ron` beside the disk
+//! manifest. See [`kept_user_data()`].
+//!
+//! Previous versions used `$XDG_CONFIG_HOME/io.crates.takecrate/` for user
+//! installs and `/var/local/lib/io.crates.takecrate/` for system installs
+//! on Unix. Existing installations there are still found.
+//!
+//! The locations can be replaced with the `user` and `system`
+//! subdirectories of the directory in the `TAKECRATE_STATE_DIR` environment
+//! variable (see [`STATE_DIR_ENV_VAR`]) or
+//! [`InstallConfig::state_dir`](crate::inst::InstallConfig::state_dir).
 //!
 
 pub use self::discovery::*;
 pub use self::disk::*;
 pub use self::id::*;
+pub use self::journal::*;
+pub use self::kept::*;
+pub use self::receipt::*;
+pub use self::version::*;
 
 mod discovery;
 mod disk;
 mod id;
+mod journal;
+mod kept;
+mod receipt;
+mod version;
@@ -1,40 +1,420 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
 
 use crate::{
-    error::{InstallerError, InstallerErrorKind},
+    error::{AddContext, InstallerError, InstallerErrorKind},
     os::AccessScope,
 };
 
 use super::{AppId, DiskManifest};
 
+/// Default directory of system scope disk manifests on Unix.
+pub const UNIX_SYSTEM_MANIFEST_DIR: &str = "/var/lib/takecrate";
+
+/// Directory of system scope disk manifests on Unix used by previous versions.
+///
+/// Existing installations in this directory are still discovered.
+pub const LEGACY_UNIX_SYSTEM_MANIFEST_DIR: &str = "/var/local/lib/io.crates.takecrate";
+
+/// Beginning of the file name of disk manifests.
+///
+/// The file name is the prefix, the namespaced ID of the application, and
+/// the extension. See [`manifest_file_name()`].
+pub const MANIFEST_FILE_PREFIX: &str = "takecrate-manifest__";
+
+/// Extension of the file name of disk manifests, without the dot.
+pub const MANIFEST_FILE_EXTENSION: &str = "ron";
+
+/// Environment variable that relocates the directory of the disk manifests.
+///
+/// If set, the disk manifests and other state files are stored in the
+/// `user` and `system` subdirectories of the given directory instead of the
+/// default locations. This is intended for containerized test environments
+/// and machines where the default locations are not usable. The variable
+/// needs to be set for both the installer and uninstaller.
+///
+/// For a single installation, see
+/// [`InstallConfig::state_dir`](crate::inst::InstallConfig::state_dir).
+pub const STATE_DIR_ENV_VAR: &str = "TAKECRATE_STATE_DIR";
+
+static UNIX_SYSTEM_MANIFEST_DIR_OVERRIDE: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Sets the directory of system scope disk manifests on Unix.
+///
+/// The default is [`UNIX_SYSTEM_MANIFEST_DIR`], or the directory within the
+/// prefix on Termux (see [`termux_prefix()`](crate::os::termux_prefix)).
+/// This setting applies to the
+/// whole process and should be set before installing or uninstalling. The
+/// same directory needs to be used by the installer and uninstaller.
+pub fn set_unix_system_manifest_dir<P: Into<PathBuf>>(path: P) {
+    let mut value = UNIX_SYSTEM_MANIFEST_DIR_OVERRIDE.write().unwrap();
+    *value = Some(path.into());
+}
+
+/// Returns the directory of system scope disk manifests on Unix.
+///
+/// See [`set_unix_system_manifest_dir()`].
+pub fn unix_system_manifest_dir() -> PathBuf {
+    UNIX_SYSTEM_MANIFEST_DIR_OVERRIDE
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| match crate::os::termux_prefix() {
+            Some(prefix) => prefix.join(UNIX_SYSTEM_MANIFEST_DIR.trim_start_matches('/')),
+            None => PathBuf::from(UNIX_SYSTEM_MANIFEST_DIR),
+        })
+}
+
+/// Directory of disk manifests within the `Library` directories used by
+/// [`MacOsPathLayout::Native`](crate::path::MacOsPathLayout::Native).
+const MACOS_MANIFEST_DIR: &str = "Library/Application Support/io.crates.takecrate";
+
+/// Returns the directory set by [`STATE_DIR_ENV_VAR`], if any.
+pub fn state_dir_override() -> Option<PathBuf> {
+    std::env::var_os(STATE_DIR_ENV_VAR)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Returns the directory containing the disk manifests for the access scope
+/// within a relocated state directory.
+///
+/// See [`STATE_DIR_ENV_VAR`].
+pub fn state_manifest_dir(state_dir: &Path, access_scope: AccessScope) -> PathBuf {
+    match access_scope {
+        AccessScope::User => state_dir.join("user"),
+        AccessScope::System => state_dir.join("system"),
+    }
+}
+
+/// Returns the file path of the [`DiskManifest`] within the given state
+/// directory or the default location if `None`.
+pub(crate) fn manifest_path_in(
+    state_dir: Option<&Path>,
+    app_id: &AppId,
+    access_scope: AccessScope,
+) -> Result<PathBuf, InstallerError> {
+    match state_dir {
+        Some(state_dir) => Ok(manifest_file_path(
+            &state_manifest_dir(state_dir, access_scope),
+            app_id,
+        )),
+        None => manifest_path(app_id, access_scope),
+    }
+}
+
+/// Returns the file path of an existing [`DiskManifest`] within the given
+/// state directory or the default locations if `None`.
+pub(crate) fn existing_manifest_path_in(
+    state_dir: Option<&Path>,
+    app_id: &AppId,
+    access_scope: AccessScope,
+) -> Result<Option<PathBuf>, InstallerError> {
+    match state_dir {
+        Some(_) => {
+            Ok(Some(manifest_path_in(state_dir, app_id, access_scope)?)
+                .filter(|path| path.exists()))
+        }
+        None => existing_manifest_path(app_id, access_scope),
+    }
+}
+
 /// Returns the expected file path of the [`DiskManifest`] on the user's machine.
 pub fn manifest_path(app_id: &AppId, access_scope: AccessScope) -> Result<PathBuf, InstallerError> {
-    let state_path = match std::env::consts::FAMILY {
-        "windows" => match access_scope {
-            AccessScope::User => PathBuf::from(crate::os::env_var("LocalAppData")?),
-            AccessScope::System => PathBuf::from(crate::os::env_var("ProgramData")?),
-        },
-
-        "unix" => match access_scope {
-            AccessScope::User => {
-                if let Some(value) = std::env::var_os("XDG_CONFIG_HOME") {
-                    PathBuf::from(value)
-                } else {
-                    PathBuf::from(crate::os::env_var("HOME")?).join(".config")
+    Ok(manifest_file_path(&manifest_dir(access_scope)?, app_id))
+}
+
+/// Returns the directory containing the disk manifests for the access scope.
+///
+/// The [`Receipt`](super::Receipt), journal, and other state files of
+/// takecrate are stored in the same directory. The directory may not exist
+/// if nothing was installed.
+///
+/// Disk manifests of applications installed by previous versions on Unix
+/// may be in [`LEGACY_UNIX_SYSTEM_MANIFEST_DIR`] instead. The directory is
+/// relocated if [`STATE_DIR_ENV_VAR`] is set. On macOS with
+/// [`MacOsPathLayout::Native`](crate::path::MacOsPathLayout::Native), the
+/// `Library/Application Support` directories are used.
+pub fn manifest_dir(access_scope: AccessScope) -> Result<PathBuf, InstallerError> {
+    if let Some(state_dir) = state_dir_override() {
+        return Ok(state_manifest_dir(&state_dir, access_scope));
+    }
+
+    match std::env::consts::FAMILY {
+        "windows" => {
+            let dir = match access_scope {
+                AccessScope::User => PathBuf::from(crate::os::env_var("LocalAppData")?),
+                AccessScope::System => PathBuf::from(crate::os::env_var("ProgramData")?),
+            };
+
+            Ok(dir.join("io.crates.takecrate"))
+        }
+        "unix" if crate::path::is_native_macos_layout() => macos_manifest_dir(access_scope),
+        "unix" => unix_manifest_dir(access_scope),
+        _ => Err(InstallerErrorKind::UnsupportedOsFamily.into()),
+    }
+}
+
+fn unix_manifest_dir(access_scope: AccessScope) -> Result<PathBuf, InstallerError> {
+    match access_scope {
+        AccessScope::User => Ok(crate::os::xdg_base_dir(
+            |key| crate::os::env_var(key),
+            "XDG_STATE_HOME",
+            ".local/state",
+        )?
+        .join("io.crates.takecrate")),
+        AccessScope::System => Ok(unix_system_manifest_dir()),
+    }
+}
+
+fn macos_manifest_dir(access_scope: AccessScope) -> Result<PathBuf, InstallerError> {
+    match access_scope {
+        AccessScope::User => {
+            Ok(PathBuf::from(crate::os::env_var("HOME")?).join(MACOS_MANIFEST_DIR))
+        }
+        AccessScope::System => {
+            // An explicitly set directory takes precedence over the layout
+            match UNIX_SYSTEM_MANIFEST_DIR_OVERRIDE.read().unwrap().clone() {
+                Some(dir) => Ok(dir),
+                None => Ok(Path::new("/").join(MACOS_MANIFEST_DIR)),
+            }
+        }
+    }
+}
+
+/// Returns the directories of the disk manifests used by previous versions
+/// or other layouts that are different from [`manifest_dir()`].
+fn legacy_manifest_dirs(access_scope: AccessScope) -> Vec<PathBuf> {
+    if std::env::consts::FAMILY != "unix" || state_dir_override().is_some() {
+        return Vec::new();
+    }
+
+    let mut dirs = Vec::new();
+
+    if crate::path::is_native_macos_layout() {
+        dirs.extend(unix_manifest_dir(access_scope).ok());
+    }
+
+    match access_scope {
+        AccessScope::User => dirs.extend(
+            crate::os::xdg_base_dir(|key| crate::os::env_var(key), "XDG_CONFIG_HOME", ".config")
+                .ok()
+                .map(|dir| dir.join("io.crates.takecrate")),
+        ),
+        AccessScope::System => dirs.push(PathBuf::from(LEGACY_UNIX_SYSTEM_MANIFEST_DIR)),
+    }
+
+    let current_dir = manifest_dir(access_scope).ok();
+    dirs.retain(|dir| Some(dir) != current_dir.as_ref());
+    dirs.dedup();
+
+    dirs
+}
+
+/// Returns the file paths of the [`DiskManifest`] used by previous versions
+/// or other layouts that are different from [`manifest_path()`].
+pub(crate) fn legacy_manifest_paths(app_id: &AppId, access_scope: AccessScope) -> Vec<PathBuf> {
+    legacy_manifest_dirs(access_scope)
+        .into_iter()
+        .map(|dir| manifest_file_path(&dir, app_id))
+        .collect()
+}
+
+/// Returns the file path of an existing [`DiskManifest`] for the given scope
+/// including the location used by previous versions.
+pub(crate) fn existing_manifest_path(
+    app_id: &AppId,
+    access_scope: AccessScope,
+) -> Result<Option<PathBuf>, InstallerError> {
+    let path = manifest_path(app_id, access_scope)?;
+
+    if path.exists() {
+        return Ok(Some(path));
+    }
+
+    Ok(legacy_manifest_paths(app_id, access_scope)
+        .into_iter()
+        .find(|path| path.exists()))
+}
+
+fn manifest_file_path(dir: &Path, app_id: &AppId) -> PathBuf {
+    dir.join(manifest_file_name(app_id))
+}
+
+/// Returns the file name of the [`DiskManifest`] such as
+/// `takecrate-manifest__com.example.my-app.ron`.
+pub fn manifest_file_name(app_id: &AppId) -> String {
+    format!(
+        "{}{}.{}",
+        MANIFEST_FILE_PREFIX,
+        app_id.namespaced_id(),
+        MANIFEST_FILE_EXTENSION
+    )
+}
+
+/// Returns the namespaced ID of the application from the file name of
+/// a [`DiskManifest`].
+///
+/// Returns `None` if the file name does not follow the naming scheme of
+/// [`manifest_file_name()`].
+pub fn manifest_file_namespaced_id(file_name: &str) -> Option<&str> {
+    file_name
+        .strip_prefix(MANIFEST_FILE_PREFIX)?
+        .strip_suffix(MANIFEST_FILE_EXTENSION)?
+        .strip_suffix('.')
+        .filter(|id| !id.is_empty())
+}
+
+/// Summary of an installed application found by [`installed_apps()`].
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct InstalledApp {
+    /// Application ID.
+    pub app_id: AppId,
+    /// Application name displayed to the user.
+    pub app_name: String,
+    /// Application version displayed to the user.
+    pub app_version: String,
+    /// Access scope.
+    pub access_scope: AccessScope,
+    /// Path of the disk manifest.
+    pub manifest_path: PathBuf,
+}
+
+impl From<DiskManifest> for InstalledApp {
+    fn from(manifest: DiskManifest) -> Self {
+        Self {
+            app_id: manifest.app_id,
+            app_name: manifest.app_name,
+            app_version: manifest.app_version,
+            access_scope: manifest.access_scope,
+            manifest_path: manifest.manifest_path,
+        }
+    }
+}
+
+/// Returns the applications installed for the current user and for all
+/// users.
+///
+/// The directories containing the disk manifests are scanned for
+/// `takecrate-manifest__*.ron` files, including the location used by
+/// previous versions. Disk manifests that can't be read are skipped.
+///
+/// The applications are sorted by ID and user installations come first.
+pub fn installed_apps() -> Result<Vec<InstalledApp>, InstallerError> {
+    let mut apps = Vec::<InstalledApp>::new();
+
+    for access_scope in [AccessScope::User, AccessScope::System] {
+        let mut dirs = vec![manifest_dir(access_scope)?];
+        dirs.extend(legacy_manifest_dirs(access_scope));
+
+        for dir in dirs {
+            for manifest_path in manifest_files_in(&dir)? {
+                let manifest = match DiskManifest::load(&manifest_path) {
+                    Ok(manifest) => manifest,
+                    Err(error) => {
+                        tracing::warn!(?manifest_path, ?error, "skipping unreadable disk manifest");
+                        continue;
+                    }
+                };
+
+                // The current location takes precedence over the legacy one
+                if apps.iter().any(|app| {
+                    app.access_scope == access_scope
+                        && app.app_id.namespaced_id() == manifest.app_id.namespaced_id()
+                }) {
+                    continue;
                 }
+
+                apps.push(InstalledApp {
+                    access_scope,
+                    manifest_path,
+                    ..InstalledApp::from(manifest)
+                });
             }
-            AccessScope::System => PathBuf::from("/var/local/lib"),
-        },
-        _ => return Err(InstallerErrorKind::UnsupportedOsFamily.into()),
+        }
     }
-    .join("io.crates.takecrate");
 
-    let manifest_path = state_path.join(format!(
-        "takecrate-manifest__{}.ron",
-        app_id.namespaced_id()
-    ));
+    apps.sort_by_key(|app| {
+        (
+            app.app_id.namespaced_id().to_string(),
+            app.access_scope == AccessScope::System,
+        )
+    });
 
-    Ok(manifest_path)
+    Ok(apps)
+}
+
+/// Returns the paths of the disk manifest files in the directory.
+fn manifest_files_in(dir: &Path) -> Result<Vec<PathBuf>, InstallerError> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => {
+            return Err(InstallerError::from(error)
+                .with_context(format!("could not read directory {:?}", dir)))
+        }
+    };
+
+    let mut paths = Vec::new();
+
+    for entry in entries {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        if manifest_file_namespaced_id(&name).is_some() && path.is_file() {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Creates the directory containing the disk manifests if needed.
+///
+/// Creation racing with another process is not an error. On Unix, the
+/// directory is readable by all users for system scope (regardless of the
+/// umask) and private for user scope.
+pub(crate) fn create_manifest_dir(
+    dir: &Path,
+    access_scope: AccessScope,
+) -> Result<(), InstallerError> {
+    if dir.is_dir() {
+        return Ok(());
+    }
+
+    tracing::debug!(?dir, "creating manifest directory");
+
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+
+    #[cfg(unix)]
+    let mode = match access_scope {
+        AccessScope::User => 0o700,
+        AccessScope::System => 0o755,
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(mode);
+    }
+
+    builder
+        .create(dir)
+        .with_contextc(|_e| format!("failed to create directory {:?}", dir))?;
+
+    #[cfg(unix)]
+    crate::os::unix::set_posix_permission(dir, mode)
+        .with_contextc(|_e| format!("failed to set permissions of directory {:?}", dir))?;
+
+    let _ = access_scope;
+
+    Ok(())
 }
 
 /// Finds the [`DiskManifest`] on the machine and returns it.
@@ -43,26 +423,102 @@ pub fn manifest_path(app_id: &AppId, access_scope: AccessScope) -> Result<PathBu
 ///
 /// Internal implementation detail: Attempts to search user, then system.
 pub fn discover_manifest(exe_path: &Path, app_id: &AppId) -> Result<DiskManifest, InstallerError> {
-    let single_dir_path = exe_path.join(format!(
-        "../takecrate-manifest__{}.ron",
-        app_id.namespaced_id()
-    ));
+    match discover_manifest_path(exe_path, app_id)? {
+        Some(path) => DiskManifest::load(&path),
+        None => Err(InstallerErrorKind::DiskManifestNotFound.into()),
+    }
+}
 
-    if single_dir_path.exists() {
-        return DiskManifest::load(&single_dir_path);
+/// Finds the [`DiskManifest`] of the given access scope and returns it.
+///
+/// The location used by previous versions is also checked. If it is not
+/// found, an error kind [`InstallerErrorKind::DiskManifestNotFound`] is
+/// returned.
+pub fn discover_manifest_in_scope(
+    app_id: &AppId,
+    access_scope: AccessScope,
+) -> Result<DiskManifest, InstallerError> {
+    match existing_manifest_path(app_id, access_scope)? {
+        Some(path) => DiskManifest::load(&path),
+        None => Err(InstallerErrorKind::DiskManifestNotFound.into()),
     }
+}
 
-    let user_path = manifest_path(app_id, AccessScope::User)?;
+/// Finds every [`DiskManifest`] of the application on the machine.
+///
+/// Unlike [`discover_manifest()`], which returns only the first one found,
+/// this is intended for when the application is installed more than once,
+/// such as a system installation and a stale user installation. The order
+/// is the same as the search order of [`discover_manifest()`]. The list is
+/// empty if the application is not installed.
+pub fn discover_manifests(
+    exe_path: &Path,
+    app_id: &AppId,
+) -> Result<Vec<DiskManifest>, InstallerError> {
+    let mut manifests = Vec::new();
+    let single_dir_path = exe_path.join("..").join(manifest_file_name(app_id));
+
+    if single_dir_path.exists() {
+        manifests.push(DiskManifest::load(&single_dir_path)?);
+    }
 
-    if user_path.exists() {
-        return DiskManifest::load(&user_path);
+    for access_scope in [AccessScope::User, AccessScope::System] {
+        if let Some(path) = existing_manifest_path(app_id, access_scope)? {
+            manifests.push(DiskManifest::load(&path)?);
+        }
     }
 
-    let system_path = manifest_path(app_id, AccessScope::System)?;
+    Ok(manifests)
+}
+
+/// Returns the file path of the [`DiskManifest`] that [`discover_manifest()`]
+/// would load without reading it.
+///
+/// Returns `None` if the application is not installed.
+pub fn discover_manifest_path(
+    exe_path: &Path,
+    app_id: &AppId,
+) -> Result<Option<PathBuf>, InstallerError> {
+    let single_dir_path = exe_path.join("..").join(manifest_file_name(app_id));
 
-    if system_path.exists() {
-        return DiskManifest::load(&system_path);
+    if single_dir_path.exists() {
+        return Ok(Some(single_dir_path));
     }
 
-    Err(InstallerErrorKind::DiskManifestNotFound.into())
+    for access_scope in [AccessScope::User, AccessScope::System] {
+        if let Some(path) = existing_manifest_path(app_id, access_scope)? {
+            return Ok(Some(path));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_file_name() {
+        let app_id = AppId::new("com.example.my-app").unwrap();
+        let name = manifest_file_name(&app_id);
+
+        assert_eq!(name, "takecrate-manifest__com.example.my-app.ron");
+        assert_eq!(
+            manifest_file_namespaced_id(&name),
+            Some("com.example.my-app")
+        );
+        assert_eq!(
+            manifest_file_namespaced_id("takecrate-manifest__.ron"),
+            None
+        );
+        assert_eq!(
+            manifest_file_namespaced_id("takecrate-receipt__my-app.json"),
+            None
+        );
+        assert_eq!(
+            manifest_file_namespaced_id("takecrate-manifest__my-app"),
+            None
+        );
+    }
 }
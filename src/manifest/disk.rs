@@ -1,7 +1,9 @@
 use std::{
+    collections::{BTreeMap, HashMap},
     fs::File,
-    io::{Cursor, Read, Write},
+    io::{Read, Write},
     path::{Path, PathBuf},
+    sync::OnceLock,
 };
 
 use ron::ser::PrettyConfig;
@@ -9,14 +11,35 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     error::{AddContext, InstallerError, InstallerErrorKind},
-    os::AccessScope,
+    inst::{InstallProfile, SearchPathStrategy},
+    os::{AccessScope, FileChecksum, FileDigest, InstallerIdentity},
     path::AppPathPrefix,
 };
 
-use super::AppId;
+use super::{AppId, AppVersion};
+
+/// Current version of the [`DiskManifest`] format.
+///
+/// Manifests written by previous versions are migrated when loaded.
+/// Version 0 is the format before versioning. Version 2 adds the integrity
+/// digest.
+pub const DISK_MANIFEST_VERSION: u64 = 2;
+
+/// JSON schema of the [`DiskManifest`] format returned by
+/// [`DiskManifest::to_json()`].
+pub const DISK_MANIFEST_JSON_SCHEMA: &str = include_str!("../../schemas/disk-manifest.schema.json");
+
+/// Prefix of the comment line at the end of the disk manifest that contains
+/// the integrity digest.
+pub(super) const INTEGRITY_PREFIX: &str = "\n// integrity: ";
+
+/// Maximum size in bytes of a disk manifest that will be read.
+///
+/// This prevents a corrupted file from exhausting memory when uninstalling.
+pub(super) const MAX_DISK_MANIFEST_SIZE: u64 = 64 * 1024 * 1024;
 
 /// A category of a file.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FileType {
     /// A program file that can be run by the user.
     Executable,
@@ -37,15 +60,10 @@ pub enum FileType {
     Documentation,
 
     /// Additional data files used by a program.
+    #[default]
     Data,
 }
 
-impl Default for FileType {
-    fn default() -> Self {
-        Self::Data
-    }
-}
-
 /// Information about an installed file.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[non_exhaustive]
@@ -60,6 +78,33 @@ pub struct DiskFileEntry {
     pub file_type: FileType,
     /// Whether this file is the main binary with the self-installer.
     pub is_main_executable: bool,
+    /// Digest computed by the [`Hasher`](crate::os::Hasher) set when
+    /// installing, if any.
+    ///
+    /// The digest includes the algorithm used, so it is checked with the
+    /// same algorithm. See [`Self::current_checksum()`].
+    #[serde(default)]
+    pub digest: Option<FileDigest>,
+}
+
+impl DiskFileEntry {
+    /// Returns the checksum of the file as installed.
+    pub fn checksum(&self) -> FileChecksum {
+        FileChecksum {
+            crc32c: self.crc32c,
+            len: self.len,
+            digest: self.digest.clone(),
+        }
+    }
+
+    /// Computes the checksum of the file currently at the path using the
+    /// digest algorithm recorded in the entry.
+    pub fn current_checksum(&self) -> std::io::Result<FileChecksum> {
+        crate::os::file_checksum_with_algorithm(
+            &self.path,
+            self.digest.as_ref().map(|digest| digest.algorithm.as_str()),
+        )
+    }
 }
 
 /// Information about an installed directory.
@@ -72,6 +117,63 @@ pub struct DiskDirEntry {
     pub preserve: bool,
 }
 
+/// Information about a registered file association.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DiskFileAssociation {
+    /// Programmatic identifier.
+    pub prog_id: String,
+    /// File extensions including the leading dot.
+    pub extensions: Vec<String>,
+}
+
+/// Choices of the [`InstallConfig`](crate::inst::InstallConfig) used for
+/// an installation.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DiskInstallOptions {
+    /// Whether the search path (PATH) was modified.
+    pub modify_os_search_path: bool,
+    /// Method of modifying the search path.
+    pub search_path_strategy: SearchPathStrategy,
+    /// Whether a Start Menu shortcut was requested.
+    pub start_menu_shortcut: bool,
+    /// Whether a desktop shortcut was requested.
+    pub desktop_shortcut: bool,
+    /// Whether scheduled tasks were registered.
+    pub register_scheduled_tasks: bool,
+    /// The profile chosen in the interactive installer, if any.
+    pub profile: Option<InstallProfile>,
+}
+
+/// Information about a persistently set environment variable.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DiskEnvVar {
+    /// Name of the variable.
+    pub name: String,
+    /// Value of the variable when it was set.
+    pub value: String,
+}
+
+/// Result of checking a [`DiskManifest`] against its integrity digest.
+///
+/// The digest is an HMAC-SHA256 of the contents keyed with the application
+/// ID's UUID. It detects corruption and modification by other programs.
+/// Because the key is not secret, it does not prevent deliberate forgery.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ManifestIntegrity {
+    /// Not loaded from a file or written by a previous version without
+    /// a digest.
+    #[default]
+    Unchecked,
+    /// The contents match the digest.
+    Valid,
+    /// The contents do not match the digest or the digest is missing.
+    Invalid,
+}
+
 /// Details about an installed application and its files.
 ///
 /// For the installer counterpart, see [`PackageManifest`](crate::inst::PackageManifest).
@@ -81,7 +183,10 @@ pub struct DiskManifest {
     #[serde(skip)]
     /// Path of manifest installed on disk.
     pub manifest_path: PathBuf,
-    /// Reserved.
+    /// Version of the format.
+    ///
+    /// Loading a manifest migrates it to [`DISK_MANIFEST_VERSION`].
+    #[serde(default)]
     pub manifest_version: u64,
     /// Application ID.
     pub app_id: AppId,
@@ -89,6 +194,11 @@ pub struct DiskManifest {
     pub app_name: String,
     /// Application version displayed to the user.
     pub app_version: String,
+    /// Application version used for comparing installations.
+    ///
+    /// Not recorded by previous versions. See [`Self::comparable_version()`].
+    #[serde(default)]
+    pub version: Option<AppVersion>,
     /// Access scope.
     pub access_scope: AccessScope,
     /// Directory paths for the installed files.
@@ -99,21 +209,214 @@ pub struct DiskManifest {
     pub files: Vec<DiskFileEntry>,
     /// If specified, the search path (PATH) installed.
     pub search_path: Option<PathBuf>,
-    /// The filename used for the App Paths entry.
+    /// Expected size in bytes of data written by the application after installation.
+    #[serde(default)]
+    pub estimated_data_size: u64,
+    /// Identifiers of the registered scheduled tasks.
+    #[serde(default)]
+    pub scheduled_tasks: Vec<String>,
+    /// Environment variables set persistently.
+    #[serde(default)]
+    pub env_vars: Vec<DiskEnvVar>,
+    /// Who performed the installation.
+    ///
+    /// Only recorded for system scope unless disabled by
+    /// [`InstallConfig::omit_installer_identity`](crate::inst::InstallConfig::omit_installer_identity).
+    #[serde(default)]
+    pub installed_by: Option<InstallerIdentity>,
+    /// When the installation was performed in seconds since the Unix epoch.
+    ///
+    /// Not recorded by previous versions.
+    #[serde(default)]
+    pub installed_at: Option<u64>,
+    /// Version of this crate that performed the installation.
+    ///
+    /// Empty if installed by a previous version.
+    #[serde(default)]
+    pub installer_version: String,
+    /// Choices of the [`InstallConfig`](crate::inst::InstallConfig) used.
+    ///
+    /// Not recorded by previous versions. See
+    /// [`InstallConfig::from_manifest()`](crate::inst::InstallConfig::from_manifest).
+    #[serde(default)]
+    pub install_options: Option<DiskInstallOptions>,
+    /// CPU architectures of the main binary, such as `x86_64` and `arm64`
+    /// for a universal binary.
+    ///
+    /// Only recorded on macOS. See [`crate::os::executable_architectures()`].
+    #[serde(default)]
+    pub main_executable_architectures: Vec<String>,
+    /// Answers to the application's custom pages of the interactive
+    /// installer that are persisted.
+    ///
+    /// See [`CustomPage::persist`](crate::inst::CustomPage::persist).
+    #[serde(default)]
+    pub custom_values: BTreeMap<String, String>,
+    /// The filename used for the App Paths entry of the main binary.
     #[cfg(any(windows, doc))]
     pub app_path_exe_name: Option<String>,
+    /// The filenames used for the App Paths entries of the other commands
+    /// and aliases.
+    #[cfg(any(windows, doc))]
+    #[serde(default)]
+    pub command_app_path_exe_names: Vec<String>,
+    /// Paths of the created shortcut (`.lnk`) files.
+    #[cfg(any(windows, doc))]
+    #[serde(default)]
+    pub shortcuts: Vec<PathBuf>,
+    /// Registered file associations.
+    #[cfg(any(windows, doc))]
+    #[serde(default)]
+    pub file_associations: Vec<DiskFileAssociation>,
+    /// Registered URL schemes.
+    #[cfg(any(windows, doc))]
+    #[serde(default)]
+    pub url_schemes: Vec<String>,
+    /// Paths of the PowerShell profiles modified instead of the Path
+    /// environment variable.
+    #[cfg(any(windows, doc))]
+    #[serde(default)]
+    pub powershell_profile_paths: Vec<PathBuf>,
+    /// The shared directory containing the shims for the commands.
+    ///
+    /// See [`SearchPathStrategy::Shims`](crate::inst::SearchPathStrategy::Shims).
+    #[cfg(any(windows, doc))]
+    #[serde(default)]
+    pub shims_dir: Option<PathBuf>,
+    /// Paths of the links to the commands in the app execution alias
+    /// directory.
+    ///
+    /// See [`SearchPathStrategy::ExecutionAlias`](crate::inst::SearchPathStrategy::ExecutionAlias).
+    #[cfg(any(windows, doc))]
+    #[serde(default)]
+    pub execution_aliases: Vec<PathBuf>,
+    /// Arguments to the main binary in the `UninstallString` of the
+    /// installed applications entry.
+    ///
+    /// Empty if no entry was added.
+    #[cfg(any(windows, doc))]
+    #[serde(default)]
+    pub interactive_uninstall_args: Vec<String>,
+    /// Arguments to the main binary in the `QuietUninstallString` of the
+    /// installed applications entry.
+    ///
+    /// Empty if no entry or no quiet uninstall string was added.
+    #[cfg(any(windows, doc))]
+    #[serde(default)]
+    pub quiet_uninstall_args: Vec<String>,
+    /// Arguments to the main binary in the `ModifyPath` of the installed
+    /// applications entry.
+    ///
+    /// Empty if no entry or no Modify button was added.
+    #[cfg(any(windows, doc))]
+    #[serde(default)]
+    pub modify_args: Vec<String>,
     /// The path of the modified shell profile.
     #[cfg(any(unix, doc))]
     pub shell_profile_path: Option<PathBuf>,
+    /// The paths of the symlinks to the commands, such as aliases and
+    /// symlinks created instead of modifying the search path.
+    #[cfg(any(unix, doc))]
+    #[serde(default)]
+    pub symlinks: Vec<PathBuf>,
+    /// The data directory containing installed MIME types and desktop entries.
+    #[cfg(any(unix, doc))]
+    #[serde(default)]
+    pub desktop_data_dir: Option<PathBuf>,
+    /// Whether the contents matched the integrity digest when loaded.
+    ///
+    /// The uninstaller refuses to remove the files of a manifest that is
+    /// [`ManifestIntegrity::Invalid`].
+    #[serde(skip)]
+    pub integrity: ManifestIntegrity,
+    #[serde(skip)]
+    pub(crate) file_index: OnceLock<DiskFileIndex>,
+}
+
+/// Fields read before the rest of the disk manifest, including the fields
+/// of previous versions that need to be migrated.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct DiskManifestHeader {
+    manifest_version: u64,
+    /// Version 0 only. Renamed to `search_path_symlinks`.
+    #[cfg(unix)]
+    search_path_symlink: Option<PathBuf>,
+    /// Version 0 only. Renamed to `symlinks`.
+    #[cfg(unix)]
+    search_path_symlinks: Vec<PathBuf>,
+}
+
+/// Positions of the file entries by path and by checksum.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DiskFileIndex {
+    /// Number of entries when the index was built.
+    len: usize,
+    by_path: HashMap<PathBuf, usize>,
+    by_checksum: HashMap<(u64, u32), Vec<usize>>,
+}
+
+impl DiskFileIndex {
+    fn new(files: &[DiskFileEntry]) -> Self {
+        let mut index = Self {
+            len: files.len(),
+            ..Default::default()
+        };
+
+        for (position, entry) in files.iter().enumerate() {
+            index.by_path.entry(entry.path.clone()).or_insert(position);
+            index
+                .by_checksum
+                .entry((entry.len, entry.crc32c))
+                .or_default()
+                .push(position);
+        }
+
+        index
+    }
+}
+
+/// Returns the contents without the integrity comment and the digest.
+pub(super) fn split_integrity_digest(contents: &[u8]) -> (&[u8], Option<&str>) {
+    let prefix = INTEGRITY_PREFIX.as_bytes();
+    let position = contents
+        .windows(prefix.len())
+        .rposition(|window| window == prefix);
+
+    match position {
+        Some(position) => {
+            let digest = std::str::from_utf8(&contents[position + prefix.len()..])
+                .map(|digest| digest.trim_end())
+                .unwrap_or_default();
+            (&contents[..position], Some(digest))
+        }
+        None => (contents, None),
+    }
+}
+
+/// Returns the HMAC-SHA256 of the contents keyed with the application ID in
+/// lowercase hexadecimal.
+pub(super) fn integrity_digest(app_id: &AppId, contents: &[u8]) -> String {
+    use hmac::{Hmac, Mac};
+
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(app_id.uuid().as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(contents);
+
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
 }
 
 impl DiskManifest {
     /// Deserialize from the given path.
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, InstallerError> {
         let path = path.as_ref();
-        let buf = std::fs::read(path)
-            .with_contextc(|_error| format!("could not open file {:?}", path))?;
-        let mut manifest = Self::from_reader(Cursor::new(buf))?;
+        let file =
+            File::open(path).with_contextc(|_error| format!("could not open file {:?}", path))?;
+        let mut manifest = Self::from_reader(file)?;
 
         manifest.manifest_path = path.to_path_buf();
 
@@ -121,14 +424,85 @@ impl DiskManifest {
     }
 
     /// Deserialize from the given reader.
+    ///
+    /// Input larger than an internal limit is rejected as malformed.
     pub fn from_reader<R: Read>(reader: R) -> Result<Self, InstallerError> {
-        let manifest = ron::de::from_reader::<R, Self>(reader).map_err(|error| {
+        let mut buf = Vec::new();
+        reader
+            .take(MAX_DISK_MANIFEST_SIZE + 1)
+            .read_to_end(&mut buf)?;
+
+        if buf.len() as u64 > MAX_DISK_MANIFEST_SIZE {
+            return Err(
+                InstallerError::new(InstallerErrorKind::MalformedDiskManifest)
+                    .with_context("disk manifest is too large"),
+            );
+        }
+
+        let (buf, digest) = split_integrity_digest(&buf);
+
+        let header = ron::de::from_bytes::<DiskManifestHeader>(buf).map_err(|error| {
+            InstallerError::new(InstallerErrorKind::MalformedDiskManifest).with_source(error)
+        })?;
+
+        if header.manifest_version > DISK_MANIFEST_VERSION {
+            return Err(
+                InstallerError::new(InstallerErrorKind::UnsupportedDiskManifestVersion)
+                    .with_context(format!(
+                        "disk manifest version {} is newer than {}",
+                        header.manifest_version, DISK_MANIFEST_VERSION
+                    )),
+            );
+        }
+
+        let mut manifest = ron::de::from_bytes::<Self>(buf).map_err(|error| {
             InstallerError::new(InstallerErrorKind::MalformedDiskManifest).with_source(error)
         })?;
 
+        manifest.integrity = match digest {
+            Some(digest) if digest == integrity_digest(&manifest.app_id, buf) => {
+                ManifestIntegrity::Valid
+            }
+            None if header.manifest_version < 2 => ManifestIntegrity::Unchecked,
+            _ => {
+                tracing::warn!(app_id = %manifest.app_id, "disk manifest integrity mismatch");
+                ManifestIntegrity::Invalid
+            }
+        };
+
+        manifest.migrate(header);
+
         Ok(manifest)
     }
 
+    /// Upgrades the fields of a manifest written by a previous version.
+    fn migrate(&mut self, header: DiskManifestHeader) {
+        if self.manifest_version == DISK_MANIFEST_VERSION {
+            return;
+        }
+
+        tracing::debug!(
+            from = self.manifest_version,
+            to = DISK_MANIFEST_VERSION,
+            "migrating disk manifest"
+        );
+
+        if self.manifest_version == 0 {
+            #[cfg(unix)]
+            if self.symlinks.is_empty() {
+                self.symlinks = header
+                    .search_path_symlinks
+                    .into_iter()
+                    .chain(header.search_path_symlink)
+                    .collect();
+            }
+            #[cfg(not(unix))]
+            let _ = header;
+        }
+
+        self.manifest_version = DISK_MANIFEST_VERSION;
+    }
+
     /// Serialize to the given path.
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), InstallerError> {
         let file = File::options()
@@ -143,22 +517,153 @@ impl DiskManifest {
     }
 
     /// Serialize to the given writer.
-    pub fn to_writer<W: Write>(&self, output: W) -> Result<(), InstallerError> {
-        ron::ser::to_writer_pretty(output, &self, PrettyConfig::default())
+    ///
+    /// The integrity digest is appended as a comment.
+    pub fn to_writer<W: Write>(&self, mut output: W) -> Result<(), InstallerError> {
+        let contents = ron::ser::to_string_pretty(&self, PrettyConfig::default())
             .map_err(|error| InstallerError::new(InstallerErrorKind::Other).with_source(error))?;
+        let digest = integrity_digest(&self.app_id, contents.as_bytes());
+
+        output.write_all(contents.as_bytes())?;
+        writeln!(output, "{}{}", INTEGRITY_PREFIX, digest)?;
 
         Ok(())
     }
 
+    /// Serialize to JSON for other programs.
+    ///
+    /// This is intended for deployment tools that need to inspect an
+    /// installation without parsing RON. The format is described by
+    /// [`DISK_MANIFEST_JSON_SCHEMA`]. New fields may be added without
+    /// changing `manifest_version`. Fields will not be removed or changed
+    /// in meaning without incrementing it.
+    ///
+    /// The manifest path and integrity status are not included.
+    pub fn to_json(&self) -> Result<String, InstallerError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|error| InstallerError::new(InstallerErrorKind::Other).with_source(error))
+    }
+
+    /// Deserialize from JSON returned by [`Self::to_json()`].
+    ///
+    /// The manifest is not checked for integrity, so it is trusted when
+    /// written with [`Self::to_writer()`].
+    pub fn from_json(value: &str) -> Result<Self, InstallerError> {
+        let mut manifest = serde_json::from_str::<Self>(value).map_err(|error| {
+            InstallerError::new(InstallerErrorKind::MalformedDiskManifest).with_source(error)
+        })?;
+
+        if manifest.manifest_version > DISK_MANIFEST_VERSION {
+            return Err(
+                InstallerError::new(InstallerErrorKind::UnsupportedDiskManifestVersion)
+                    .with_context(format!(
+                        "disk manifest version {} is newer than {}",
+                        manifest.manifest_version, DISK_MANIFEST_VERSION
+                    )),
+            );
+        }
+
+        manifest.migrate(DiskManifestHeader::default());
+
+        Ok(manifest)
+    }
+
+    /// Returns the version used for comparing installations, if any.
+    ///
+    /// For manifests written by previous versions, it is parsed from
+    /// `app_version`.
+    pub fn comparable_version(&self) -> Option<AppVersion> {
+        self.version
+            .clone()
+            .or_else(|| AppVersion::parse(&self.app_version))
+    }
+
     /// Returns the sum of `len` of the file entries.
     pub fn total_file_size(&self) -> u64 {
         self.files.iter().map(|entry| entry.len).sum()
     }
 
+    /// Returns the sum of [`Self::total_file_size()`] and `estimated_data_size`.
+    pub fn estimated_size(&self) -> u64 {
+        self.total_file_size()
+            .saturating_add(self.estimated_data_size)
+    }
+
     /// Returns the file entry for the main executable.
     pub fn main_executable(&self) -> Option<&DiskFileEntry> {
         self.files.iter().find(|entry| entry.is_main_executable)
     }
+
+    /// Returns the file entry with the given path.
+    ///
+    /// The lookup uses an index built on first use. If entries are added or
+    /// removed afterwards, the entries are searched directly instead. If
+    /// entries are modified in place, call [`Self::reset_file_index()`].
+    pub fn file_by_path<P: AsRef<Path>>(&self, path: P) -> Option<&DiskFileEntry> {
+        let path = path.as_ref();
+        let index = self.file_index();
+
+        match index
+            .by_path
+            .get(path)
+            .map(|&position| self.files.get(position))
+        {
+            Some(Some(entry)) if entry.path == path => Some(entry),
+            None if index.len == self.files.len() => None,
+            _ => {
+                tracing::trace!("file index is stale");
+                self.files.iter().find(|entry| entry.path == path)
+            }
+        }
+    }
+
+    /// Returns the file entries with the given size and checksum.
+    ///
+    /// This is useful for finding whether a file on disk is one that was
+    /// installed, such as a link or a moved file. Like
+    /// [`Self::file_by_path()`], the lookup uses an index built on first use.
+    pub fn files_by_checksum(&self, checksum: &FileChecksum) -> Vec<&DiskFileEntry> {
+        let key = (checksum.len, checksum.crc32c);
+        let index = self.file_index();
+        let mut entries = self.files_by_checksum_key(index, key);
+
+        // Digests aren't indexed
+        entries.retain(|entry| entry.checksum() == *checksum);
+        entries
+    }
+
+    fn files_by_checksum_key(&self, index: &DiskFileIndex, key: (u64, u32)) -> Vec<&DiskFileEntry> {
+        let positions = index.by_checksum.get(&key).map(Vec::as_slice);
+        let entries = positions
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|&position| self.files.get(position))
+            .filter(|entry| (entry.len, entry.crc32c) == key)
+            .collect::<Vec<_>>();
+
+        if entries.len() == positions.map(<[usize]>::len).unwrap_or_default()
+            && index.len == self.files.len()
+        {
+            entries
+        } else {
+            tracing::trace!("file index is stale");
+            self.files
+                .iter()
+                .filter(|entry| (entry.len, entry.crc32c) == key)
+                .collect()
+        }
+    }
+
+    /// Discards the index used by [`Self::file_by_path()`] and
+    /// [`Self::files_by_checksum()`] so it is built again on the next lookup.
+    pub fn reset_file_index(&mut self) {
+        self.file_index = OnceLock::new();
+    }
+
+    fn file_index(&self) -> &DiskFileIndex {
+        self.file_index
+            .get_or_init(|| DiskFileIndex::new(&self.files))
+    }
 }
 
 /// Information about the application's location on disk.
@@ -182,4 +687,227 @@ pub struct DiskPaths {
 
     /// Directory where the application's [`FileType::Data`] files are installed.
     pub data: PathBuf,
+
+    /// Program Files directory the prefix was resolved to on Windows, such
+    /// as `C:\Program Files (x86)` for a 32-bit binary.
+    #[serde(default)]
+    pub program_files_dir: Option<PathBuf>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_lookup() {
+        let mut manifest = DiskManifest::default();
+
+        for (name, len, crc32c) in [("a", 1, 10), ("b", 2, 20), ("c", 1, 10)] {
+            manifest.files.push(DiskFileEntry {
+                path: PathBuf::from(name),
+                len,
+                crc32c,
+                ..Default::default()
+            });
+        }
+
+        assert_eq!(manifest.file_by_path("b").unwrap().len, 2);
+        assert!(manifest.file_by_path("d").is_none());
+
+        let checksum = FileChecksum {
+            crc32c: 10,
+            len: 1,
+            ..Default::default()
+        };
+        let entries = manifest.files_by_checksum(&checksum);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].path, Path::new("c"));
+        assert!(manifest
+            .files_by_checksum(&FileChecksum {
+                crc32c: 10,
+                len: 2,
+                ..Default::default()
+            })
+            .is_empty());
+
+        // Modified after the index was built
+        manifest.files[0].path = PathBuf::from("d");
+        manifest.reset_file_index();
+        assert!(manifest.file_by_path("a").is_none());
+        assert!(manifest.file_by_path("d").is_some());
+
+        manifest.files.push(DiskFileEntry {
+            path: PathBuf::from("e"),
+            len: 1,
+            crc32c: 10,
+            ..Default::default()
+        });
+
+        assert!(manifest.file_by_path("a").is_none());
+        assert!(manifest.file_by_path("d").is_some());
+        assert!(manifest.file_by_path("e").is_some());
+        assert_eq!(manifest.files_by_checksum(&checksum).len(), 3);
+    }
+
+    #[test]
+    fn test_from_reader_version() {
+        let manifest = DiskManifest {
+            manifest_version: DISK_MANIFEST_VERSION,
+            app_name: "Example".to_string(),
+            ..Default::default()
+        };
+        let mut contents = Vec::new();
+        manifest.to_writer(&mut contents).unwrap();
+
+        let manifest = DiskManifest::from_reader(contents.as_slice()).unwrap();
+        assert_eq!(manifest.manifest_version, DISK_MANIFEST_VERSION);
+        assert_eq!(manifest.app_name, "Example");
+
+        let contents = String::from_utf8(contents).unwrap().replace(
+            &format!("manifest_version: {}", DISK_MANIFEST_VERSION),
+            &format!("manifest_version: {}", DISK_MANIFEST_VERSION + 1),
+        );
+        let error = DiskManifest::from_reader(contents.as_bytes()).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            InstallerErrorKind::UnsupportedDiskManifestVersion
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_migrate_version_0() {
+        let manifest = DiskManifest {
+            manifest_version: 0,
+            ..Default::default()
+        };
+        let mut contents = Vec::new();
+        manifest.to_writer(&mut contents).unwrap();
+
+        let contents = String::from_utf8(contents).unwrap();
+        let contents = contents[..contents.find(INTEGRITY_PREFIX).unwrap()].replace(
+            "symlinks: [],",
+            r#"search_path_symlink: Some("/home/user/.local/bin/example"),"#,
+        );
+        let manifest = DiskManifest::from_reader(contents.as_bytes()).unwrap();
+
+        assert_eq!(manifest.manifest_version, DISK_MANIFEST_VERSION);
+        assert_eq!(manifest.integrity, ManifestIntegrity::Unchecked);
+        assert_eq!(
+            manifest.symlinks,
+            [PathBuf::from("/home/user/.local/bin/example")]
+        );
+    }
+
+    #[test]
+    fn test_integrity() {
+        let manifest = DiskManifest {
+            manifest_version: DISK_MANIFEST_VERSION,
+            app_id: AppId::new("io.example.my-app").unwrap(),
+            app_name: "Example".to_string(),
+            ..Default::default()
+        };
+        let mut contents = Vec::new();
+        manifest.to_writer(&mut contents).unwrap();
+        let contents = String::from_utf8(contents).unwrap();
+
+        let manifest = DiskManifest::from_reader(contents.as_bytes()).unwrap();
+        assert_eq!(manifest.integrity, ManifestIntegrity::Valid);
+
+        let modified = contents.replace("\"Example\"", "\"Other\"");
+        let manifest = DiskManifest::from_reader(modified.as_bytes()).unwrap();
+        assert_eq!(manifest.app_name, "Other");
+        assert_eq!(manifest.integrity, ManifestIntegrity::Invalid);
+
+        let removed = &contents[..contents.find(INTEGRITY_PREFIX).unwrap()];
+        let manifest = DiskManifest::from_reader(removed.as_bytes()).unwrap();
+        assert_eq!(manifest.integrity, ManifestIntegrity::Invalid);
+
+        // Bound to the application ID
+        let other_app = contents.replace("io.example.my-app", "io.example.other-app");
+        let manifest = DiskManifest::from_reader(other_app.as_bytes()).unwrap();
+        assert_eq!(manifest.integrity, ManifestIntegrity::Invalid);
+    }
+
+    #[test]
+    fn test_json_schema_matches_fields() {
+        let schema: serde_json::Value = serde_json::from_str(DISK_MANIFEST_JSON_SCHEMA).unwrap();
+        let manifest = DiskManifest {
+            files: vec![DiskFileEntry::default()],
+            dirs: vec![DiskDirEntry::default()],
+            ..Default::default()
+        };
+        let manifest: serde_json::Value =
+            serde_json::from_str(&manifest.to_json().unwrap()).unwrap();
+
+        let check_keys = |schema: &serde_json::Value, value: &serde_json::Value| {
+            let properties = schema["properties"].as_object().unwrap();
+
+            for key in value.as_object().unwrap().keys() {
+                assert!(properties.contains_key(key), "{} not in schema", key);
+            }
+            for key in schema["required"].as_array().unwrap() {
+                assert!(value.get(key.as_str().unwrap()).is_some());
+            }
+        };
+
+        check_keys(&schema, &manifest);
+        check_keys(&schema["properties"]["app_id"], &manifest["app_id"]);
+        check_keys(&schema["properties"]["app_paths"], &manifest["app_paths"]);
+        check_keys(
+            &schema["properties"]["files"]["items"],
+            &manifest["files"][0],
+        );
+        check_keys(&schema["properties"]["dirs"]["items"], &manifest["dirs"][0]);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let manifest = DiskManifest {
+            manifest_version: DISK_MANIFEST_VERSION,
+            app_id: AppId::new("io.example.my-app").unwrap(),
+            access_scope: AccessScope::System,
+            app_paths: DiskPaths {
+                prefix: AppPathPrefix::SingleDir(PathBuf::from("/opt/my-app")),
+                ..Default::default()
+            },
+            files: vec![DiskFileEntry {
+                path: PathBuf::from("/opt/my-app/my-app"),
+                file_type: FileType::Executable,
+                is_main_executable: true,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let text = manifest.to_json().unwrap();
+        assert!(text.contains(r#""namespaced_id": "io.example.my-app""#));
+        assert!(text.contains(r#""access_scope": "System""#));
+        assert!(text.contains(r#""SingleDir": "/opt/my-app""#));
+
+        let imported = DiskManifest::from_json(&text).unwrap();
+        assert_eq!(imported.app_id, manifest.app_id);
+        assert_eq!(imported.app_paths.prefix, manifest.app_paths.prefix);
+        assert!(imported.main_executable().unwrap().is_main_executable);
+
+        let newer = text.replace(
+            &format!(r#""manifest_version": {}"#, DISK_MANIFEST_VERSION),
+            r#""manifest_version": 999"#,
+        );
+        assert!(matches!(
+            DiskManifest::from_json(&newer).unwrap_err().kind(),
+            InstallerErrorKind::UnsupportedDiskManifestVersion
+        ));
+    }
+
+    #[test]
+    fn test_from_reader_too_large() {
+        let reader = std::io::repeat(b' ').take(MAX_DISK_MANIFEST_SIZE + 1);
+        let error = DiskManifest::from_reader(reader).unwrap_err();
+
+        assert!(matches!(
+            error.kind(),
+            InstallerErrorKind::MalformedDiskManifest
+        ));
+    }
 }
@@ -1,10 +1,14 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Ordering, collections::HashMap, fmt::Display, hash::Hash, path::PathBuf, str::FromStr,
+};
 
 use serde::{Deserialize, Serialize};
 
 /// Reexport from [`uuid`] crate:
 pub use uuid::Uuid;
 
+use super::AppVersion;
+
 /// Represents a unique ID for an application.
 ///
 /// There are 3 representations: namespaced, plain, and UUID.
@@ -30,6 +34,10 @@ pub use uuid::Uuid;
 ///
 /// This crate will use the plain format in most cases, such as, directory
 /// names. The dotted and UUID format is used internally and for the OS.
+///
+/// The [`Display`] and [`FromStr`] implementations use the namespaced format.
+/// Comparison and hashing use the normalized namespaced ID
+/// (see [`normalize_namespaced_id()`]) and the UUID.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AppId {
     plain_id: String,
@@ -50,7 +58,7 @@ impl AppId {
         let uuid = app_id_to_uuid(namespaced_id);
 
         Ok(Self {
-            plain_id: namespaced_id.split('.').last().unwrap().to_string(),
+            plain_id: namespaced_id.split('.').next_back().unwrap().to_string(),
             namespaced_id: namespaced_id.to_string(),
             uuid,
         })
@@ -84,6 +92,50 @@ impl AppId {
     pub fn uuid(&self) -> Uuid {
         self.uuid
     }
+
+    fn comparison_key(&self) -> (String, Uuid) {
+        (normalize_namespaced_id(&self.namespaced_id), self.uuid)
+    }
+}
+
+impl Display for AppId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.namespaced_id)
+    }
+}
+
+impl FromStr for AppId {
+    type Err = AppIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl PartialEq for AppId {
+    fn eq(&self, other: &Self) -> bool {
+        self.comparison_key() == other.comparison_key()
+    }
+}
+
+impl Eq for AppId {}
+
+impl PartialOrd for AppId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AppId {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.comparison_key().cmp(&other.comparison_key())
+    }
+}
+
+impl Hash for AppId {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.comparison_key().hash(state);
+    }
 }
 
 /// Returns whether the given namespaced ID is valid.
@@ -150,10 +202,39 @@ pub struct AppMetadata {
     pub display_name: String,
     /// Version of application shown to the user.
     pub display_version: String,
+    /// Version of application used for comparing installations.
+    ///
+    /// If not given, it is parsed from [`Self::display_version`].
+    #[serde(default)]
+    pub version: Option<AppVersion>,
     /// Localized application names.
     ///
     /// Each key is a BCP 47 language tag.
     pub locale_display_name: HashMap<String, String>,
+    /// Name of the person or organization that distributes the application.
+    #[serde(default)]
+    pub publisher: String,
+    /// URL of the application's website.
+    #[serde(default)]
+    pub homepage_url: String,
+    /// Short description of the application, such as "Edits photos".
+    #[serde(default)]
+    pub description: String,
+    /// Localized short descriptions.
+    ///
+    /// Each key is a BCP 47 language tag.
+    #[serde(default)]
+    pub locale_description: HashMap<String, String>,
+    /// SPDX license expression, such as `MIT OR Apache-2.0`.
+    #[serde(default)]
+    pub license: String,
+    /// Target path of an icon file among the package's files, such as
+    /// `my_app.ico`.
+    ///
+    /// The installed icon is shown in the OS's list of installed
+    /// applications on Windows and by desktop entries on Unix.
+    #[serde(default)]
+    pub icon: Option<PathBuf>,
 }
 
 impl AppMetadata {
@@ -164,6 +245,21 @@ impl AppMetadata {
             .map(|v| v.as_str())
             .unwrap_or_else(|| self.display_name.as_str())
     }
+
+    /// Returns a localized description with fallback.
+    pub fn get_description(&self, lang_tag: &str) -> &str {
+        self.locale_description
+            .get(lang_tag)
+            .map(|v| v.as_str())
+            .unwrap_or_else(|| self.description.as_str())
+    }
+
+    /// Returns the version used for comparing installations, if any.
+    pub fn comparable_version(&self) -> Option<AppVersion> {
+        self.version
+            .clone()
+            .or_else(|| AppVersion::parse(&self.display_version))
+    }
 }
 
 /// Error for ID validation.
@@ -189,3 +285,60 @@ pub enum AppIdError {
     #[error("length")]
     Length,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeSet, HashSet};
+
+    use super::*;
+
+    #[test]
+    fn test_display_from_str() {
+        let app_id = AppId::new("io.Example.my-app").unwrap();
+
+        assert_eq!(app_id.to_string(), "io.Example.my-app");
+        assert_eq!(app_id.to_string().parse::<AppId>().unwrap(), app_id);
+        assert!("my-app".parse::<AppId>().is_err());
+    }
+
+    #[test]
+    fn test_eq_normalized() {
+        let app_id_1 = AppId::new("io.example.my-app").unwrap();
+        let app_id_2 = AppId::new("IO.Example.My_App").unwrap();
+        let app_id_3 = AppId::new("io.example.my-app")
+            .unwrap()
+            .with_uuid(Uuid::nil());
+
+        assert_eq!(app_id_1, app_id_2);
+        assert_ne!(app_id_1, app_id_3);
+
+        let set = HashSet::from([app_id_1.clone(), app_id_2.clone(), app_id_3.clone()]);
+        assert_eq!(set.len(), 2);
+
+        let set = BTreeSet::from([
+            AppId::new("io.example.zz").unwrap(),
+            AppId::new("io.example.aa").unwrap(),
+        ]);
+        let ids = set.iter().map(|id| id.namespaced_id()).collect::<Vec<_>>();
+        assert_eq!(ids, ["io.example.aa", "io.example.zz"]);
+    }
+
+    #[test]
+    fn test_serialization_stable() {
+        let app_id = AppId::new("com.example.my-app").unwrap();
+
+        // Changing these values breaks existing installations.
+        assert_eq!(
+            app_id.uuid(),
+            uuid::uuid!("cd337c13-c1e0-57ee-8085-d0099b69375e")
+        );
+        assert_eq!(
+            ron::to_string(&app_id).unwrap(),
+            r#"(plain_id:"my-app",namespaced_id:"com.example.my-app",uuid:"cd337c13-c1e0-57ee-8085-d0099b69375e")"#
+        );
+
+        let app_id_2 = ron::from_str::<AppId>(&ron::to_string(&app_id).unwrap()).unwrap();
+        assert_eq!(app_id_2.plain_id(), "my-app");
+        assert_eq!(app_id_2, app_id);
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{AddContext, InstallerError, InstallerErrorKind},
+    os::AccessScope,
+};
+
+use super::{
+    disk::{integrity_digest, split_integrity_digest, INTEGRITY_PREFIX, MAX_DISK_MANIFEST_SIZE},
+    AppId, DiskManifest, ManifestIntegrity,
+};
+
+/// An operation recorded in the journal while it is in progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum JournalOperation {
+    /// Installing the application.
+    Install,
+    /// Uninstalling the application.
+    Uninstall,
+}
+
+/// An installation or uninstallation that did not complete.
+///
+/// See [`interrupted_operation()`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct InterruptedOperation {
+    /// The operation that was in progress.
+    pub operation: JournalOperation,
+    /// The disk manifest of the installation being installed or uninstalled.
+    ///
+    /// The disk manifest file itself may not exist.
+    pub manifest: DiskManifest,
+    /// Path of the journal file.
+    pub journal_path: PathBuf,
+}
+
+/// Contents of the journal file.
+#[derive(Debug, Serialize, Deserialize)]
+struct Journal {
+    operation: JournalOperation,
+    manifest_path: PathBuf,
+    manifest: DiskManifest,
+}
+
+/// Returns an installation or uninstallation of the application that was
+/// interrupted, such as by a crash or power loss.
+///
+/// The journal is written beside the disk manifest before the installer or
+/// uninstaller modifies anything and is removed when it completes.
+/// Operations currently running in another process are not returned.
+///
+/// An interrupted installation is resumed by installing again or rolled
+/// back with [`Installer::roll_back_interrupted()`](crate::inst::Installer::roll_back_interrupted).
+/// An interrupted uninstallation is completed by uninstalling again or
+/// before installing.
+pub fn interrupted_operation(
+    app_id: &AppId,
+) -> Result<Option<InterruptedOperation>, InstallerError> {
+    let exe_path = std::env::current_exe()?;
+    let mut manifest_paths = vec![exe_path.join("..").join(super::manifest_file_name(app_id))];
+
+    for access_scope in [AccessScope::User, AccessScope::System] {
+        manifest_paths.push(super::manifest_path(app_id, access_scope)?);
+        manifest_paths.extend(super::legacy_manifest_paths(app_id, access_scope));
+    }
+
+    for manifest_path in manifest_paths {
+        let Some(journal_path) = journal_path(&manifest_path, app_id) else {
+            continue;
+        };
+
+        if !journal_path.exists() {
+            continue;
+        }
+
+        // The journal of a running installer or uninstaller is not stale
+        match crate::os::AppLock::acquire(app_id, journal_path.parent().unwrap()) {
+            Ok(_lock) => {}
+            Err(error) if matches!(error.kind(), InstallerErrorKind::AlreadyRunning) => continue,
+            // Such as a system directory while not elevated
+            Err(error) => tracing::debug!(?error, "could not check lock"),
+        }
+
+        return load_journal(&journal_path, app_id).map(Some);
+    }
+
+    Ok(None)
+}
+
+/// Returns the journal beside the disk manifest, if any.
+///
+/// The caller must hold the [`AppLock`](crate::os::AppLock) so the journal
+/// is known to be stale.
+pub(crate) fn stale_journal(
+    manifest_path: &Path,
+    app_id: &AppId,
+) -> Result<Option<InterruptedOperation>, InstallerError> {
+    match journal_path(manifest_path, app_id) {
+        Some(path) if path.exists() => load_journal(&path, app_id).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Writes the journal beside the disk manifest of the given installation.
+///
+/// The journal is signed like the disk manifest so a planted journal
+/// cannot direct the installer to remove files.
+pub(crate) fn write_journal(
+    operation: JournalOperation,
+    manifest: &DiskManifest,
+) -> Result<(), InstallerError> {
+    let Some(path) = journal_path(&manifest.manifest_path, &manifest.app_id) else {
+        return Ok(());
+    };
+
+    tracing::debug!(?path, ?operation, "writing journal");
+
+    let journal = Journal {
+        operation,
+        manifest_path: manifest.manifest_path.clone(),
+        manifest: manifest.clone(),
+    };
+    let mut contents = ron::ser::to_string_pretty(&journal, PrettyConfig::default())
+        .map_err(|error| InstallerError::new(InstallerErrorKind::Other).with_source(error))?;
+    let digest = integrity_digest(&manifest.app_id, contents.as_bytes());
+    contents.push_str(&format!("{}{}\n", INTEGRITY_PREFIX, digest));
+
+    crate::os::write_file_atomic(&path, contents.as_bytes(), true)
+        .with_contextc(|_e| format!("could not write file {:?}", path))?;
+
+    Ok(())
+}
+
+/// Removes the journal beside the disk manifest once the operation completed.
+pub(crate) fn remove_journal(manifest_path: &Path, app_id: &AppId) -> Result<(), InstallerError> {
+    let Some(path) = journal_path(manifest_path, app_id) else {
+        return Ok(());
+    };
+
+    tracing::debug!(?path, "removing journal");
+
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(error) => {
+            Err(InstallerError::from(error)
+                .with_context(format!("could not remove file {:?}", path)))
+        }
+    }
+}
+
+fn journal_path(manifest_path: &Path, app_id: &AppId) -> Option<PathBuf> {
+    let dir = manifest_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())?;
+
+    Some(dir.join(format!("takecrate-journal__{}.ron", app_id.namespaced_id())))
+}
+
+fn load_journal(path: &Path, app_id: &AppId) -> Result<InterruptedOperation, InstallerError> {
+    tracing::debug!(?path, "loading journal");
+
+    let file =
+        File::open(path).with_contextc(|_error| format!("could not open file {:?}", path))?;
+    let mut buf = Vec::new();
+    file.take(MAX_DISK_MANIFEST_SIZE + 1)
+        .read_to_end(&mut buf)?;
+
+    if buf.len() as u64 > MAX_DISK_MANIFEST_SIZE {
+        return Err(
+            InstallerError::new(InstallerErrorKind::MalformedDiskManifest)
+                .with_context("journal is too large"),
+        );
+    }
+
+    let (buf, digest) = split_integrity_digest(&buf);
+
+    let journal = ron::de::from_bytes::<Journal>(buf).map_err(|error| {
+        InstallerError::new(InstallerErrorKind::MalformedDiskManifest)
+            .with_source(error)
+            .with_context(format!("could not read journal {:?}", path))
+    })?;
+
+    if journal.manifest.app_id != *app_id || digest != Some(&integrity_digest(app_id, buf)) {
+        tracing::warn!(?path, "journal integrity mismatch");
+
+        return Err(
+            InstallerError::new(InstallerErrorKind::TamperedDiskManifest)
+                .with_context(format!("journal {:?} failed the integrity check", path)),
+        );
+    }
+
+    let mut manifest = journal.manifest;
+    manifest.manifest_path = journal.manifest_path;
+    manifest.integrity = ManifestIntegrity::Valid;
+
+    Ok(InterruptedOperation {
+        operation: journal.operation,
+        manifest,
+        journal_path: path.to_path_buf(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_id = AppId::new("com.example.journal").unwrap();
+        let manifest_path = dir
+            .path()
+            .join("takecrate-manifest__com.example.journal.ron");
+        let manifest = DiskManifest {
+            manifest_path: manifest_path.clone(),
+            app_id: app_id.clone(),
+            app_version: "1.0.0".to_string(),
+            ..Default::default()
+        };
+
+        assert!(stale_journal(&manifest_path, &app_id).unwrap().is_none());
+
+        write_journal(JournalOperation::Install, &manifest).unwrap();
+
+        let operation = stale_journal(&manifest_path, &app_id).unwrap().unwrap();
+        assert_eq!(operation.operation, JournalOperation::Install);
+        assert_eq!(operation.manifest.manifest_path, manifest_path);
+        assert_eq!(operation.manifest.app_version, "1.0.0");
+
+        remove_journal(&manifest_path, &app_id).unwrap();
+        remove_journal(&manifest_path, &app_id).unwrap();
+        assert!(stale_journal(&manifest_path, &app_id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_journal_integrity() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_id = AppId::new("com.example.journal").unwrap();
+        let manifest_path = dir
+            .path()
+            .join("takecrate-manifest__com.example.journal.ron");
+        let manifest = DiskManifest {
+            manifest_path: manifest_path.clone(),
+            app_id: app_id.clone(),
+            app_version: "1.0.0".to_string(),
+            ..Default::default()
+        };
+
+        write_journal(JournalOperation::Install, &manifest).unwrap();
+
+        let path = journal_path(&manifest_path, &app_id).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, contents.replace("\"1.0.0\"", "\"2.0.0\"")).unwrap();
+
+        let error = stale_journal(&manifest_path, &app_id).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            InstallerErrorKind::TamperedDiskManifest
+        ));
+
+        let removed = &contents[..contents.find(INTEGRITY_PREFIX).unwrap()];
+        std::fs::write(&path, removed).unwrap();
+
+        let error = stale_journal(&manifest_path, &app_id).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            InstallerErrorKind::TamperedDiskManifest
+        ));
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{AddContext, InstallerError, InstallerErrorKind},
+    os::AccessScope,
+};
+
+use super::{disk::MAX_DISK_MANIFEST_SIZE, AppId, DiskDirEntry, DiskFileEntry};
+
+/// Settings and data files left behind by an uninstallation that kept the
+/// user's data.
+///
+/// See [`Uninstaller::with_keep_user_data()`](crate::uninst::Uninstaller::with_keep_user_data)
+/// and [`kept_user_data()`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct KeptUserData {
+    #[serde(skip)]
+    /// Path of the record file.
+    pub record_path: PathBuf,
+    /// Application ID.
+    pub app_id: AppId,
+    /// Directories that were kept.
+    pub dirs: Vec<DiskDirEntry>,
+    /// Installed files that were kept.
+    ///
+    /// The checksums are of the files as originally installed.
+    pub files: Vec<DiskFileEntry>,
+}
+
+/// Returns the data that a previous uninstallation of the application kept.
+///
+/// The record is stored beside the disk manifest as
+/// `takecrate-kept-data__[app-id].ron` and is removed when the application is
+/// installed again to the same access scope.
+pub fn kept_user_data(app_id: &AppId) -> Result<Option<KeptUserData>, InstallerError> {
+    for access_scope in [AccessScope::User, AccessScope::System] {
+        let manifest_path = super::manifest_path(app_id, access_scope)?;
+
+        if let Some(kept) = kept_user_data_beside(&manifest_path, app_id)? {
+            return Ok(Some(kept));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns the record of kept data beside the disk manifest, if any.
+pub(crate) fn kept_user_data_beside(
+    manifest_path: &Path,
+    app_id: &AppId,
+) -> Result<Option<KeptUserData>, InstallerError> {
+    match record_path(manifest_path, app_id) {
+        Some(path) if path.exists() => load_record(&path).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Writes the record of kept data beside the disk manifest.
+pub(crate) fn write_kept_user_data(
+    manifest_path: &Path,
+    kept: &KeptUserData,
+) -> Result<(), InstallerError> {
+    let Some(path) = record_path(manifest_path, &kept.app_id) else {
+        return Ok(());
+    };
+
+    tracing::debug!(?path, "writing kept user data record");
+
+    let mut contents = Vec::new();
+    ron::ser::to_writer_pretty(&mut contents, kept, PrettyConfig::default())
+        .map_err(|error| InstallerError::new(InstallerErrorKind::Other).with_source(error))?;
+
+    crate::os::write_file_atomic(&path, &contents, true)
+        .with_contextc(|_e| format!("could not write file {:?}", path))?;
+
+    Ok(())
+}
+
+/// Removes the record of kept data once the application is installed again.
+pub(crate) fn remove_kept_user_data(
+    manifest_path: &Path,
+    app_id: &AppId,
+) -> Result<(), InstallerError> {
+    let Some(path) = record_path(manifest_path, app_id) else {
+        return Ok(());
+    };
+
+    match std::fs::remove_file(&path) {
+        Ok(()) => {
+            tracing::debug!(?path, "removed kept user data record");
+            Ok(())
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(error) => {
+            Err(InstallerError::from(error)
+                .with_context(format!("could not remove file {:?}", path)))
+        }
+    }
+}
+
+fn record_path(manifest_path: &Path, app_id: &AppId) -> Option<PathBuf> {
+    let dir = manifest_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())?;
+
+    Some(dir.join(format!(
+        "takecrate-kept-data__{}.ron",
+        app_id.namespaced_id()
+    )))
+}
+
+fn load_record(path: &Path) -> Result<KeptUserData, InstallerError> {
+    tracing::debug!(?path, "loading kept user data record");
+
+    let file =
+        File::open(path).with_contextc(|_error| format!("could not open file {:?}", path))?;
+    let mut buf = Vec::new();
+    file.take(MAX_DISK_MANIFEST_SIZE + 1)
+        .read_to_end(&mut buf)?;
+
+    if buf.len() as u64 > MAX_DISK_MANIFEST_SIZE {
+        return Err(
+            InstallerError::new(InstallerErrorKind::MalformedDiskManifest)
+                .with_context("kept user data record is too large"),
+        );
+    }
+
+    let mut kept = ron::de::from_bytes::<KeptUserData>(&buf).map_err(|error| {
+        InstallerError::new(InstallerErrorKind::MalformedDiskManifest)
+            .with_source(error)
+            .with_context(format!("could not read kept user data record {:?}", path))
+    })?;
+    kept.record_path = path.to_path_buf();
+
+    Ok(kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kept_user_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_id = AppId::new("com.example.kept").unwrap();
+        let manifest_path = dir.path().join("takecrate-manifest__com.example.kept.ron");
+        let kept = KeptUserData {
+            app_id: app_id.clone(),
+            dirs: vec![DiskDirEntry {
+                path: dir.path().join("data"),
+                preserve: false,
+            }],
+            files: vec![DiskFileEntry {
+                path: dir.path().join("data").join("settings.txt"),
+                crc32c: 123,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert!(kept_user_data_beside(&manifest_path, &app_id)
+            .unwrap()
+            .is_none());
+
+        write_kept_user_data(&manifest_path, &kept).unwrap();
+
+        let loaded = kept_user_data_beside(&manifest_path, &app_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.dirs[0].path, kept.dirs[0].path);
+        assert_eq!(loaded.files.len(), 1);
+        assert_eq!(loaded.files[0].crc32c, 123);
+        assert!(loaded.record_path.exists());
+
+        remove_kept_user_data(&manifest_path, &app_id).unwrap();
+        remove_kept_user_data(&manifest_path, &app_id).unwrap();
+        assert!(kept_user_data_beside(&manifest_path, &app_id)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{AddContext, InstallerError, InstallerErrorKind},
+    os::AccessScope,
+};
+
+use super::AppId;
+
+/// Current version of the [`Receipt`] format.
+pub const RECEIPT_SCHEMA_VERSION: u64 = 1;
+
+/// JSON schema of the [`Receipt`] format.
+pub const RECEIPT_JSON_SCHEMA: &str = include_str!("../../schemas/receipt.schema.json");
+
+/// Machine-readable summary of an installation for use by scripts.
+///
+/// Unlike [`DiskManifest`](super::DiskManifest), the receipt is a stable
+/// format intended to be read by other programs, such as a
+/// "curl | sh" bootstrap script deciding whether to update or perform a fresh
+/// install. It is a JSON file described by [`RECEIPT_JSON_SCHEMA`] and
+/// located beside the disk manifest:
+///
+/// * `$XDG_STATE_HOME/io.crates.takecrate/takecrate-receipt__[app-id].json`
+///   (or `$HOME/.local/state` if `XDG_STATE_HOME` is not set)
+/// * `/var/lib/takecrate/takecrate-receipt__[app-id].json`
+/// * `%LocalAppData%/io.crates.takecrate/takecrate-receipt__[app-id].json`
+/// * `%ProgramData%/io.crates.takecrate/takecrate-receipt__[app-id].json`
+///
+/// where `[app-id]` is the namespaced ID format.
+///
+/// New fields may be added without changing `schema_version`.
+/// Fields will not be removed or changed in meaning without incrementing it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Receipt {
+    /// Version of the receipt format.
+    pub schema_version: u64,
+    /// Application ID in the namespaced format.
+    pub app_id: String,
+    /// Application name displayed to the user.
+    pub app_name: String,
+    /// Application version displayed to the user.
+    pub app_version: String,
+    /// Access scope.
+    pub access_scope: AccessScope,
+    /// Path of the installed main binary.
+    pub executable: PathBuf,
+    /// Directory where the executable files are installed.
+    pub bin_dir: PathBuf,
+    /// Directory where the data files are installed.
+    pub data_dir: PathBuf,
+    /// Path of the disk manifest.
+    pub manifest_path: PathBuf,
+}
+
+impl Receipt {
+    /// Deserialize from the given path.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, InstallerError> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).with_contextc(|_error| format!("could not open file {:?}", path))?;
+
+        Self::from_reader(file)
+    }
+
+    /// Deserialize from the given reader.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, InstallerError> {
+        serde_json::from_reader(reader).map_err(|error| {
+            InstallerError::new(InstallerErrorKind::MalformedDiskManifest).with_source(error)
+        })
+    }
+
+    /// Serialize to the given writer.
+    pub fn to_writer<W: Write>(&self, output: W) -> Result<(), InstallerError> {
+        serde_json::to_writer_pretty(output, self)
+            .map_err(|error| InstallerError::new(InstallerErrorKind::Other).with_source(error))
+    }
+}
+
+/// Returns the expected file path of the [`Receipt`] on the user's machine.
+pub fn receipt_path(app_id: &AppId, access_scope: AccessScope) -> Result<PathBuf, InstallerError> {
+    let manifest_path = super::manifest_path(app_id, access_scope)?;
+
+    Ok(receipt_path_beside(&manifest_path, app_id))
+}
+
+/// Returns the file path of the [`Receipt`] beside the given disk manifest.
+pub(crate) fn receipt_path_beside(manifest_path: &Path, app_id: &AppId) -> PathBuf {
+    manifest_path.with_file_name(receipt_file_name(app_id))
+}
+
+fn receipt_file_name(app_id: &AppId) -> String {
+    format!("takecrate-receipt__{}.json", app_id.namespaced_id())
+}
+
+/// Finds the [`Receipt`] on the machine and returns it.
+///
+/// The user scope location is checked before the system scope.
+/// If it is not found, an error kind [`InstallerErrorKind::DiskManifestNotFound`] is returned.
+pub fn discover_receipt(app_id: &AppId) -> Result<Receipt, InstallerError> {
+    for access_scope in [AccessScope::User, AccessScope::System] {
+        let Some(manifest_path) = super::existing_manifest_path(app_id, access_scope)? else {
+            continue;
+        };
+        let path = manifest_path.with_file_name(receipt_file_name(app_id));
+
+        if path.exists() {
+            return Receipt::load(&path);
+        }
+    }
+
+    Err(InstallerErrorKind::DiskManifestNotFound.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_matches_fields() {
+        let schema: serde_json::Value = serde_json::from_str(RECEIPT_JSON_SCHEMA).unwrap();
+        let receipt = serde_json::to_value(Receipt::default()).unwrap();
+
+        let mut schema_keys = schema["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect::<Vec<_>>();
+        let mut receipt_keys = receipt.as_object().unwrap().keys().collect::<Vec<_>>();
+        schema_keys.sort();
+        receipt_keys.sort();
+
+        assert_eq!(schema_keys, receipt_keys);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let receipt = Receipt {
+            schema_version: RECEIPT_SCHEMA_VERSION,
+            app_id: "io.example.my-app".to_string(),
+            access_scope: AccessScope::System,
+            executable: PathBuf::from("/usr/local/bin/my-app"),
+            ..Default::default()
+        };
+
+        let mut buf = Vec::new();
+        receipt.to_writer(&mut buf).unwrap();
+        let text = String::from_utf8(buf.clone()).unwrap();
+
+        assert!(text.contains(r#""access_scope": "System""#));
+        assert_eq!(Receipt::from_reader(buf.as_slice()).unwrap(), receipt);
+    }
+}
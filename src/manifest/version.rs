@@ -0,0 +1,156 @@
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Application version that can be compared with other versions.
+///
+/// Only the leading numbers separated by dots are used, such as `1.10.2`
+/// in `v1.10.2-beta`. Missing numbers are zero, so `1.2` and `1.2.0` are
+/// equal.
+///
+/// Stored as a string, such as `"1.10.2"`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AppVersion {
+    parts: Vec<u64>,
+}
+
+impl AppVersion {
+    /// Creates a version from the given numbers.
+    ///
+    /// Returns `None` if there are no numbers.
+    pub fn new(parts: &[u64]) -> Option<Self> {
+        if parts.is_empty() {
+            None
+        } else {
+            Some(Self {
+                parts: parts.to_vec(),
+            })
+        }
+    }
+
+    /// Parses the leading numbers of a version string.
+    ///
+    /// Returns `None` if the version does not start with a number, such as
+    /// `nightly`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = Vec::new();
+
+        for part in value.trim_start_matches(['v', 'V']).split('.') {
+            let end = part
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(part.len());
+
+            match part[..end].parse::<u64>() {
+                Ok(number) => parts.push(number),
+                Err(_) => break,
+            }
+
+            // Numbers after a suffix, such as `-beta.1`, are not part of
+            // the version
+            if end < part.len() {
+                break;
+            }
+        }
+
+        Self::new(&parts)
+    }
+
+    /// Returns the numbers.
+    pub fn parts(&self) -> &[u64] {
+        &self.parts
+    }
+
+    fn part(&self, index: usize) -> u64 {
+        self.parts.get(index).copied().unwrap_or_default()
+    }
+}
+
+impl PartialEq for AppVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for AppVersion {}
+
+impl PartialOrd for AppVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AppVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.parts.len().max(other.parts.len());
+
+        (0..len)
+            .map(|index| self.part(index).cmp(&other.part(index)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl Display for AppVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, part) in self.parts.iter().enumerate() {
+            if index > 0 {
+                f.write_str(".")?;
+            }
+            write!(f, "{}", part)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for AppVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s).ok_or_else(|| format!("invalid version {:?}", s))
+    }
+}
+
+impl TryFrom<String> for AppVersion {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<AppVersion> for String {
+    fn from(value: AppVersion) -> Self {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(value: &str) -> AppVersion {
+        AppVersion::parse(value).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(version("1.10.2").parts(), [1, 10, 2]);
+        assert_eq!(version("v1.2.0-beta.1").parts(), [1, 2, 0]);
+        assert_eq!(version("3").parts(), [3]);
+        assert_eq!(version("2.0 (build 5)").to_string(), "2.0");
+        assert!(AppVersion::parse("nightly").is_none());
+        assert!(AppVersion::parse("").is_none());
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert!(version("2.0") > version("1.9.9"));
+        assert!(version("1.10.0") > version("1.9"));
+        assert!(version("1.2.1") > version("1.2"));
+        assert_eq!(version("1.2"), version("1.2.0"));
+        assert_eq!(version("1.2.0-beta"), version("1.2.0"));
+        assert!(version("1.0") < version("2.0"));
+    }
+}
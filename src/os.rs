@@ -2,15 +2,32 @@
 
 pub use std::env::current_exe;
 use std::{
+    borrow::Cow,
     ffi::{OsStr, OsString},
     fs::File,
+    io::Write,
     path::{Path, PathBuf},
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
 };
 
 use serde::{Deserialize, Serialize};
 
-use crate::error::{InstallerError, InstallerErrorKind};
+use crate::{
+    error::{InstallerError, InstallerErrorKind},
+    manifest::AppId,
+};
+
+pub use self::event_log::*;
+pub use self::hash::*;
+pub use self::macho::*;
+pub use self::platform::*;
 
+mod event_log;
+mod hash;
+pub(crate) mod interrupt;
+mod macho;
+mod platform;
 #[cfg(unix)]
 pub(crate) mod unix;
 #[cfg(windows)]
@@ -43,46 +60,238 @@ impl From<OsError> for InstallerError {
 /// Specifies who can use the binary on a machine.
 ///
 /// See also [`crate::path::AppPathPrefix`].
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AccessScope {
     /// For the current user only.
+    #[default]
     User,
     /// For all users.
     System,
 }
 
-impl Default for AccessScope {
-    fn default() -> Self {
-        Self::User
-    }
-}
-
 /// Information returned by [`file_checksum`].
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Checksums are equal if the CRC32C and size are equal and, if both have
+/// a digest computed with the same algorithm, the digests are equal.
+#[derive(Debug, Clone, Default)]
 pub struct FileChecksum {
     /// CRC32C checksum of the file.
     pub crc32c: u32,
     /// Size of the file.
     pub len: u64,
+    /// Digest computed by the [`Hasher`] set with [`set_hasher()`].
+    pub digest: Option<FileDigest>,
+}
+
+impl PartialEq for FileChecksum {
+    fn eq(&self, other: &Self) -> bool {
+        let digest_eq = match (&self.digest, &other.digest) {
+            (Some(digest), Some(other_digest)) if digest.algorithm == other_digest.algorithm => {
+                digest.value == other_digest.value
+            }
+            _ => true,
+        };
+
+        self.crc32c == other.crc32c && self.len == other.len && digest_eq
+    }
 }
 
 /// Computes a checksum for a file.
+///
+/// The file is read once for the CRC32C and the digest of the
+/// current [`Hasher`].
 pub fn file_checksum<P: AsRef<Path>>(path: P) -> std::io::Result<FileChecksum> {
-    let path = path.as_ref();
+    file_checksum_impl(path.as_ref(), hasher())
+}
+
+/// Computes a checksum for a file with the given digest algorithm.
+///
+/// This is intended for comparing with a checksum recorded in the disk
+/// manifest, which may have been computed with a different [`Hasher`] than
+/// the current one. If the algorithm is `None` or not available, the
+/// checksum has no digest.
+pub fn file_checksum_with_algorithm<P: AsRef<Path>>(
+    path: P,
+    algorithm: Option<&str>,
+) -> std::io::Result<FileChecksum> {
+    file_checksum_impl(
+        path.as_ref(),
+        algorithm.and_then(hash::hasher_for_algorithm),
+    )
+}
+
+fn file_checksum_impl(
+    path: &Path,
+    hasher: Option<std::sync::Arc<dyn Hasher>>,
+) -> std::io::Result<FileChecksum> {
     tracing::trace!(?path, "file checksum");
     let len = path.metadata()?.len();
 
-    let mut file = File::open(path)?;
-    let mut compute = crc32c::Crc32cWriter::new(std::io::empty());
+    let mut reader = hash::Crc32cReader::new(File::open(path)?);
+    let digest = hash::digest_reader(&mut reader, hasher)?;
 
-    std::io::copy(&mut file, &mut compute)?;
+    // Hashers aren't required to read to the end
+    std::io::copy(&mut reader, &mut std::io::sink())?;
 
     Ok(FileChecksum {
-        crc32c: compute.crc32c(),
+        crc32c: reader.crc32c(),
         len,
+        digest,
     })
 }
 
+/// Computes a checksum for contents in memory.
+pub fn bytes_checksum(data: &[u8]) -> std::io::Result<FileChecksum> {
+    Ok(FileChecksum {
+        crc32c: crc32c::crc32c(data),
+        len: data.len() as u64,
+        digest: hash::digest_reader(&mut &data[..], hasher())?,
+    })
+}
+
+/// Returns whether the process can install for [`AccessScope::System`].
+///
+/// This is the same as [`is_elevated()`] except on Termux, where the
+/// system prefix is owned by the user.
+pub fn has_system_access() -> bool {
+    is_elevated() || termux_prefix().is_some()
+}
+
+/// Returns whether the process is running as root or as an administrator.
+///
+/// This is required for [`AccessScope::System`] except on Termux.
+pub fn is_elevated() -> bool {
+    #[cfg(unix)]
+    {
+        unix::is_elevated()
+    }
+    #[cfg(windows)]
+    {
+        windows::is_elevated()
+    }
+}
+
+/// How the installer obtained root or administrator privileges.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum ElevationMethod {
+    /// Not elevated.
+    #[default]
+    None,
+    /// Run as root directly, such as from a root login.
+    Root,
+    /// Run with `sudo`.
+    Sudo,
+    /// Run with `doas`.
+    Doas,
+    /// Run with `pkexec`.
+    Pkexec,
+    /// Run as an administrator on Windows.
+    Administrator,
+}
+
+/// The user and machine that performed an installation.
+///
+/// See [`DiskManifest::installed_by`](crate::manifest::DiskManifest::installed_by).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct InstallerIdentity {
+    /// Name of the user account.
+    ///
+    /// When elevated with a tool such as `sudo`, this is the user that
+    /// invoked the tool instead of root.
+    pub username: String,
+    /// Name of the computer.
+    pub hostname: String,
+    /// How privileges were obtained.
+    pub elevation: ElevationMethod,
+}
+
+/// Returns the user and machine running the current process.
+///
+/// Values that cannot be determined are empty.
+pub fn installer_identity() -> InstallerIdentity {
+    #[cfg(unix)]
+    {
+        unix::installer_identity()
+    }
+    #[cfg(windows)]
+    {
+        windows::installer_identity()
+    }
+}
+
+/// Moves the file to the user's trash instead of deleting it permanently.
+///
+/// This is the Recycle Bin on Windows, `~/.Trash` on macOS, and the home
+/// trash of the FreeDesktop.org Trash specification otherwise.
+pub(crate) fn move_to_trash(path: &Path) -> Result<(), OsError> {
+    tracing::debug!(?path, "moving to trash");
+
+    #[cfg(unix)]
+    {
+        unix::move_to_trash(path)
+    }
+    #[cfg(windows)]
+    {
+        windows::move_to_trash(path)
+    }
+}
+
+/// Exclusive lock held while an application is installed or uninstalled.
+///
+/// On Unix, this is a lock file in the directory of the disk manifest.
+/// On Windows, this is a named mutex shared by all sessions. The lock is
+/// released when dropped, including when the process exits.
+#[derive(Debug)]
+pub(crate) struct AppLock {
+    #[cfg(unix)]
+    _inner: unix::FileLock,
+    #[cfg(windows)]
+    _inner: windows::NamedMutex,
+}
+
+impl AppLock {
+    /// Acquires the lock for the application without waiting.
+    ///
+    /// Returns [`InstallerErrorKind::AlreadyRunning`] if another process
+    /// holds the lock.
+    pub fn acquire(app_id: &AppId, manifest_dir: &Path) -> Result<Self, InstallerError> {
+        #[cfg(unix)]
+        let inner = {
+            use crate::error::AddContext;
+
+            std::fs::create_dir_all(manifest_dir)
+                .with_contextc(|_e| format!("could not create directory {:?}", manifest_dir))?;
+
+            let path =
+                manifest_dir.join(format!("takecrate-lock__{}.lock", app_id.namespaced_id()));
+            tracing::debug!(?path, "acquiring lock file");
+
+            unix::FileLock::try_lock(&path)?
+        };
+
+        #[cfg(windows)]
+        let inner = {
+            let _ = manifest_dir;
+            let name = format!(r"Global\takecrate-lock__{}", app_id.namespaced_id());
+            tracing::debug!(name, "acquiring named mutex");
+
+            windows::NamedMutex::try_lock(&name)?
+        };
+
+        match inner {
+            Some(inner) => Ok(Self { _inner: inner }),
+            None => Err(
+                InstallerError::new(InstallerErrorKind::AlreadyRunning).with_context(format!(
+                    "another installer or uninstaller for {} is running",
+                    app_id.namespaced_id()
+                )),
+            ),
+        }
+    }
+}
+
 /// Returns the filename portion of the current execuable's path.
 ///
 /// See also [`std::env::current_exe()`].
@@ -105,8 +314,448 @@ pub fn current_exe_dir() -> std::io::Result<PathBuf> {
     Ok(path)
 }
 
+/// Returns whether the path is on a medium that may not be reliable as a
+/// source of files while installing.
+///
+/// This includes file systems mounted read-only, such as an ISO, DMG, or
+/// squashfs image. On Windows, optical discs and network shares are also
+/// included.
+pub fn is_read_only_medium(path: &Path) -> Result<bool, InstallerError> {
+    #[cfg(unix)]
+    {
+        Ok(unix::is_read_only_file_system(path)?)
+    }
+    #[cfg(windows)]
+    {
+        Ok(windows::is_read_only_or_remote_volume(path)?)
+    }
+}
+
+/// Number of attempts for operations failing with a sharing violation.
+const SHARING_VIOLATION_ATTEMPTS: u32 = 6;
+
+/// Delay before the first retry, which is doubled for each later retry.
+const SHARING_VIOLATION_DELAY: Duration = Duration::from_millis(100);
+
+/// Runs the function again with increasing delays while it fails with a
+/// sharing violation.
+///
+/// On Windows, antivirus software commonly opens new files and registry
+/// values to scan them, which briefly prevents them from being modified.
+/// If the function still fails, an error with kind
+/// [`InstallerErrorKind::SharingViolation`] is returned.
+pub(crate) fn retry_sharing_violation<T, F>(mut func: F) -> Result<T, InstallerError>
+where
+    F: FnMut() -> Result<T, InstallerError>,
+{
+    let mut delay = SHARING_VIOLATION_DELAY;
+    let mut attempt = 1;
+
+    loop {
+        match func() {
+            Err(error) if is_sharing_violation(&error) => {
+                if attempt >= SHARING_VIOLATION_ATTEMPTS {
+                    tracing::error!(attempt, ?error, "sharing violation, giving up");
+
+                    return Err(InstallerError::new(InstallerErrorKind::SharingViolation)
+                        .with_source(error));
+                }
+
+                tracing::warn!(attempt, ?error, ?delay, "sharing violation, retrying");
+
+                std::thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Returns whether the error is caused by a file that is in use, such as a
+/// running binary, which can only be replaced or removed after a restart.
+#[cfg(windows)]
+pub(crate) fn is_file_in_use(error: &InstallerError) -> bool {
+    matches!(error.kind(), InstallerErrorKind::SharingViolation)
+        || error.chain().any(|item| {
+            item.error()
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|error| {
+                    error.kind() == std::io::ErrorKind::PermissionDenied
+                        || is_io_sharing_violation(error)
+                })
+        })
+}
+
+/// Returns whether the error or any of its sources is a sharing or lock
+/// violation.
+fn is_sharing_violation(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(error);
+
+    while let Some(error) = current {
+        if let Some(error) = error.downcast_ref::<std::io::Error>() {
+            if is_io_sharing_violation(error) {
+                return true;
+            }
+        }
+
+        if let Some(error) = error.downcast_ref::<OsError>() {
+            match error {
+                OsError::Io(error) if is_io_sharing_violation(error) => return true,
+                #[cfg(windows)]
+                OsError::Windows(error) if windows::is_sharing_violation(error) => return true,
+                _ => {}
+            }
+        }
+
+        current = error.source();
+    }
+
+    false
+}
+
+fn is_io_sharing_violation(error: &std::io::Error) -> bool {
+    #[cfg(windows)]
+    {
+        error
+            .raw_os_error()
+            .is_some_and(windows::is_sharing_violation_code)
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = error;
+        false
+    }
+}
+
+/// Returns the path in the extended-length form on Windows, such as
+/// `\\?\C:\Program Files\app`, so it is not limited to `MAX_PATH`.
+///
+/// Relative paths and paths already in a verbatim or device form are
+/// returned unchanged. On other platforms, the path is returned unchanged.
+pub fn extended_length_path(path: &Path) -> Cow<'_, Path> {
+    if cfg!(windows) {
+        if let Some(extended) = path.to_str().and_then(extended_length_path_str) {
+            return Cow::Owned(PathBuf::from(extended));
+        }
+    }
+
+    Cow::Borrowed(path)
+}
+
+/// Converts an absolute Windows path to the extended-length form.
+///
+/// Separators are normalized and `.` and `..` components are resolved
+/// because the OS does not process them for extended-length paths.
+fn extended_length_path_str(path: &str) -> Option<String> {
+    let path = path.replace('/', "\\");
+
+    if path.starts_with("\\\\?\\") || path.starts_with("\\\\.\\") {
+        return None;
+    }
+
+    let (mut result, root_len, rest) = if let Some(rest) = path.strip_prefix("\\\\") {
+        // UNC paths also need the server and share names as the root
+        let mut parts = rest.splitn(3, '\\');
+        let server = parts.next().filter(|part| !part.is_empty())?;
+        let share = parts.next().filter(|part| !part.is_empty())?;
+        let root = format!("\\\\?\\UNC\\{}\\{}", server, share);
+        let root_len = root.len();
+
+        (root, root_len, parts.next().unwrap_or_default().to_string())
+    } else {
+        let bytes = path.as_bytes();
+
+        if bytes.len() < 3 || !bytes[0].is_ascii_alphabetic() || &bytes[1..3] != b":\\" {
+            return None;
+        }
+
+        let root = format!("\\\\?\\{}", &path[..2]);
+        let root_len = root.len();
+
+        (root, root_len, path[3..].to_string())
+    };
+
+    for component in rest.split('\\') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                if let Some(index) = result.rfind('\\').filter(|index| *index >= root_len) {
+                    result.truncate(index);
+                }
+            }
+            component => {
+                result.push('\\');
+                result.push_str(component);
+            }
+        }
+    }
+
+    if result.len() == root_len {
+        result.push('\\');
+    }
+
+    Some(result)
+}
+
+/// Number of temporary file names tried before giving up.
+const TEMP_FILE_ATTEMPTS: u32 = 100;
+
+/// Writes the contents to a file without exposing a partially written file.
+///
+/// The contents are written to a uniquely named temporary file beside the
+/// destination which is then moved into place. If `overwrite` is false and
+/// the destination exists, an error with kind
+/// [`std::io::ErrorKind::AlreadyExists`] is returned, even if the file is
+/// created concurrently by another process.
+pub(crate) fn write_file_atomic(
+    path: &Path,
+    contents: &[u8],
+    overwrite: bool,
+) -> std::io::Result<()> {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    let path = &extended_length_path(path);
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+    let mut attempt = 0;
+
+    let (temp_path, mut file) = loop {
+        let mut temp_name = OsString::from(".");
+        temp_name.push(file_name);
+        temp_name.push(format!(
+            ".{}-{}.takecrate-tmp",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let temp_path = path.with_file_name(temp_name);
+
+        match File::options()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => break (temp_path, file),
+            Err(error)
+                if error.kind() == std::io::ErrorKind::AlreadyExists
+                    && attempt < TEMP_FILE_ATTEMPTS =>
+            {
+                tracing::debug!(?temp_path, "temporary file exists, retrying");
+                attempt += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    };
+
+    let result = file
+        .write_all(contents)
+        .and_then(|_| file.sync_all())
+        .and_then(|_| {
+            drop(file);
+            move_temp_file(&temp_path, path, contents, overwrite)
+        });
+
+    if temp_path.exists() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+fn move_temp_file(
+    temp_path: &Path,
+    path: &Path,
+    contents: &[u8],
+    overwrite: bool,
+) -> std::io::Result<()> {
+    if overwrite {
+        return std::fs::rename(temp_path, path);
+    }
+
+    // Linking fails if the destination exists unlike renaming
+    match std::fs::hard_link(temp_path, path) {
+        Ok(_) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => Err(error),
+        Err(error) => {
+            tracing::debug!(?error, "hard link failed, writing directly");
+
+            let mut file = File::options().write(true).create_new(true).open(path)?;
+            file.write_all(contents)?;
+            file.sync_all()
+        }
+    }
+}
+
 pub(crate) fn env_var<A: AsRef<OsStr>>(key: A) -> Result<OsString, InstallerError> {
     tracing::trace!(key = ?key.as_ref(), "env_var");
     std::env::var_os(key.as_ref())
         .ok_or_else(|| InstallerErrorKind::InvalidEnvironmentVariable.into())
 }
+
+/// Returns an XDG base directory, such as `XDG_STATE_HOME`, using the given
+/// function to read environment variables.
+///
+/// As required by the XDG Base Directory Specification, a value that is
+/// empty or not an absolute path is ignored and `default` relative to
+/// `HOME` is used instead.
+pub(crate) fn xdg_base_dir<F>(
+    env_var: F,
+    key: &str,
+    default: &str,
+) -> Result<PathBuf, InstallerError>
+where
+    F: Fn(&str) -> Result<OsString, InstallerError>,
+{
+    if let Ok(value) = env_var(key) {
+        let path = PathBuf::from(value);
+
+        if path.is_absolute() {
+            return Ok(path);
+        }
+
+        tracing::debug!(key, ?path, "ignoring invalid XDG base directory");
+    }
+
+    Ok(PathBuf::from(env_var("HOME")?).join(default))
+}
+
+/// Returns the prefix of the Termux environment on Android, such as
+/// `/data/data/com.termux/files/usr`, if running in it.
+///
+/// Termux has no `/usr/local` or root access, so system scope files are
+/// installed under the prefix instead.
+pub fn termux_prefix() -> Option<PathBuf> {
+    if cfg!(unix) {
+        termux_prefix_with(|key| env_var(key))
+    } else {
+        None
+    }
+}
+
+/// Returns the prefix of Termux using the given function to read
+/// environment variables.
+///
+/// Termux is detected by `TERMUX_VERSION` or a `PREFIX` in the app's
+/// data directory, since `PREFIX` alone is commonly set by build tools.
+pub(crate) fn termux_prefix_with<F>(env_var: F) -> Option<PathBuf>
+where
+    F: Fn(&str) -> Result<OsString, InstallerError>,
+{
+    let prefix = PathBuf::from(env_var("PREFIX").ok()?);
+
+    if !prefix.is_absolute() {
+        return None;
+    }
+
+    let is_termux =
+        env_var("TERMUX_VERSION").is_ok() || prefix.to_string_lossy().contains("/com.termux/");
+
+    is_termux.then_some(prefix)
+}
+
+/// Parameters for registering a periodic task with the OS scheduler.
+#[derive(Debug, Clone)]
+pub(crate) struct ScheduledTaskConfig {
+    /// Identifier unique across applications.
+    pub id: String,
+    pub schedule: crate::inst::TaskSchedule,
+    pub exe_path: PathBuf,
+    pub args: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extended_length_path_str() {
+        assert_eq!(
+            extended_length_path_str("C:\\Program Files\\app\\bin\\app.exe").unwrap(),
+            "\\\\?\\C:\\Program Files\\app\\bin\\app.exe"
+        );
+        assert_eq!(
+            extended_length_path_str("c:/users/rust//./app/../app2/").unwrap(),
+            "\\\\?\\c:\\users\\rust\\app2"
+        );
+        assert_eq!(
+            extended_length_path_str("C:\\..\\..").unwrap(),
+            "\\\\?\\C:\\"
+        );
+        assert_eq!(
+            extended_length_path_str("\\\\server\\share\\apps\\..\\..\\app").unwrap(),
+            "\\\\?\\UNC\\server\\share\\app"
+        );
+        assert!(extended_length_path_str("\\\\?\\C:\\app").is_none());
+        assert!(extended_length_path_str("\\\\.\\pipe\\app").is_none());
+        assert!(extended_length_path_str("\\\\server").is_none());
+        assert!(extended_length_path_str("app\\bin").is_none());
+        assert!(extended_length_path_str("C:app").is_none());
+        assert!(extended_length_path_str("/home/rust/app").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extended_length_path_unchanged_on_unix() {
+        let path = Path::new("/home/rust/app");
+        assert_eq!(extended_length_path(path), path);
+    }
+
+    #[test]
+    fn test_retry_sharing_violation_other_error() {
+        let mut count = 0;
+        let result: Result<(), InstallerError> = retry_sharing_violation(|| {
+            count += 1;
+            Err(std::io::Error::from(std::io::ErrorKind::NotFound).into())
+        });
+
+        assert!(result.unwrap_err().is_io());
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_write_file_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+
+        write_file_atomic(&path, b"hello", false).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello");
+
+        let error = write_file_atomic(&path, b"world", false).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello");
+
+        write_file_atomic(&path, b"world", true).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"world");
+
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_write_file_atomic_concurrent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+
+        let results = std::thread::scope(|scope| {
+            let handles = (0..8)
+                .map(|index| {
+                    let path = &path;
+                    scope.spawn(move || write_file_atomic(path, &[index; 1000], false))
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+
+        let contents = std::fs::read(&path).unwrap();
+        assert_eq!(contents.len(), 1000);
+        assert!(contents.iter().all(|byte| *byte == contents[0]));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::RwLock;
+
+use super::AccessScope;
+
+static EVENT_LOG_ENABLED: RwLock<bool> = RwLock::new(false);
+
+/// An event written to the Windows Event Log.
+///
+/// See [`set_windows_event_log()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EventLogEvent {
+    /// An installation started. Event ID 1000.
+    InstallStarted,
+    /// An installation completed. Event ID 1001.
+    InstallSucceeded,
+    /// An installation failed. Event ID 1002.
+    InstallFailed,
+    /// An uninstallation started. Event ID 1100.
+    UninstallStarted,
+    /// An uninstallation completed. Event ID 1101.
+    UninstallSucceeded,
+    /// An uninstallation failed. Event ID 1102.
+    UninstallFailed,
+}
+
+impl EventLogEvent {
+    /// Returns the event ID.
+    pub fn event_id(self) -> u32 {
+        match self {
+            Self::InstallStarted => 1000,
+            Self::InstallSucceeded => 1001,
+            Self::InstallFailed => 1002,
+            Self::UninstallStarted => 1100,
+            Self::UninstallSucceeded => 1101,
+            Self::UninstallFailed => 1102,
+        }
+    }
+
+    /// Returns whether the event is logged as an error instead of information.
+    pub fn is_error(self) -> bool {
+        matches!(self, Self::InstallFailed | Self::UninstallFailed)
+    }
+}
+
+/// Sets whether system scope operations write events to the Windows Event Log.
+///
+/// When enabled, installing and uninstalling for all users writes an event
+/// to the Application log when the operation starts, succeeds, or fails.
+/// The source of the events is the application's display name. User scope
+/// operations don't write events.
+///
+/// This setting applies to the whole process and has no effect on other
+/// operating systems. The default is disabled.
+pub fn set_windows_event_log(enabled: bool) {
+    let mut value = EVENT_LOG_ENABLED.write().unwrap();
+    *value = enabled;
+}
+
+/// Returns whether events are written to the Windows Event Log.
+///
+/// See [`set_windows_event_log()`].
+pub fn windows_event_log() -> bool {
+    *EVENT_LOG_ENABLED.read().unwrap()
+}
+
+/// Writes the event if enabled for the access scope.
+///
+/// Failing to write is only logged since it should not interrupt
+/// the operation.
+pub(crate) fn report_event(
+    access_scope: AccessScope,
+    source: &str,
+    event: EventLogEvent,
+    message: &str,
+) {
+    if access_scope != AccessScope::System || !windows_event_log() {
+        return;
+    }
+
+    tracing::debug!(source, ?event, message, "writing event log");
+
+    #[cfg(windows)]
+    if let Err(error) =
+        super::windows::report_event(source, event.event_id(), event.is_error(), message)
+    {
+        tracing::warn!(?error, "could not write event log");
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    fmt::Debug,
+    io::Read,
+    sync::{Arc, RwLock},
+};
+
+use serde::{Deserialize, Serialize};
+
+static HASHER: RwLock<Option<Arc<dyn Hasher>>> = RwLock::new(None);
+
+/// Algorithm for computing a digest of a file's contents.
+///
+/// A CRC32C checksum is always computed to detect changed files. It does
+/// not protect against deliberate modification, so a hasher set with
+/// [`set_hasher()`] can compute an additional digest, such as SHA-256 or an
+/// HMAC keyed with a secret. The digest and the algorithm identifier are
+/// recorded for each file in the disk manifest and compared with the
+/// installed files.
+///
+/// The built-in [`Sha256Hasher`] and [`Blake3Hasher`] are available with
+/// the `sha256` and `blake3` features. Digests recorded with a built-in
+/// algorithm are checked even if a different hasher or none is set.
+pub trait Hasher: Debug + Send + Sync {
+    /// Identifier of the algorithm recorded in the disk manifest,
+    /// such as `blake3`.
+    ///
+    /// Digests with different identifiers are not compared, so the
+    /// identifier should change when the key or parameters change.
+    fn algorithm(&self) -> &str;
+
+    /// Returns the digest of the contents read to the end.
+    fn digest(&self, reader: &mut dyn Read) -> std::io::Result<Vec<u8>>;
+}
+
+/// A digest computed by a [`Hasher`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct FileDigest {
+    /// Identifier of the algorithm from [`Hasher::algorithm()`].
+    pub algorithm: String,
+    /// The digest in lowercase hexadecimal.
+    pub value: String,
+}
+
+impl FileDigest {
+    /// Creates a digest from the raw bytes.
+    pub fn new(algorithm: &str, digest: &[u8]) -> Self {
+        Self {
+            algorithm: algorithm.to_string(),
+            value: digest.iter().map(|byte| format!("{:02x}", byte)).collect(),
+        }
+    }
+}
+
+/// Sets the hasher used to compute file digests.
+///
+/// This setting applies to the whole process and should be set before
+/// installing or uninstalling. The default is no additional digest.
+pub fn set_hasher<H: Hasher + 'static>(hasher: H) {
+    let mut value = HASHER.write().unwrap();
+    *value = Some(Arc::new(hasher));
+}
+
+/// Removes the hasher set with [`set_hasher()`].
+pub fn clear_hasher() {
+    let mut value = HASHER.write().unwrap();
+    *value = None;
+}
+
+/// Returns the hasher set with [`set_hasher()`].
+pub fn hasher() -> Option<Arc<dyn Hasher>> {
+    HASHER.read().unwrap().clone()
+}
+
+/// SHA-256 digest with the algorithm identifier `sha256`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256Hasher;
+
+impl Hasher for Sha256Hasher {
+    fn algorithm(&self) -> &str {
+        "sha256"
+    }
+
+    fn digest(&self, reader: &mut dyn Read) -> std::io::Result<Vec<u8>> {
+        use sha2::Digest;
+
+        let mut hasher = sha2::Sha256::new();
+        std::io::copy(reader, &mut hasher)?;
+        Ok(hasher.finalize().to_vec())
+    }
+}
+
+/// BLAKE3 digest with the algorithm identifier `blake3`.
+#[cfg(feature = "blake3")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Blake3Hasher;
+
+#[cfg(feature = "blake3")]
+impl Hasher for Blake3Hasher {
+    fn algorithm(&self) -> &str {
+        "blake3"
+    }
+
+    fn digest(&self, reader: &mut dyn Read) -> std::io::Result<Vec<u8>> {
+        let mut hasher = blake3::Hasher::new();
+        std::io::copy(reader, &mut hasher)?;
+        Ok(hasher.finalize().as_bytes().to_vec())
+    }
+}
+
+/// Returns the hasher for the algorithm identifier.
+///
+/// The hasher set with [`set_hasher()`] is preferred over a built-in one.
+pub(super) fn hasher_for_algorithm(algorithm: &str) -> Option<Arc<dyn Hasher>> {
+    if let Some(hasher) = hasher() {
+        if hasher.algorithm() == algorithm {
+            return Some(hasher);
+        }
+    }
+
+    match algorithm {
+        "sha256" => Some(Arc::new(Sha256Hasher)),
+        #[cfg(feature = "blake3")]
+        "blake3" => Some(Arc::new(Blake3Hasher)),
+        _ => None,
+    }
+}
+
+/// Computes the digest with the given hasher, if any.
+pub(super) fn digest_reader(
+    reader: &mut dyn Read,
+    hasher: Option<Arc<dyn Hasher>>,
+) -> std::io::Result<Option<FileDigest>> {
+    match hasher {
+        Some(hasher) => {
+            let digest = hasher.digest(reader)?;
+            Ok(Some(FileDigest::new(hasher.algorithm(), &digest)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Reader that computes the CRC32C of the bytes read.
+pub(super) struct Crc32cReader<R> {
+    inner: R,
+    crc32c: u32,
+}
+
+impl<R: Read> Crc32cReader<R> {
+    pub(super) fn new(inner: R) -> Self {
+        Self { inner, crc32c: 0 }
+    }
+
+    pub(super) fn crc32c(&self) -> u32 {
+        self.crc32c
+    }
+}
+
+impl<R: Read> Read for Crc32cReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.crc32c = crc32c::crc32c_append(self.crc32c, &buf[..len]);
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::os::FileChecksum;
+
+    #[derive(Debug)]
+    struct SumHasher;
+
+    impl Hasher for SumHasher {
+        fn algorithm(&self) -> &str {
+            "test-sum"
+        }
+
+        fn digest(&self, reader: &mut dyn Read) -> std::io::Result<Vec<u8>> {
+            let mut buf = Vec::new();
+            reader.read_to_end(&mut buf)?;
+            let sum = buf.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+            Ok(vec![sum, 0xab])
+        }
+    }
+
+    #[test]
+    fn test_file_digest_hex() {
+        let digest = FileDigest::new("test", &[0x00, 0x0f, 0xff]);
+        assert_eq!(digest.value, "000fff");
+    }
+
+    #[test]
+    fn test_checksum_digest_eq() {
+        let checksum = FileChecksum {
+            crc32c: 1,
+            len: 2,
+            digest: Some(FileDigest::new("a", &[1])),
+        };
+        let without_digest = FileChecksum {
+            digest: None,
+            ..checksum.clone()
+        };
+        let other_value = FileChecksum {
+            digest: Some(FileDigest::new("a", &[2])),
+            ..checksum.clone()
+        };
+        let other_algorithm = FileChecksum {
+            digest: Some(FileDigest::new("b", &[2])),
+            ..checksum.clone()
+        };
+
+        assert_eq!(checksum, without_digest);
+        assert_ne!(checksum, other_value);
+        assert_eq!(checksum, other_algorithm);
+    }
+
+    #[test]
+    fn test_hasher() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        std::fs::write(&path, [1, 2, 3]).unwrap();
+
+        set_hasher(SumHasher);
+        let file_checksum = crate::os::file_checksum(&path);
+        let bytes_checksum = crate::os::bytes_checksum(&[1, 2, 3]);
+        clear_hasher();
+
+        let file_checksum = file_checksum.unwrap();
+        let bytes_checksum = bytes_checksum.unwrap();
+
+        assert_eq!(file_checksum.crc32c, crc32c::crc32c(&[1, 2, 3]));
+        assert_eq!(file_checksum.len, 3);
+        assert_eq!(
+            file_checksum.digest,
+            Some(FileDigest::new("test-sum", &[6, 0xab]))
+        );
+        assert_eq!(file_checksum.digest, bytes_checksum.digest);
+    }
+
+    #[test]
+    fn test_checksum_with_recorded_algorithm() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        std::fs::write(&path, [1, 2, 3]).unwrap();
+
+        set_hasher(SumHasher);
+        let checksum = crate::os::file_checksum_with_algorithm(&path, Some("test-sum"));
+        clear_hasher();
+
+        assert_eq!(
+            checksum.unwrap().digest,
+            Some(FileDigest::new("test-sum", &[6, 0xab]))
+        );
+
+        let checksum = crate::os::file_checksum_with_algorithm(&path, Some("test-sum")).unwrap();
+        assert_eq!(checksum.digest, None);
+        assert_eq!(checksum.crc32c, crc32c::crc32c(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn test_sha256_hasher() {
+        let digest = Sha256Hasher.digest(&mut &b"abc"[..]).unwrap();
+
+        assert_eq!(
+            FileDigest::new("sha256", &digest).value,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_blake3_hasher() {
+        let digest = Blake3Hasher.digest(&mut &b"abc"[..]).unwrap();
+
+        assert_eq!(
+            FileDigest::new("blake3", &digest).value,
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPT_REQUESTED: AtomicBool = AtomicBool::new(false);
+#[cfg(all(windows, feature = "ui"))]
+static INTERRUPT_FINISHED: AtomicBool = AtomicBool::new(false);
+
+/// Requests the running operation to stop at the next safe point.
+#[cfg(feature = "ui")]
+pub fn request_interrupt() {
+    tracing::info!("interrupt requested");
+    INTERRUPT_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Returns whether the running operation should stop.
+pub fn is_interrupt_requested() -> bool {
+    INTERRUPT_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Starts an operation that can be interrupted.
+///
+/// Closing the terminal requests an interrupt instead of exiting
+/// immediately. On Unix, this handles `SIGHUP`. On Windows, this handles
+/// closing the console window, which only gives a few seconds before the
+/// process is terminated.
+#[cfg(feature = "ui")]
+pub fn begin_interruptible() {
+    INTERRUPT_REQUESTED.store(false, Ordering::SeqCst);
+
+    #[cfg(unix)]
+    {
+        let handler = handle_hangup as extern "C" fn(libc::c_int);
+        let result = unsafe { libc::signal(libc::SIGHUP, handler as libc::sighandler_t) };
+
+        if result == libc::SIG_ERR {
+            tracing::warn!(error = ?std::io::Error::last_os_error(), "could not set SIGHUP handler");
+        }
+    }
+    #[cfg(windows)]
+    {
+        INTERRUPT_FINISHED.store(false, Ordering::SeqCst);
+
+        if let Err(error) = unsafe {
+            windows::Win32::System::Console::SetConsoleCtrlHandler(
+                Some(handle_console_ctrl),
+                windows::Win32::Foundation::TRUE,
+            )
+        } {
+            tracing::warn!(?error, "could not set console control handler");
+        }
+    }
+}
+
+/// Ends the operation started by [`begin_interruptible()`] after any
+/// interrupt was cleaned up.
+#[cfg(feature = "ui")]
+pub fn end_interruptible() {
+    #[cfg(unix)]
+    unsafe {
+        libc::signal(libc::SIGHUP, libc::SIG_DFL);
+    }
+    #[cfg(windows)]
+    {
+        let _ = unsafe {
+            windows::Win32::System::Console::SetConsoleCtrlHandler(
+                Some(handle_console_ctrl),
+                windows::Win32::Foundation::FALSE,
+            )
+        };
+        INTERRUPT_FINISHED.store(true, Ordering::SeqCst);
+    }
+
+    INTERRUPT_REQUESTED.store(false, Ordering::SeqCst);
+}
+
+#[cfg(all(unix, feature = "ui"))]
+extern "C" fn handle_hangup(_signal: libc::c_int) {
+    // Only async-signal-safe operations are allowed here
+    INTERRUPT_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+#[cfg(all(windows, feature = "ui"))]
+unsafe extern "system" fn handle_console_ctrl(ctrl_type: u32) -> windows::Win32::Foundation::BOOL {
+    use std::time::{Duration, Instant};
+
+    if ctrl_type != windows::Win32::System::Console::CTRL_CLOSE_EVENT {
+        return windows::Win32::Foundation::FALSE;
+    }
+
+    INTERRUPT_REQUESTED.store(true, Ordering::SeqCst);
+
+    // The process exits when this returns, or after about 5 seconds
+    let deadline = Instant::now() + Duration::from_millis(4500);
+
+    while !INTERRUPT_FINISHED.load(Ordering::SeqCst) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    windows::Win32::Foundation::TRUE
+}
+
+#[cfg(all(test, feature = "ui"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interrupt() {
+        begin_interruptible();
+        assert!(!is_interrupt_requested());
+
+        request_interrupt();
+        assert!(is_interrupt_requested());
+
+        end_interruptible();
+        assert!(!is_interrupt_requested());
+    }
+}
@@ -0,0 +1,154 @@
+use std::{fs::File, io::Read, path::Path};
+
+const FAT_MAGIC: u32 = 0xcafe_babe;
+const FAT_MAGIC_64: u32 = 0xcafe_babf;
+const MH_MAGIC: u32 = 0xfeed_face;
+const MH_MAGIC_64: u32 = 0xfeed_facf;
+
+const CPU_ARCH_ABI64: u32 = 0x0100_0000;
+const CPU_ARCH_ABI64_32: u32 = 0x0200_0000;
+const CPU_TYPE_X86: u32 = 7;
+const CPU_TYPE_ARM: u32 = 12;
+const CPU_TYPE_POWERPC: u32 = 18;
+const CPU_SUBTYPE_ARM64E: u32 = 2;
+const CPU_SUBTYPE_MASK: u32 = 0x00ff_ffff;
+
+/// Java class files share the magic number of universal binaries, but their
+/// version number is much larger than the number of slices.
+const MAX_FAT_ARCHS: u32 = 30;
+
+/// Returns the CPU architectures of a Mach-O executable, such as
+/// `["x86_64", "arm64"]` for a universal binary or `["arm64"]` for a
+/// single-architecture binary.
+///
+/// The names are the ones used by `lipo`. If the file is not a Mach-O
+/// executable, the list is empty.
+pub fn executable_architectures<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<String>> {
+    let mut file = File::open(path.as_ref())?;
+    let mut header = Vec::new();
+    file.by_ref()
+        .take(8 + MAX_FAT_ARCHS as u64 * 32)
+        .read_to_end(&mut header)?;
+
+    Ok(parse_architectures(&header))
+}
+
+fn parse_architectures(header: &[u8]) -> Vec<String> {
+    let Some(magic) = read_u32_be(header, 0) else {
+        return Vec::new();
+    };
+
+    match magic {
+        FAT_MAGIC | FAT_MAGIC_64 => {
+            let count = read_u32_be(header, 4).unwrap_or_default();
+            let entry_len = if magic == FAT_MAGIC { 20 } else { 32 };
+
+            if count > MAX_FAT_ARCHS {
+                return Vec::new();
+            }
+
+            (0..count as usize)
+                .map_while(|index| {
+                    let offset = 8 + index * entry_len;
+                    let cpu_type = read_u32_be(header, offset)?;
+                    let cpu_subtype = read_u32_be(header, offset + 4)?;
+                    Some(architecture_name(cpu_type, cpu_subtype))
+                })
+                .collect()
+        }
+        _ => {
+            let read_u32 = if magic == MH_MAGIC || magic == MH_MAGIC_64 {
+                read_u32_be
+            } else if magic.swap_bytes() == MH_MAGIC || magic.swap_bytes() == MH_MAGIC_64 {
+                read_u32_le
+            } else {
+                return Vec::new();
+            };
+
+            match (read_u32(header, 4), read_u32(header, 8)) {
+                (Some(cpu_type), Some(cpu_subtype)) => {
+                    vec![architecture_name(cpu_type, cpu_subtype)]
+                }
+                _ => Vec::new(),
+            }
+        }
+    }
+}
+
+fn architecture_name(cpu_type: u32, cpu_subtype: u32) -> String {
+    let name = match cpu_type {
+        CPU_TYPE_X86 => "i386",
+        type_ if type_ == CPU_TYPE_X86 | CPU_ARCH_ABI64 => "x86_64",
+        CPU_TYPE_ARM => "arm",
+        type_ if type_ == CPU_TYPE_ARM | CPU_ARCH_ABI64 => {
+            if cpu_subtype & CPU_SUBTYPE_MASK == CPU_SUBTYPE_ARM64E {
+                "arm64e"
+            } else {
+                "arm64"
+            }
+        }
+        type_ if type_ == CPU_TYPE_ARM | CPU_ARCH_ABI64_32 => "arm64_32",
+        CPU_TYPE_POWERPC => "ppc",
+        type_ if type_ == CPU_TYPE_POWERPC | CPU_ARCH_ABI64 => "ppc64",
+        _ => return format!("cputype{}", cpu_type),
+    };
+
+    name.to_string()
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fat_header(archs: &[(u32, u32)]) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend(FAT_MAGIC.to_be_bytes());
+        header.extend((archs.len() as u32).to_be_bytes());
+
+        for (cpu_type, cpu_subtype) in archs {
+            header.extend(cpu_type.to_be_bytes());
+            header.extend(cpu_subtype.to_be_bytes());
+            header.extend([0; 12]);
+        }
+
+        header
+    }
+
+    #[test]
+    fn test_universal_binary() {
+        let header = fat_header(&[
+            (CPU_TYPE_X86 | CPU_ARCH_ABI64, 3),
+            (CPU_TYPE_ARM | CPU_ARCH_ABI64, 0),
+        ]);
+
+        assert_eq!(parse_architectures(&header), ["x86_64", "arm64"]);
+    }
+
+    #[test]
+    fn test_single_architecture_binary() {
+        let mut header = Vec::new();
+        header.extend(MH_MAGIC_64.to_le_bytes());
+        header.extend((CPU_TYPE_ARM | CPU_ARCH_ABI64).to_le_bytes());
+        header.extend(CPU_SUBTYPE_ARM64E.to_le_bytes());
+
+        assert_eq!(parse_architectures(&header), ["arm64e"]);
+    }
+
+    #[test]
+    fn test_not_mach_o() {
+        assert!(parse_architectures(b"\x7fELF\x02\x01\x01\x00").is_empty());
+        assert!(parse_architectures(b"MZ").is_empty());
+        // Java class file version 52
+        assert!(parse_architectures(b"\xca\xfe\xba\xbe\x00\x00\x00\x34").is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+use std::path::Path;
+
+/// Operating system detected at runtime.
+///
+/// Unlike the OS family, this distinguishes the Unix-like systems, which
+/// differ in the features used by the installer. See [`OsCapabilities`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OsKind {
+    /// Windows.
+    Windows,
+    /// macOS.
+    MacOs,
+    /// Linux.
+    Linux,
+    /// Android, such as in Termux.
+    Android,
+    /// FreeBSD.
+    FreeBsd,
+    /// OpenBSD.
+    OpenBsd,
+    /// NetBSD.
+    NetBsd,
+    /// DragonFly BSD.
+    DragonFly,
+    /// illumos.
+    Illumos,
+    /// Solaris.
+    Solaris,
+    /// Another Unix-like system.
+    OtherUnix,
+    /// Another operating system.
+    Other,
+}
+
+impl OsKind {
+    /// Returns the operating system the crate was compiled for.
+    pub fn current() -> Self {
+        Self::from_name(std::env::consts::OS, std::env::consts::FAMILY)
+    }
+
+    /// Returns the operating system from the values of
+    /// [`std::env::consts::OS`] and [`std::env::consts::FAMILY`].
+    pub fn from_name(os: &str, family: &str) -> Self {
+        match os {
+            "windows" => Self::Windows,
+            "macos" => Self::MacOs,
+            "linux" => Self::Linux,
+            "android" => Self::Android,
+            "freebsd" => Self::FreeBsd,
+            "openbsd" => Self::OpenBsd,
+            "netbsd" => Self::NetBsd,
+            "dragonfly" => Self::DragonFly,
+            "illumos" => Self::Illumos,
+            "solaris" => Self::Solaris,
+            _ if family == "unix" => Self::OtherUnix,
+            _ => Self::Other,
+        }
+    }
+
+    /// Returns whether the operating system is Unix-like.
+    pub fn is_unix(self) -> bool {
+        !matches!(self, Self::Windows | Self::Other)
+    }
+
+    /// Returns whether the operating system is one of the BSDs.
+    pub fn is_bsd(self) -> bool {
+        matches!(
+            self,
+            Self::FreeBsd | Self::OpenBsd | Self::NetBsd | Self::DragonFly
+        )
+    }
+}
+
+/// Features of the operating system used by the installer.
+///
+/// Optional features that are not available are skipped. Features that
+/// the package requires, such as scheduled tasks, return the error kind
+/// [`InstallerErrorKind::UnsupportedOsFeature`](crate::error::InstallerErrorKind::UnsupportedOsFeature)
+/// before anything is installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct OsCapabilities {
+    /// The operating system.
+    pub os: OsKind,
+    /// Whether a login shell profile, such as `~/.profile`, can add
+    /// directories to the search path.
+    pub shell_profile: bool,
+    /// Whether desktop entries and MIME types of the freedesktop.org
+    /// specifications are used by the desktop environments.
+    pub desktop_entries: bool,
+    /// Whether tasks can be registered with the OS scheduler.
+    ///
+    /// On Unix, this requires the `crontab` program.
+    pub scheduled_tasks: bool,
+    /// Whether there is a directory of scripts read by all login shells,
+    /// such as `/etc/profile.d`.
+    pub system_profile_scripts: bool,
+}
+
+impl OsCapabilities {
+    /// Detects the features of the current machine.
+    pub fn detect() -> Self {
+        let is_termux = super::termux_prefix().is_some();
+
+        #[cfg(unix)]
+        let system_profile_scripts = super::unix::system_profile_script_path("").is_some();
+        #[cfg(not(unix))]
+        let system_profile_scripts = false;
+
+        let capabilities = Self::for_os(
+            OsKind::current(),
+            is_termux,
+            system_profile_scripts,
+            |program| find_program(program).is_some(),
+        );
+
+        tracing::debug!(?capabilities, "detected OS capabilities");
+
+        capabilities
+    }
+
+    fn for_os<F>(
+        os: OsKind,
+        is_termux: bool,
+        system_profile_scripts: bool,
+        program_exists: F,
+    ) -> Self
+    where
+        F: Fn(&str) -> bool,
+    {
+        let desktop_entries =
+            os.is_unix() && !matches!(os, OsKind::MacOs | OsKind::Android) && !is_termux;
+        let scheduled_tasks = match os {
+            OsKind::Windows => true,
+            _ if os.is_unix() => program_exists("crontab"),
+            _ => false,
+        };
+
+        Self {
+            os,
+            shell_profile: os.is_unix(),
+            desktop_entries,
+            scheduled_tasks,
+            system_profile_scripts: os.is_unix() && system_profile_scripts,
+        }
+    }
+}
+
+/// Returns the path of the program found in the `PATH` environment
+/// variable.
+fn find_program(name: &str) -> Option<std::path::PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(name))
+        .find(|path| is_executable_file(path))
+}
+
+fn is_executable_file(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        path.metadata()
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_os_kind_from_name() {
+        assert_eq!(OsKind::from_name("linux", "unix"), OsKind::Linux);
+        assert_eq!(OsKind::from_name("freebsd", "unix"), OsKind::FreeBsd);
+        assert_eq!(OsKind::from_name("illumos", "unix"), OsKind::Illumos);
+        assert_eq!(OsKind::from_name("haiku", "unix"), OsKind::OtherUnix);
+        assert_eq!(OsKind::from_name("uefi", ""), OsKind::Other);
+        assert!(OsKind::OpenBsd.is_bsd());
+        assert!(OsKind::OtherUnix.is_unix());
+        assert!(!OsKind::Windows.is_unix());
+    }
+
+    #[test]
+    fn test_capabilities_for_os() {
+        let linux = OsCapabilities::for_os(OsKind::Linux, false, true, |_| true);
+        assert!(linux.shell_profile);
+        assert!(linux.desktop_entries);
+        assert!(linux.scheduled_tasks);
+        assert!(linux.system_profile_scripts);
+
+        let termux = OsCapabilities::for_os(OsKind::Android, true, false, |_| false);
+        assert!(termux.shell_profile);
+        assert!(!termux.desktop_entries);
+        assert!(!termux.scheduled_tasks);
+
+        let macos = OsCapabilities::for_os(OsKind::MacOs, false, false, |_| true);
+        assert!(!macos.desktop_entries);
+
+        let freebsd = OsCapabilities::for_os(OsKind::FreeBsd, false, false, |_| true);
+        assert!(freebsd.desktop_entries);
+        assert!(!freebsd.system_profile_scripts);
+
+        let windows = OsCapabilities::for_os(OsKind::Windows, false, true, |_| false);
+        assert!(!windows.shell_profile);
+        assert!(windows.scheduled_tasks);
+        assert!(!windows.system_profile_scripts);
+    }
+}
@@ -1,19 +1,27 @@
 // Notes for app menus:
 // https://specifications.freedesktop.org/desktop-entry-spec/latest/index.html
 //
+// Notes on MIME types:
+// https://specifications.freedesktop.org/shared-mime-info-spec/latest/
+// https://specifications.freedesktop.org/mime-apps-spec/latest/
+//
+// Notes on Nushell:
+// https://www.nushell.sh/book/configuration.html
+//
 // Notes on filesystem directories:
 // https://specifications.freedesktop.org/basedir-spec/latest/index.html
 // https://en.wikipedia.org/wiki/Filesystem_Hierarchy_Standard
 
-use std::ffi::OsStr;
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
 use std::io::Write;
-use std::os::unix::fs::PermissionsExt;
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
 use std::path::PathBuf;
 use std::{fs::File, path::Path, sync::LazyLock};
 
-use crate::manifest::FileType;
+use crate::{inst::TaskSchedule, manifest::FileType};
 
-use super::{AccessScope, OsError};
+use super::{AccessScope, ElevationMethod, InstallerIdentity, OsError, ScheduledTaskConfig};
 
 pub fn get_umask() -> u32 {
     static UMASK: LazyLock<u32> = LazyLock::new(|| {
@@ -53,6 +61,183 @@ fi
 ## </io.crates.takecrate>
 "#;
 
+const NUSHELL_PROFILE_TEMPLATE_SNIPPET: &str = r#"
+## <io.crates.takecrate> Automatically inserted snippet
+if ('{path}' | path exists) {
+    $env.PATH = ($env.PATH | split row (char esep) | prepend '{path}')
+}
+## </io.crates.takecrate>
+"#;
+
+const PROFILE_DIR: &str = "/etc/profile.d";
+const PATHS_D_DIR: &str = "/etc/paths.d";
+const NIX_STORE_DIR: &str = "/nix/store";
+/// Programs tried in order to run a command as root.
+#[cfg(feature = "ui")]
+const ELEVATE_PROGRAMS: [&str; 2] = ["sudo", "pkexec"];
+
+/// Adds `export` statements for the environment variables to the shell profile.
+///
+/// The statements are wrapped in markers containing the given ID so they can
+/// be removed later.
+pub fn add_env_vars(
+    profile_path: &Path,
+    id: &str,
+    env_vars: &[(String, String)],
+) -> Result<(), OsError> {
+    remove_env_vars(profile_path, id)?;
+
+    let snippet = env_vars_snippet(id, env_vars, is_nushell_profile(profile_path))?;
+
+    create_profile_parent_dir(profile_path)?;
+
+    tracing::debug!(snippet, "saving profile");
+    let mut file = File::options()
+        .create(true)
+        .append(true)
+        .open(profile_path)?;
+    file.write_all(snippet.as_bytes())?;
+    file.flush()?;
+
+    Ok(())
+}
+
+/// Removes the `export` statements added by [`add_env_vars`].
+pub fn remove_env_vars(profile_path: &Path, id: &str) -> Result<(), OsError> {
+    if !profile_path.exists() {
+        return Ok(());
+    }
+
+    tracing::debug!(?profile_path, "reading profile");
+    let contents = std::fs::read_to_string(profile_path)?;
+    let new_contents = remove_env_vars_snippet(&contents, id);
+
+    if new_contents != contents {
+        tracing::debug!(?profile_path, "saving profile");
+        std::fs::write(profile_path, new_contents)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the path of a script in `/etc/profile.d`, or the one in the prefix
+/// on Termux, for setting system-wide environment variables if the
+/// directory exists.
+pub fn system_profile_script_path(id: &str) -> Option<PathBuf> {
+    let dir = match super::termux_prefix() {
+        Some(prefix) => prefix.join(PROFILE_DIR.trim_start_matches('/')),
+        None => PathBuf::from(PROFILE_DIR),
+    };
+
+    if dir.is_dir() {
+        Some(dir.join(format!("takecrate-{}.sh", id)))
+    } else {
+        None
+    }
+}
+
+/// Returns the path of a file in `/etc/paths.d` for adding a system-wide
+/// search path if the directory exists.
+///
+/// This directory is read by `path_helper` on macOS.
+pub fn system_paths_d_path(id: &str) -> Option<PathBuf> {
+    let dir = Path::new(PATHS_D_DIR);
+
+    if dir.is_dir() {
+        Some(dir.join(id))
+    } else {
+        None
+    }
+}
+
+/// Returns the contents of a `/etc/paths.d` file containing the directory.
+pub fn paths_d_contents(exe_dir: &OsStr) -> Result<String, OsError> {
+    let exe_dir = Path::new(exe_dir)
+        .to_str()
+        .ok_or(OsError::Other("non-unicode path"))?;
+
+    // Each line is a directory so there are no escapes
+    if exe_dir.contains('\n') {
+        return Err(OsError::Other("invalid path character"));
+    }
+
+    Ok(format!("{}\n", exe_dir))
+}
+
+/// Returns a shell script that exports the environment variables.
+pub fn env_vars_script(env_vars: &[(String, String)]) -> Result<String, OsError> {
+    let mut script = String::new();
+
+    for (name, value) in env_vars {
+        if value.chars().any(|c| c.is_control()) {
+            return Err(OsError::Other("invalid environment variable character"));
+        }
+
+        script.push_str(&format!("export {}={}\n", name, shell_quote(value)));
+    }
+
+    Ok(script)
+}
+
+/// Returns a Nushell script that sets the environment variables.
+fn nushell_env_vars_script(env_vars: &[(String, String)]) -> Result<String, OsError> {
+    let mut script = String::new();
+
+    for (name, value) in env_vars {
+        if value.chars().any(|c| c.is_control()) || value.contains("'#") {
+            return Err(OsError::Other("invalid environment variable character"));
+        }
+
+        script.push_str(&format!("$env.{} = r#'{}'#\n", name, value));
+    }
+
+    Ok(script)
+}
+
+fn env_vars_snippet(
+    id: &str,
+    env_vars: &[(String, String)],
+    nushell: bool,
+) -> Result<String, OsError> {
+    let script = if nushell {
+        nushell_env_vars_script(env_vars)?
+    } else {
+        env_vars_script(env_vars)?
+    };
+
+    Ok(format!(
+        "\n## <io.crates.takecrate env={}> Automatically inserted snippet\n{}## </io.crates.takecrate env={}>\n",
+        id, script, id
+    ))
+}
+
+fn remove_env_vars_snippet(contents: &str, id: &str) -> String {
+    let start_marker = format!("## <io.crates.takecrate env={}>", id);
+    let end_marker = format!("## </io.crates.takecrate env={}>", id);
+
+    let Some(start) = contents.find(&start_marker) else {
+        return contents.to_string();
+    };
+    let Some(end_offset) = contents[start..].find(&end_marker) else {
+        return contents.to_string();
+    };
+
+    let mut end = start + end_offset + end_marker.len();
+
+    if contents[end..].starts_with('\n') {
+        end += 1;
+    }
+
+    // Also remove the blank line inserted before the snippet
+    let start = if contents[..start].ends_with('\n') {
+        start - 1
+    } else {
+        start
+    };
+
+    format!("{}{}", &contents[..start], &contents[end..])
+}
+
 pub fn add_path_env_var(
     access_scope: AccessScope,
     exe_dir: &OsStr,
@@ -75,11 +260,35 @@ pub fn remove_path_env_var(
     }
 }
 
-fn add_path_env_var_user(exe_dir: &OsStr, profile_path: &Path) -> Result<(), OsError> {
-    let home = get_home()?;
+/// Returns the path as written in the profile and the snippet that adds it to
+/// the search path.
+fn path_env_var_snippet(exe_dir: &OsStr, profile_path: &Path) -> Result<(String, String), OsError> {
+    if is_nushell_profile(profile_path) {
+        // Nushell single quoted strings have no escapes or variable expansion
+        let exe_dir_shell_path = Path::new(exe_dir).to_string_lossy().to_string();
+        verify_safe_for_shell_script(&exe_dir_shell_path)?;
 
-    let exe_dir_shell_path = path_to_shell_script_path(Path::new(exe_dir), &home);
-    verify_safe_for_shell_script(&exe_dir_shell_path)?;
+        if exe_dir_shell_path.contains('\'') {
+            return Err(OsError::Other("invalid path character"));
+        }
+
+        let snippet = NUSHELL_PROFILE_TEMPLATE_SNIPPET.replace("{path}", &exe_dir_shell_path);
+
+        Ok((exe_dir_shell_path, snippet))
+    } else {
+        let home = get_home()?;
+
+        let exe_dir_shell_path = path_to_shell_script_path(Path::new(exe_dir), &home);
+        verify_safe_for_shell_script(&exe_dir_shell_path)?;
+
+        let snippet = PROFILE_SHELL_TEMPLATE_SNIPPET.replace("{path}", &exe_dir_shell_path);
+
+        Ok((exe_dir_shell_path, snippet))
+    }
+}
+
+fn add_path_env_var_user(exe_dir: &OsStr, profile_path: &Path) -> Result<(), OsError> {
+    let (exe_dir_shell_path, snippet) = path_env_var_snippet(exe_dir, profile_path)?;
 
     if profile_path.exists() {
         tracing::debug!(?profile_path, "reading profile");
@@ -90,7 +299,7 @@ fn add_path_env_var_user(exe_dir: &OsStr, profile_path: &Path) -> Result<(), OsE
         }
     }
 
-    let snippet = PROFILE_SHELL_TEMPLATE_SNIPPET.replace("{path}", &exe_dir_shell_path);
+    create_profile_parent_dir(profile_path)?;
 
     tracing::debug!(snippet, "saving profile");
     let mut file = File::options()
@@ -103,18 +312,27 @@ fn add_path_env_var_user(exe_dir: &OsStr, profile_path: &Path) -> Result<(), OsE
     Ok(())
 }
 
-fn remove_path_env_var_user(exe_dir: &OsStr, profile_path: &Path) -> Result<(), OsError> {
-    let home = get_home()?;
+/// Returns whether the profile adds the directory to the search path.
+pub fn is_in_path_env_var(exe_dir: &OsStr, profile_path: &Path) -> Result<bool, OsError> {
+    let (exe_dir_shell_path, _snippet) = path_env_var_snippet(exe_dir, profile_path)?;
+
+    if !profile_path.exists() {
+        return Ok(false);
+    }
+
+    tracing::debug!(?profile_path, "reading profile");
+    let contents = std::fs::read_to_string(profile_path)?;
 
-    let exe_dir_shell_path = path_to_shell_script_path(Path::new(exe_dir), &home);
-    verify_safe_for_shell_script(&exe_dir_shell_path)?;
+    Ok(contents.contains(&exe_dir_shell_path))
+}
+
+fn remove_path_env_var_user(exe_dir: &OsStr, profile_path: &Path) -> Result<(), OsError> {
+    let (_exe_dir_shell_path, snippet) = path_env_var_snippet(exe_dir, profile_path)?;
 
     if !profile_path.exists() {
         return Ok(());
     }
 
-    let snippet = PROFILE_SHELL_TEMPLATE_SNIPPET.replace("{path}", &exe_dir_shell_path);
-
     tracing::debug!(?profile_path, "reading profile");
     let contents = std::fs::read_to_string(profile_path)?;
 
@@ -130,11 +348,838 @@ fn remove_path_env_var_user(exe_dir: &OsStr, profile_path: &Path) -> Result<(),
     Ok(())
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct MimeTypeInfo {
+    pub mime_type: String,
+    pub comment: String,
+    pub globs: Vec<String>,
+}
+
+/// Returns a shared-mime-info package XML document.
+pub fn mime_info_xml(types: &[MimeTypeInfo]) -> String {
+    let mut doc = String::new();
+    doc.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    doc.push_str("<mime-info xmlns=\"http://www.freedesktop.org/standards/shared-mime-info\">\n");
+
+    for info in types {
+        doc.push_str(&format!(
+            "  <mime-type type=\"{}\">\n",
+            xml_escape(&info.mime_type)
+        ));
+
+        if !info.comment.is_empty() {
+            doc.push_str(&format!(
+                "    <comment>{}</comment>\n",
+                xml_escape(&info.comment)
+            ));
+        }
+
+        for glob in &info.globs {
+            doc.push_str(&format!("    <glob pattern=\"{}\"/>\n", xml_escape(glob)));
+        }
+
+        doc.push_str("  </mime-type>\n");
+    }
+
+    doc.push_str("</mime-info>\n");
+    doc
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+const CRON_MARKER_PREFIX: &str = "# io.crates.takecrate:";
+const CRON_SYSTEM_DIR: &str = "/etc/cron.d";
+
+pub fn add_scheduled_task(
+    access_scope: AccessScope,
+    task: &ScheduledTaskConfig,
+) -> Result<(), OsError> {
+    remove_scheduled_task(access_scope, &task.id)?;
+
+    if let Some(path) = system_cron_file_path(access_scope, &task.id) {
+        let contents = format!("SHELL=/bin/sh\n{}\n", crontab_line(task, Some("root")));
+
+        tracing::debug!(?path, "saving cron file");
+        std::fs::write(&path, contents)?;
+        set_posix_permission(&path, 0o644)?;
+    } else {
+        let mut contents = read_crontab()?;
+
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents.push_str(&crontab_line(task, None));
+        contents.push('\n');
+
+        write_crontab(&contents)?;
+    }
+
+    Ok(())
+}
+
+pub fn remove_scheduled_task(access_scope: AccessScope, id: &str) -> Result<(), OsError> {
+    if let Some(path) = system_cron_file_path(access_scope, id) {
+        if path.exists() {
+            tracing::debug!(?path, "removing cron file");
+            std::fs::remove_file(&path)?;
+        }
+
+        return Ok(());
+    }
+
+    let contents = read_crontab()?;
+    let marker = format!("{}{}", CRON_MARKER_PREFIX, id);
+    let new_contents = remove_crontab_lines(&contents, &marker);
+
+    if new_contents != contents {
+        write_crontab(&new_contents)?;
+    }
+
+    Ok(())
+}
+
+fn system_cron_file_path(access_scope: AccessScope, id: &str) -> Option<PathBuf> {
+    let dir = Path::new(CRON_SYSTEM_DIR);
+
+    if access_scope == AccessScope::System && dir.is_dir() {
+        // run-parts only accepts letters, digits, underscores, and hyphens
+        Some(dir.join(format!("takecrate-{}", id.replace('.', "_"))))
+    } else {
+        None
+    }
+}
+
+fn crontab_line(task: &ScheduledTaskConfig, user: Option<&str>) -> String {
+    let schedule = match task.schedule {
+        TaskSchedule::Hourly => "@hourly",
+        TaskSchedule::Daily => "@daily",
+        TaskSchedule::Weekly => "@weekly",
+    };
+
+    let mut command = shell_quote(&task.exe_path.to_string_lossy());
+
+    for arg in &task.args {
+        command.push(' ');
+        command.push_str(&shell_quote(arg));
+    }
+
+    // Unescaped percent signs are converted to newlines by cron
+    let command = command.replace('%', "\\%");
+
+    match user {
+        Some(user) => format!(
+            "{} {} {} {}{}",
+            schedule, user, command, CRON_MARKER_PREFIX, task.id
+        ),
+        None => format!("{} {} {}{}", schedule, command, CRON_MARKER_PREFIX, task.id),
+    }
+}
+
+fn remove_crontab_lines(contents: &str, marker: &str) -> String {
+    let mut new_contents = String::with_capacity(contents.len());
+
+    for line in contents.split_inclusive('\n') {
+        if !line.trim_end().ends_with(marker) {
+            new_contents.push_str(line);
+        }
+    }
+
+    new_contents
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn read_crontab() -> Result<String, OsError> {
+    tracing::debug!("reading crontab");
+
+    // The "no crontab" message is matched below, so it can't be translated
+    let output = std::process::Command::new("crontab")
+        .arg("-l")
+        .env("LC_ALL", "C")
+        .stdin(std::process::Stdio::null())
+        .output()?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else if String::from_utf8_lossy(&output.stderr).contains("no crontab") {
+        Ok(String::new())
+    } else {
+        tracing::error!(status = ?output.status, stderr = ?String::from_utf8_lossy(&output.stderr), "crontab failed");
+        Err(OsError::Other("failed to read crontab"))
+    }
+}
+
+fn write_crontab(contents: &str) -> Result<(), OsError> {
+    tracing::debug!("saving crontab");
+
+    let mut child = std::process::Command::new("crontab")
+        .arg("-")
+        .stdin(std::process::Stdio::piped())
+        .spawn()?;
+
+    child
+        .stdin
+        .take()
+        .expect("stdin should be piped")
+        .write_all(contents.as_bytes())?;
+
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        Err(OsError::Other("failed to write crontab"))
+    }
+}
+
+/// MIME type prefix used by desktop entries for URL scheme handlers.
+pub const URL_SCHEME_MIME_TYPE_PREFIX: &str = "x-scheme-handler/";
+
+#[derive(Debug, Clone, Default)]
+pub struct DesktopEntryConfig {
+    pub name: String,
+    /// Localized names keyed by BCP 47 language tag.
+    pub locale_names: BTreeMap<String, String>,
+    /// Tooltip text, such as a short description.
+    pub comment: String,
+    /// Localized comments keyed by BCP 47 language tag.
+    pub locale_comments: BTreeMap<String, String>,
+    pub icon: Option<PathBuf>,
+    pub exe_path: PathBuf,
+    /// URL scheme handlers are specified as `x-scheme-handler/<scheme>` MIME types.
+    pub mime_types: Vec<String>,
+}
+
+/// Returns a desktop entry file for opening files with the given binary.
+pub fn desktop_entry(config: &DesktopEntryConfig) -> String {
+    let exe_path = desktop_entry_quote(&config.exe_path.to_string_lossy());
+    let field_code = if config
+        .mime_types
+        .iter()
+        .any(|mime_type| mime_type.starts_with(URL_SCHEME_MIME_TYPE_PREFIX))
+    {
+        "%u"
+    } else {
+        "%f"
+    };
+    let mut doc = String::new();
+
+    doc.push_str("[Desktop Entry]\n");
+    doc.push_str("Type=Application\n");
+    doc.push_str(&format!("Name={}\n", desktop_entry_escape(&config.name)));
+    push_desktop_entry_locale_keys(&mut doc, "Name", &config.locale_names);
+
+    if !config.comment.is_empty() {
+        doc.push_str(&format!(
+            "Comment={}\n",
+            desktop_entry_escape(&config.comment)
+        ));
+    }
+
+    push_desktop_entry_locale_keys(&mut doc, "Comment", &config.locale_comments);
+
+    if let Some(icon) = &config.icon {
+        doc.push_str(&format!(
+            "Icon={}\n",
+            desktop_entry_escape(&icon.to_string_lossy())
+        ));
+    }
+    doc.push_str(&format!(
+        "Exec={} {}\n",
+        desktop_entry_escape(&exe_path),
+        field_code
+    ));
+    doc.push_str("Terminal=true\n");
+    doc.push_str("NoDisplay=true\n");
+
+    if !config.mime_types.is_empty() {
+        doc.push_str("MimeType=");
+
+        for mime_type in &config.mime_types {
+            doc.push_str(&desktop_entry_escape(mime_type));
+            doc.push(';');
+        }
+
+        doc.push('\n');
+    }
+
+    doc
+}
+
+fn push_desktop_entry_locale_keys(doc: &mut String, key: &str, values: &BTreeMap<String, String>) {
+    for (lang_tag, value) in values {
+        let Some(locale) = desktop_entry_locale(lang_tag) else {
+            tracing::warn!(lang_tag, "unsupported language tag for desktop entry");
+            continue;
+        };
+
+        doc.push_str(&format!(
+            "{}[{}]={}\n",
+            key,
+            locale,
+            desktop_entry_escape(value)
+        ));
+    }
+}
+
+/// Converts a BCP 47 language tag, such as `pt-BR`, to the POSIX locale
+/// format used by desktop entries, such as `pt_BR`.
+///
+/// Script subtags are omitted.
+fn desktop_entry_locale(lang_tag: &str) -> Option<String> {
+    let mut subtags = lang_tag.split(['-', '_']);
+    let language = subtags
+        .next()
+        .filter(|value| (2..=3).contains(&value.len()))
+        .filter(|value| value.chars().all(|c| c.is_ascii_alphabetic()))?;
+    let region = subtags.find(|value| {
+        value.len() == 2 && value.chars().all(|c| c.is_ascii_alphabetic())
+            || value.len() == 3 && value.chars().all(|c| c.is_ascii_digit())
+    });
+
+    match region {
+        Some(region) => Some(format!(
+            "{}_{}",
+            language.to_ascii_lowercase(),
+            region.to_ascii_uppercase()
+        )),
+        None => Some(language.to_ascii_lowercase()),
+    }
+}
+
+fn desktop_entry_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+fn desktop_entry_quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for c in value.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+/// Refreshes the caches for MIME types and desktop entries in the given
+/// data directory (such as `/usr/local/share`).
+///
+/// Missing tools are ignored because not all systems have them installed.
+pub fn update_desktop_database(data_dir: &Path) {
+    let commands = [
+        ("update-mime-database", data_dir.join("mime")),
+        ("update-desktop-database", data_dir.join("applications")),
+    ];
+
+    for (program, dir) in commands {
+        if !dir.is_dir() {
+            continue;
+        }
+
+        tracing::debug!(program, ?dir, "running command");
+
+        match std::process::Command::new(program)
+            .arg(&dir)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+        {
+            Ok(status) if !status.success() => {
+                tracing::warn!(program, ?status, "command failed");
+            }
+            Ok(_) => {}
+            Err(error) => {
+                tracing::debug!(program, ?error, "command not run");
+            }
+        }
+    }
+}
+
+/// Returns whether the path does not exist or is a symlink to the target.
+pub fn is_replaceable_symlink(link_path: &Path, target_path: &Path) -> bool {
+    match std::fs::symlink_metadata(link_path) {
+        Ok(metadata) => {
+            metadata.is_symlink()
+                && std::fs::read_link(link_path).is_ok_and(|path| path == target_path)
+        }
+        Err(_) => true,
+    }
+}
+
+/// Creates a symlink, replacing an existing symlink to the same target.
+pub fn add_symlink(link_path: &Path, target_path: &Path) -> Result<(), OsError> {
+    if !is_replaceable_symlink(link_path, target_path) {
+        return Err(OsError::Other("file already exists"));
+    }
+
+    if let Some(parent) = link_path.parent() {
+        if !parent.exists() {
+            tracing::debug!(?parent, "creating symlink directory");
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+
+    if std::fs::symlink_metadata(link_path).is_ok() {
+        std::fs::remove_file(link_path)?;
+    }
+
+    tracing::debug!(?link_path, ?target_path, "creating symlink");
+    std::os::unix::fs::symlink(target_path, link_path)?;
+
+    Ok(())
+}
+
+pub fn move_to_trash(path: &Path) -> Result<(), OsError> {
+    let home = PathBuf::from(std::env::var_os("HOME").ok_or(OsError::Other("missing HOME"))?);
+    let name = path
+        .file_name()
+        .ok_or(OsError::Other("path has no file name"))?;
+
+    if cfg!(target_os = "macos") {
+        let trash_dir = home.join(".Trash");
+
+        for index in 1..=MAX_TRASH_NAME_INDEX {
+            let destination = trash_dir.join(trash_name(name, index));
+
+            if std::fs::symlink_metadata(&destination).is_err() {
+                return Ok(move_file(path, &destination)?);
+            }
+        }
+    } else {
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(".local").join("share"));
+        let trash_dir = data_home.join("Trash");
+        let files_dir = trash_dir.join("files");
+        let info_dir = trash_dir.join("info");
+
+        for dir in [&files_dir, &info_dir] {
+            std::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)?;
+        }
+
+        let info = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            trash_info_path(path),
+            local_timestamp()
+        );
+
+        for index in 1..=MAX_TRASH_NAME_INDEX {
+            let name = trash_name(name, index);
+            let mut info_name = name.clone();
+            info_name.push(".trashinfo");
+            let info_path = info_dir.join(info_name);
+
+            // Creating the info file reserves the name
+            let mut file = match File::options()
+                .write(true)
+                .create_new(true)
+                .open(&info_path)
+            {
+                Ok(file) => file,
+                Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error.into()),
+            };
+            file.write_all(info.as_bytes())?;
+
+            if let Err(error) = move_file(path, &files_dir.join(&name)) {
+                let _ = std::fs::remove_file(&info_path);
+                return Err(error.into());
+            }
+
+            return Ok(());
+        }
+    }
+
+    Err(OsError::Other("too many files with the same name in trash"))
+}
+
+const MAX_TRASH_NAME_INDEX: u32 = 1000;
+
+/// Returns the name, with a number appended if it is not the first.
+fn trash_name(name: &OsStr, index: u32) -> OsString {
+    let mut name = name.to_os_string();
+
+    if index > 1 {
+        name.push(format!(".{}", index));
+    }
+
+    name
+}
+
+/// Renames the file or, if on a different file system, copies it.
+fn move_file(source: &Path, destination: &Path) -> std::io::Result<()> {
+    match std::fs::rename(source, destination) {
+        Err(error) if error.raw_os_error() == Some(libc::EXDEV) => {
+            tracing::debug!(?source, "copying to trash on another file system");
+            std::fs::copy(source, destination)?;
+            std::fs::remove_file(source)
+        }
+        result => result,
+    }
+}
+
+/// Percent-encodes the path for the `Path` key of a `.trashinfo` file.
+fn trash_info_path(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut encoded = String::new();
+
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    encoded
+}
+
+/// Returns the current local time like `2024-01-02T03:04:05`.
+fn local_timestamp() -> String {
+    // SAFETY: A null pointer is allowed.
+    let time = unsafe { libc::time(std::ptr::null_mut()) };
+    // SAFETY: tm is plain data that is fully written by localtime_r().
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+
+    // SAFETY: Both pointers are valid for the call.
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return String::new();
+    }
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec
+    )
+}
+
+/// Returns whether the path is on a file system mounted read-only, such as
+/// a disk image, optical disc, or squashfs.
+pub fn is_read_only_file_system(path: &Path) -> Result<bool, OsError> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| OsError::Other("path contains a null byte"))?;
+    // SAFETY: statvfs is plain data that is fully written by statvfs().
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+
+    // SAFETY: Both pointers are valid for the call.
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    Ok(stat.f_flag & libc::ST_RDONLY != 0)
+}
+
+/// Removes the symlink if it points to the target.
+pub fn remove_symlink(link_path: &Path, target_path: &Path) -> Result<(), OsError> {
+    if std::fs::symlink_metadata(link_path).is_err() {
+        return Ok(());
+    }
+
+    if is_replaceable_symlink(link_path, target_path) {
+        tracing::debug!(?link_path, "removing symlink");
+        std::fs::remove_file(link_path)?;
+    } else {
+        tracing::warn!(?link_path, "not removing symlink to unknown target");
+    }
+
+    Ok(())
+}
+
+/// Returns whether the OS is NixOS.
+///
+/// NixOS has a read-only system configuration managed declaratively, so
+/// system installs are not possible.
+pub fn is_nixos() -> bool {
+    if Path::new("/etc/NIXOS").exists() {
+        return true;
+    }
+
+    match std::fs::read_to_string("/etc/os-release") {
+        Ok(contents) => is_nixos_os_release(&contents),
+        Err(_) => false,
+    }
+}
+
+fn is_nixos_os_release(contents: &str) -> bool {
+    contents
+        .lines()
+        .filter_map(|line| line.strip_prefix("ID="))
+        .any(|value| value.trim_matches('"') == "nixos")
+}
+
+/// Returns whether running inside the Windows Subsystem for Linux.
+///
+/// Changes to the shell profile only apply to the Linux shells, not to
+/// Windows terminals such as PowerShell.
+pub fn is_wsl() -> bool {
+    if std::env::var_os("WSL_DISTRO_NAME").is_some() {
+        return true;
+    }
+
+    match std::fs::read_to_string("/proc/sys/kernel/osrelease") {
+        Ok(contents) => is_wsl_kernel_release(&contents),
+        Err(_) => false,
+    }
+}
+
+fn is_wsl_kernel_release(release: &str) -> bool {
+    let release = release.to_ascii_lowercase();
+
+    release.contains("microsoft") || release.contains("wsl")
+}
+
+/// Returns whether the path is, or is a symlink to, a read-only file in the
+/// Nix store, such as a profile managed by Home Manager.
+pub fn is_nix_store_path(path: &Path) -> bool {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+    path.starts_with(NIX_STORE_DIR)
+}
+
+/// Returns whether the process is running as root.
+pub fn is_elevated() -> bool {
+    // SAFETY: geteuid() has no preconditions and always succeeds.
+    unsafe { libc::geteuid() == 0 }
+}
+
+pub fn installer_identity() -> InstallerIdentity {
+    let elevation = if !is_elevated() {
+        ElevationMethod::None
+    } else if std::env::var_os("SUDO_USER").is_some() {
+        ElevationMethod::Sudo
+    } else if std::env::var_os("DOAS_USER").is_some() {
+        ElevationMethod::Doas
+    } else if std::env::var_os("PKEXEC_UID").is_some() {
+        ElevationMethod::Pkexec
+    } else {
+        ElevationMethod::Root
+    };
+
+    let username = match elevation {
+        ElevationMethod::Sudo => std::env::var("SUDO_USER").ok(),
+        ElevationMethod::Doas => std::env::var("DOAS_USER").ok(),
+        ElevationMethod::Pkexec => std::env::var("PKEXEC_UID")
+            .ok()
+            .and_then(|uid| uid.parse().ok())
+            .and_then(user_name),
+        _ => None,
+    };
+    // SAFETY: geteuid() has no preconditions and always succeeds.
+    let username = username
+        .or_else(|| user_name(unsafe { libc::geteuid() }))
+        .unwrap_or_default();
+
+    InstallerIdentity {
+        username,
+        hostname: host_name().unwrap_or_default(),
+        elevation,
+    }
+}
+
+/// Returns the login name of the user ID.
+fn user_name(uid: libc::uid_t) -> Option<String> {
+    let mut buf = vec![0 as libc::c_char; 4096];
+    // SAFETY: passwd is plain data that is fully written by getpwuid_r().
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+
+    // SAFETY: The pointers are valid for the given buffer length and the
+    // strings in passwd point into buf which outlives their use.
+    let code =
+        unsafe { libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+
+    if code != 0 || result.is_null() || passwd.pw_name.is_null() {
+        return None;
+    }
+
+    // SAFETY: pw_name is a NUL terminated string within buf.
+    let name = unsafe { std::ffi::CStr::from_ptr(passwd.pw_name) };
+
+    Some(name.to_string_lossy().into_owned())
+}
+
+fn host_name() -> Option<String> {
+    let mut buf = vec![0u8; 256];
+
+    // SAFETY: The pointer is valid for the given buffer length.
+    let code = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+
+    if code != 0 {
+        return None;
+    }
+
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    buf.truncate(len);
+
+    Some(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Exclusive advisory lock on a file using `flock()`.
+///
+/// The file is removed when the lock is released.
+#[derive(Debug)]
+pub struct FileLock {
+    _file: std::fs::File,
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Locks the file, creating it if needed, without waiting.
+    ///
+    /// Returns `None` if another process holds the lock.
+    pub fn try_lock(path: &Path) -> Result<Option<Self>, OsError> {
+        use std::os::{fd::AsRawFd, unix::fs::MetadataExt};
+
+        loop {
+            let file = std::fs::File::options()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(path)?;
+
+            // SAFETY: The file descriptor is valid while the file is open.
+            let result = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+
+            if result != 0 {
+                let error = std::io::Error::last_os_error();
+
+                if error.kind() == std::io::ErrorKind::WouldBlock {
+                    return Ok(None);
+                } else {
+                    return Err(error.into());
+                }
+            }
+
+            // The previous holder removes the file before releasing the lock,
+            // so the file opened may no longer be the one at the path.
+            let locked = file.metadata()?;
+
+            match std::fs::metadata(path) {
+                Ok(current) if current.dev() == locked.dev() && current.ino() == locked.ino() => {
+                    return Ok(Some(Self {
+                        _file: file,
+                        path: path.to_path_buf(),
+                    }));
+                }
+                Ok(_) => {}
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+                Err(error) => return Err(error.into()),
+            }
+
+            tracing::debug!(?path, "lock file replaced, trying again");
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // Removed while still locked; the file is closed afterwards.
+        if let Err(error) = std::fs::remove_file(&self.path) {
+            tracing::warn!(path = ?self.path, ?error, "could not remove lock file");
+        }
+    }
+}
+
+/// Runs the current executable with the same arguments as root and waits
+/// for it to exit.
+///
+/// Returns whether the process exited successfully. The environment
+/// variables are passed explicitly using `env` because sudo and pkexec
+/// clear the environment. The values are visible to other users in the
+/// process list, so they shouldn't contain anything private.
+#[cfg(feature = "ui")]
+pub fn run_current_exe_elevated(env_vars: &[(&str, &str)]) -> Result<bool, OsError> {
+    let exe_path = std::env::current_exe()?;
+
+    for program in ELEVATE_PROGRAMS {
+        let mut command = std::process::Command::new(program);
+        command.arg("/usr/bin/env");
+
+        for (name, value) in env_vars {
+            command.arg(format!("{}={}", name, value));
+        }
+
+        command.arg(&exe_path).args(std::env::args_os().skip(1));
+
+        tracing::debug!(program, ?exe_path, "running elevated");
+
+        match command.status() {
+            Ok(status) => {
+                tracing::debug!(program, ?status, "elevated process exited");
+                return Ok(status.success());
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                tracing::debug!(program, "program not found");
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    Err(OsError::Other("sudo or pkexec not found"))
+}
+
 pub fn get_home() -> Result<PathBuf, OsError> {
-    let home = std::env::var_os("HOME").ok_or_else(|| OsError::Other("missing HOME"))?;
+    let home = std::env::var_os("HOME").ok_or(OsError::Other("missing HOME"))?;
     Ok(PathBuf::from(home))
 }
 
+/// Returns whether the profile is a Nushell script instead of a POSIX shell script.
+fn is_nushell_profile(profile_path: &Path) -> bool {
+    profile_path.extension() == Some(OsStr::new("nu"))
+}
+
+fn create_profile_parent_dir(profile_path: &Path) -> Result<(), OsError> {
+    if let Some(parent) = profile_path.parent() {
+        if !parent.exists() {
+            tracing::debug!(?parent, "creating profile directory");
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn nushell_config_dir(home: &Path) -> PathBuf {
+    match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(value) if Path::new(&value).is_absolute() => PathBuf::from(value).join("nushell"),
+        _ => {
+            if cfg!(target_os = "macos") {
+                home.join("Library/Application Support/nushell")
+            } else {
+                home.join(".config/nushell")
+            }
+        }
+    }
+}
+
 pub fn get_current_shell_profile() -> Result<PathBuf, OsError> {
     let home = get_home()?;
     let zsh_profile = home.join(".zprofile");
@@ -147,6 +1192,11 @@ pub fn get_current_shell_profile() -> Result<PathBuf, OsError> {
     if let Some(shell_name) = shell_path.file_name() {
         let shell_name = shell_name.to_str().unwrap_or_default();
 
+        // Nushell doesn't read the POSIX profiles when used as a login shell
+        if shell_name == "nu" {
+            return Ok(nushell_config_dir(&home).join("env.nu"));
+        }
+
         match shell_name {
             "zsh" if zsh_profile.exists() => return Ok(zsh_profile),
             "bash" if bash_profile.exists() => return Ok(bash_profile),
@@ -188,6 +1238,44 @@ fn path_to_shell_script_path(path: &Path, home: &Path) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_trash_name() {
+        assert_eq!(trash_name(OsStr::new("a.txt"), 1), "a.txt");
+        assert_eq!(trash_name(OsStr::new("a.txt"), 2), "a.txt.2");
+    }
+
+    #[test]
+    fn test_trash_info_path() {
+        assert_eq!(
+            trash_info_path(Path::new("/home/user/My Files/a%b.txt")),
+            "/home/user/My%20Files/a%25b.txt"
+        );
+        assert_eq!(trash_info_path(Path::new("/tmp/é")), "/tmp/%C3%A9");
+    }
+
+    #[test]
+    fn test_installer_identity() {
+        let identity = installer_identity();
+
+        assert!(!identity.username.is_empty());
+        assert!(!identity.hostname.is_empty());
+        assert_eq!(identity.elevation == ElevationMethod::None, !is_elevated());
+    }
+
+    #[test]
+    fn test_file_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.lock");
+
+        let lock = FileLock::try_lock(&path).unwrap().unwrap();
+        assert!(FileLock::try_lock(&path).unwrap().is_none());
+
+        drop(lock);
+        assert!(!path.exists());
+
+        let _lock = FileLock::try_lock(&path).unwrap().unwrap();
+    }
+
     #[test]
     fn test_path_to_shell_script_path() {
         assert_eq!(
@@ -199,4 +1287,179 @@ mod tests {
             "/mnt/my_data/bin/"
         );
     }
+
+    #[test]
+    fn test_mime_info_xml() {
+        let xml = mime_info_xml(&[MimeTypeInfo {
+            mime_type: "application/x-my-app".to_string(),
+            comment: "My App <Document>".to_string(),
+            globs: vec!["*.myapp".to_string()],
+        }]);
+
+        assert!(xml.contains(r#"<mime-type type="application/x-my-app">"#));
+        assert!(xml.contains("<comment>My App &lt;Document&gt;</comment>"));
+        assert!(xml.contains(r#"<glob pattern="*.myapp"/>"#));
+    }
+
+    #[test]
+    fn test_desktop_entry() {
+        let entry = desktop_entry(&DesktopEntryConfig {
+            name: "My App".to_string(),
+            locale_names: BTreeMap::from([
+                ("fr".to_string(), "Mon appli".to_string()),
+                ("zh-Hant-TW".to_string(), "我的應用".to_string()),
+            ]),
+            comment: "Edits\ndocuments".to_string(),
+            locale_comments: BTreeMap::from([(
+                "pt-BR".to_string(),
+                "Edita documentos".to_string(),
+            )]),
+            icon: Some(PathBuf::from("/home/rust/.local/share/my_app/icon.png")),
+            exe_path: PathBuf::from("/home/rust/.local/bin/my $app"),
+            mime_types: vec!["application/x-my-app".to_string(), "text/plain".to_string()],
+        });
+
+        assert!(entry.starts_with("[Desktop Entry]\n"));
+        assert!(entry.contains("Name=My App\n"));
+        assert!(entry.contains("Name[fr]=Mon appli\n"));
+        assert!(entry.contains("Name[zh_TW]=我的應用\n"));
+        assert!(entry.contains("Comment=Edits\\ndocuments\n"));
+        assert!(entry.contains("Comment[pt_BR]=Edita documentos\n"));
+        assert!(entry.contains("Icon=/home/rust/.local/share/my_app/icon.png\n"));
+        assert!(entry.contains(r#"Exec="/home/rust/.local/bin/my \\$app" %f"#));
+        assert!(entry.contains("MimeType=application/x-my-app;text/plain;\n"));
+    }
+
+    #[test]
+    fn test_env_vars_snippet() {
+        let env_vars = vec![
+            (
+                "MY_APP_HOME".to_string(),
+                "/home/rust/.local/share/my_app".to_string(),
+            ),
+            ("MY_APP_GREETING".to_string(), "it's".to_string()),
+        ];
+        let snippet = env_vars_snippet("io.example.my-app", &env_vars, false).unwrap();
+
+        assert!(snippet.contains("export MY_APP_HOME='/home/rust/.local/share/my_app'\n"));
+        assert!(snippet.contains(r"export MY_APP_GREETING='it'\''s'"));
+
+        let profile = format!("# existing\n{}# after\n", snippet);
+        assert_eq!(
+            remove_env_vars_snippet(&profile, "io.example.my-app"),
+            "# existing\n# after\n"
+        );
+        assert_eq!(
+            remove_env_vars_snippet(&profile, "io.example.other"),
+            profile
+        );
+    }
+
+    #[test]
+    fn test_nushell_snippets() {
+        let env_vars = vec![("MY_APP_GREETING".to_string(), "it's".to_string())];
+        let snippet = env_vars_snippet("io.example.my-app", &env_vars, true).unwrap();
+
+        assert!(snippet.contains("$env.MY_APP_GREETING = r#'it's'#\n"));
+
+        let env_vars = vec![("MY_APP_GREETING".to_string(), "'#".to_string())];
+        assert!(env_vars_snippet("io.example.my-app", &env_vars, true).is_err());
+
+        let (path, snippet) = path_env_var_snippet(
+            OsStr::new("/home/rust/.local/bin"),
+            Path::new("/home/rust/.config/nushell/env.nu"),
+        )
+        .unwrap();
+
+        assert_eq!(path, "/home/rust/.local/bin");
+        assert!(snippet.contains("prepend '/home/rust/.local/bin'"));
+
+        assert!(path_env_var_snippet(
+            OsStr::new("/home/rust's/bin"),
+            Path::new("/home/rust/.config/nushell/env.nu"),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_is_wsl_kernel_release() {
+        assert!(is_wsl_kernel_release(
+            "5.15.153.1-microsoft-standard-WSL2\n"
+        ));
+        assert!(is_wsl_kernel_release("4.4.0-19041-Microsoft\n"));
+        assert!(!is_wsl_kernel_release("6.8.0-45-generic\n"));
+    }
+
+    #[test]
+    fn test_is_nixos_os_release() {
+        assert!(is_nixos_os_release(
+            "NAME=NixOS\nID=nixos\nVERSION_ID=\"24.05\"\n"
+        ));
+        assert!(is_nixos_os_release("ID=\"nixos\"\n"));
+        assert!(!is_nixos_os_release(
+            "NAME=Debian\nID=debian\nID_LIKE=nixos\n"
+        ));
+    }
+
+    #[test]
+    fn test_paths_d_contents() {
+        assert_eq!(
+            paths_d_contents(OsStr::new("/opt/my app/bin")).unwrap(),
+            "/opt/my app/bin\n"
+        );
+        assert!(paths_d_contents(OsStr::new("/opt/a\nb")).is_err());
+    }
+
+    #[test]
+    fn test_crontab_line() {
+        let task = ScheduledTaskConfig {
+            id: "io.example.my-app.sync".to_string(),
+            schedule: TaskSchedule::Daily,
+            exe_path: PathBuf::from("/home/rust/.local/bin/my app"),
+            args: vec!["--it's".to_string(), "100%".to_string()],
+        };
+
+        assert_eq!(
+            crontab_line(&task, None),
+            r"@daily '/home/rust/.local/bin/my app' '--it'\''s' '100\%' # io.crates.takecrate:io.example.my-app.sync"
+        );
+        assert!(crontab_line(&task, Some("root")).starts_with("@daily root '/home"));
+    }
+
+    #[test]
+    fn test_remove_crontab_lines() {
+        let contents = "MAILTO=\"\"\n\
+            @daily 'a' # io.crates.takecrate:io.example.a.sync\n\
+            @hourly 'b' # io.crates.takecrate:io.example.a.sync2\n";
+
+        assert_eq!(
+            remove_crontab_lines(contents, "# io.crates.takecrate:io.example.a.sync"),
+            "MAILTO=\"\"\n@hourly 'b' # io.crates.takecrate:io.example.a.sync2\n"
+        );
+    }
+
+    #[test]
+    fn test_desktop_entry_locale() {
+        assert_eq!(desktop_entry_locale("fr").unwrap(), "fr");
+        assert_eq!(desktop_entry_locale("pt-BR").unwrap(), "pt_BR");
+        assert_eq!(desktop_entry_locale("zh-Hans-CN").unwrap(), "zh_CN");
+        assert_eq!(desktop_entry_locale("es-419").unwrap(), "es_419");
+        assert!(desktop_entry_locale("").is_none());
+        assert!(desktop_entry_locale("x-private").is_none());
+    }
+
+    #[test]
+    fn test_desktop_entry_url_scheme() {
+        let entry = desktop_entry(&DesktopEntryConfig {
+            name: "My App".to_string(),
+            exe_path: PathBuf::from("/usr/bin/my_app"),
+            mime_types: vec!["x-scheme-handler/myapp".to_string()],
+            ..Default::default()
+        });
+
+        assert!(!entry.contains("Comment="));
+        assert!(!entry.contains("Icon="));
+        assert!(entry.contains(r#"Exec="/usr/bin/my_app" %u"#));
+        assert!(entry.contains("MimeType=x-scheme-handler/myapp;\n"));
+    }
 }
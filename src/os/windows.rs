@@ -1,13 +1,55 @@
 use std::{
     ffi::{OsStr, OsString},
-    path::PathBuf,
+    io::Write,
+    path::{Path, PathBuf},
 };
 
+use windows::{
+    core::{Interface, GUID, HSTRING, PCWSTR},
+    Win32::{
+        Foundation::{
+            CloseHandle, GetLastError, ERROR_ALREADY_EXISTS, HANDLE, LPARAM, TRUE, WPARAM,
+        },
+        Security::PSID,
+        Storage::FileSystem::{
+            GetDriveTypeW, GetVolumeInformationW, GetVolumePathNameW, MoveFileExW,
+            MOVEFILE_DELAY_UNTIL_REBOOT, MOVEFILE_REPLACE_EXISTING,
+        },
+        System::{
+            Com::{
+                CoCreateInstance, CoInitializeEx, CoTaskMemFree, CoUninitialize, IPersistFile,
+                CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED,
+            },
+            Environment::ExpandEnvironmentStringsW,
+            EventLog::{
+                DeregisterEventSource, RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE,
+                EVENTLOG_INFORMATION_TYPE,
+            },
+            Registry::{
+                RegCreateKeyExW, RegDeleteKeyExW, RegDeleteTreeW, RegOpenKeyExW, HKEY,
+                KEY_ALL_ACCESS, KEY_READ, KEY_WOW64_64KEY, KEY_WRITE, REG_OPTION_NON_VOLATILE,
+                REG_SAM_FLAGS,
+            },
+            Threading::{CreateMutexW, ReleaseMutex},
+        },
+        UI::{
+            Shell::{
+                FOLDERID_CommonPrograms, FOLDERID_Desktop, FOLDERID_Programs,
+                FOLDERID_PublicDesktop, IShellLinkW, IsUserAnAdmin, SHChangeNotify,
+                SHFileOperationW, SHGetKnownFolderPath, ShellLink, FOF_ALLOWUNDO, FOF_NO_UI,
+                FO_DELETE, KF_FLAG_DEFAULT, SHCNE_ASSOCCHANGED, SHCNF_IDLIST, SHFILEOPSTRUCTW,
+            },
+            WindowsAndMessaging::{
+                SendMessageTimeoutW, HWND_BROADCAST, SMTO_ABORTIFHUNG, WM_SETTINGCHANGE,
+            },
+        },
+    },
+};
 use windows_registry::Key;
 
-use crate::manifest::AppId;
+use crate::{inst::TaskSchedule, manifest::AppId};
 
-use super::{AccessScope, OsError};
+use super::{AccessScope, ElevationMethod, InstallerIdentity, OsError, ScheduledTaskConfig};
 
 // Notes on environment variables:
 // https://winreg-kb.readthedocs.io/en/latest/sources/system-keys/Environment-variables.html
@@ -15,24 +57,52 @@ use super::{AccessScope, OsError};
 // https://learn.microsoft.com/en-us/windows/deployment/usmt/usmt-recognized-environment-variables
 // https://gist.github.com/pkfrom/f6eb82316b725a51f357
 //
+// https://learn.microsoft.com/en-us/windows/win32/winmsg/wm-settingchange
+//
 // Notes on app paths:
 // https://learn.microsoft.com/en-us/windows/win32/shell/app-registration
 //
 // Notes on installed app entries:
 // https://learn.microsoft.com/en-us/windows/win32/msi/uninstall-registry-key
 //
+// Notes on file associations:
+// https://learn.microsoft.com/en-us/windows/win32/shell/fa-progids
+// https://learn.microsoft.com/en-us/windows/win32/shell/fa-how-work
+//
 // Notes on app menu entries:
 // https://superuser.com/a/960566
+// https://learn.microsoft.com/en-us/windows/win32/shell/links
+//
+// Notes on WOW64 registry redirection:
+// https://learn.microsoft.com/en-us/windows/win32/winprog64/registry-redirector
+// https://learn.microsoft.com/en-us/windows/win32/winprog64/accessing-an-alternate-registry-view
 //
 // Note on registry API:
 // * open() is open read-only
 // * create() is open read/write
+// * The windows-registry crate has no option for the registry view, so
+//   create_key_64() and friends are used for redirected keys.
 
 pub const REGISTRY_ENV_USER_KEY: &str = "Environment";
 pub const REGISTRY_ENV_SYSTEM_KEY: &str =
     r"SYSTEM\CurrentControlSet\Control\Session Manager\Environment";
 pub const REGISTRY_APP_PATHS_KEY: &str = r"SOFTWARE\Microsoft\Windows\CurrentVersion\App Paths";
 pub const REGISTRY_UNINSTALL_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Uninstall";
+pub const REGISTRY_CLASSES_KEY: &str = r"Software\Classes";
+pub const REGISTRY_SHELL_FOLDERS_KEY: &str =
+    r"Software\Microsoft\Windows\CurrentVersion\Explorer\Shell Folders";
+
+/// Maximum number of characters in an environment variable value,
+/// excluding the terminating null.
+const MAX_ENV_VAR_LEN: usize = 32766;
+
+const POWERSHELL_PROFILE_TEMPLATE_SNIPPET: &str = r#"
+## <io.crates.takecrate> Automatically inserted snippet
+if (Test-Path -LiteralPath '{path}') {
+    $env:Path = '{path};' + $env:Path
+}
+## </io.crates.takecrate>
+"#;
 
 fn get_registry_predefined_key(access_scope: AccessScope) -> &'static Key {
     match access_scope {
@@ -41,10 +111,123 @@ fn get_registry_predefined_key(access_scope: AccessScope) -> &'static Key {
     }
 }
 
-pub fn add_path_env_var(access_scope: AccessScope, exe_dir: &OsStr) -> Result<(), OsError> {
-    // Remove any existing duplicates of exe_dir
-    remove_path_env_var(access_scope, exe_dir)?;
+// For 32-bit (x86) processes on 64-bit Windows, including ARM64,
+// HKLM\Software is redirected to HKLM\Software\WOW6432Node. The App Paths
+// and uninstall entries are always placed in the 64-bit view so the shell
+// finds them and installers built for any architecture see the same
+// entries. The flag is ignored on 32-bit Windows.
+
+/// Creates or opens the key read/write in the 64-bit registry view.
+fn create_key_64(parent: &Key, path: &str) -> Result<Key, OsError> {
+    let path = HSTRING::from(path);
+    let mut handle = HKEY::default();
+
+    // SAFETY: The path outlives the call and the new handle is owned by the
+    // returned key.
+    unsafe {
+        RegCreateKeyExW(
+            HKEY(parent.as_raw()),
+            &path,
+            0,
+            PCWSTR::null(),
+            REG_OPTION_NON_VOLATILE,
+            KEY_READ | KEY_WRITE | KEY_WOW64_64KEY,
+            None,
+            &mut handle,
+            None,
+        )
+        .ok()?;
+
+        Ok(Key::from_raw(handle.0))
+    }
+}
+
+/// Opens an existing key in the 64-bit registry view.
+fn open_key_64(parent: &Key, path: &str, access: REG_SAM_FLAGS) -> Result<Key, OsError> {
+    let path = HSTRING::from(path);
+    let mut handle = HKEY::default();
+
+    // SAFETY: The path outlives the call and the new handle is owned by the
+    // returned key.
+    unsafe {
+        RegOpenKeyExW(
+            HKEY(parent.as_raw()),
+            &path,
+            0,
+            access | KEY_WOW64_64KEY,
+            &mut handle,
+        )
+        .ok()?;
+
+        Ok(Key::from_raw(handle.0))
+    }
+}
+
+/// Removes the key and its subkeys in the 64-bit registry view.
+///
+/// A missing key is not an error.
+fn remove_tree_64(parent: &Key, path: &str) -> Result<(), OsError> {
+    let key = match open_key_64(parent, path, KEY_ALL_ACCESS) {
+        Ok(key) => key,
+        Err(OsError::Windows(error)) if error.code() == windows_result::HRESULT::from_win32(2) => {
+            return Ok(());
+        }
+        Err(error) => return Err(error),
+    };
+
+    // SAFETY: The handle is valid while the key is alive.
+    unsafe { RegDeleteTreeW(HKEY(key.as_raw()), PCWSTR::null()).ok()? };
+    drop(key);
+
+    let path = HSTRING::from(path);
+
+    // SAFETY: The path outlives the call.
+    unsafe { RegDeleteKeyExW(HKEY(parent.as_raw()), &path, KEY_WOW64_64KEY.0, 0).ok()? };
+
+    Ok(())
+}
+
+/// Value of the Path environment variable as stored in the registry.
+///
+/// Variables, such as `%USERPROFILE%`, are not expanded so the entries of
+/// other installers are preserved when the value is rewritten.
+struct RawPathEnvVar {
+    value: OsString,
+    expandable: bool,
+}
 
+impl RawPathEnvVar {
+    fn read(hkey: &Key) -> Result<Self, OsError> {
+        let expandable = match hkey.get_type("Path") {
+            Ok(ty) => ty != windows_registry::Type::String,
+            Err(error) if error.code() == windows_result::HRESULT::from_win32(2) => {
+                return Ok(Self {
+                    value: OsString::new(),
+                    expandable: true,
+                });
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let value = hkey.get_hstring("Path")?.to_os_string();
+
+        Ok(Self { value, expandable })
+    }
+
+    fn write(&self, hkey: &Key) -> Result<(), OsError> {
+        let value = windows_registry::HSTRING::from(self.value.as_os_str());
+
+        // Keep the type so the variables in the value are interpreted the same
+        if self.expandable {
+            hkey.set_expand_hstring("Path", &value)?;
+        } else {
+            hkey.set_hstring("Path", &value)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn add_path_env_var(access_scope: AccessScope, exe_dir: &OsStr) -> Result<(), OsError> {
     let predef_key = crate::os::windows::get_registry_predefined_key(access_scope);
     let key_path = match access_scope {
         AccessScope::User => REGISTRY_ENV_USER_KEY,
@@ -54,16 +237,90 @@ pub fn add_path_env_var(access_scope: AccessScope, exe_dir: &OsStr) -> Result<()
     tracing::debug!(key_path, "opening path key read/write");
     let hkey = predef_key.create(key_path)?;
 
-    let mut value = hkey.get_hstring("Path")?.to_os_string();
-    value.push(";");
-    value.push(exe_dir);
+    let mut raw = RawPathEnvVar::read(&hkey)?;
+
+    // An existing entry may have been written with variables by the user
+    if contains_part_in_path_env_var_str(&raw.value, exe_dir, expand_env_vars) {
+        tracing::debug!(key_path, ?exe_dir, "directory already in path key");
+        return Ok(());
+    }
+
+    raw.value = append_part_in_path_env_var_str(&raw.value, exe_dir);
+    let other_value = read_path_env_var(other_access_scope(access_scope)).unwrap_or_default();
 
-    tracing::debug!(key_path, ?value, "saving path key");
-    hkey.set_expand_hstring("Path", &value.into())?;
+    // The OS truncates the value of a process instead, which breaks other programs
+    if !is_path_env_var_len_ok(&raw.value, &other_value) {
+        tracing::error!(key_path, ?exe_dir, "Path environment variable too long");
+        return Err(OsError::Other("Path environment variable too long"));
+    }
+
+    tracing::debug!(key_path, value = ?raw.value, "saving path key");
+    raw.write(&hkey)?;
+
+    notify_environment_changed();
 
     Ok(())
 }
 
+/// Returns whether the directory can be added to the Path environment
+/// variable without exceeding the length limit of environment variables.
+///
+/// The Path of a process is the system value followed by the user value,
+/// so both are counted. Values containing variables, such as
+/// `%SystemRoot%`, are counted unexpanded.
+pub fn path_env_var_has_room(access_scope: AccessScope, exe_dir: &OsStr) -> bool {
+    let value = read_path_env_var(access_scope).unwrap_or_default();
+
+    if contains_part_in_path_env_var_str(&value, exe_dir, expand_env_vars) {
+        return true;
+    }
+
+    let value = append_part_in_path_env_var_str(&value, exe_dir);
+    let other_value = read_path_env_var(other_access_scope(access_scope)).unwrap_or_default();
+
+    is_path_env_var_len_ok(&value, &other_value)
+}
+
+fn read_path_env_var(access_scope: AccessScope) -> Result<OsString, OsError> {
+    let predef_key = crate::os::windows::get_registry_predefined_key(access_scope);
+    let key_path = match access_scope {
+        AccessScope::User => REGISTRY_ENV_USER_KEY,
+        AccessScope::System => REGISTRY_ENV_SYSTEM_KEY,
+    };
+
+    tracing::debug!(key_path, "opening path key read-only");
+    let hkey = predef_key.open(key_path)?;
+
+    Ok(RawPathEnvVar::read(&hkey)?.value)
+}
+
+fn other_access_scope(access_scope: AccessScope) -> AccessScope {
+    match access_scope {
+        AccessScope::User => AccessScope::System,
+        AccessScope::System => AccessScope::User,
+    }
+}
+
+fn is_path_env_var_len_ok(value: &OsStr, other_value: &OsStr) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+
+    // Joined with a semicolon
+    let len = value.encode_wide().count() + 1 + other_value.encode_wide().count();
+
+    len <= MAX_ENV_VAR_LEN
+}
+
+/// Returns whether the directory is in the Path environment variable in the registry.
+pub fn is_in_path_env_var(access_scope: AccessScope, exe_dir: &OsStr) -> Result<bool, OsError> {
+    let value = read_path_env_var(access_scope)?;
+
+    Ok(contains_part_in_path_env_var_str(
+        &value,
+        exe_dir,
+        expand_env_vars,
+    ))
+}
+
 pub fn remove_path_env_var(access_scope: AccessScope, exe_dir: &OsStr) -> Result<(), OsError> {
     let predef_key = crate::os::windows::get_registry_predefined_key(access_scope);
     let key_path = match access_scope {
@@ -74,23 +331,89 @@ pub fn remove_path_env_var(access_scope: AccessScope, exe_dir: &OsStr) -> Result
     tracing::debug!(key_path, "opening path key read/write");
     let hkey = predef_key.create(key_path)?;
 
-    let value = hkey.get_hstring("Path")?.to_os_string();
-    let value = remove_part_in_path_env_var_str(&value, exe_dir);
+    let mut raw = RawPathEnvVar::read(&hkey)?;
+    let value = remove_part_in_path_env_var_str(&raw.value, exe_dir, expand_env_vars);
+
+    // Avoid rewriting values that don't contain the directory
+    if value == raw.value {
+        tracing::debug!(key_path, ?exe_dir, "directory not in path key");
+        return Ok(());
+    }
+
+    raw.value = value;
 
-    tracing::debug!(key_path, ?value, "saving path key");
-    hkey.set_expand_hstring("Path", &value.into())?;
+    tracing::debug!(key_path, value = ?raw.value, "saving path key");
+    raw.write(&hkey)?;
+
+    notify_environment_changed();
 
     Ok(())
 }
 
-fn remove_part_in_path_env_var_str(path_env_var: &OsStr, path_dir: &OsStr) -> OsString {
+/// Expands variables, such as `%SystemRoot%`, in a part of the Path value.
+///
+/// Returns the part unchanged if it could not be expanded.
+fn expand_env_vars(part: &OsStr) -> OsString {
+    use std::os::windows::ffi::OsStringExt;
+
+    let source = HSTRING::from(part);
+
+    // SAFETY: The string outlives the call. Without a buffer, the call
+    // returns the required length including the terminating null.
+    let len = unsafe { ExpandEnvironmentStringsW(&source, None) };
+    let mut buffer = vec![0u16; len as usize];
+
+    // SAFETY: The string and buffer outlive the call.
+    let written = unsafe { ExpandEnvironmentStringsW(&source, Some(&mut buffer)) };
+
+    if written == 0 || written > len {
+        tracing::debug!(?part, "could not expand variables");
+        return part.to_os_string();
+    }
+
+    buffer.truncate(written as usize - 1);
+    OsString::from_wide(&buffer)
+}
+
+fn append_part_in_path_env_var_str(path_env_var: &OsStr, path_dir: &OsStr) -> OsString {
+    let mut value = path_env_var.to_os_string();
+
+    if value.is_empty() {
+        value.push(path_dir);
+    } else if value.as_encoded_bytes().ends_with(b";") {
+        // Keep the trailing separator so removing the part restores the original
+        value.push(path_dir);
+        value.push(";");
+    } else {
+        value.push(";");
+        value.push(path_dir);
+    }
+
+    value
+}
+
+fn contains_part_in_path_env_var_str<F>(path_env_var: &OsStr, path_dir: &OsStr, expand: F) -> bool
+where
+    F: Fn(&OsStr) -> OsString,
+{
+    let path_dir = normalize_path_env_var_part(path_dir.as_encoded_bytes());
+
+    split_path_env_var_str(path_env_var.as_encoded_bytes())
+        .into_iter()
+        .any(|part| is_path_env_var_part_dir(part, &path_dir, &expand))
+}
+
+/// Removes the parts that are the directory while keeping the other parts
+/// as written, including their variables.
+fn remove_part_in_path_env_var_str<F>(path_env_var: &OsStr, path_dir: &OsStr, expand: F) -> OsString
+where
+    F: Fn(&OsStr) -> OsString,
+{
+    let path_dir = normalize_path_env_var_part(path_dir.as_encoded_bytes());
     let values = Vec::from_iter(
-        path_env_var
-            .as_encoded_bytes()
-            .split(|&value| value == b';')
-            .filter(|&part| {
-                !part.is_empty() && !part.eq_ignore_ascii_case(path_dir.as_encoded_bytes())
-            }),
+        split_path_env_var_str(path_env_var.as_encoded_bytes())
+            .into_iter()
+            .filter(|part| !is_path_env_var_part_dir(part, &path_dir, &expand)),
     );
 
     unsafe {
@@ -100,6 +423,300 @@ fn remove_part_in_path_env_var_str(path_env_var: &OsStr, path_dir: &OsStr) -> Os
     }
 }
 
+/// Returns whether the part is the normalized directory as written or with
+/// its variables expanded.
+fn is_path_env_var_part_dir<F>(part: &[u8], path_dir: &[u8], expand: &F) -> bool
+where
+    F: Fn(&OsStr) -> OsString,
+{
+    let part = normalize_path_env_var_part(part);
+
+    if is_path_env_var_part_eq(&part, path_dir) {
+        return true;
+    }
+
+    if !part.contains(&b'%') {
+        return false;
+    }
+
+    // SAFETY: The part was split and unquoted at 1-byte code points.
+    let part = unsafe { OsStr::from_encoded_bytes_unchecked(&part) };
+    let expanded = expand(part);
+
+    is_path_env_var_part_eq(
+        &normalize_path_env_var_part(expanded.as_encoded_bytes()),
+        path_dir,
+    )
+}
+
+/// Splits the value at semicolons that are not enclosed in double quotes.
+fn split_path_env_var_str(value: &[u8]) -> Vec<&[u8]> {
+    let mut parts = Vec::new();
+    let mut in_quote = false;
+    let mut start = 0;
+
+    for (index, &byte) in value.iter().enumerate() {
+        match byte {
+            b'"' => in_quote = !in_quote,
+            b';' if !in_quote => {
+                parts.push(&value[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Removes quotes and trailing backslashes so equivalent directories compare equal.
+fn normalize_path_env_var_part(part: &[u8]) -> Vec<u8> {
+    let mut part = Vec::from_iter(part.iter().copied().filter(|&byte| byte != b'"'));
+
+    while part.len() > 1 && part.ends_with(b"\\") {
+        part.pop();
+    }
+
+    part
+}
+
+fn is_path_env_var_part_eq(part: &[u8], path_dir: &[u8]) -> bool {
+    if part.is_empty() {
+        return false;
+    }
+
+    match (std::str::from_utf8(part), std::str::from_utf8(path_dir)) {
+        (Ok(part), Ok(path_dir)) => part.to_lowercase() == path_dir.to_lowercase(),
+        _ => part.eq_ignore_ascii_case(path_dir),
+    }
+}
+
+fn env_key(access_scope: AccessScope) -> Result<Key, OsError> {
+    let predef_key = crate::os::windows::get_registry_predefined_key(access_scope);
+    let key_path = match access_scope {
+        AccessScope::User => REGISTRY_ENV_USER_KEY,
+        AccessScope::System => REGISTRY_ENV_SYSTEM_KEY,
+    };
+
+    tracing::debug!(key_path, "opening environment key read/write");
+    Ok(predef_key.create(key_path)?)
+}
+
+pub fn add_env_var(access_scope: AccessScope, name: &str, value: &str) -> Result<(), OsError> {
+    let hkey = env_key(access_scope)?;
+
+    tracing::debug!(?access_scope, name, value, "setting environment variable");
+
+    if value.contains('%') {
+        hkey.set_expand_hstring(name, &windows_registry::HSTRING::from(value))?;
+    } else {
+        hkey.set_string(name, value)?;
+    }
+
+    notify_environment_changed();
+
+    Ok(())
+}
+
+/// Removes the environment variable if it still has the given value.
+pub fn remove_env_var(access_scope: AccessScope, name: &str, value: &str) -> Result<(), OsError> {
+    let hkey = env_key(access_scope)?;
+
+    match hkey.get_hstring(name) {
+        Ok(current) if current.to_string_lossy() == value => {
+            tracing::debug!(?access_scope, name, "removing environment variable");
+            hkey.remove_value(name)?;
+            notify_environment_changed();
+        }
+        Ok(_) => {
+            tracing::warn!(?access_scope, name, "environment variable was modified");
+        }
+        Err(_) => {
+            tracing::debug!(?access_scope, name, "environment variable not found");
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the paths of the "current user, all hosts" profiles for Windows
+/// PowerShell and PowerShell (Core).
+pub fn powershell_profile_paths() -> Result<Vec<PathBuf>, OsError> {
+    let documents = documents_dir()?;
+
+    Ok(vec![
+        documents.join(r"WindowsPowerShell\profile.ps1"),
+        documents.join(r"PowerShell\profile.ps1"),
+    ])
+}
+
+fn documents_dir() -> Result<PathBuf, OsError> {
+    // The Documents folder may be redirected, such as to OneDrive.
+    if let Ok(hkey) = windows_registry::CURRENT_USER.open(REGISTRY_SHELL_FOLDERS_KEY) {
+        if let Ok(value) = hkey.get_hstring("Personal") {
+            return Ok(PathBuf::from(value.to_os_string()));
+        }
+    }
+
+    let dir = std::env::var_os("USERPROFILE").ok_or(OsError::Other("missing USERPROFILE"))?;
+
+    Ok(PathBuf::from(dir).join("Documents"))
+}
+
+pub fn add_powershell_profile_path(profile_path: &Path, exe_dir: &OsStr) -> Result<(), OsError> {
+    let snippet = powershell_path_snippet(exe_dir)?;
+    let contents = read_powershell_profile(profile_path)?;
+
+    if contents.contains(&snippet) {
+        return Ok(());
+    }
+
+    if let Some(parent) = profile_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut file = std::fs::File::options()
+        .create(true)
+        .append(true)
+        .open(profile_path)?;
+
+    // Windows PowerShell reads files without a BOM using the legacy code page
+    if contents.is_empty() {
+        file.write_all("\u{FEFF}".as_bytes())?;
+    }
+
+    tracing::debug!(?profile_path, snippet, "saving PowerShell profile");
+    file.write_all(snippet.as_bytes())?;
+    file.flush()?;
+
+    Ok(())
+}
+
+pub fn remove_powershell_profile_path(profile_path: &Path, exe_dir: &OsStr) -> Result<(), OsError> {
+    let snippet = powershell_path_snippet(exe_dir)?;
+    let contents = read_powershell_profile(profile_path)?;
+
+    if !contents.contains(&snippet) {
+        return Ok(());
+    }
+
+    tracing::debug!(?profile_path, "saving PowerShell profile");
+    std::fs::write(profile_path, contents.replace(&snippet, ""))?;
+
+    Ok(())
+}
+
+/// Returns whether the PowerShell profile adds the directory to the search path.
+pub fn is_in_powershell_profile(profile_path: &Path, exe_dir: &OsStr) -> Result<bool, OsError> {
+    let snippet = powershell_path_snippet(exe_dir)?;
+    let contents = read_powershell_profile(profile_path)?;
+
+    Ok(contents.contains(&snippet))
+}
+
+fn read_powershell_profile(profile_path: &Path) -> Result<String, OsError> {
+    if !profile_path.exists() {
+        return Ok(String::new());
+    }
+
+    tracing::debug!(?profile_path, "reading PowerShell profile");
+    let contents = std::fs::read(profile_path)?;
+
+    if contents.starts_with(&[0xFF, 0xFE]) || contents.starts_with(&[0xFE, 0xFF]) {
+        return Err(OsError::Other("unsupported PowerShell profile encoding"));
+    }
+
+    String::from_utf8(contents)
+        .map_err(|_| OsError::Other("unsupported PowerShell profile encoding"))
+}
+
+fn powershell_path_snippet(exe_dir: &OsStr) -> Result<String, OsError> {
+    let path = exe_dir
+        .to_str()
+        .ok_or(OsError::Other("invalid path character"))?;
+
+    if path.chars().any(|c| c.is_control()) {
+        return Err(OsError::Other("invalid path character"));
+    }
+
+    Ok(POWERSHELL_PROFILE_TEMPLATE_SNIPPET
+        .replace("{path}", &powershell_single_quote_escape(path))
+        .replace('\n', "\r\n"))
+}
+
+/// Escapes the value for use inside a single quoted PowerShell string.
+fn powershell_single_quote_escape(value: &str) -> String {
+    // Single quoted strings are escaped by doubling the quote, which includes
+    // the typographic quotes PowerShell also accepts.
+    let mut quoted = String::new();
+
+    for c in value.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            quoted.push(c);
+        }
+
+        quoted.push(c);
+    }
+
+    quoted
+}
+
+/// Returns the directory of the user's app execution aliases.
+pub fn windows_apps_dir() -> Result<PathBuf, OsError> {
+    let local_app_data =
+        std::env::var_os("LocalAppData").ok_or(OsError::Other("LocalAppData not set"))?;
+
+    Ok(PathBuf::from(local_app_data)
+        .join("Microsoft")
+        .join("WindowsApps"))
+}
+
+/// Creates a hard link to the executable, replacing an existing link.
+pub fn add_execution_alias(link_path: &Path, target_path: &Path) -> Result<(), OsError> {
+    if link_path.exists() {
+        std::fs::remove_file(link_path)?;
+    }
+
+    std::fs::hard_link(target_path, link_path)?;
+
+    Ok(())
+}
+
+/// Returns the `.cmd` and `.ps1` scripts that run the executable with the
+/// given arguments from another directory.
+pub fn shim_scripts(exe_path: &Path) -> Result<(String, String), OsError> {
+    let path = exe_path
+        .to_str()
+        .ok_or(OsError::Other("invalid path character"))?;
+
+    if path.chars().any(|c| c.is_control()) {
+        return Err(OsError::Other("invalid path character"));
+    }
+
+    // Percent signs are doubled so they are not expanded as variables.
+    let cmd_script = format!(
+        "@\"{}\" %*\r\n@exit /b %ERRORLEVEL%\r\n",
+        path.replace('%', "%%")
+    );
+    let ps1_script = format!(
+        "& '{}' @args\r\nexit $LASTEXITCODE\r\n",
+        powershell_single_quote_escape(path)
+    );
+
+    Ok((cmd_script, ps1_script))
+}
+
+/// Returns a batch script that runs the executable in the same directory
+/// with the given arguments.
+pub fn alias_script(exe_name: &str) -> String {
+    // Percent signs are doubled so they are not expanded as variables.
+    format!(
+        "@\"%~dp0{}\" %*\r\n@exit /b %ERRORLEVEL%\r\n",
+        exe_name.replace('%', "%%")
+    )
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct AppPathConfig {
     pub additional_path_envs: Vec<OsString>,
@@ -119,7 +736,7 @@ pub fn add_app_path(
     );
 
     tracing::debug!(?access_scope, key_path, "opening key read/write");
-    let hkey = predef_key.create(&key_path)?;
+    let hkey = create_key_64(predef_key, &key_path)?;
 
     tracing::debug!(?access_scope, key_path, ?exe_path, "setting key");
     hkey.set_hstring("", &exe_path.into())?;
@@ -142,56 +759,133 @@ pub fn remove_app_path(access_scope: AccessScope, exe_name: &str) -> Result<(),
     );
 
     tracing::debug!(?access_scope, key_path, "deleting key tree");
-    if predef_key.get_type(&key_path).is_ok() {
-        predef_key.remove_tree(key_path)?;
-    }
+    remove_tree_64(predef_key, &key_path)?;
 
     Ok(())
 }
 
+/// Returns whether the App Paths entry exists.
+pub fn has_app_path(access_scope: AccessScope, exe_name: &str) -> bool {
+    let predef_key = crate::os::windows::get_registry_predefined_key(access_scope);
+    let key_path = format!(
+        r"{}\{}",
+        crate::os::windows::REGISTRY_APP_PATHS_KEY,
+        exe_name
+    );
+
+    open_key_64(predef_key, &key_path, KEY_READ).is_ok()
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct UninstallEntryConfig {
     pub manifest_path: PathBuf,
     pub display_name: String,
     pub display_version: String,
     pub publisher: String,
+    pub homepage_url: String,
+    pub comments: String,
+    pub display_icon: OsString,
     pub estimated_size: u64,
-    pub quiet_exe_args: OsString,
+    pub quiet_exe_args: Vec<String>,
+    pub modify_exe_args: Vec<String>,
+    pub repairable: bool,
 }
 
 pub fn add_uninstall_entry(
     access_scope: AccessScope,
     app_id: &AppId,
     exe_path: &OsStr,
-    exe_args: &OsStr,
+    exe_args: &[String],
     config: &UninstallEntryConfig,
 ) -> Result<(), OsError> {
     let predef_key = crate::os::windows::get_registry_predefined_key(access_scope);
     let key_path = format!(r"{}\{}", REGISTRY_UNINSTALL_KEY, app_id.uuid());
 
     tracing::debug!(?access_scope, key_path, "creating key");
-    let hkey = predef_key.create(key_path)?;
+    let hkey = create_key_64(predef_key, &key_path)?;
 
-    let mut uninstall_string = OsString::new();
-    uninstall_string.push("\"");
-    uninstall_string.push(exe_path);
-    uninstall_string.push("\" ");
-    uninstall_string.push(exe_args);
+    let uninstall_string = command_line(exe_path, exe_args);
 
     tracing::debug!(?uninstall_string, "writing keys");
     hkey.set_hstring("UninstallString", &uninstall_string.into())?;
-    hkey.set_string("DisplayName", &config.display_name)?;
     hkey.set_hstring(
         "takecrate_manifest_path",
         &config.manifest_path.as_os_str().into(),
     )?;
 
-    if !config.display_version.is_empty() {
+    set_uninstall_entry_metadata(&hkey, config)?;
+
+    if !config.display_icon.is_empty() {
+        hkey.set_hstring("DisplayIcon", &config.display_icon.as_os_str().into())?;
+    }
+
+    if !config.quiet_exe_args.is_empty() {
+        let quiet_string = command_line(exe_path, &config.quiet_exe_args);
+
+        hkey.set_hstring("QuietUninstallString", &quiet_string.into())?;
+    }
+
+    // Without NoModify, the Modify button runs the UninstallString
+    if config.modify_exe_args.is_empty() {
+        let _ = hkey.remove_value("ModifyPath");
+        hkey.set_u32("NoModify", 1)?;
+        hkey.set_u32("NoRepair", 1)?;
+    } else {
+        let modify_string = command_line(exe_path, &config.modify_exe_args);
+
+        tracing::debug!(?modify_string, config.repairable, "writing modify keys");
+        hkey.set_hstring("ModifyPath", &modify_string.into())?;
+        hkey.set_u32("NoModify", 0)?;
+        hkey.set_u32("NoRepair", u32::from(!config.repairable))?;
+    }
+
+    Ok(())
+}
+
+/// Rewrites the name, version, publisher, URL, comments, and size of an
+/// existing uninstall entry.
+///
+/// Returns `false` if there is no entry.
+pub fn update_uninstall_entry(
+    access_scope: AccessScope,
+    app_id: &AppId,
+    config: &UninstallEntryConfig,
+) -> Result<bool, OsError> {
+    let predef_key = crate::os::windows::get_registry_predefined_key(access_scope);
+    let key_path = format!(r"{}\{}", REGISTRY_UNINSTALL_KEY, app_id.uuid());
+
+    if open_key_64(predef_key, &key_path, KEY_READ).is_err() {
+        tracing::debug!(?access_scope, key_path, "no uninstall entry");
+        return Ok(false);
+    }
+
+    tracing::debug!(?access_scope, key_path, "updating key");
+    let hkey = create_key_64(predef_key, &key_path)?;
+
+    set_uninstall_entry_metadata(&hkey, config)?;
+
+    Ok(true)
+}
+
+fn set_uninstall_entry_metadata(hkey: &Key, config: &UninstallEntryConfig) -> Result<(), OsError> {
+    hkey.set_string("DisplayName", &config.display_name)?;
+
+    if config.display_version.is_empty() {
+        let _ = hkey.remove_value("DisplayVersion");
+    } else {
         hkey.set_string("DisplayVersion", &config.display_version)?;
     }
 
-    if !config.publisher.is_empty() {
-        hkey.set_string("Publisher", &config.publisher)?;
+    for (name, value) in [
+        ("Publisher", &config.publisher),
+        ("URLInfoAbout", &config.homepage_url),
+        ("Comments", &config.comments),
+    ] {
+        if value.is_empty() {
+            let _ = hkey.remove_value(name);
+        } else {
+            hkey.set_string(name, value)?;
+        }
     }
 
     if config.estimated_size > 0 {
@@ -200,19 +894,19 @@ pub fn add_uninstall_entry(
             "EstimatedSize",
             (config.estimated_size >> 10).try_into().unwrap_or(u32::MAX),
         )?;
+    } else {
+        let _ = hkey.remove_value("EstimatedSize");
     }
 
-    if !config.quiet_exe_args.is_empty() {
-        let mut quiet_string = OsString::new();
-        quiet_string.push("\"");
-        quiet_string.push(exe_path);
-        quiet_string.push("\" ");
-        quiet_string.push(&config.quiet_exe_args);
+    Ok(())
+}
 
-        hkey.set_hstring("QuietInstallString", &quiet_string.into())?;
-    }
+/// Returns whether the entry in the list of installed applications exists.
+pub fn has_uninstall_entry(access_scope: AccessScope, app_id: &AppId) -> bool {
+    let predef_key = crate::os::windows::get_registry_predefined_key(access_scope);
+    let key_path = format!(r"{}\{}", REGISTRY_UNINSTALL_KEY, app_id.uuid());
 
-    Ok(())
+    open_key_64(predef_key, &key_path, KEY_READ).is_ok()
 }
 
 pub fn remove_uninstall_entry(access_scope: AccessScope, app_id: &AppId) -> Result<(), OsError> {
@@ -220,26 +914,986 @@ pub fn remove_uninstall_entry(access_scope: AccessScope, app_id: &AppId) -> Resu
     let key_path = format!(r"{}\{}", REGISTRY_UNINSTALL_KEY, app_id.uuid());
 
     tracing::debug!(?access_scope, key_path, "removing key tree");
+    remove_tree_64(predef_key, &key_path)?;
 
-    if predef_key.open(&key_path).is_ok() {
-        predef_key.remove_tree(key_path)?;
-    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FileAssociationConfig {
+    pub prog_id: String,
+    pub extensions: Vec<String>,
+    pub description: String,
+    pub icon: OsString,
+    pub command: OsString,
+}
+
+pub fn add_file_association(
+    access_scope: AccessScope,
+    config: &FileAssociationConfig,
+) -> Result<(), OsError> {
+    let predef_key = crate::os::windows::get_registry_predefined_key(access_scope);
+    let key_path = format!(r"{}\{}", REGISTRY_CLASSES_KEY, config.prog_id);
+
+    tracing::debug!(?access_scope, key_path, "creating key");
+    let hkey = predef_key.create(&key_path)?;
+
+    if !config.description.is_empty() {
+        hkey.set_string("", config.description.as_str())?;
+    }
+
+    if !config.icon.is_empty() {
+        hkey.create("DefaultIcon")?
+            .set_hstring("", &config.icon.as_os_str().into())?;
+    }
+
+    tracing::debug!(?access_scope, key_path, command = ?config.command, "setting open verb");
+    hkey.create(r"shell\open\command")?
+        .set_hstring("", &config.command.as_os_str().into())?;
+
+    for extension in &config.extensions {
+        let key_path = format!(r"{}\{}\OpenWithProgids", REGISTRY_CLASSES_KEY, extension);
+
+        tracing::debug!(?access_scope, key_path, "adding ProgID to extension");
+        predef_key
+            .create(&key_path)?
+            .set_string(config.prog_id.as_str(), "")?;
+    }
+
+    notify_association_changed();
+
+    Ok(())
+}
+
+pub fn remove_file_association(
+    access_scope: AccessScope,
+    prog_id: &str,
+    extensions: &[String],
+) -> Result<(), OsError> {
+    let predef_key = crate::os::windows::get_registry_predefined_key(access_scope);
+    let key_path = format!(r"{}\{}", REGISTRY_CLASSES_KEY, prog_id);
+
+    tracing::debug!(?access_scope, key_path, "removing key tree");
+    if predef_key.open(&key_path).is_ok() {
+        predef_key.remove_tree(key_path)?;
+    }
+
+    for extension in extensions {
+        let key_path = format!(r"{}\{}\OpenWithProgids", REGISTRY_CLASSES_KEY, extension);
+
+        if predef_key.open(&key_path).is_ok() {
+            let hkey = predef_key.create(&key_path)?;
+
+            if hkey.get_type(prog_id).is_ok() {
+                tracing::debug!(?access_scope, key_path, "removing ProgID from extension");
+                hkey.remove_value(prog_id)?;
+            }
+        }
+    }
+
+    notify_association_changed();
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UrlSchemeConfig {
+    pub scheme: String,
+    pub description: String,
+    pub icon: OsString,
+    pub command: OsString,
+}
+
+pub fn add_url_scheme(access_scope: AccessScope, config: &UrlSchemeConfig) -> Result<(), OsError> {
+    let predef_key = crate::os::windows::get_registry_predefined_key(access_scope);
+    let key_path = format!(r"{}\{}", REGISTRY_CLASSES_KEY, config.scheme);
+
+    tracing::debug!(?access_scope, key_path, "creating key");
+    let hkey = predef_key.create(&key_path)?;
+
+    hkey.set_string("", format!("URL:{}", config.description).as_str())?;
+    hkey.set_string("URL Protocol", "")?;
+
+    if !config.icon.is_empty() {
+        hkey.create("DefaultIcon")?
+            .set_hstring("", &config.icon.as_os_str().into())?;
+    }
+
+    tracing::debug!(?access_scope, key_path, command = ?config.command, "setting open verb");
+    hkey.create(r"shell\open\command")?
+        .set_hstring("", &config.command.as_os_str().into())?;
+
+    notify_association_changed();
+
+    Ok(())
+}
+
+pub fn remove_url_scheme(access_scope: AccessScope, scheme: &str) -> Result<(), OsError> {
+    let predef_key = crate::os::windows::get_registry_predefined_key(access_scope);
+    let key_path = format!(r"{}\{}", REGISTRY_CLASSES_KEY, scheme);
+
+    tracing::debug!(?access_scope, key_path, "removing key tree");
+    if predef_key.open(&key_path).is_ok() {
+        predef_key.remove_tree(key_path)?;
+    }
+
+    notify_association_changed();
+
+    Ok(())
+}
+
+const TASK_SCHEDULER_FOLDER: &str = r"\takecrate";
+
+fn task_scheduler_name(id: &str) -> String {
+    format!(r"{}\{}", TASK_SCHEDULER_FOLDER, id)
+}
+
+pub fn add_scheduled_task(
+    access_scope: AccessScope,
+    task: &ScheduledTaskConfig,
+) -> Result<(), OsError> {
+    let name = task_scheduler_name(&task.id);
+    let schedule = match task.schedule {
+        TaskSchedule::Hourly => "HOURLY",
+        TaskSchedule::Daily => "DAILY",
+        TaskSchedule::Weekly => "WEEKLY",
+    };
+
+    let mut command = OsString::from("\"");
+    command.push(&task.exe_path);
+    command.push("\"");
+
+    for arg in &task.args {
+        command.push(" ");
+        command.push(quote_command_line_arg(arg));
+    }
+
+    let mut schtasks = std::process::Command::new("schtasks");
+    schtasks
+        .args(["/Create", "/F", "/TN", &name, "/SC", schedule, "/TR"])
+        .arg(&command);
+
+    if access_scope == AccessScope::System {
+        schtasks.args(["/RU", "SYSTEM"]);
+    }
+
+    tracing::debug!(?access_scope, name, ?command, "creating scheduled task");
+    let output = schtasks.output()?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        tracing::error!(status = ?output.status, stderr = ?String::from_utf8_lossy(&output.stderr), "schtasks failed");
+        Err(OsError::Other("failed to create scheduled task"))
+    }
+}
+
+pub fn remove_scheduled_task(access_scope: AccessScope, id: &str) -> Result<(), OsError> {
+    let name = task_scheduler_name(id);
+
+    let exists = std::process::Command::new("schtasks")
+        .args(["/Query", "/TN", &name])
+        .output()?
+        .status
+        .success();
+
+    if !exists {
+        return Ok(());
+    }
+
+    tracing::debug!(?access_scope, name, "deleting scheduled task");
+    let output = std::process::Command::new("schtasks")
+        .args(["/Delete", "/F", "/TN", &name])
+        .output()?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        tracing::error!(status = ?output.status, stderr = ?String::from_utf8_lossy(&output.stderr), "schtasks failed");
+        Err(OsError::Other("failed to delete scheduled task"))
+    }
+}
+
+/// Returns whether the process is running as an administrator.
+pub fn is_elevated() -> bool {
+    // SAFETY: The function has no arguments.
+    unsafe { IsUserAnAdmin().as_bool() }
+}
+
+pub fn installer_identity() -> InstallerIdentity {
+    let env_string = |name| {
+        std::env::var_os(name)
+            .map(|value| value.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+
+    InstallerIdentity {
+        username: env_string("USERNAME"),
+        hostname: env_string("COMPUTERNAME"),
+        elevation: if is_elevated() {
+            ElevationMethod::Administrator
+        } else {
+            ElevationMethod::None
+        },
+    }
+}
+
+/// Writes an event with the message to the Application log.
+///
+/// The source doesn't need to be registered. Event Viewer notes that the
+/// description is missing but still shows the message.
+pub fn report_event(
+    source: &str,
+    event_id: u32,
+    is_error: bool,
+    message: &str,
+) -> Result<(), OsError> {
+    let source = HSTRING::from(source);
+    let message = HSTRING::from(message);
+    let event_type = if is_error {
+        EVENTLOG_ERROR_TYPE
+    } else {
+        EVENTLOG_INFORMATION_TYPE
+    };
+
+    // SAFETY: The strings outlive the calls and the handle is closed once.
+    unsafe {
+        let handle = RegisterEventSourceW(PCWSTR::null(), &source)?;
+        let result = ReportEventW(
+            handle,
+            event_type,
+            0,
+            event_id,
+            PSID::default(),
+            0,
+            Some(&[PCWSTR(message.as_ptr())]),
+            None,
+        );
+        let _ = DeregisterEventSource(handle);
+
+        result?;
+    }
+
+    Ok(())
+}
+
+pub fn move_to_trash(path: &Path) -> Result<(), OsError> {
+    use std::os::windows::ffi::OsStrExt;
+
+    // The list of paths is terminated by an additional NUL
+    let mut from = path.as_os_str().encode_wide().collect::<Vec<u16>>();
+    from.extend([0, 0]);
+
+    let mut operation = SHFILEOPSTRUCTW {
+        wFunc: FO_DELETE,
+        pFrom: PCWSTR(from.as_ptr()),
+        fFlags: (FOF_ALLOWUNDO.0 | FOF_NO_UI.0) as u16,
+        ..Default::default()
+    };
+
+    // SAFETY: The path buffer outlives the call.
+    let code = unsafe { SHFileOperationW(&mut operation) };
+
+    if code != 0 || operation.fAnyOperationsAborted.as_bool() {
+        return Err(std::io::Error::other(format!(
+            "could not move {:?} to Recycle Bin (code {:#x})",
+            path, code
+        ))
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Named mutex owned by this process.
+///
+/// The mutex is released and closed when dropped.
+#[derive(Debug)]
+pub struct NamedMutex {
+    handle: HANDLE,
+}
+
+impl NamedMutex {
+    /// Creates and owns the mutex without waiting.
+    ///
+    /// Returns `None` if another process has the mutex open.
+    pub fn try_lock(name: &str) -> Result<Option<Self>, OsError> {
+        let name = HSTRING::from(name);
+
+        // SAFETY: The name outlives the call.
+        let result = unsafe { CreateMutexW(None, TRUE, &name) };
+
+        let handle = match result {
+            Ok(handle) => handle,
+            // Created by an elevated process with a more restrictive DACL
+            Err(error) if error.code() == windows_result::HRESULT::from_win32(5) => {
+                return Ok(None)
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        // SAFETY: The function has no arguments.
+        if unsafe { GetLastError() } == ERROR_ALREADY_EXISTS {
+            // SAFETY: The handle is valid and not used afterwards.
+            let _ = unsafe { CloseHandle(handle) };
+            return Ok(None);
+        }
+
+        Ok(Some(Self { handle }))
+    }
+}
+
+impl Drop for NamedMutex {
+    fn drop(&mut self) {
+        // SAFETY: The handle is owned by this struct and not used afterwards.
+        unsafe {
+            let _ = ReleaseMutex(self.handle);
+            let _ = CloseHandle(self.handle);
+        }
+    }
+}
+
+/// Runs the current executable with the same arguments followed by the extra
+/// arguments as an administrator and waits for it to exit.
+///
+/// The user is shown the UAC prompt. Returns whether the process exited
+/// successfully.
+#[cfg(feature = "ui")]
+pub fn run_current_exe_elevated(extra_args: &[&str]) -> Result<bool, OsError> {
+    use windows::{
+        core::w,
+        Win32::{
+            System::Threading::{GetExitCodeProcess, WaitForSingleObject, INFINITE},
+            UI::{
+                Shell::{
+                    ShellExecuteExW, SEE_MASK_NOASYNC, SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW,
+                },
+                WindowsAndMessaging::SW_SHOWNORMAL,
+            },
+        },
+    };
+
+    let exe_path = std::env::current_exe()?;
+    let mut args = Vec::new();
+
+    for arg in std::env::args_os().skip(1) {
+        let arg = arg
+            .to_str()
+            .ok_or(OsError::Other("invalid argument character"))?;
+        args.push(quote_command_line_arg(arg));
+    }
+
+    for arg in extra_args {
+        args.push(quote_command_line_arg(arg));
+    }
+
+    let file = HSTRING::from(exe_path.as_os_str());
+    let parameters = HSTRING::from(args.join(" "));
+
+    let mut info = SHELLEXECUTEINFOW {
+        cbSize: std::mem::size_of::<SHELLEXECUTEINFOW>() as u32,
+        fMask: SEE_MASK_NOCLOSEPROCESS | SEE_MASK_NOASYNC,
+        lpVerb: w!("runas"),
+        lpFile: PCWSTR(file.as_ptr()),
+        lpParameters: PCWSTR(parameters.as_ptr()),
+        nShow: SW_SHOWNORMAL.0,
+        ..Default::default()
+    };
+
+    tracing::debug!(?exe_path, "running elevated");
+
+    unsafe {
+        // SAFETY: The strings outlive the call.
+        ShellExecuteExW(&mut info)?;
+    }
+
+    if info.hProcess.is_invalid() {
+        return Err(OsError::Other("elevated process not started"));
+    }
+
+    let mut exit_code = 1;
+
+    let result = unsafe {
+        // SAFETY: The handle is valid until it is closed below.
+        WaitForSingleObject(info.hProcess, INFINITE);
+        let result = GetExitCodeProcess(info.hProcess, &mut exit_code);
+        let _ = CloseHandle(info.hProcess);
+        result
+    };
+    result?;
+
+    tracing::debug!(exit_code, "elevated process exited");
+
+    Ok(exit_code == 0)
+}
+
+/// Returns the quoted executable path followed by the quoted arguments.
+///
+/// The result is split back into the same arguments by `CommandLineToArgvW`,
+/// which is how the `UninstallString` is run. The executable path is always
+/// quoted so that paths with spaces, such as those in Program Files, are not
+/// split. It is not escaped because Windows paths cannot contain quotes.
+pub fn command_line(exe_path: &OsStr, args: &[String]) -> OsString {
+    let mut command = OsString::from("\"");
+    command.push(exe_path);
+    command.push("\"");
+
+    for arg in args {
+        command.push(" ");
+        command.push(quote_command_line_arg(arg));
+    }
+
+    command
+}
+
+/// Quotes the argument using the rules of `CommandLineToArgvW`.
+/// Opens the existing registry key for writing without modifying it.
+pub fn check_registry_key_writable(
+    access_scope: AccessScope,
+    key_path: &str,
+) -> Result<(), OsError> {
+    let predef_key = crate::os::windows::get_registry_predefined_key(access_scope);
+    create_key_64(predef_key, key_path)?;
+
+    Ok(())
+}
+
+/// Returns whether the error is `E_ACCESSDENIED`.
+pub fn is_access_denied(error: &OsError) -> bool {
+    match error {
+        OsError::Io(error) => error.kind() == std::io::ErrorKind::PermissionDenied,
+        OsError::Windows(error) => error.code() == windows_result::HRESULT::from_win32(5),
+        _ => false,
+    }
+}
+
+/// Moves the file to the destination or, if `None`, deletes the file when
+/// the computer restarts.
+///
+/// This requires administrator privileges.
+pub fn move_file_on_reboot(source: &Path, destination: Option<&Path>) -> Result<(), OsError> {
+    let source = HSTRING::from(super::extended_length_path(source).as_os_str());
+    let destination =
+        destination.map(|path| HSTRING::from(super::extended_length_path(path).as_os_str()));
+    let destination_ptr = destination
+        .as_ref()
+        .map(|value| PCWSTR(value.as_ptr()))
+        .unwrap_or(PCWSTR::null());
+    let mut flags = MOVEFILE_DELAY_UNTIL_REBOOT;
+
+    if destination.is_some() {
+        flags |= MOVEFILE_REPLACE_EXISTING;
+    }
+
+    tracing::debug!(?source, ?destination, "scheduling file move on reboot");
+
+    // SAFETY: The strings outlive the call.
+    unsafe { MoveFileExW(PCWSTR(source.as_ptr()), destination_ptr, flags)? };
+
+    Ok(())
+}
+
+const DRIVE_REMOTE: u32 = 4;
+const DRIVE_CDROM: u32 = 5;
+const FILE_READ_ONLY_VOLUME: u32 = 0x0008_0000;
+
+/// Returns whether the path is on a read-only volume, an optical disc,
+/// or a network share.
+pub fn is_read_only_or_remote_volume(path: &Path) -> Result<bool, OsError> {
+    let path = HSTRING::from(path.as_os_str());
+    let mut root = [0u16; 261];
+
+    // SAFETY: The string outlives the call and the buffer length is given.
+    unsafe { GetVolumePathNameW(&path, &mut root)? };
+
+    let root = PCWSTR(root.as_ptr());
+    // SAFETY: The buffer is null terminated by GetVolumePathNameW().
+    let drive_type = unsafe { GetDriveTypeW(root) };
+
+    if drive_type == DRIVE_REMOTE || drive_type == DRIVE_CDROM {
+        return Ok(true);
+    }
+
+    let mut flags = 0u32;
+    // SAFETY: The buffer is null terminated and the flags outlive the call.
+    unsafe { GetVolumeInformationW(root, None, None, None, Some(&mut flags), None)? };
+
+    Ok(flags & FILE_READ_ONLY_VOLUME != 0)
+}
+
+/// Returns whether the Win32 error code is `ERROR_SHARING_VIOLATION` or
+/// `ERROR_LOCK_VIOLATION`.
+pub fn is_sharing_violation_code(code: i32) -> bool {
+    matches!(code, 32 | 33)
+}
+
+pub fn is_sharing_violation(error: &windows_result::Error) -> bool {
+    [32, 33]
+        .into_iter()
+        .any(|code| error.code() == windows_result::HRESULT::from_win32(code))
+}
+
+fn quote_command_line_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\x0b', '"']) {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Backslashes before a quote are escaped, then the quote itself.
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push(c);
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+
+    // Trailing backslashes would escape the closing quote.
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+
+    quoted
+}
+
+/// Tells running programs, such as Explorer, to reload the environment
+/// variables from the registry.
+///
+/// Without this, new terminals won't see the changes until the user logs out.
+fn notify_environment_changed() {
+    tracing::debug!("broadcasting environment setting change");
+
+    let area = HSTRING::from("Environment");
+
+    let status = unsafe {
+        // SAFETY: The string outlives the call since the send is synchronous.
+        SendMessageTimeoutW(
+            HWND_BROADCAST,
+            WM_SETTINGCHANGE,
+            WPARAM(0),
+            LPARAM(area.as_ptr() as isize),
+            SMTO_ABORTIFHUNG,
+            5000,
+            None,
+        )
+    };
+
+    if status.0 == 0 {
+        tracing::warn!("environment setting change broadcast timed out or failed");
+    }
+}
+
+fn notify_association_changed() {
+    unsafe {
+        // SAFETY: No item pointers are passed for this event.
+        SHChangeNotify(SHCNE_ASSOCCHANGED, SHCNF_IDLIST, None, None);
+    }
+}
+
+pub fn start_menu_programs_dir(access_scope: AccessScope) -> Result<PathBuf, OsError> {
+    match access_scope {
+        AccessScope::User => known_folder_path(&FOLDERID_Programs),
+        AccessScope::System => known_folder_path(&FOLDERID_CommonPrograms),
+    }
+}
+
+pub fn desktop_dir(access_scope: AccessScope) -> Result<PathBuf, OsError> {
+    match access_scope {
+        AccessScope::User => known_folder_path(&FOLDERID_Desktop),
+        AccessScope::System => known_folder_path(&FOLDERID_PublicDesktop),
+    }
+}
+
+/// Returns the location of a known folder, which may be redirected such as
+/// to OneDrive.
+fn known_folder_path(folder_id: &GUID) -> Result<PathBuf, OsError> {
+    use std::os::windows::ffi::OsStringExt;
+
+    unsafe {
+        // SAFETY: The string is freed once it is copied.
+        let path = SHGetKnownFolderPath(folder_id, KF_FLAG_DEFAULT, HANDLE::default())?;
+        let value = OsString::from_wide(path.as_wide());
+        CoTaskMemFree(Some(path.0 as *const _));
+
+        Ok(PathBuf::from(value))
+    }
+}
+
+/// Returns a filename suitable for a shortcut file (without the extension).
+pub fn shortcut_file_stem(display_name: &str) -> String {
+    display_name
+        .chars()
+        .map(|c| {
+            if c.is_control() || r#"<>:"/\|?*"#.contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect::<String>()
+        .trim_end_matches(['.', ' '])
+        .to_string()
+}
+
+pub fn create_shortcut(path: &Path, target: &Path, description: &str) -> Result<(), OsError> {
+    tracing::debug!(?path, ?target, "creating shortcut");
+
+    let working_dir = target.parent().unwrap_or(Path::new(""));
+
+    let initialized = unsafe {
+        // SAFETY: The call is paired with CoUninitialize() below.
+        CoInitializeEx(None, COINIT_APARTMENTTHREADED).is_ok()
+    };
+
+    let result = create_shortcut_impl(path, target, working_dir, description);
+
+    if initialized {
+        unsafe {
+            // SAFETY: Paired with the successful CoInitializeEx() above.
+            CoUninitialize();
+        }
+    }
+
+    result?;
+
+    Ok(())
+}
+
+fn create_shortcut_impl(
+    path: &Path,
+    target: &Path,
+    working_dir: &Path,
+    description: &str,
+) -> windows_result::Result<()> {
+    unsafe {
+        // SAFETY: COM is initialized by the caller and the string arguments
+        // outlive the calls.
+        let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+        link.SetPath(&HSTRING::from(target.as_os_str()))?;
+        link.SetWorkingDirectory(&HSTRING::from(working_dir.as_os_str()))?;
+
+        if !description.is_empty() {
+            link.SetDescription(&HSTRING::from(description))?;
+        }
+
+        let file: IPersistFile = link.cast()?;
+        file.Save(&HSTRING::from(path.as_os_str()), TRUE)?;
+    }
+
+    Ok(())
+}
+
+pub fn remove_shortcut(path: &Path) -> Result<(), OsError> {
+    tracing::debug!(?path, "removing shortcut");
+
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
 
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use super::*;
 
+    fn no_expand(part: &OsStr) -> OsString {
+        part.to_os_string()
+    }
+
+    fn expand(part: &OsStr) -> OsString {
+        OsString::from(
+            part.to_str()
+                .unwrap()
+                .replace("%LOCALAPPDATA%", r"C:\Users\rust\AppData\Local"),
+        )
+    }
+
     #[test]
     fn test_remove_in_path_env_var() {
         assert_eq!(
             remove_part_in_path_env_var_str(
                 OsStr::new(r"C:\things\bin;C:\Rust\bin;C:\Windows Apps"),
-                OsStr::new(r"c:\rust\bin")
+                OsStr::new(r"c:\rust\bin"),
+                no_expand
             ),
             r"C:\things\bin;C:\Windows Apps",
         )
     }
+
+    #[test]
+    fn test_remove_in_path_env_var_quoted() {
+        assert_eq!(
+            remove_part_in_path_env_var_str(
+                OsStr::new(r#"C:\things\bin;"C:\Program Files\x";C:\Windows Apps"#),
+                OsStr::new(r"C:\Program Files\x"),
+                no_expand
+            ),
+            r"C:\things\bin;C:\Windows Apps",
+        );
+        assert_eq!(
+            remove_part_in_path_env_var_str(
+                OsStr::new(r#""C:\a;b";C:\x\;%SystemRoot%\bin;"#),
+                OsStr::new(r"C:\x"),
+                no_expand
+            ),
+            r#""C:\a;b";%SystemRoot%\bin;"#,
+        );
+    }
+
+    #[test]
+    fn test_remove_in_path_env_var_expandable() {
+        let value = OsStr::new(
+            r"%LOCALAPPDATA%\Programs\x\bin;%USERPROFILE%\bin;C:\Users\rust\AppData\Local\y",
+        );
+
+        assert!(contains_part_in_path_env_var_str(
+            value,
+            OsStr::new(r"C:\Users\rust\AppData\Local\Programs\x\bin"),
+            expand
+        ));
+        assert_eq!(
+            remove_part_in_path_env_var_str(
+                value,
+                OsStr::new(r"C:\Users\rust\AppData\Local\Programs\x\bin"),
+                expand
+            ),
+            r"%USERPROFILE%\bin;C:\Users\rust\AppData\Local\y",
+        );
+        assert_eq!(
+            remove_part_in_path_env_var_str(value, OsStr::new(r"C:\Users\rust\bin"), expand),
+            value,
+        );
+    }
+
+    #[test]
+    fn test_path_env_var_len() {
+        let value = OsString::from("a".repeat(20000));
+
+        assert!(is_path_env_var_len_ok(&value, OsStr::new(r"C:\Windows")));
+        assert!(is_path_env_var_len_ok(
+            &value,
+            &OsString::from("b".repeat(12765))
+        ));
+        assert!(!is_path_env_var_len_ok(
+            &value,
+            &OsString::from("b".repeat(12766))
+        ));
+    }
+
+    fn path_dir_strategy() -> impl Strategy<Value = String> {
+        r"[A-Za-z]:\\[A-Za-z0-9 %._éÉ]{1,12}(\\[A-Za-z0-9 %._]{1,8}){0,2}"
+    }
+
+    fn path_env_var_strategy() -> impl Strategy<Value = String> {
+        prop::collection::vec(
+            r#"[A-Za-z0-9:\\ %._]{0,12}|"[A-Za-z0-9:\\ %.;_]{0,12}""#,
+            0..6,
+        )
+        .prop_map(|parts| parts.join(";"))
+    }
+
+    fn contains_part(path_env_var: &str, path_dir: &str) -> bool {
+        let path_dir = normalize_path_env_var_part(path_dir.as_bytes());
+        split_path_env_var_str(path_env_var.as_bytes())
+            .into_iter()
+            .any(|part| is_path_env_var_part_eq(&normalize_path_env_var_part(part), &path_dir))
+    }
+
+    proptest! {
+        #[test]
+        fn proptest_append_remove_round_trip(
+            path_env_var in path_env_var_strategy(),
+            path_dir in path_dir_strategy(),
+        ) {
+            prop_assume!(!contains_part(&path_env_var, &path_dir));
+
+            let appended =
+                append_part_in_path_env_var_str(OsStr::new(&path_env_var), OsStr::new(&path_dir));
+            prop_assert!(contains_part(appended.to_str().unwrap(), &path_dir));
+
+            let removed = remove_part_in_path_env_var_str(&appended, OsStr::new(&path_dir), no_expand);
+            prop_assert_eq!(removed, OsString::from(path_env_var));
+        }
+
+        #[test]
+        fn proptest_remove_unrelated_unchanged(
+            path_env_var in path_env_var_strategy(),
+            path_dir in path_dir_strategy(),
+        ) {
+            prop_assume!(!contains_part(&path_env_var, &path_dir));
+
+            let removed =
+                remove_part_in_path_env_var_str(OsStr::new(&path_env_var), OsStr::new(&path_dir), no_expand);
+            prop_assert_eq!(removed, OsString::from(path_env_var));
+        }
+
+        #[test]
+        fn proptest_remove_all_variants(
+            path_env_var in path_env_var_strategy(),
+            path_dir in path_dir_strategy(),
+            quoted in any::<bool>(),
+            uppercase in any::<bool>(),
+            trailing_slash in any::<bool>(),
+            index in any::<prop::sample::Index>(),
+        ) {
+            let mut variant = if uppercase {
+                path_dir.to_uppercase()
+            } else {
+                path_dir.clone()
+            };
+            if trailing_slash {
+                variant.push('\\');
+            }
+            if quoted {
+                variant = format!("\"{}\"", variant);
+            }
+
+            let mut parts = Vec::from_iter(path_env_var.split(';').map(str::to_string));
+            parts.insert(index.index(parts.len() + 1), variant);
+            let path_env_var = parts.join(";");
+
+            let removed =
+                remove_part_in_path_env_var_str(OsStr::new(&path_env_var), OsStr::new(&path_dir), no_expand);
+            prop_assert!(!contains_part(removed.to_str().unwrap(), &path_dir));
+        }
+    }
+
+    #[test]
+    fn test_powershell_path_snippet() {
+        let snippet = powershell_path_snippet(OsStr::new(r"C:\Users\O'Brien\bin")).unwrap();
+
+        assert!(snippet.contains(r"Test-Path -LiteralPath 'C:\Users\O''Brien\bin'"));
+        assert!(snippet.contains(r"$env:Path = 'C:\Users\O''Brien\bin;' + $env:Path"));
+        assert!(snippet.contains("\r\n"));
+        assert!(!snippet.replace("\r\n", "").contains('\n'));
+
+        assert!(powershell_path_snippet(OsStr::new("C:\\bin\n")).is_err());
+    }
+
+    #[test]
+    fn test_shim_scripts() {
+        let (cmd_script, ps1_script) =
+            shim_scripts(Path::new(r"C:\Users\O'Brien\100%\app.exe")).unwrap();
+
+        assert_eq!(
+            cmd_script,
+            "@\"C:\\Users\\O'Brien\\100%%\\app.exe\" %*\r\n@exit /b %ERRORLEVEL%\r\n"
+        );
+        assert_eq!(
+            ps1_script,
+            "& 'C:\\Users\\O''Brien\\100%\\app.exe' @args\r\nexit $LASTEXITCODE\r\n"
+        );
+    }
+
+    #[test]
+    fn test_quote_command_line_arg() {
+        assert_eq!(quote_command_line_arg("--sync"), "--sync");
+        assert_eq!(quote_command_line_arg(""), r#""""#);
+        assert_eq!(quote_command_line_arg("a b"), r#""a b""#);
+        assert_eq!(quote_command_line_arg(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote_command_line_arg(r"C:\my dir\"), r#""C:\my dir\\""#);
+        assert_eq!(quote_command_line_arg(r#"a\"b c"#), r#""a\\\"b c""#);
+        assert_eq!(quote_command_line_arg(r"a\b"), r"a\b");
+    }
+
+    /// Splits the command line like Control Panel does when running the
+    /// `UninstallString`.
+    fn command_line_to_argv(command: &OsStr) -> Vec<OsString> {
+        use std::os::windows::ffi::OsStringExt;
+        use windows::Win32::{
+            Foundation::{LocalFree, HLOCAL},
+            UI::Shell::CommandLineToArgvW,
+        };
+
+        let command = HSTRING::from(command);
+        let mut count = 0;
+
+        // SAFETY: The string outlives the call. The returned array has
+        // `count` valid strings and is freed once after copying them.
+        unsafe {
+            let argv = CommandLineToArgvW(PCWSTR(command.as_ptr()), &mut count);
+            assert!(!argv.is_null());
+
+            let args = (0..count as usize)
+                .map(|index| OsString::from_wide((*argv.add(index)).as_wide()))
+                .collect();
+            let _ = LocalFree(HLOCAL(argv.cast()));
+
+            args
+        }
+    }
+
+    fn assert_command_line_round_trip(exe_path: &str, args: &[&str]) {
+        let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let command = command_line(OsStr::new(exe_path), &args);
+
+        let mut expected = vec![OsString::from(exe_path)];
+        expected.extend(args.iter().map(OsString::from));
+
+        assert_eq!(command_line_to_argv(&command), expected, "{:?}", command);
+    }
+
+    #[test]
+    fn test_command_line_round_trip() {
+        let exe_path = r"C:\Program Files\My App\my app.exe";
+
+        assert_command_line_round_trip(exe_path, &[]);
+        assert_command_line_round_trip(exe_path, &["self", "uninstall", "--quiet"]);
+        assert_command_line_round_trip(
+            r"C:\Users\Jane Doe\AppData\Local\Programs\my-app\bin\my-app.exe",
+            &["self", "uninstall"],
+        );
+        assert_command_line_round_trip(
+            exe_path,
+            &[
+                "",
+                "a b",
+                r#"say "hi""#,
+                r"C:\my dir\",
+                r#"a\"b c"#,
+                r"a\\b",
+                "tab\there",
+                r#"""#,
+                r"\",
+            ],
+        );
+    }
+
+    fn command_line_arg_strategy() -> impl Strategy<Value = String> {
+        r#"[A-Za-z0-9 \t"\\%._éÉ-]{0,12}"#
+    }
+
+    proptest! {
+        #[test]
+        fn proptest_command_line_round_trip(
+            exe_path in path_dir_strategy(),
+            args in prop::collection::vec(command_line_arg_strategy(), 0..5),
+        ) {
+            let command = command_line(OsStr::new(&exe_path), &args);
+
+            let mut expected = vec![OsString::from(&exe_path)];
+            expected.extend(args.iter().map(OsString::from));
+
+            prop_assert_eq!(command_line_to_argv(&command), expected);
+        }
+    }
+
+    #[test]
+    fn test_shortcut_file_stem() {
+        assert_eq!(shortcut_file_stem("My App"), "My App");
+        assert_eq!(shortcut_file_stem("My App: Reloaded?"), "My App_ Reloaded_");
+        assert_eq!(shortcut_file_stem("My App..."), "My App");
+    }
 }
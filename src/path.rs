@@ -7,24 +7,31 @@
 //!
 //! For binaries, they will be located in:
 //!
-//! * `$HOME/.local/bin/`
-//! * `/usr/local/bin/`
+//! * `$XDG_BIN_HOME/` (or `$HOME/.local/bin/` if `XDG_BIN_HOME` is not set)
+//! * `/usr/local/bin/` (or `$PREFIX/bin/` on Termux)
 //! * `%LocalAppData%/Programs/[app-id]/bin/`
 //! * `%ProgramFiles%/[app-id]/bin/`
 //!
-//! `[app-id]` is the plain ID format.
+//! `[app-id]` is the plain ID format. On 64-bit Windows, `%ProgramFiles%`
+//! is `Program Files (x86)` for 32-bit x86 binaries and `Program Files`
+//! otherwise.
 //!
 //! For any data files:
 //!
-//! * `$HOME/.local/share/[app-id]/`
-//! * `/usr/local/share/[app-id]/`
+//! * `$XDG_DATA_HOME/[app-id]/` (or `$HOME/.local/share/[app-id]/` if
+//!   `XDG_DATA_HOME` is not set)
+//! * `/usr/local/share/[app-id]/` (or `$PREFIX/share/[app-id]/` on Termux)
 //! * `%LocalAppData%/Programs/[app-id]/bin/`
 //! * `%ProgramFiles%/[app-id]/bin/`
 //!
+//! On macOS, the native conventions can be used instead with
+//! [`set_macos_path_layout()`].
+//!
 use std::{
     collections::HashMap,
     ffi::{OsStr, OsString},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::RwLock,
 };
 
 use serde::{Deserialize, Serialize};
@@ -37,18 +44,78 @@ use crate::{
 // For notes on OS specific paths, please see the submodules of the
 // `crate::os` module
 
+static MACOS_PATH_LAYOUT: RwLock<MacOsPathLayout> = RwLock::new(MacOsPathLayout::Unix);
+
+/// Conventions for file locations on macOS.
+///
+/// See [`set_macos_path_layout()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MacOsPathLayout {
+    /// The same locations as other Unix systems.
+    #[default]
+    Unix,
+    /// The locations used by macOS applications.
+    ///
+    /// * Data files are in `~/Library/Application Support/[app-id]/` for
+    ///   user scope.
+    /// * The system scope prefix is `/opt/homebrew` on Apple silicon if
+    ///   Homebrew is installed there, otherwise `/usr/local`.
+    /// * Disk manifests are in `~/Library/Application Support/` and
+    ///   `/Library/Application Support/`.
+    ///
+    /// Binaries are still in `~/.local/bin/` for user scope since macOS has
+    /// no equivalent. Installations made with [`Self::Unix`] are still
+    /// found.
+    Native,
+}
+
+/// Sets the conventions for file locations on macOS.
+///
+/// This setting applies to the whole process and should be set before
+/// installing or uninstalling. It has no effect on other operating systems.
+/// The default is [`MacOsPathLayout::Unix`].
+pub fn set_macos_path_layout(layout: MacOsPathLayout) {
+    let mut value = MACOS_PATH_LAYOUT.write().unwrap();
+    *value = layout;
+}
+
+/// Returns the conventions for file locations on macOS.
+///
+/// See [`set_macos_path_layout()`].
+pub fn macos_path_layout() -> MacOsPathLayout {
+    *MACOS_PATH_LAYOUT.read().unwrap()
+}
+
+/// Returns whether the native macOS locations are in effect.
+pub(crate) fn is_native_macos_layout() -> bool {
+    cfg!(target_os = "macos") && macos_path_layout() == MacOsPathLayout::Native
+}
+
+/// Returns the system scope prefix for the native macOS layout.
+fn macos_system_prefix(arch: &str, homebrew_prefix_exists: bool) -> PathBuf {
+    if arch == "aarch64" && homebrew_prefix_exists {
+        PathBuf::from("/opt/homebrew")
+    } else {
+        PathBuf::from("/usr/local")
+    }
+}
+
 /// Specifies abstractly where the files are installed on the machine.
 ///
 /// See also [`AccessScope`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AppPathPrefix {
     /// In the current user's account.
+    #[default]
     User,
     /// In the system directories accessible for all users.
     System,
-    /// Reserved.
-    ///
     /// In a single directory.
+    ///
+    /// Executables are placed in a `bin` subdirectory. The interactive
+    /// installer offers this when enabled by
+    /// [`Installer::with_custom_destination()`](crate::inst::Installer::with_custom_destination).
     SingleDir(PathBuf),
     /// Reserved.
     ///
@@ -56,12 +123,6 @@ pub enum AppPathPrefix {
     CustomUnix(PathBuf),
 }
 
-impl Default for AppPathPrefix {
-    fn default() -> Self {
-        Self::User
-    }
-}
-
 impl From<AccessScope> for AppPathPrefix {
     fn from(value: AccessScope) -> Self {
         match value {
@@ -75,6 +136,16 @@ impl From<AccessScope> for AppPathPrefix {
 enum ResolvedPrefix {
     SingleDir(PathBuf),
     Unix(PathBuf),
+    /// User's home directory following the XDG Base Directory Specification.
+    Xdg {
+        bin_home: PathBuf,
+        data_home: PathBuf,
+    },
+    /// User's home directory following the macOS conventions.
+    MacOs {
+        bin_home: PathBuf,
+        app_support: PathBuf,
+    },
 }
 
 impl Default for ResolvedPrefix {
@@ -89,6 +160,8 @@ pub struct PathResolver {
     app_id: String,
     prefix: ResolvedPrefix,
     env_map: Option<HashMap<OsString, OsString>>,
+    native_macos: bool,
+    program_files_dir: Option<PathBuf>,
 }
 
 impl PathResolver {
@@ -106,11 +179,22 @@ impl PathResolver {
         app_id: &str,
         prefix: &AppPathPrefix,
         env_map: Option<HashMap<OsString, OsString>>,
+    ) -> Result<Self, InstallerError> {
+        Self::new_with_layout(app_id, prefix, env_map, is_native_macos_layout())
+    }
+
+    fn new_with_layout(
+        app_id: &str,
+        prefix: &AppPathPrefix,
+        env_map: Option<HashMap<OsString, OsString>>,
+        native_macos: bool,
     ) -> Result<Self, InstallerError> {
         let mut self_ = Self {
             prefix: ResolvedPrefix::default(),
             app_id: app_id.to_string(),
             env_map,
+            native_macos,
+            program_files_dir: None,
         };
         self_.prefix = self_.resolve_prefix(prefix)?;
 
@@ -136,12 +220,27 @@ impl PathResolver {
 
                 Ok(ResolvedPrefix::SingleDir(dir))
             }
+            "unix" if self.native_macos => {
+                let get_env_var = |key: &str| self.get_env_var(key);
+                let bin_home = crate::os::xdg_base_dir(get_env_var, "XDG_BIN_HOME", ".local/bin")?;
+                let app_support =
+                    PathBuf::from(self.get_env_var("HOME")?).join("Library/Application Support");
+
+                Ok(ResolvedPrefix::MacOs {
+                    bin_home,
+                    app_support,
+                })
+            }
             "unix" => {
-                let dir = self.get_env_var("HOME")?;
-                let mut dir = PathBuf::from(dir);
-                dir.push(".local");
-
-                Ok(ResolvedPrefix::Unix(dir))
+                let get_env_var = |key: &str| self.get_env_var(key);
+                let bin_home = crate::os::xdg_base_dir(get_env_var, "XDG_BIN_HOME", ".local/bin")?;
+                let data_home =
+                    crate::os::xdg_base_dir(get_env_var, "XDG_DATA_HOME", ".local/share")?;
+
+                Ok(ResolvedPrefix::Xdg {
+                    bin_home,
+                    data_home,
+                })
             }
             _ => Err(InstallerErrorKind::UnsupportedOsFamily.into()),
         }
@@ -150,22 +249,66 @@ impl PathResolver {
     fn resolve_system_prefix(&mut self) -> Result<ResolvedPrefix, InstallerError> {
         match std::env::consts::FAMILY {
             "windows" => {
-                let dir = self.get_env_var("PROGRAMFILES")?;
-                let mut dir = PathBuf::from(dir);
-                dir.push(&self.app_id);
+                let program_files_dir = self.resolve_program_files_dir(std::env::consts::ARCH)?;
+                let dir = program_files_dir.join(&self.app_id);
+                self.program_files_dir = Some(program_files_dir);
 
                 Ok(ResolvedPrefix::SingleDir(dir))
             }
-            "unix" => Ok(ResolvedPrefix::Unix(PathBuf::from("/usr/local"))),
+            "unix" => {
+                if let Some(prefix) = crate::os::termux_prefix_with(|key| self.get_env_var(key)) {
+                    return Ok(ResolvedPrefix::Unix(prefix));
+                }
+
+                if self.native_macos {
+                    return Ok(ResolvedPrefix::Unix(macos_system_prefix(
+                        std::env::consts::ARCH,
+                        Path::new("/opt/homebrew/bin").is_dir(),
+                    )));
+                }
+
+                Ok(ResolvedPrefix::Unix(PathBuf::from("/usr/local")))
+            }
             _ => Err(InstallerErrorKind::UnsupportedOsFamily.into()),
         }
     }
 
+    /// Returns the Program Files directory matching the architecture of
+    /// the binary.
+    ///
+    /// `PROGRAMFILES` depends on whether the process reading it runs under
+    /// WOW64 and may be inherited from a process of a different
+    /// architecture, so the architecture-specific variables are preferred.
+    fn resolve_program_files_dir(&self, arch: &str) -> Result<PathBuf, InstallerError> {
+        let key = match arch {
+            "x86" => "ProgramFiles(x86)",
+            _ => "ProgramW6432",
+        };
+
+        // The variables don't exist on 32-bit Windows
+        let dir = self
+            .get_env_var(key)
+            .or_else(|_| self.get_env_var("PROGRAMFILES"))?;
+
+        tracing::debug!(arch, ?dir, "resolved Program Files");
+
+        Ok(PathBuf::from(dir))
+    }
+
+    /// Returns the Program Files directory the prefix was resolved to.
+    ///
+    /// Returns `None` if the prefix is not in Program Files.
+    pub fn program_files_dir(&self) -> Option<&Path> {
+        self.program_files_dir.as_deref()
+    }
+
     /// Returns a directory containing this package's binaries.
     pub fn bin_dir(&self) -> PathBuf {
         match &self.prefix {
             ResolvedPrefix::SingleDir(path) => path.join("bin"),
             ResolvedPrefix::Unix(path) => path.join("bin"),
+            ResolvedPrefix::Xdg { bin_home, .. } => bin_home.clone(),
+            ResolvedPrefix::MacOs { bin_home, .. } => bin_home.clone(),
         }
     }
 
@@ -174,6 +317,20 @@ impl PathResolver {
         match &self.prefix {
             ResolvedPrefix::SingleDir(path) => path.to_path_buf(),
             ResolvedPrefix::Unix(path) => path.join("share").join(&self.app_id),
+            ResolvedPrefix::Xdg { data_home, .. } => data_home.join(&self.app_id),
+            ResolvedPrefix::MacOs { app_support, .. } => app_support.join(&self.app_id),
+        }
+    }
+
+    /// Returns the shared data directory of the prefix, such as `/usr/local/share`.
+    ///
+    /// Returns `None` if the prefix is not a Unix-style prefix.
+    pub fn share_dir(&self) -> Option<PathBuf> {
+        match &self.prefix {
+            ResolvedPrefix::SingleDir(_) => None,
+            ResolvedPrefix::Unix(path) => Some(path.join("share")),
+            ResolvedPrefix::Xdg { data_home, .. } => Some(data_home.clone()),
+            ResolvedPrefix::MacOs { .. } => None,
         }
     }
 
@@ -199,6 +356,8 @@ mod tests {
             [
                 ("LOCALAPPDATA", "c:/users/rust/appdata/local"),
                 ("PROGRAMFILES", "c:/program files"),
+                ("ProgramW6432", "c:/program files"),
+                ("ProgramFiles(x86)", "c:/program files (x86)"),
                 ("XDG_DATA_HOME", "/home/rust/.local/share"),
                 ("HOME", "/home/rust"),
             ]
@@ -247,6 +406,82 @@ mod tests {
         assert_eq!(data_dir, Path::new("/home/rust/.local/share/my_app"));
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_user_unix_xdg_bin_home() {
+        let mut env_map = get_env_map();
+        env_map.insert("XDG_BIN_HOME".into(), "/home/rust/bin".into());
+
+        let resolver =
+            PathResolver::new_impl("my_app", &AppPathPrefix::User, Some(env_map.clone())).unwrap();
+
+        assert_eq!(resolver.bin_dir(), Path::new("/home/rust/bin"));
+
+        // Relative paths are invalid
+        env_map.insert("XDG_BIN_HOME".into(), "bin".into());
+
+        let resolver =
+            PathResolver::new_impl("my_app", &AppPathPrefix::User, Some(env_map)).unwrap();
+
+        assert_eq!(resolver.bin_dir(), Path::new("/home/rust/.local/bin"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_user_unix_xdg_data_home() {
+        let mut env_map = get_env_map();
+        env_map.insert("XDG_DATA_HOME".into(), "/home/rust/data".into());
+
+        let resolver =
+            PathResolver::new_impl("my_app", &AppPathPrefix::User, Some(env_map.clone())).unwrap();
+
+        assert_eq!(resolver.data_dir(), Path::new("/home/rust/data/my_app"));
+        assert_eq!(resolver.share_dir().unwrap(), Path::new("/home/rust/data"));
+        assert_eq!(resolver.bin_dir(), Path::new("/home/rust/.local/bin"));
+
+        env_map.remove(OsStr::new("XDG_DATA_HOME"));
+
+        let resolver =
+            PathResolver::new_impl("my_app", &AppPathPrefix::User, Some(env_map)).unwrap();
+
+        assert_eq!(
+            resolver.data_dir(),
+            Path::new("/home/rust/.local/share/my_app")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_user_macos_native() {
+        let resolver = PathResolver::new_with_layout(
+            "my_app",
+            &AppPathPrefix::User,
+            Some(get_env_map()),
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(resolver.bin_dir(), Path::new("/home/rust/.local/bin"));
+        assert_eq!(
+            resolver.data_dir(),
+            Path::new("/home/rust/Library/Application Support/my_app")
+        );
+        assert!(resolver.share_dir().is_none());
+    }
+
+    #[test]
+    fn test_macos_system_prefix() {
+        assert_eq!(
+            macos_system_prefix("aarch64", true),
+            Path::new("/opt/homebrew")
+        );
+        assert_eq!(
+            macos_system_prefix("aarch64", false),
+            Path::new("/usr/local")
+        );
+        assert_eq!(macos_system_prefix("x86_64", true), Path::new("/usr/local"));
+    }
+
     #[cfg(windows)]
     #[test]
     fn test_system_windows() {
@@ -277,6 +512,73 @@ mod tests {
         assert_eq!(data_dir, Path::new("/usr/local/share/my_app"));
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_system_termux() {
+        let mut env_map = get_env_map();
+        env_map.insert("PREFIX".into(), "/data/data/com.termux/files/usr".into());
+
+        let resolver =
+            PathResolver::new_impl("my_app", &AppPathPrefix::System, Some(env_map.clone()))
+                .unwrap();
+
+        assert_eq!(
+            resolver.bin_dir(),
+            Path::new("/data/data/com.termux/files/usr/bin")
+        );
+        assert_eq!(
+            resolver.data_dir(),
+            Path::new("/data/data/com.termux/files/usr/share/my_app")
+        );
+
+        // PREFIX is also used by build tools outside of Termux
+        env_map.insert("PREFIX".into(), "/opt/build".into());
+
+        let resolver =
+            PathResolver::new_impl("my_app", &AppPathPrefix::System, Some(env_map.clone()))
+                .unwrap();
+
+        assert_eq!(resolver.bin_dir(), Path::new("/usr/local/bin"));
+
+        env_map.insert("TERMUX_VERSION".into(), "0.118.0".into());
+
+        let resolver =
+            PathResolver::new_impl("my_app", &AppPathPrefix::System, Some(env_map)).unwrap();
+
+        assert_eq!(resolver.bin_dir(), Path::new("/opt/build/bin"));
+    }
+
+    #[test]
+    fn test_program_files_dir() {
+        let resolver =
+            PathResolver::new_impl("my_app", &AppPathPrefix::User, Some(get_env_map())).unwrap();
+
+        assert_eq!(
+            resolver.resolve_program_files_dir("x86_64").unwrap(),
+            Path::new("c:/program files")
+        );
+        assert_eq!(
+            resolver.resolve_program_files_dir("aarch64").unwrap(),
+            Path::new("c:/program files")
+        );
+        assert_eq!(
+            resolver.resolve_program_files_dir("x86").unwrap(),
+            Path::new("c:/program files (x86)")
+        );
+
+        // 32-bit Windows
+        let mut env_map = get_env_map();
+        env_map.remove(OsStr::new("ProgramW6432"));
+        env_map.remove(OsStr::new("ProgramFiles(x86)"));
+        let resolver =
+            PathResolver::new_impl("my_app", &AppPathPrefix::User, Some(env_map)).unwrap();
+
+        assert_eq!(
+            resolver.resolve_program_files_dir("x86").unwrap(),
+            Path::new("c:/program files")
+        );
+    }
+
     #[test]
     fn test_single_dir() {
         let resolver = PathResolver::new_impl(
@@ -293,6 +595,7 @@ mod tests {
         let data_dir = resolver.data_dir();
 
         assert_eq!(data_dir, Path::new("/opt/my_app"));
+        assert!(resolver.share_dir().is_none());
     }
 
     #[test]
@@ -311,5 +614,6 @@ mod tests {
         let data_dir = resolver.data_dir();
 
         assert_eq!(data_dir, Path::new("/usr2/share/my_app"));
+        assert_eq!(resolver.share_dir().unwrap(), Path::new("/usr2/share"));
     }
 }
@@ -1,24 +1,30 @@
 use std::{
     fmt::Debug,
-    sync::{mpsc::Receiver, Arc},
+    path::{Path, PathBuf},
+    sync::{atomic::Ordering, mpsc::Receiver, Arc},
     thread::JoinHandle,
+    time::Duration,
 };
 
 use cursive::{
     theme::Theme,
-    view::Scrollable,
+    view::{Nameable, Scrollable, View},
     views::{
         stack_view::{Fullscreen, Transparent},
-        Dialog, LinearLayout, RadioGroup, TextView,
+        EditView, LinearLayout, RadioGroup, TextView,
     },
-    CbSink, Cursive, CursiveExt,
+    CbSink, Cursive,
 };
 use dialog::GuidedDialogButton;
 
 use crate::{
     error::{InstallerError, InstallerErrorKind},
+    inst::{CopyProgress, CustomPage, CustomPageKind, InstallPreview, InstallProfile},
     locale::Locale,
     os::AccessScope,
+    ui::{
+        format_bytes_with_locale, format_duration_with_locale, format_install_preview_with_locale,
+    },
 };
 
 mod bg;
@@ -29,11 +35,46 @@ pub struct Tui {
     handle: Option<JoinHandle<std::io::Result<()>>>,
     app_name: String,
     app_version: String,
+    app_details: AppDetails,
     locale: Locale,
     theme: Option<Theme>,
     enable_branding: bool,
 }
 
+/// Additional application metadata shown on the introduction screen.
+#[derive(Debug, Clone, Default)]
+pub struct AppDetails {
+    pub publisher: String,
+    pub description: String,
+    pub homepage_url: String,
+    pub license: String,
+}
+
+/// Options chosen by the user before uninstalling.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UninstallChoices {
+    pub purge: bool,
+    pub backup: bool,
+    pub trash: bool,
+}
+
+/// Suggestion shown when the interactive interface can't be used.
+const UNSUPPORTED_TERMINAL_HINT: &str = "use the non-interactive (quiet) mode instead";
+const CUSTOM_PAGE_INPUT: &str = "custom_page_input";
+const DESTINATION_INPUT: &str = "destination_input";
+
+/// Checks the terminal is capable of displaying the TUI before taking it over.
+fn check_terminal() -> Result<(), InstallerError> {
+    if std::env::var_os("TERM").is_some_and(|value| value == "dumb") {
+        tracing::debug!("TERM is dumb");
+
+        return Err(InstallerError::new(InstallerErrorKind::UnsupportedTerminal)
+            .with_context(format!("TERM is \"dumb\"; {}", UNSUPPORTED_TERMINAL_HINT)));
+    }
+
+    Ok(())
+}
+
 impl Tui {
     pub fn new() -> Self {
         Self {
@@ -41,6 +82,7 @@ impl Tui {
             handle: None,
             app_name: String::new(),
             app_version: String::new(),
+            app_details: AppDetails::default(),
             locale: Locale::with_system(),
             theme: None,
             enable_branding: true,
@@ -56,6 +98,10 @@ impl Tui {
         self.app_version = app_version.to_string();
     }
 
+    pub fn set_details(&mut self, details: AppDetails) {
+        self.app_details = details;
+    }
+
     pub fn set_lang_tag(&mut self, value: &str) {
         self.locale.set_language_tag(value);
     }
@@ -69,9 +115,15 @@ impl Tui {
         self.enable_branding = enable_branding;
     }
 
-    pub fn run_background(&mut self) {
+    /// Starts the TUI in a thread.
+    ///
+    /// Returns [`InstallerErrorKind::UnsupportedTerminal`] if the terminal
+    /// is dumb or could not be initialized.
+    pub fn run_background(&mut self) -> Result<(), InstallerError> {
         assert!(self.channel.is_none());
 
+        check_terminal()?;
+
         let (sender, receiver) = std::sync::mpsc::sync_channel(1);
         let theme = self.theme.clone();
 
@@ -82,14 +134,37 @@ impl Tui {
                 cursive.set_theme(theme);
             }
 
-            sender.send(cursive.cb_sink().clone()).unwrap();
+            let backend = match cursive::backends::crossterm::Backend::init() {
+                Ok(backend) => backend,
+                Err(error) => {
+                    sender.send(Err(error)).unwrap();
+                    return Ok(());
+                }
+            };
+
+            sender.send(Ok(cursive.cb_sink().clone())).unwrap();
 
-            cursive.run_crossterm()
+            cursive.try_run_with(|| Ok(backend))
         });
 
-        self.handle = Some(join_handle);
+        match receiver.recv().unwrap() {
+            Ok(channel) => {
+                self.handle = Some(join_handle);
+                self.channel = Some(channel);
 
-        self.channel = Some(receiver.recv().unwrap());
+                Ok(())
+            }
+            Err(error) => {
+                let _ = join_handle.join();
+
+                Err(InstallerError::new(InstallerErrorKind::UnsupportedTerminal)
+                    .with_source(error)
+                    .with_context(format!(
+                        "could not start the interactive interface; {}",
+                        UNSUPPORTED_TERMINAL_HINT
+                    )))
+            }
+        }
     }
 
     pub fn stop(&mut self) -> Result<(), InstallerError> {
@@ -104,12 +179,13 @@ impl Tui {
         Ok(())
     }
 
-    fn show_wait_dialog<T>(
+    fn show_wait_dialog<V, T>(
         &self,
-        dialog: Dialog,
+        dialog: V,
         dialog_receiver: Receiver<T>,
     ) -> Result<T, InstallerError>
     where
+        V: View,
         T: Send + 'static,
     {
         self.in_cursive(move |cursive| {
@@ -180,75 +256,531 @@ impl Tui {
             ("app_name", (&self.app_name).into()),
             ("app_version", (&self.app_version).into()),
         ];
-        let text = self.locale.text_args("installer-intro", args);
+        let mut text = self.locale.text_args("installer-intro", args);
+        let details = &self.app_details;
+
+        if !details.description.is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&details.description);
+        }
+
+        let detail_lines = [
+            ("installer-intro-publisher", "publisher", &details.publisher),
+            ("installer-intro-homepage", "url", &details.homepage_url),
+            ("installer-intro-license", "license", &details.license),
+        ]
+        .into_iter()
+        .filter(|(_, _, value)| !value.is_empty())
+        .map(|(id, key, value)| self.locale.text_args(id, [(key, value.into())]))
+        .collect::<Vec<_>>();
+
+        if !detail_lines.is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&detail_lines.join("\n"));
+        }
 
         let (mut dialog, dialog_receiver) = dialog::guided_dialog(&self.locale, "", |_| ());
-        dialog.set_content(TextView::new(text).scrollable());
+        dialog
+            .get_inner_mut()
+            .set_content(TextView::new(text).scrollable());
 
         self.show_wait_dialog(dialog, dialog_receiver)
     }
 
-    pub fn installation_conclusion(&self) -> Result<(), InstallerError> {
+    /// If `reboot_required`, a note is shown that some files are replaced
+    /// when the computer restarts.
+    pub fn installation_conclusion(
+        &self,
+        duration: Duration,
+        reboot_required: bool,
+    ) -> Result<(), InstallerError> {
         let args = [("app_name", (&self.app_name).into())];
         let text = self.locale.text_args("installer-conclusion", args);
+        let duration_text = self.locale.text_args(
+            "install-duration",
+            [(
+                "duration",
+                format_duration_with_locale(&self.locale, duration).into(),
+            )],
+        );
+
+        let mut layout = LinearLayout::vertical()
+            .child(TextView::new(text))
+            .child(TextView::new("\n"))
+            .child(TextView::new(duration_text));
+
+        if reboot_required {
+            layout.add_child(TextView::new("\n"));
+            layout.add_child(TextView::new(self.locale.text("install-reboot-required")));
+        }
 
         let (mut dialog, dialog_receiver) = dialog::info_dialog(&self.locale, "");
-        dialog.set_content(TextView::new(text).scrollable());
+        dialog.set_content(layout.scrollable());
 
         self.show_wait_dialog(dialog, dialog_receiver)
     }
 
-    pub fn prompt_access_scope(&self) -> Result<GuidedDialogButton<AccessScope>, InstallerError> {
+    /// If not `is_elevated`, a note is shown that the installer will ask for
+    /// privileges to install for all users.
+    pub fn prompt_access_scope(
+        &self,
+        current: AccessScope,
+        is_elevated: bool,
+    ) -> Result<GuidedDialogButton<AccessScope>, InstallerError> {
         let mut layout = LinearLayout::vertical();
         layout.add_child(TextView::new(self.locale.text("access-scope-prompt")));
 
         let mut radio_group = RadioGroup::new();
-        layout.add_child(radio_group.button(AccessScope::User, self.locale.text("for-this-user")));
-        layout
-            .add_child(radio_group.button(AccessScope::System, self.locale.text("for-all-users")));
+        layout.add_child(dialog::radio_button(
+            &mut radio_group,
+            AccessScope::User,
+            &current,
+            self.locale.text("for-this-user"),
+        ));
+        layout.add_child(dialog::radio_button(
+            &mut radio_group,
+            AccessScope::System,
+            &current,
+            self.locale.text("for-all-users"),
+        ));
+
+        if !is_elevated {
+            layout.add_child(TextView::new(
+                self.locale.text("for-all-users-requires-elevation"),
+            ));
+        }
 
-        let (mut dialog, dialog_receiver) = dialog::guided_dialog(&self.locale, "", move |_| {
-            Arc::unwrap_or_clone(radio_group.selection())
-        });
-        dialog.set_content(layout.scrollable());
+        let (mut dialog, dialog_receiver) =
+            dialog::guided_step_dialog(&self.locale, "", move |_| {
+                Arc::unwrap_or_clone(radio_group.selection())
+            });
+        dialog.get_inner_mut().set_content(layout.scrollable());
 
         self.show_wait_dialog(dialog, dialog_receiver)
     }
 
-    pub fn prompt_modify_search_path(&self) -> Result<GuidedDialogButton<bool>, InstallerError> {
+    /// Returns the directory entered or `None` for the default location.
+    pub fn prompt_destination(
+        &self,
+        default_dir: &Path,
+        current: Option<&Path>,
+    ) -> Result<GuidedDialogButton<Option<PathBuf>>, InstallerError> {
+        let mut layout = LinearLayout::vertical();
+        layout.add_child(TextView::new(self.locale.text("destination-prompt")));
+
+        let mut radio_group = RadioGroup::new();
+        layout.add_child(dialog::radio_button(
+            &mut radio_group,
+            false,
+            &current.is_some(),
+            self.locale.text_args(
+                "default-destination",
+                [("path", default_dir.display().to_string().into())],
+            ),
+        ));
+        layout.add_child(dialog::radio_button(
+            &mut radio_group,
+            true,
+            &current.is_some(),
+            self.locale.text("custom-destination"),
+        ));
+        layout.add_child(
+            EditView::new()
+                .content(current.unwrap_or(default_dir).display().to_string())
+                .with_name(DESTINATION_INPUT),
+        );
+
+        let (mut dialog, dialog_receiver) =
+            dialog::guided_step_dialog(&self.locale, "", move |cursive| {
+                if !*radio_group.selection() {
+                    return None;
+                }
+
+                cursive.call_on_name(DESTINATION_INPUT, |view: &mut EditView| {
+                    PathBuf::from(view.get_content().trim())
+                })
+            });
+        dialog.get_inner_mut().set_content(layout.scrollable());
+
+        self.show_wait_dialog(dialog, dialog_receiver)
+    }
+
+    pub fn show_invalid_destination(
+        &self,
+        dir: &Path,
+        text_id: &str,
+    ) -> Result<(), InstallerError> {
+        let text = self
+            .locale
+            .text_args(text_id, [("path", dir.display().to_string().into())]);
+
+        let (mut dialog, dialog_receiver) = dialog::info_dialog(&self.locale, "");
+        dialog.set_content(TextView::new(text).scrollable());
+
+        self.show_wait_dialog(dialog, dialog_receiver)
+    }
+
+    /// Returns the chosen profile or `None` to choose options individually.
+    pub fn prompt_install_profile(
+        &self,
+        profiles: &[InstallProfile],
+        current: Option<InstallProfile>,
+    ) -> Result<GuidedDialogButton<Option<InstallProfile>>, InstallerError> {
+        let mut layout = LinearLayout::vertical();
+        layout.add_child(TextView::new(self.locale.text("install-profile-prompt")));
+
+        let mut radio_group = RadioGroup::new();
+
+        for profile in profiles {
+            let id = match profile {
+                InstallProfile::Minimal => "install-profile-minimal",
+                InstallProfile::Developer => "install-profile-developer",
+                InstallProfile::Full => "install-profile-full",
+            };
+            layout.add_child(dialog::radio_button(
+                &mut radio_group,
+                Some(*profile),
+                &current,
+                self.locale.text(id),
+            ));
+        }
+
+        layout.add_child(dialog::radio_button(
+            &mut radio_group,
+            None,
+            &current,
+            self.locale.text("install-profile-custom"),
+        ));
+
+        let (mut dialog, dialog_receiver) =
+            dialog::guided_step_dialog(&self.locale, "", move |_| {
+                Arc::unwrap_or_clone(radio_group.selection())
+            });
+        dialog.get_inner_mut().set_content(layout.scrollable());
+
+        self.show_wait_dialog(dialog, dialog_receiver)
+    }
+
+    pub fn prompt_modify_search_path(
+        &self,
+        current: bool,
+    ) -> Result<GuidedDialogButton<bool>, InstallerError> {
         let mut layout = LinearLayout::vertical();
         layout.add_child(TextView::new(self.locale.text("modify-search-path-prompt")));
 
         let mut radio_group = RadioGroup::new();
-        layout.add_child(radio_group.button(true, self.locale.text("modify-search-path")));
-        layout.add_child(radio_group.button(false, self.locale.text("do-not-modify-search-path")));
+        layout.add_child(dialog::radio_button(
+            &mut radio_group,
+            true,
+            &current,
+            self.locale.text("modify-search-path"),
+        ));
+        layout.add_child(dialog::radio_button(
+            &mut radio_group,
+            false,
+            &current,
+            self.locale.text("do-not-modify-search-path"),
+        ));
+
+        let (mut dialog, dialog_receiver) =
+            dialog::guided_step_dialog(&self.locale, "", move |_| {
+                Arc::unwrap_or_clone(radio_group.selection())
+            });
+        dialog.get_inner_mut().set_content(layout.scrollable());
+
+        self.show_wait_dialog(dialog, dialog_receiver)
+    }
+
+    pub fn prompt_scheduled_tasks(
+        &self,
+        current: bool,
+    ) -> Result<GuidedDialogButton<bool>, InstallerError> {
+        let mut layout = LinearLayout::vertical();
+        layout.add_child(TextView::new(self.locale.text("scheduled-tasks-prompt")));
+
+        let mut radio_group = RadioGroup::new();
+        layout.add_child(dialog::radio_button(
+            &mut radio_group,
+            true,
+            &current,
+            self.locale.text("register-scheduled-tasks"),
+        ));
+        layout.add_child(dialog::radio_button(
+            &mut radio_group,
+            false,
+            &current,
+            self.locale.text("do-not-register-scheduled-tasks"),
+        ));
+
+        let (mut dialog, dialog_receiver) =
+            dialog::guided_step_dialog(&self.locale, "", move |_| {
+                Arc::unwrap_or_clone(radio_group.selection())
+            });
+        dialog.get_inner_mut().set_content(layout.scrollable());
+
+        self.show_wait_dialog(dialog, dialog_receiver)
+    }
+
+    pub fn prompt_custom_page(
+        &self,
+        page: &CustomPage,
+        current: &str,
+    ) -> Result<GuidedDialogButton<String>, InstallerError> {
+        let mut layout = LinearLayout::vertical();
+        layout.add_child(TextView::new(page.text.as_str()));
+
+        let (mut dialog, dialog_receiver) = match &page.kind {
+            CustomPageKind::Choice(options) => {
+                let mut radio_group = RadioGroup::new();
+                let current = current.to_string();
+
+                for option in options {
+                    layout.add_child(dialog::radio_button(
+                        &mut radio_group,
+                        option.value.clone(),
+                        &current,
+                        option.label.clone(),
+                    ));
+                }
+
+                dialog::guided_step_dialog(&self.locale, "", move |_| {
+                    Arc::unwrap_or_clone(radio_group.selection())
+                })
+            }
+            CustomPageKind::TextInput => {
+                layout.add_child(
+                    EditView::new()
+                        .content(current)
+                        .with_name(CUSTOM_PAGE_INPUT),
+                );
+
+                dialog::guided_step_dialog(&self.locale, "", |cursive| {
+                    cursive
+                        .call_on_name(CUSTOM_PAGE_INPUT, |view: &mut EditView| {
+                            view.get_content().to_string()
+                        })
+                        .unwrap_or_default()
+                })
+            }
+        };
+        dialog.get_inner_mut().set_content(layout.scrollable());
+
+        self.show_wait_dialog(dialog, dialog_receiver)
+    }
+
+    pub fn prompt_shortcuts(
+        &self,
+        current: (bool, bool),
+    ) -> Result<GuidedDialogButton<(bool, bool)>, InstallerError> {
+        let (start_menu_view, start_menu) =
+            dialog::labeled_checkbox(&self.locale.text("start-menu-shortcut"), current.0);
+        let (desktop_view, desktop) =
+            dialog::labeled_checkbox(&self.locale.text("desktop-shortcut"), current.1);
+
+        let layout = LinearLayout::vertical()
+            .child(TextView::new(self.locale.text("shortcuts-prompt")))
+            .child(start_menu_view)
+            .child(desktop_view);
+
+        let (mut dialog, dialog_receiver) =
+            dialog::guided_step_dialog(&self.locale, "", move |_| {
+                (
+                    start_menu.load(Ordering::Relaxed),
+                    desktop.load(Ordering::Relaxed),
+                )
+            });
+        dialog.get_inner_mut().set_content(layout.scrollable());
+
+        self.show_wait_dialog(dialog, dialog_receiver)
+    }
+
+    /// Returns `true` to resume the interrupted installation or `false` to
+    /// roll it back.
+    pub fn prompt_resume_interrupted(&self) -> Result<GuidedDialogButton<bool>, InstallerError> {
+        let args = [("app_name", (&self.app_name).into())];
+        let mut layout = LinearLayout::vertical();
+        layout.add_child(TextView::new(
+            self.locale.text_args("interrupted-install-prompt", args),
+        ));
+
+        let mut radio_group = RadioGroup::new();
+        layout.add_child(dialog::radio_button(
+            &mut radio_group,
+            true,
+            &true,
+            self.locale.text("resume-interrupted-install"),
+        ));
+        layout.add_child(dialog::radio_button(
+            &mut radio_group,
+            false,
+            &true,
+            self.locale.text("roll-back-interrupted-install"),
+        ));
 
         let (mut dialog, dialog_receiver) = dialog::guided_dialog(&self.locale, "", move |_| {
             Arc::unwrap_or_clone(radio_group.selection())
         });
-        dialog.set_content(layout.scrollable());
+        dialog.get_inner_mut().set_content(layout.scrollable());
 
         self.show_wait_dialog(dialog, dialog_receiver)
     }
 
-    pub fn prompt_uninstall_existing(&self) -> Result<GuidedDialogButton<()>, InstallerError> {
-        let (mut dialog, dialog_receiver) = dialog::guided_dialog(&self.locale, "", move |_| ());
-        dialog.set_content(
-            TextView::new(self.locale.text("removing-existing-before-install")).scrollable(),
+    pub fn show_interrupted_rolled_back(&self) -> Result<(), InstallerError> {
+        let text = self.locale.text("interrupted-install-rolled-back");
+
+        let (mut dialog, dialog_receiver) = dialog::info_dialog(&self.locale, "");
+        dialog.set_content(TextView::new(text).scrollable());
+
+        self.show_wait_dialog(dialog, dialog_receiver)
+    }
+
+    pub fn prompt_read_only_system_root(
+        &self,
+        location: &Path,
+    ) -> Result<GuidedDialogButton<()>, InstallerError> {
+        let args = [
+            ("app_name", (&self.app_name).into()),
+            ("location", location.display().to_string().into()),
+        ];
+        let (mut dialog, dialog_receiver) =
+            dialog::guided_step_dialog(&self.locale, "", move |_| ());
+        dialog.get_inner_mut().set_content(
+            TextView::new(self.locale.text_args("read-only-system-root-prompt", args)).scrollable(),
+        );
+
+        self.show_wait_dialog(dialog, dialog_receiver)
+    }
+
+    pub fn show_wsl_search_path_info(&self) -> Result<GuidedDialogButton<()>, InstallerError> {
+        let args = [("app_name", (&self.app_name).into())];
+        let (mut dialog, dialog_receiver) =
+            dialog::guided_step_dialog(&self.locale, "", move |_| ());
+        dialog.get_inner_mut().set_content(
+            TextView::new(self.locale.text_args("wsl-search-path-info", args)).scrollable(),
+        );
+
+        self.show_wait_dialog(dialog, dialog_receiver)
+    }
+
+    pub fn prompt_newer_system_install(
+        &self,
+        system_version: &str,
+    ) -> Result<GuidedDialogButton<()>, InstallerError> {
+        let args = [
+            ("app_name", (&self.app_name).into()),
+            ("system_version", system_version.into()),
+        ];
+        let (mut dialog, dialog_receiver) =
+            dialog::guided_step_dialog(&self.locale, "", move |_| ());
+        dialog.get_inner_mut().set_content(
+            TextView::new(self.locale.text_args("newer-system-install-warning", args)).scrollable(),
         );
 
         self.show_wait_dialog(dialog, dialog_receiver)
     }
 
-    pub fn prompt_install_confirm(&self) -> Result<GuidedDialogButton<()>, InstallerError> {
-        let (mut dialog, dialog_receiver) = dialog::guided_dialog(&self.locale, "", move |_| ());
-        dialog.set_content(TextView::new(self.locale.text("installer-confirm")).scrollable());
+    /// Shows the existing version if it is a different version.
+    pub fn prompt_uninstall_existing(
+        &self,
+        existing_version: Option<&str>,
+        downgrade: bool,
+    ) -> Result<GuidedDialogButton<()>, InstallerError> {
+        let text = match existing_version {
+            Some(existing_version) => {
+                let args = [
+                    ("app_name", (&self.app_name).into()),
+                    ("app_version", (&self.app_version).into()),
+                    ("existing_version", existing_version.into()),
+                ];
+                let id = if downgrade {
+                    "downgrade-existing-warning"
+                } else {
+                    "upgrading-existing"
+                };
+
+                self.locale.text_args(id, args)
+            }
+            None => self.locale.text("removing-existing-before-install"),
+        };
+
+        let (mut dialog, dialog_receiver) =
+            dialog::guided_step_dialog(&self.locale, "", move |_| ());
+        dialog
+            .get_inner_mut()
+            .set_content(TextView::new(text).scrollable());
+
+        self.show_wait_dialog(dialog, dialog_receiver)
+    }
+
+    pub fn show_install_summary(
+        &self,
+        preview: &InstallPreview,
+    ) -> Result<GuidedDialogButton<()>, InstallerError> {
+        let summary = format_install_preview_with_locale(&self.locale, preview);
+        let layout = LinearLayout::vertical()
+            .child(TextView::new(self.locale.text("install-summary-prompt")))
+            .child(TextView::new("\n"))
+            .child(TextView::new(summary));
+
+        let (mut dialog, dialog_receiver) =
+            dialog::guided_step_dialog(&self.locale, "", move |_| ());
+        dialog.get_inner_mut().set_content(layout.scrollable());
+
+        self.show_wait_dialog(dialog, dialog_receiver)
+    }
+
+    pub fn prompt_install_confirm(
+        &self,
+        install_size: u64,
+        estimated_size: u64,
+    ) -> Result<GuidedDialogButton<()>, InstallerError> {
+        let size_text = if estimated_size > install_size {
+            self.locale.text_args(
+                "install-size-with-data",
+                [
+                    (
+                        "size",
+                        format_bytes_with_locale(&self.locale, install_size).into(),
+                    ),
+                    (
+                        "estimated_size",
+                        format_bytes_with_locale(&self.locale, estimated_size).into(),
+                    ),
+                ],
+            )
+        } else {
+            self.locale.text_args(
+                "install-size",
+                [(
+                    "size",
+                    format_bytes_with_locale(&self.locale, install_size).into(),
+                )],
+            )
+        };
+
+        let layout = LinearLayout::vertical()
+            .child(TextView::new(self.locale.text("installer-confirm")))
+            .child(TextView::new("\n"))
+            .child(TextView::new(size_text));
+
+        let (mut dialog, dialog_receiver) =
+            dialog::guided_step_dialog(&self.locale, "", move |_| ());
+        dialog.get_inner_mut().set_content(layout.scrollable());
 
         self.show_wait_dialog(dialog, dialog_receiver)
     }
 
     pub fn show_install_progress_dialog(&self) -> Result<(), InstallerError> {
-        let dialog = dialog::progress_dialog("");
+        let cancelling_text = self.locale.text("cancelling");
+        let dialog = dialog::cancelable_progress_dialog(
+            &self.locale,
+            "",
+            &self.locale.text("cancel-install-confirm"),
+            move |cursive| {
+                crate::os::interrupt::request_interrupt();
+                dialog::set_progress_dialog_text(cursive, &cancelling_text);
+            },
+        );
 
         let text = self.locale.text("installing");
 
@@ -266,30 +798,87 @@ impl Tui {
 
     pub fn update_install_progress(
         &mut self,
-        current: u64,
-        total: u64,
+        progress: &CopyProgress,
     ) -> Result<(), InstallerError> {
+        let subtext = install_progress_text(&self.locale, progress);
+        let current = progress.current;
+        let total = progress.total;
+
         self.in_cursive(move |cursive| {
+            dialog::set_progress_dialog_subtext(cursive, &subtext);
             dialog::update_progress_dialog_bar(cursive, current, total);
         })
     }
 
-    pub fn uninstallation_intro(&self) -> Result<GuidedDialogButton<()>, InstallerError> {
+    pub fn uninstallation_intro(
+        &self,
+        choices: UninstallChoices,
+    ) -> Result<GuidedDialogButton<UninstallChoices>, InstallerError> {
         let args = [
             ("app_name", (&self.app_name).into()),
             ("app_version", (&self.app_version).into()),
         ];
         let text = self.locale.text_args("uninstaller-intro", args);
+        let (purge_view, purge) =
+            dialog::labeled_checkbox(&self.locale.text("uninstall-purge"), choices.purge);
+        let (backup_view, backup) =
+            dialog::labeled_checkbox(&self.locale.text("uninstall-backup"), choices.backup);
+        let (trash_view, trash) =
+            dialog::labeled_checkbox(&self.locale.text("uninstall-trash"), choices.trash);
 
-        let (mut dialog, dialog_receiver) = dialog::guided_dialog(&self.locale, "", |_| ());
-        dialog.set_content(TextView::new(text).scrollable());
+        let layout = LinearLayout::vertical()
+            .child(TextView::new(text))
+            .child(purge_view)
+            .child(backup_view)
+            .child(trash_view);
+
+        let (mut dialog, dialog_receiver) =
+            dialog::guided_dialog(&self.locale, "", move |_| UninstallChoices {
+                purge: purge.load(Ordering::Relaxed),
+                backup: backup.load(Ordering::Relaxed),
+                trash: trash.load(Ordering::Relaxed),
+            });
+        dialog.get_inner_mut().set_content(layout.scrollable());
 
         self.show_wait_dialog(dialog, dialog_receiver)
     }
 
-    pub fn uninstallation_conclusion(&self) -> Result<(), InstallerError> {
+    /// If `reboot_required`, a note is shown that some files are removed
+    /// when the computer restarts.
+    pub fn uninstallation_conclusion(
+        &self,
+        reboot_required: bool,
+        kept_user_data: &[PathBuf],
+        backup_path: Option<&Path>,
+        feedback_message: Option<&str>,
+    ) -> Result<(), InstallerError> {
         let args = [("app_name", (&self.app_name).into())];
-        let text = self.locale.text_args("uninstaller-conclusion", args);
+        let mut text = self.locale.text_args("uninstaller-conclusion", args);
+
+        if let Some(path) = backup_path {
+            let args = [("path", path.display().to_string().into())];
+            text.push_str("\n\n");
+            text.push_str(&self.locale.text_args("uninstall-backup-written", args));
+        }
+
+        if !kept_user_data.is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&self.locale.text("uninstall-kept-user-data"));
+
+            for dir in kept_user_data {
+                text.push_str(&format!("\n{}", dir.display()));
+            }
+        }
+
+        if reboot_required {
+            text.push_str("\n\n");
+            text.push_str(&self.locale.text("uninstall-reboot-required"));
+        }
+
+        if let Some(message) = feedback_message {
+            text.push_str("\n\n");
+            text.push_str(message);
+        }
 
         let (mut dialog, dialog_receiver) = dialog::info_dialog(&self.locale, "");
         dialog.set_content(TextView::new(text).scrollable());
@@ -346,6 +935,24 @@ impl Tui {
     }
 }
 
+/// Returns the text showing the size and number of copied files.
+fn install_progress_text(locale: &Locale, progress: &CopyProgress) -> String {
+    let args = [
+        (
+            "size",
+            format_bytes_with_locale(locale, progress.bytes).into(),
+        ),
+        (
+            "total_size",
+            format_bytes_with_locale(locale, progress.total_bytes).into(),
+        ),
+        ("count", progress.files.into()),
+        ("total_count", progress.total_files.into()),
+    ];
+
+    locale.text_args("install-progress-detail", args)
+}
+
 impl Debug for Tui {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Tui").field("...", &"...").finish()
@@ -359,3 +966,24 @@ impl Drop for Tui {
         }
     }
 }
+
+#[cfg(all(test, feature = "i18n-static"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_progress_text() {
+        let progress = CopyProgress {
+            bytes: 12_900_000,
+            total_bytes: 47_800_000,
+            files: 3,
+            total_files: 7,
+            ..Default::default()
+        };
+        let locale = Locale::with_language_tag("en-US");
+        let mut text = install_progress_text(&locale, &progress);
+        text.retain(|c| c != '\u{2068}' && c != '\u{2069}');
+
+        assert_eq!(text, "Copied 12 MB of 46 MB (3/7 files)");
+    }
+}
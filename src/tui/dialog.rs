@@ -1,9 +1,17 @@
-use std::sync::mpsc::Receiver;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::Receiver,
+    Arc,
+};
 
 use cursive::{
     align::HAlign,
-    view::Nameable,
-    views::{Dialog, DialogFocus, LinearLayout, NamedView, ProgressBar, TextView},
+    event::Key,
+    view::{Nameable, Scrollable},
+    views::{
+        Checkbox, Dialog, DialogFocus, LinearLayout, NamedView, OnEventView, ProgressBar,
+        RadioButton, RadioGroup, TextView,
+    },
     Cursive,
 };
 
@@ -14,50 +22,150 @@ use crate::{
 
 pub enum GuidedDialogButton<T> {
     Exit,
+    Back,
     Next(T),
 }
 
 impl<T> GuidedDialogButton<T> {
+    /// Returns the value for dialogs without a Back button.
     pub fn unwrap_button(self) -> Result<T, InstallerError> {
         match self {
             GuidedDialogButton::Exit => Err(InstallerErrorKind::InterruptedByUser.into()),
+            GuidedDialogButton::Back => unreachable!("dialog has no back button"),
             GuidedDialogButton::Next(value) => Ok(value),
         }
     }
+
+    /// Returns the value, or `None` if the user wants to go back.
+    pub fn unwrap_step(self) -> Result<Option<T>, InstallerError> {
+        match self {
+            GuidedDialogButton::Exit => Err(InstallerErrorKind::InterruptedByUser.into()),
+            GuidedDialogButton::Back => Ok(None),
+            GuidedDialogButton::Next(value) => Ok(Some(value)),
+        }
+    }
 }
 
+/// Dialog that asks to confirm exiting when ESC is pressed.
+pub type GuidedDialog = OnEventView<Dialog>;
+
 pub fn guided_dialog<T, F>(
     locale: &Locale,
     title: &str,
     value_callback: F,
-) -> (Dialog, Receiver<GuidedDialogButton<T>>)
+) -> (GuidedDialog, Receiver<GuidedDialogButton<T>>)
+where
+    F: Fn(&mut Cursive) -> T + Send + Sync + 'static,
+    T: Send + 'static,
+{
+    guided_dialog_impl(locale, title, false, value_callback)
+}
+
+/// Like [`guided_dialog`] but with a Back button.
+pub fn guided_step_dialog<T, F>(
+    locale: &Locale,
+    title: &str,
+    value_callback: F,
+) -> (GuidedDialog, Receiver<GuidedDialogButton<T>>)
+where
+    F: Fn(&mut Cursive) -> T + Send + Sync + 'static,
+    T: Send + 'static,
+{
+    guided_dialog_impl(locale, title, true, value_callback)
+}
+
+fn guided_dialog_impl<T, F>(
+    locale: &Locale,
+    title: &str,
+    back: bool,
+    value_callback: F,
+) -> (GuidedDialog, Receiver<GuidedDialogButton<T>>)
 where
     F: Fn(&mut Cursive) -> T + Send + Sync + 'static,
     T: Send + 'static,
 {
     let (sender, receiver) = std::sync::mpsc::sync_channel(1);
     let sender2 = sender.clone();
+    let sender3 = sender.clone();
 
     let exit_text = locale.text("button-exit");
+    let back_text = locale.text("button-back");
     let next_text = locale.text("button-next");
 
     let mut dialog = Dialog::new().title(title).h_align(HAlign::Right);
 
-    dialog.add_button(exit_text, move |cursive| {
+    let exit = Arc::new(move |cursive: &mut Cursive| {
         cursive.pop_layer();
         sender.send(GuidedDialogButton::Exit).unwrap();
     });
+    let exit2 = exit.clone();
+
+    dialog.add_button(exit_text, move |cursive| exit(cursive));
+
+    if back {
+        dialog.add_button(back_text, move |cursive| {
+            cursive.pop_layer();
+            sender3.send(GuidedDialogButton::Back).unwrap();
+        });
+    }
+
     dialog.add_button(next_text, move |cursive| {
         cursive.pop_layer();
         let value = value_callback(cursive);
         sender2.send(GuidedDialogButton::Next(value)).unwrap();
     });
 
-    let _ = dialog.set_focus(DialogFocus::Button(1));
+    let _ = dialog.set_focus(DialogFocus::Button(if back { 2 } else { 1 }));
+
+    let confirm_text = locale.text("exit-confirm");
+    let yes_text = locale.text("button-yes");
+    let no_text = locale.text("button-no");
+
+    let dialog = OnEventView::new(dialog).on_event(Key::Esc, move |cursive| {
+        let exit = exit2.clone();
+        cursive.add_layer(confirm_dialog(
+            &confirm_text,
+            &yes_text,
+            &no_text,
+            move |cursive| exit(cursive),
+        ));
+    });
 
     (dialog, receiver)
 }
 
+/// Returns a dialog asking a yes or no question.
+///
+/// The callback is called after the dialog is closed with Yes. Closing it
+/// with No or ESC does nothing.
+pub fn confirm_dialog<F>(
+    text: &str,
+    yes_text: &str,
+    no_text: &str,
+    yes_callback: F,
+) -> OnEventView<Dialog>
+where
+    F: Fn(&mut Cursive) + Send + Sync + 'static,
+{
+    let mut dialog = Dialog::new()
+        .content(TextView::new(text).scrollable())
+        .button(no_text, |cursive| {
+            cursive.pop_layer();
+        })
+        .button(yes_text, move |cursive| {
+            cursive.pop_layer();
+            yes_callback(cursive);
+        })
+        .h_align(HAlign::Right);
+
+    // Default to the answer that doesn't lose anything
+    let _ = dialog.set_focus(DialogFocus::Button(0));
+
+    OnEventView::new(dialog).on_event(Key::Esc, |cursive| {
+        cursive.pop_layer();
+    })
+}
+
 pub fn info_dialog(locale: &Locale, title: &str) -> (Dialog, Receiver<()>) {
     let (sender, receiver) = std::sync::mpsc::sync_channel(1);
 
@@ -74,10 +182,46 @@ pub fn info_dialog(locale: &Locale, title: &str) -> (Dialog, Receiver<()>) {
     (dialog, receiver)
 }
 
+/// Returns a radio button that is selected if the value matches the current one.
+pub fn radio_button<T>(
+    group: &mut RadioGroup<T>,
+    value: T,
+    current: &T,
+    label: String,
+) -> RadioButton<T>
+where
+    T: PartialEq + Send + Sync + 'static,
+{
+    let is_current = value == *current;
+    let button = group.button(value, label);
+
+    if is_current {
+        button.selected()
+    } else {
+        button
+    }
+}
+
+pub fn labeled_checkbox(label: &str, checked: bool) -> (LinearLayout, Arc<AtomicBool>) {
+    let value = Arc::new(AtomicBool::new(checked));
+    let value2 = value.clone();
+
+    let checkbox = Checkbox::new()
+        .with_checked(checked)
+        .on_change(move |_, checked| value2.store(checked, Ordering::Relaxed));
+
+    let layout = LinearLayout::horizontal()
+        .child(checkbox)
+        .child(TextView::new(format!(" {}", label)));
+
+    (layout, value)
+}
+
 const PROGRESS_DIALOG: &str = "progress_dialog";
 const PROGRESS_DIALOG_TEXT: &str = "progress_dialog_text";
 const PROGRESS_DIALOG_SUBTEXT: &str = "progress_dialog_subtext";
 const PROGRESS_DIALOG_PROGRESS_BAR: &str = "progress_dialog_progress_bar";
+const PROGRESS_CANCEL_DIALOG: &str = "progress_cancel_dialog";
 
 pub fn progress_dialog(title: &str) -> NamedView<Dialog> {
     let layout = LinearLayout::vertical()
@@ -91,12 +235,44 @@ pub fn progress_dialog(title: &str) -> NamedView<Dialog> {
         .with_name(PROGRESS_DIALOG)
 }
 
+/// Like [`progress_dialog`] but asks to cancel when ESC is pressed.
+///
+/// The callback is called when the user confirms.
+pub fn cancelable_progress_dialog<F>(
+    locale: &Locale,
+    title: &str,
+    confirm_text: &str,
+    cancel_callback: F,
+) -> OnEventView<NamedView<Dialog>>
+where
+    F: Fn(&mut Cursive) + Send + Sync + 'static,
+{
+    let confirm_text = confirm_text.to_string();
+    let yes_text = locale.text("button-yes");
+    let no_text = locale.text("button-no");
+    let cancel_callback = Arc::new(cancel_callback);
+
+    OnEventView::new(progress_dialog(title)).on_event(Key::Esc, move |cursive| {
+        let cancel_callback = cancel_callback.clone();
+        let dialog = confirm_dialog(&confirm_text, &yes_text, &no_text, move |cursive| {
+            cancel_callback(cursive)
+        });
+        cursive.add_layer(dialog.with_name(PROGRESS_CANCEL_DIALOG));
+    })
+}
+
 pub fn set_progress_dialog_text(cursive: &mut Cursive, value: &str) {
     if let Some(mut text_view) = cursive.find_name::<TextView>(PROGRESS_DIALOG_TEXT) {
         text_view.set_content(value);
     }
 }
 
+pub fn set_progress_dialog_subtext(cursive: &mut Cursive, value: &str) {
+    if let Some(mut text_view) = cursive.find_name::<TextView>(PROGRESS_DIALOG_SUBTEXT) {
+        text_view.set_content(value);
+    }
+}
+
 pub fn update_progress_dialog_bar(cursive: &mut Cursive, current: u64, total: u64) {
     if let Some(mut progress_bar) = cursive.find_name::<ProgressBar>(PROGRESS_DIALOG_PROGRESS_BAR) {
         progress_bar.set_max(total as usize);
@@ -105,6 +281,13 @@ pub fn update_progress_dialog_bar(cursive: &mut Cursive, current: u64, total: u6
 }
 
 pub fn dismiss_progress_dialog(cursive: &mut Cursive) {
+    if let Some(position) = cursive
+        .screen_mut()
+        .find_layer_from_name(PROGRESS_CANCEL_DIALOG)
+    {
+        cursive.screen_mut().remove_layer(position);
+    }
+
     if let Some(position) = cursive.screen_mut().find_layer_from_name(PROGRESS_DIALOG) {
         cursive.screen_mut().remove_layer(position);
     }
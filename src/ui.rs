@@ -0,0 +1,349 @@
+//! Formatting helpers shared with the user interface.
+//!
+//! These allow host applications, such as ones performing a quiet install,
+//! to present information consistently with the installer.
+
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use crate::{inst::InstallPreview, locale::Locale, os::AccessScope};
+
+const BYTE_UNITS: [(&str, &str); 5] = [
+    ("byte-unit-b", "B"),
+    ("byte-unit-kb", "KB"),
+    ("byte-unit-mb", "MB"),
+    ("byte-unit-gb", "GB"),
+    ("byte-unit-tb", "TB"),
+];
+
+/// Languages that use a comma as the decimal separator.
+const DECIMAL_COMMA_LANGUAGES: [&str; 30] = [
+    "bg", "ca", "cs", "da", "de", "el", "es", "et", "fi", "fr", "hr", "hu", "id", "it", "lt", "lv",
+    "nb", "nl", "nn", "no", "pl", "pt", "ro", "ru", "sk", "sl", "sr", "sv", "tr", "uk",
+];
+
+/// Formats a file size in bytes for display.
+///
+/// The language tag is in BCP 47 format, such as `en-US`. The unit labels
+/// are taken from the language translation files and the decimal separator
+/// from the language. Unknown languages are formatted in English.
+///
+/// Sizes use 1024-based multiples.
+///
+/// ```
+/// assert_eq!(takecrate::ui::format_bytes("en-US", 1536), "1.5 KB");
+/// ```
+pub fn format_bytes(lang_tag: &str, value: u64) -> String {
+    format_bytes_with_locale(&Locale::with_language_tag(lang_tag), value)
+}
+
+/// Formats a file size in bytes for display using the system language.
+///
+/// See [`format_bytes()`] for details.
+pub fn format_bytes_system(value: u64) -> String {
+    format_bytes_with_locale(&Locale::with_system(), value)
+}
+
+pub(crate) fn format_bytes_with_locale(locale: &Locale, value: u64) -> String {
+    let mut size = value as f64;
+    let mut unit_index = 0;
+
+    while size >= 1024.0 && unit_index < BYTE_UNITS.len() - 1 {
+        size /= 1024.0;
+        unit_index += 1;
+    }
+
+    let number = if unit_index == 0 {
+        value.to_string()
+    } else if size < 10.0 {
+        format!("{:.1}", size)
+    } else {
+        format!("{:.0}", size)
+    };
+    let number = localize_decimal(locale, number);
+
+    let (unit_text_id, unit_fallback) = BYTE_UNITS[unit_index];
+    let unit = locale
+        .try_text(unit_text_id)
+        .unwrap_or_else(|| unit_fallback.to_string());
+
+    format!("{} {}", number, unit)
+}
+
+/// Formats a duration in seconds for display.
+pub(crate) fn format_duration_with_locale(locale: &Locale, value: Duration) -> String {
+    let seconds = value.as_secs_f64();
+    let number = if seconds < 10.0 {
+        format!("{:.1}", seconds)
+    } else {
+        format!("{:.0}", seconds)
+    };
+
+    format!("{} s", localize_decimal(locale, number))
+}
+
+/// Formats the changes of an installation as an indented tree for display.
+///
+/// This is intended for the output of a "dry run" option. The headings are
+/// taken from the language translation files. Installed files are grouped
+/// by directory and shown with their sizes.
+///
+/// The interactive installer shows the same tree before the final
+/// confirmation.
+///
+/// See [`Installer::preview()`](crate::inst::Installer::preview).
+pub fn format_install_preview(lang_tag: &str, preview: &InstallPreview) -> String {
+    format_install_preview_with_locale(&Locale::with_language_tag(lang_tag), preview)
+}
+
+/// Formats the changes of an installation using the system language.
+///
+/// See [`format_install_preview()`] for details.
+pub fn format_install_preview_system(preview: &InstallPreview) -> String {
+    format_install_preview_with_locale(&Locale::with_system(), preview)
+}
+
+pub(crate) fn format_install_preview_with_locale(
+    locale: &Locale,
+    preview: &InstallPreview,
+) -> String {
+    let title = without_isolation_marks(locale.text_args(
+        "dry-run-title",
+        [
+            ("app_name", (&preview.app_name).into()),
+            ("app_version", (&preview.app_version).into()),
+        ],
+    ));
+    let scope_text_id = match preview.access_scope {
+        AccessScope::User => "for-this-user",
+        AccessScope::System => "for-all-users",
+    };
+    let mut nodes = vec![TreeNode::new(locale.text(scope_text_id))];
+
+    if let Some(path) = &preview.existing_manifest_path {
+        nodes.push(TreeNode::with_paths(
+            locale.text("dry-run-existing-installation"),
+            std::slice::from_ref(path),
+        ));
+    }
+
+    let mut file_groups = BTreeMap::<PathBuf, Vec<TreeNode>>::new();
+
+    for dir in &preview.dirs {
+        file_groups.entry(dir.clone()).or_default();
+    }
+
+    for (index, path) in preview.files.iter().enumerate() {
+        let dir = path.parent().unwrap_or(path).to_path_buf();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let label = match preview.file_sizes.get(index) {
+            Some(size) => format!("{} ({})", name, format_bytes_with_locale(locale, *size)),
+            None => name.to_string(),
+        };
+        file_groups
+            .entry(dir)
+            .or_default()
+            .push(TreeNode::new(label));
+    }
+
+    let size = format_bytes_with_locale(locale, preview.files_size);
+    nodes.push(TreeNode {
+        label: without_isolation_marks(locale.text_args("dry-run-files", [("size", size.into())])),
+        children: file_groups
+            .into_iter()
+            .map(|(dir, children)| TreeNode {
+                label: dir.display().to_string(),
+                children,
+            })
+            .collect(),
+    });
+
+    let path_sections = [
+        ("dry-run-links", preview.links.as_slice()),
+        ("dry-run-shortcuts", preview.shortcuts.as_slice()),
+        ("dry-run-search-path", preview.search_path.as_slice()),
+        ("dry-run-modified-files", preview.modified_files.as_slice()),
+    ];
+
+    for (text_id, paths) in path_sections {
+        if !paths.is_empty() {
+            nodes.push(TreeNode::with_paths(locale.text(text_id), paths));
+        }
+    }
+
+    let text_sections = [
+        ("dry-run-registry-keys", preview.registry_keys.clone()),
+        (
+            "dry-run-env-vars",
+            preview
+                .env_vars
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect(),
+        ),
+        ("dry-run-scheduled-tasks", preview.scheduled_tasks.clone()),
+    ];
+
+    for (text_id, items) in text_sections {
+        if !items.is_empty() {
+            nodes.push(TreeNode {
+                label: locale.text(text_id),
+                children: items.into_iter().map(TreeNode::new).collect(),
+            });
+        }
+    }
+
+    nodes.push(TreeNode::with_paths(
+        locale.text("dry-run-manifest"),
+        std::slice::from_ref(&preview.manifest_path),
+    ));
+
+    let mut output = title;
+    output.push('\n');
+    push_tree(&mut output, &nodes, "");
+
+    output
+}
+
+/// Removes the Unicode isolation marks that Fluent adds around arguments.
+///
+/// Terminals often display the marks as visible characters and the output
+/// may be copied into scripts or logs.
+fn without_isolation_marks(mut text: String) -> String {
+    text.retain(|c| c != '\u{2068}' && c != '\u{2069}');
+    text
+}
+
+/// An item in a tree for display.
+struct TreeNode {
+    label: String,
+    children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    fn new(label: String) -> Self {
+        Self {
+            label,
+            children: Vec::new(),
+        }
+    }
+
+    fn with_paths(label: String, paths: &[PathBuf]) -> Self {
+        Self {
+            label,
+            children: paths
+                .iter()
+                .map(|path| Self::new(path.display().to_string()))
+                .collect(),
+        }
+    }
+}
+
+fn push_tree(output: &mut String, nodes: &[TreeNode], prefix: &str) {
+    for (index, node) in nodes.iter().enumerate() {
+        let (branch, indent) = if index + 1 == nodes.len() {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+
+        output.push_str(prefix);
+        output.push_str(branch);
+        output.push_str(&node.label);
+        output.push('\n');
+
+        push_tree(output, &node.children, &format!("{}{}", prefix, indent));
+    }
+}
+
+fn localize_decimal(locale: &Locale, number: String) -> String {
+    if DECIMAL_COMMA_LANGUAGES.contains(&locale.language()) {
+        number.replace('.', ",")
+    } else {
+        number
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes("en-US", 0), "0 B");
+        assert_eq!(format_bytes("en-US", 1000), "1000 B");
+        assert_eq!(format_bytes("en-US", 1536), "1.5 KB");
+        assert_eq!(format_bytes("en-US", 40 * 1024 * 1024), "40 MB");
+        assert_eq!(format_bytes("en-US", 500 * 1024 * 1024 * 1024), "500 GB");
+        assert_eq!(format_bytes("invalid tag!", 1536), "1.5 KB");
+    }
+
+    #[cfg(feature = "i18n-static")]
+    #[test]
+    fn test_format_bytes_localized() {
+        assert_eq!(format_bytes("fr-FR", 1536), "1,5 Ko");
+        assert_eq!(format_bytes("ru", 40 * 1024 * 1024), "40 МБ");
+        assert_eq!(format_bytes("de-DE", 1536), "1,5 KB");
+        assert_eq!(format_bytes("zh-CN", 1536), "1.5 KB");
+    }
+
+    #[cfg(all(unix, feature = "i18n-static"))]
+    #[test]
+    fn test_format_install_preview() {
+        let preview = InstallPreview {
+            app_name: "My App".to_string(),
+            app_version: "1.0.0".to_string(),
+            manifest_path: PathBuf::from("/home/user/.config/takecrate-manifest__my-app.ron"),
+            dirs: vec![
+                PathBuf::from("/home/user/.local/bin"),
+                PathBuf::from("/home/user/.local/share/my-app"),
+            ],
+            files: vec![
+                PathBuf::from("/home/user/.local/bin/my-app"),
+                PathBuf::from("/home/user/.local/share/my-app/readme.txt"),
+            ],
+            file_sizes: vec![1024, 512],
+            files_size: 1536,
+            search_path: Some(PathBuf::from("/home/user/.local/bin")),
+            modified_files: vec![PathBuf::from("/home/user/.profile")],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format_install_preview("en-US", &preview),
+            "\
+Changes to install My App 1.0.0:
+├── 👤 For this user only
+├── Files (1.5 KB)
+│   ├── /home/user/.local/bin
+│   │   └── my-app (1.0 KB)
+│   └── /home/user/.local/share/my-app
+│       └── readme.txt (512 B)
+├── Search path (PATH)
+│   └── /home/user/.local/bin
+├── Modified files
+│   └── /home/user/.profile
+└── Disk manifest
+    └── /home/user/.config/takecrate-manifest__my-app.ron
+"
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        let locale = Locale::with_language_tag("en-US");
+        assert_eq!(
+            format_duration_with_locale(&locale, Duration::from_millis(1250)),
+            "1.2 s"
+        );
+        assert_eq!(
+            format_duration_with_locale(&locale, Duration::from_secs(12)),
+            "12 s"
+        );
+
+        let locale = Locale::with_language_tag("de");
+        assert_eq!(
+            format_duration_with_locale(&locale, Duration::from_millis(1500)),
+            "1,5 s"
+        );
+    }
+}
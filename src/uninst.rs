@@ -1,12 +1,23 @@
 //! Uninstaller functionality.
 
-use std::{cell::RefCell, rc::Rc};
+mod backup;
+
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 #[cfg(feature = "ui")]
-use crate::tui::Tui;
+use crate::tui::{Tui, UninstallChoices};
 use crate::{
     error::{AddContext, AddInstallerContext, InstallerError, InstallerErrorKind},
-    manifest::{AppId, DiskManifest},
+    inst::ProgressWeight,
+    manifest::{
+        AppId, DiskDirEntry, DiskFileEntry, DiskManifest, FileType, JournalOperation, KeptUserData,
+        ManifestIntegrity,
+    },
+    os::{AccessScope, EventLogEvent},
 };
 
 /// The uninstaller interface.
@@ -15,10 +26,52 @@ pub struct Uninstaller {
     app_id: AppId,
     manifest: DiskManifest,
     manual_manifest: Option<DiskManifest>,
+    access_scope: Option<AccessScope>,
+    state_dir: Option<PathBuf>,
+    keep_main_executable: bool,
+    kept_main_executable: Option<PathBuf>,
+    purge: bool,
+    keep_user_data: bool,
+    kept_user_data: Vec<PathBuf>,
+    backup_user_data: bool,
+    backup_dir: Option<PathBuf>,
+    backup_path: Option<PathBuf>,
+    trash: bool,
+    progress_weight: ProgressWeight,
+    reboot_required: bool,
+    app_lock: bool,
+    recovering: bool,
+    feedback_hook: Option<FeedbackHook>,
     #[cfg(feature = "ui")]
     tui: Rc<RefCell<Tui>>,
 }
 
+/// Details passed to the function set with
+/// [`Uninstaller::with_feedback_hook()`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct UninstallFeedback<'a> {
+    /// Disk manifest of the removed installation.
+    pub manifest: &'a DiskManifest,
+    /// Whether the interactive uninstaller is running.
+    ///
+    /// When `false`, the returned message is not shown, so the function
+    /// should print it if needed.
+    pub interactive: bool,
+    /// Directories containing settings and data files that were kept.
+    pub kept_user_data: &'a [PathBuf],
+}
+
+type FeedbackHookFn = dyn FnMut(&UninstallFeedback) -> Option<String>;
+
+struct FeedbackHook(Box<FeedbackHookFn>);
+
+impl std::fmt::Debug for FeedbackHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FeedbackHook")
+    }
+}
+
 impl Uninstaller {
     /// Creates a new uninstaller.
     ///
@@ -32,6 +85,22 @@ impl Uninstaller {
             tui: Rc::new(RefCell::new(Tui::new())),
             manifest: Default::default(),
             manual_manifest: None,
+            access_scope: None,
+            state_dir: None,
+            keep_main_executable: false,
+            kept_main_executable: None,
+            purge: false,
+            keep_user_data: false,
+            kept_user_data: Vec::new(),
+            backup_user_data: false,
+            backup_dir: None,
+            backup_path: None,
+            trash: false,
+            progress_weight: ProgressWeight::default(),
+            reboot_required: false,
+            app_lock: true,
+            recovering: false,
+            feedback_hook: None,
         }
     }
 
@@ -41,6 +110,179 @@ impl Uninstaller {
         self
     }
 
+    /// Uninstalls the installation of the given access scope only.
+    ///
+    /// By default, if there is both a User and System installation, the
+    /// User version is uninstalled. This option is intended for
+    /// administrators removing the System version specifically. If the
+    /// application is not installed in the scope, the error kind
+    /// [`InstallerErrorKind::NotInstalled`] is returned.
+    ///
+    /// This option is ignored if the disk manifest is specified with
+    /// [`Self::with_manifest()`].
+    pub fn with_scope(mut self, value: AccessScope) -> Self {
+        self.access_scope = Some(value);
+        self
+    }
+
+    /// Finds the disk manifest in the given state directory instead of the
+    /// default location.
+    ///
+    /// This is the same directory as
+    /// [`InstallConfig::state_dir`](crate::inst::InstallConfig::state_dir)
+    /// used to install.
+    ///
+    /// This option is ignored if the disk manifest is specified with
+    /// [`Self::with_manifest()`].
+    pub fn with_state_dir<P: Into<PathBuf>>(mut self, value: P) -> Self {
+        self.state_dir = Some(value.into());
+        self
+    }
+
+    /// Sets whether to leave the main binary on the disk instead of deleting it.
+    ///
+    /// This is intended for platforms where self-deletion is not possible.
+    /// The path of the binary left behind is returned by
+    /// [`Self::kept_main_executable()`].
+    ///
+    /// Default is `false`.
+    pub fn with_keep_main_executable(mut self, value: bool) -> Self {
+        self.keep_main_executable = value;
+        self
+    }
+
+    /// Sets whether to remove everything including files modified or added
+    /// by the user.
+    ///
+    /// By default, the uninstaller only removes files that are unmodified
+    /// since installation and directories that are empty and did not exist
+    /// before installation. With this option, modified files and preserved
+    /// directories are also removed when empty. Leftover files, such as
+    /// settings, are removed from the data directory if the directory
+    /// belongs to the application; that is, it was created by the installer
+    /// or is named after the application ID.
+    ///
+    /// Default is `false`.
+    pub fn with_purge(mut self, value: bool) -> Self {
+        self.purge = value;
+        self
+    }
+
+    /// Sets whether to leave the settings and data files in place so that
+    /// installing the application again restores the user's state.
+    ///
+    /// With this option, the binaries, search path entries, and other
+    /// integrations are removed, but the installed [`FileType::Data`] files
+    /// and the data directory, including any settings the application wrote
+    /// there, are left untouched. The kept directories are returned by
+    /// [`Self::kept_user_data()`] and recorded beside the disk manifest
+    /// (see [`kept_user_data()`](crate::manifest::kept_user_data)).
+    /// Files modified by the user are kept when installing again.
+    ///
+    /// This option takes precedence over [`Self::with_purge()`] for the
+    /// settings and data files.
+    ///
+    /// Default is `false`.
+    pub fn with_keep_user_data(mut self, value: bool) -> Self {
+        self.keep_user_data = value;
+        self
+    }
+
+    /// Returns the directories containing settings and data files that were
+    /// kept by [`Self::with_keep_user_data()`].
+    pub fn kept_user_data(&self) -> &[PathBuf] {
+        &self.kept_user_data
+    }
+
+    /// Sets whether to archive the settings and data files before
+    /// uninstalling.
+    ///
+    /// The data and configuration directories are stored in a tar archive
+    /// named like `[app-id]-backup-[YYYYMMDD]-[HHMMSS].tar` (UTC time) in the
+    /// directory set by [`Self::with_backup_dir()`]. If the archive cannot be
+    /// written, nothing is uninstalled. The path of the archive is returned
+    /// by [`Self::backup_path()`].
+    ///
+    /// Default is `false`.
+    pub fn with_backup_user_data(mut self, value: bool) -> Self {
+        self.backup_user_data = value;
+        self
+    }
+
+    /// Sets the directory where the backup archive is written.
+    ///
+    /// Default is the user's home directory.
+    pub fn with_backup_dir<P: Into<PathBuf>>(mut self, value: P) -> Self {
+        self.backup_dir = Some(value.into());
+        self
+    }
+
+    /// Returns the path of the backup archive if one was written.
+    ///
+    /// No archive is written if there were no settings or data files.
+    pub fn backup_path(&self) -> Option<&Path> {
+        self.backup_path.as_deref()
+    }
+
+    /// Sets whether to move removed files to the trash instead of deleting
+    /// them permanently.
+    ///
+    /// Files are moved to the Recycle Bin on Windows, `~/.Trash` on macOS,
+    /// and the trash in the user's data directory (`$XDG_DATA_HOME/Trash`)
+    /// otherwise. Directories and the main binary are still deleted. If a
+    /// file cannot be moved to the trash, uninstalling stops with an error.
+    ///
+    /// Default is `false`.
+    pub fn with_trash(mut self, value: bool) -> Self {
+        self.trash = value;
+        self
+    }
+
+    /// Sets a function called after the files are removed, such as for
+    /// collecting optional feedback on why the application was uninstalled.
+    ///
+    /// The function is called only if the uninstallation succeeds. It may,
+    /// for example, write a file or return a message containing the URL of
+    /// a feedback form. The returned message is shown on the conclusion
+    /// screen of the interactive uninstaller. The crate does not perform any
+    /// network requests itself.
+    ///
+    /// The function is not called when an existing installation is removed
+    /// by the installer.
+    pub fn with_feedback_hook<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(&UninstallFeedback) -> Option<String> + 'static,
+    {
+        self.feedback_hook = Some(FeedbackHook(Box::new(hook)));
+        self
+    }
+
+    /// Sets how the progress of removing files is computed.
+    ///
+    /// See [`ProgressWeight`].
+    pub fn with_progress_weight(mut self, value: ProgressWeight) -> Self {
+        self.progress_weight = value;
+        self
+    }
+
+    /// Returns the path of the main binary if it was not removed.
+    ///
+    /// This happens when [`Self::with_keep_main_executable()`] is enabled
+    /// or when self-deletion is not supported.
+    pub fn kept_main_executable(&self) -> Option<&Path> {
+        self.kept_main_executable.as_deref()
+    }
+
+    /// Returns whether files in use could not be removed and will be
+    /// removed when the computer restarts.
+    ///
+    /// Only applies to Windows. Scheduling the removal requires
+    /// administrator privileges; otherwise, removing a file in use is an
+    /// error.
+    pub fn reboot_required(&self) -> bool {
+        self.reboot_required
+    }
+
     /// Sets the BCP 47 language tag used for the UI.
     #[cfg(feature = "ui")]
     pub fn with_language_tag(self, value: String) -> Self {
@@ -64,6 +306,12 @@ impl Uninstaller {
         self
     }
 
+    // To be called from the installer only, which already holds the lock
+    pub(crate) fn with_app_lock(mut self, value: bool) -> Self {
+        self.app_lock = value;
+        self
+    }
+
     #[cfg(feature = "ui")]
     // To be called from the installer only
     pub(crate) fn with_tui(mut self, tui: Rc<RefCell<Tui>>) -> Self {
@@ -74,7 +322,7 @@ impl Uninstaller {
     /// Uninstall with a TUI.
     #[cfg(feature = "ui")]
     pub fn run_interactive(&mut self) -> Result<(), InstallerError> {
-        self.tui.borrow_mut().run_background();
+        self.tui.borrow_mut().run_background()?;
 
         let result = self.run_interactive_impl();
 
@@ -107,27 +355,60 @@ impl Uninstaller {
 
         tui.set_name(&self.manifest.app_name, &self.manifest.app_version);
 
-        tui.uninstallation_intro()?.unwrap_button()?;
+        let choices = tui
+            .uninstallation_intro(UninstallChoices {
+                purge: self.purge,
+                backup: self.backup_user_data,
+                trash: self.trash,
+            })?
+            .unwrap_button()?;
+        self.purge = choices.purge;
+        self.backup_user_data = choices.backup;
+        self.trash = choices.trash;
         tui.show_uninstall_progress_dialog()?;
 
         drop(tui);
         self.run_impl()?;
 
+        let feedback_message = self.run_feedback_hook(true);
         let tui = self.tui.borrow_mut();
 
         // As described in the installer, pause briefly so the user can see we did something.
         std::thread::sleep(Duration::from_millis(500));
 
         tui.hide_uninstall_progress_dialog()?;
-        tui.uninstallation_conclusion()?;
+        tui.uninstallation_conclusion(
+            self.reboot_required,
+            &self.kept_user_data,
+            self.backup_path.as_deref(),
+            feedback_message.as_deref(),
+        )?;
 
         Ok(())
     }
 
     /// Automatically uninstall the binary.
+    ///
+    /// Returns [`InstallerErrorKind::AlreadyRunning`] if another installer
+    /// or uninstaller for the application is running.
     pub fn run(&mut self) -> Result<(), InstallerError> {
         self.discover_manifest()?;
-        self.run_impl()
+        self.run_impl()?;
+        self.run_feedback_hook(false);
+
+        Ok(())
+    }
+
+    fn run_feedback_hook(&mut self, interactive: bool) -> Option<String> {
+        let hook = self.feedback_hook.as_mut()?;
+
+        tracing::debug!("running feedback hook");
+
+        (hook.0)(&UninstallFeedback {
+            manifest: &self.manifest,
+            interactive,
+            kept_user_data: &self.kept_user_data,
+        })
     }
 
     #[cfg(feature = "ui")]
@@ -142,19 +423,126 @@ impl Uninstaller {
     }
 
     fn run_impl(&mut self) -> Result<(), InstallerError> {
+        let name = format!("{} {}", self.manifest.app_name, self.manifest.app_version);
+        self.report_event(
+            EventLogEvent::UninstallStarted,
+            &format!("Uninstalling {}.", name),
+        );
+
+        let result = self.run_steps();
+
+        match &result {
+            Ok(()) => self.report_event(
+                EventLogEvent::UninstallSucceeded,
+                &format!("Uninstalled {}.", name),
+            ),
+            Err(error) => self.report_event(
+                EventLogEvent::UninstallFailed,
+                &format!("Uninstallation of {} failed: {}", name, error),
+            ),
+        }
+
+        result
+    }
+
+    fn report_event(&self, event: EventLogEvent, message: &str) {
+        let source = if self.manifest.app_name.is_empty() {
+            self.app_id.namespaced_id()
+        } else {
+            &self.manifest.app_name
+        };
+
+        crate::os::report_event(self.manifest.access_scope, source, event, message);
+    }
+
+    fn run_steps(&mut self) -> Result<(), InstallerError> {
+        let _lock = if self.app_lock {
+            Some(crate::os::AppLock::acquire(
+                &self.app_id,
+                self.manifest
+                    .manifest_path
+                    .parent()
+                    .unwrap_or(Path::new("")),
+            )?)
+        } else {
+            None
+        };
+
         self.verify_matching_manifest()?;
-        self.remove_app_path()
-            .inst_context("failed to remove App Path")?;
-        self.remove_path_env_var()
-            .inst_context("failed to remove PATH environment variable")?;
-        self.remove_files()?;
-        self.remove_self()
-            .inst_context("failed to remove self executable")?;
+        self.verify_manifest_integrity()?;
+
+        // Any journal is stale because the lock is held
+        if crate::manifest::stale_journal(&self.manifest.manifest_path, &self.app_id)?.is_some() {
+            tracing::warn!("recovering from interrupted operation");
+            self.recovering = true;
+        }
+
+        crate::manifest::write_journal(JournalOperation::Uninstall, &self.manifest)?;
+
+        let recovering = self.recovering;
+
+        self.back_up_user_data()
+            .inst_context("failed to back up user data")?;
+
+        best_effort(
+            recovering,
+            self.remove_app_path()
+                .inst_context("failed to remove App Path"),
+        )?;
+        best_effort(
+            recovering,
+            self.remove_path_env_var()
+                .inst_context("failed to remove PATH environment variable"),
+        )?;
+        best_effort(
+            recovering,
+            self.remove_shortcuts()
+                .inst_context("failed to remove shortcuts"),
+        )?;
+        best_effort(
+            recovering,
+            self.remove_file_associations()
+                .inst_context("failed to remove file associations"),
+        )?;
+        best_effort(
+            recovering,
+            self.remove_url_schemes()
+                .inst_context("failed to remove URL schemes"),
+        )?;
+        best_effort(
+            recovering,
+            self.remove_scheduled_tasks()
+                .inst_context("failed to remove scheduled tasks"),
+        )?;
+        best_effort(
+            recovering,
+            self.remove_env_vars()
+                .inst_context("failed to remove environment variables"),
+        )?;
+        best_effort(recovering, self.remove_files())?;
+        best_effort(
+            recovering,
+            self.remove_shims_dir()
+                .inst_context("failed to remove shims directory"),
+        )?;
+        self.update_desktop_database();
+        best_effort(
+            recovering,
+            self.remove_self()
+                .inst_context("failed to remove self executable"),
+        )?;
         self.remove_manifest_file()
             .inst_context("failed to remove manifest file")?;
-        self.remove_dirs()?;
-        self.remove_uninstall_entry()
-            .inst_context("failed to remove uninstall entry")?;
+        self.record_kept_user_data()
+            .inst_context("failed to record kept user data")?;
+        best_effort(recovering, self.remove_dirs())?;
+        best_effort(
+            recovering,
+            self.remove_uninstall_entry()
+                .inst_context("failed to remove uninstall entry"),
+        )?;
+
+        crate::manifest::remove_journal(&self.manifest.manifest_path, &self.app_id)?;
 
         Ok(())
     }
@@ -163,18 +551,62 @@ impl Uninstaller {
         if let Some(manifest) = self.manual_manifest.take() {
             self.manifest = manifest;
         } else {
-            self.manifest = crate::manifest(&self.app_id).map_err(|error| {
-                if matches!(error.kind(), InstallerErrorKind::DiskManifestNotFound) {
-                    InstallerError::new(InstallerErrorKind::NotInstalled).with_source(error)
-                } else {
-                    error
+            match self.find_manifest() {
+                Ok(manifest) => self.manifest = manifest,
+                Err(error) if matches!(error.kind(), InstallerErrorKind::DiskManifestNotFound) => {
+                    // The disk manifest is removed near the end of
+                    // uninstalling, so the journal's copy is used to finish
+                    let operation =
+                        crate::manifest::interrupted_operation(&self.app_id)?.filter(|operation| {
+                            self.access_scope
+                                .map_or(true, |scope| operation.manifest.access_scope == scope)
+                        });
+
+                    match operation {
+                        Some(operation) => {
+                            tracing::warn!(?operation.operation, "found interrupted operation");
+                            self.manifest = operation.manifest;
+                        }
+                        None => {
+                            return Err(InstallerError::new(InstallerErrorKind::NotInstalled)
+                                .with_source(error))
+                        }
+                    }
                 }
-            })?;
+                Err(error) => return Err(error),
+            }
         }
 
         Ok(())
     }
 
+    fn find_manifest(&self) -> Result<DiskManifest, InstallerError> {
+        if let Some(state_dir) = &self.state_dir {
+            let access_scopes = match self.access_scope {
+                Some(access_scope) => vec![access_scope],
+                None => vec![AccessScope::User, AccessScope::System],
+            };
+
+            for access_scope in access_scopes {
+                if let Some(path) = crate::manifest::existing_manifest_path_in(
+                    Some(state_dir),
+                    &self.app_id,
+                    access_scope,
+                )? {
+                    return DiskManifest::load(&path);
+                }
+            }
+
+            return Err(InstallerErrorKind::DiskManifestNotFound.into());
+        }
+
+        let Some(access_scope) = self.access_scope else {
+            return crate::manifest(&self.app_id);
+        };
+
+        crate::manifest::discover_manifest_in_scope(&self.app_id, access_scope)
+    }
+
     fn verify_matching_manifest(&self) -> Result<(), InstallerError> {
         tracing::info!("verify matching manifest");
 
@@ -185,10 +617,33 @@ impl Uninstaller {
         }
     }
 
+    fn verify_manifest_integrity(&self) -> Result<(), InstallerError> {
+        if self.manifest.integrity == ManifestIntegrity::Invalid {
+            tracing::error!(manifest_path = ?self.manifest.manifest_path, "disk manifest integrity mismatch");
+
+            return Err(
+                InstallerError::new(InstallerErrorKind::TamperedDiskManifest).with_context(
+                    format!(
+                        "refusing to remove files of {:?}",
+                        self.manifest.manifest_path
+                    ),
+                ),
+            );
+        }
+
+        Ok(())
+    }
+
     fn remove_app_path(&self) -> Result<(), InstallerError> {
         #[cfg(windows)]
         {
-            if let Some(exe_name) = &self.manifest.app_path_exe_name {
+            let exe_names = self
+                .manifest
+                .app_path_exe_name
+                .iter()
+                .chain(&self.manifest.command_app_path_exe_names);
+
+            for exe_name in exe_names {
                 tracing::info!(exe_name, "remove app path");
 
                 crate::os::windows::remove_app_path(self.manifest.access_scope, exe_name)?;
@@ -196,16 +651,49 @@ impl Uninstaller {
         }
         Ok(())
     }
-    fn remove_path_env_var(&self) -> Result<(), InstallerError> {
+    fn remove_path_env_var(&mut self) -> Result<(), InstallerError> {
         #[cfg(windows)]
         {
             if let Some(exe_dir) = &self.manifest.search_path {
-                tracing::info!(?exe_dir, "remove PATH environment variable");
+                if self.manifest.powershell_profile_paths.is_empty() {
+                    tracing::info!(?exe_dir, "remove PATH environment variable");
 
-                crate::os::windows::remove_path_env_var(
-                    self.manifest.access_scope,
-                    exe_dir.as_os_str(),
-                )?;
+                    crate::os::windows::remove_path_env_var(
+                        self.manifest.access_scope,
+                        exe_dir.as_os_str(),
+                    )?;
+                }
+
+                for profile in &self.manifest.powershell_profile_paths {
+                    tracing::info!(?exe_dir, ?profile, "remove PATH from PowerShell profile");
+
+                    crate::os::windows::remove_powershell_profile_path(
+                        profile,
+                        exe_dir.as_os_str(),
+                    )?;
+                }
+            }
+
+            for link_path in &self.manifest.execution_aliases {
+                // Only remove links to files that we installed
+                let Ok(checksum) = crate::os::file_checksum(link_path) else {
+                    continue;
+                };
+
+                if !self.manifest.files_by_checksum(&checksum).is_empty() {
+                    tracing::info!(?link_path, "remove execution alias");
+
+                    // The link can't be removed while the binary is running
+                    // through it or any other link to the same file.
+                    match remove_file_or_schedule(link_path) {
+                        Ok(scheduled) => self.reboot_required |= scheduled,
+                        Err(error) => {
+                            tracing::warn!(?link_path, ?error, "could not remove execution alias");
+                        }
+                    }
+                } else {
+                    tracing::warn!(?link_path, "cannot remove execution alias: is modified");
+                }
             }
         }
         #[cfg(unix)]
@@ -222,6 +710,88 @@ impl Uninstaller {
                     )?;
                 }
             }
+
+            for link_path in &self.manifest.symlinks {
+                // Only remove symlinks to files that we installed
+                let Ok(target) = std::fs::read_link(link_path) else {
+                    continue;
+                };
+
+                if self.manifest.file_by_path(&target).is_some() {
+                    tracing::info!(?link_path, "remove symlink to executable");
+
+                    crate::os::unix::remove_symlink(link_path, &target)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_shortcuts(&self) -> Result<(), InstallerError> {
+        #[cfg(windows)]
+        for path in &self.manifest.shortcuts {
+            tracing::info!(?path, "remove shortcut");
+
+            crate::os::windows::remove_shortcut(path)?;
+        }
+        Ok(())
+    }
+
+    fn remove_file_associations(&self) -> Result<(), InstallerError> {
+        #[cfg(windows)]
+        for association in &self.manifest.file_associations {
+            tracing::info!(prog_id = association.prog_id, "remove file association");
+
+            crate::os::windows::remove_file_association(
+                self.manifest.access_scope,
+                &association.prog_id,
+                &association.extensions,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn remove_env_vars(&self) -> Result<(), InstallerError> {
+        #[cfg(windows)]
+        for env_var in &self.manifest.env_vars {
+            tracing::info!(name = env_var.name, "remove environment variable");
+
+            crate::os::windows::remove_env_var(
+                self.manifest.access_scope,
+                &env_var.name,
+                &env_var.value,
+            )?;
+        }
+
+        #[cfg(unix)]
+        if !self.manifest.env_vars.is_empty() {
+            if let Some(profile) = &self.manifest.shell_profile_path {
+                tracing::info!(?profile, "remove environment variables");
+
+                crate::os::unix::remove_env_vars(profile, self.manifest.app_id.namespaced_id())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_scheduled_tasks(&self) -> Result<(), InstallerError> {
+        for id in &self.manifest.scheduled_tasks {
+            tracing::info!(id, "remove scheduled task");
+
+            #[cfg(unix)]
+            crate::os::unix::remove_scheduled_task(self.manifest.access_scope, id)?;
+            #[cfg(windows)]
+            crate::os::windows::remove_scheduled_task(self.manifest.access_scope, id)?;
+        }
+        Ok(())
+    }
+
+    fn remove_url_schemes(&self) -> Result<(), InstallerError> {
+        #[cfg(windows)]
+        for scheme in &self.manifest.url_schemes {
+            tracing::info!(scheme, "remove URL scheme");
+
+            crate::os::windows::remove_url_scheme(self.manifest.access_scope, scheme)?;
         }
         Ok(())
     }
@@ -239,33 +809,50 @@ impl Uninstaller {
         Ok(())
     }
 
-    fn remove_files(&self) -> Result<(), InstallerError> {
+    fn remove_files(&mut self) -> Result<(), InstallerError> {
         let mut current = 0;
-        let total = self.manifest.total_file_size();
+        let total: u64 = self
+            .manifest
+            .files
+            .iter()
+            .map(|entry| self.progress_weight.cost(entry.len))
+            .sum();
 
         for entry in &self.manifest.files {
             if entry.is_main_executable {
                 continue;
             }
 
+            if self.is_user_data_file(entry) {
+                tracing::info!(path = ?entry.path, "keeping user data file");
+                continue;
+            }
+
             if entry.path.exists() {
-                let checksum = crate::os::file_checksum(&entry.path).with_contextc(|_e| {
+                let checksum = entry.current_checksum().with_contextc(|_e| {
                     format!("failed to read checksum for file {:?}", entry.path)
                 })?;
 
-                if checksum.crc32c != entry.crc32c {
+                if checksum != entry.checksum() && !self.purge {
                     tracing::warn!(path = ?entry.path, "cannot remove file: is modified");
                     continue;
                 }
 
-                tracing::info!(path = ?entry.path, "removing file");
-                std::fs::remove_file(&entry.path)
-                    .with_contextc(|_e| format!("failed to remove file {:?}", entry.path))?;
+                if self.trash {
+                    tracing::info!(path = ?entry.path, "moving file to trash");
+                    crate::os::move_to_trash(&entry.path).with_contextc(|_e| {
+                        format!("failed to move file to trash {:?}", entry.path)
+                    })?;
+                } else {
+                    tracing::info!(path = ?entry.path, "removing file");
+                    self.reboot_required |= remove_file_or_schedule(&entry.path)
+                        .inst_contextc(|| format!("failed to remove file {:?}", entry.path))?;
+                }
             } else {
                 tracing::warn!(path = ?entry.path, "cannot remove file: is missing");
             }
 
-            current += entry.len;
+            current += self.progress_weight.cost(entry.len);
 
             #[cfg(feature = "ui")]
             if self.tui.borrow().is_running() {
@@ -278,14 +865,49 @@ impl Uninstaller {
         Ok(())
     }
 
+    fn update_desktop_database(&self) {
+        #[cfg(unix)]
+        if let Some(dir) = &self.manifest.desktop_data_dir {
+            tracing::info!(?dir, "update desktop database");
+            crate::os::unix::update_desktop_database(dir);
+        }
+    }
+
+    /// Removes the shared shims directory if no other application uses it.
+    fn remove_shims_dir(&self) -> Result<(), InstallerError> {
+        #[cfg(windows)]
+        if let Some(dir) = &self.manifest.shims_dir {
+            if dir.exists() && std::fs::read_dir(dir)?.count() == 0 {
+                tracing::info!(?dir, "removing shims directory");
+
+                crate::os::windows::remove_path_env_var(
+                    self.manifest.access_scope,
+                    dir.as_os_str(),
+                )?;
+                std::fs::remove_dir(dir)?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn remove_dirs(&self) -> Result<(), InstallerError> {
+        if self.purge && !self.keep_user_data {
+            self.purge_data_dir()?;
+        }
+
         for entry in &self.manifest.dirs {
-            if !entry.preserve {
+            if self.is_user_data_dir(&entry.path) {
+                continue;
+            }
+
+            if !entry.preserve || self.purge {
                 if entry.path.exists() {
                     if std::fs::read_dir(&entry.path)?.count() == 0 {
                         tracing::info!(path = ?entry.path, "removing directory");
 
-                        std::fs::remove_dir(&entry.path).with_contextc(|_e| {
+                        let path = crate::os::extended_length_path(&entry.path);
+                        std::fs::remove_dir(path).with_contextc(|_e| {
                             format!("failed to remove directory {:?}", entry.path)
                         })?;
                     } else {
@@ -300,15 +922,174 @@ impl Uninstaller {
         Ok(())
     }
 
-    fn remove_manifest_file(&self) -> Result<(), InstallerError> {
-        tracing::info!(path = ?&self.manifest.manifest_path, "removing manifest file");
+    /// Removes the leftover files in the data directory if it belongs to
+    /// the application.
+    fn purge_data_dir(&self) -> Result<(), InstallerError> {
+        let data_dir = &self.manifest.app_paths.data;
+        let created = self
+            .manifest
+            .dirs
+            .iter()
+            .any(|entry| entry.path == *data_dir && !entry.preserve);
+        let named = data_dir
+            .file_name()
+            .is_some_and(|name| name == self.manifest.app_id.plain_id());
+
+        if data_dir.as_os_str().is_empty() || !data_dir.is_dir() || !(created || named) {
+            tracing::warn!(
+                ?data_dir,
+                "not purging data directory: not owned by application"
+            );
+            return Ok(());
+        }
+
+        tracing::info!(?data_dir, "purging data directory");
 
-        std::fs::remove_file(&self.manifest.manifest_path)?;
+        purge_dir(data_dir, self.kept_main_executable.as_deref(), self.trash)
+            .with_contextc(|_e| format!("failed to purge directory {:?}", data_dir))?;
 
         Ok(())
     }
 
-    fn remove_self(&self) -> Result<(), InstallerError> {
+    /// Returns whether the file is a data file, excluding files generated
+    /// outside of the application's directories.
+    fn is_user_data_file(&self, entry: &DiskFileEntry) -> bool {
+        entry.file_type == FileType::Data && self.is_user_data_dir(&entry.path)
+    }
+
+    fn back_up_user_data(&mut self) -> Result<(), InstallerError> {
+        if !self.backup_user_data {
+            return Ok(());
+        }
+
+        let exe_dir = self.manifest.app_paths.executable.as_path();
+        let mut dirs = self
+            .user_data_dirs()
+            .filter(|dir| {
+                dir.is_dir()
+                    && std::fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some())
+            })
+            .collect::<Vec<_>>();
+        dirs.dedup();
+
+        if dirs.is_empty() {
+            tracing::info!("no user data to back up");
+            return Ok(());
+        }
+
+        let backup_dir = match &self.backup_dir {
+            Some(dir) => dir.clone(),
+            None => backup::default_backup_dir()?,
+        };
+
+        if dirs.iter().any(|dir| backup_dir.starts_with(dir)) {
+            return Err(
+                InstallerError::new(InstallerErrorKind::InvalidInput).with_context(format!(
+                    "backup directory {:?} is inside the data directory",
+                    backup_dir
+                )),
+            );
+        }
+
+        let archive_path = backup_dir.join(backup::archive_name(
+            &self.manifest.app_id,
+            std::time::SystemTime::now(),
+        ));
+
+        tracing::info!(?archive_path, ?dirs, "backing up user data");
+
+        backup::write_archive(&archive_path, &dirs, &[exe_dir])
+            .with_contextc(|_e| format!("failed to write archive {:?}", archive_path))?;
+
+        self.backup_path = Some(archive_path);
+
+        Ok(())
+    }
+
+    fn is_user_data_dir(&self, path: &Path) -> bool {
+        let exe_dir = &self.manifest.app_paths.executable;
+
+        // The data directory contains the bin directory in single directory installs
+        if !exe_dir.as_os_str().is_empty() && path.starts_with(exe_dir) {
+            return false;
+        }
+
+        self.keep_user_data && self.user_data_dirs().any(|dir| path.starts_with(dir))
+    }
+
+    fn user_data_dirs(&self) -> impl Iterator<Item = &Path> {
+        let paths = &self.manifest.app_paths;
+
+        [&paths.data, &paths.configuration]
+            .into_iter()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(PathBuf::as_path)
+    }
+
+    /// Records where the settings and data files were kept so that they
+    /// can be found by the user and restored by installing again.
+    fn record_kept_user_data(&mut self) -> Result<(), InstallerError> {
+        if !self.keep_user_data {
+            return Ok(());
+        }
+
+        let mut dirs = self
+            .user_data_dirs()
+            .filter(|dir| dir.exists())
+            .map(|dir| DiskDirEntry {
+                path: dir.to_path_buf(),
+                // Directories not created by the installer stay preserved
+                preserve: !self
+                    .manifest
+                    .dirs
+                    .iter()
+                    .any(|entry| entry.path == dir && !entry.preserve),
+            })
+            .collect::<Vec<_>>();
+        dirs.dedup_by(|a, b| a.path == b.path);
+
+        let files = self
+            .manifest
+            .files
+            .iter()
+            .filter(|entry| self.is_user_data_file(entry) && entry.path.exists())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if dirs.is_empty() && files.is_empty() {
+            return Ok(());
+        }
+
+        for dir in &dirs {
+            tracing::info!(dir = ?dir.path, "kept user data directory");
+        }
+
+        self.kept_user_data = dirs.iter().map(|entry| entry.path.clone()).collect();
+
+        let kept = KeptUserData {
+            app_id: self.manifest.app_id.clone(),
+            dirs,
+            files,
+            ..Default::default()
+        };
+        crate::manifest::write_kept_user_data(&self.manifest.manifest_path, &kept)?;
+
+        Ok(())
+    }
+
+    fn remove_manifest_file(&self) -> Result<(), InstallerError> {
+        tracing::info!(path = ?&self.manifest.manifest_path, "removing manifest file");
+
+        match std::fs::remove_file(&self.manifest.manifest_path) {
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound && self.recovering => {
+                tracing::warn!("manifest file already removed");
+                Ok(())
+            }
+            result => Ok(result?),
+        }
+    }
+
+    fn remove_self(&mut self) -> Result<(), InstallerError> {
         if let Some(entry) = self
             .manifest
             .files
@@ -316,16 +1097,25 @@ impl Uninstaller {
             .find(|entry| entry.is_main_executable)
         {
             if entry.path.exists() {
-                let checksum = crate::os::file_checksum(&entry.path)?;
+                let checksum = entry.current_checksum()?;
 
-                if checksum.crc32c != entry.crc32c {
+                if checksum != entry.checksum() && !self.purge {
                     tracing::warn!(path = ?entry.path, "cannot remove file: is modified");
                     return Ok(());
                 }
 
+                if self.keep_main_executable {
+                    tracing::warn!(path = ?entry.path, "keeping self executable");
+                    self.kept_main_executable = Some(entry.path.clone());
+                    return Ok(());
+                }
+
                 tracing::info!(path = ?&entry.path, "removing self executable");
 
-                self_replace::self_delete_at(&entry.path)?;
+                if !delete_executable(&entry.path)? {
+                    tracing::warn!(path = ?entry.path, "cannot remove running executable");
+                    self.kept_main_executable = Some(entry.path.clone());
+                }
             } else {
                 tracing::warn!(path = ?&entry.path, "self executable not found");
             }
@@ -336,3 +1126,95 @@ impl Uninstaller {
         Ok(())
     }
 }
+
+/// Removes the contents of the directory except for the given file.
+///
+/// Symbolic links are removed instead of followed. If `trash`, files are
+/// moved to the trash instead.
+fn purge_dir(dir: &Path, keep: Option<&Path>, trash: bool) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if std::fs::symlink_metadata(&path)?.is_dir() {
+            purge_dir(&path, keep, trash)?;
+
+            if std::fs::read_dir(&path)?.next().is_none() {
+                std::fs::remove_dir(&path)?;
+            }
+        } else if Some(path.as_path()) != keep {
+            tracing::debug!(?path, trash, "removing leftover file");
+
+            if trash {
+                crate::os::move_to_trash(&path).map_err(std::io::Error::other)?;
+            } else {
+                std::fs::remove_file(&path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns `Ok` for an error when recovering from an interrupted operation
+/// because the step may have already completed.
+fn best_effort(recovering: bool, result: Result<(), InstallerError>) -> Result<(), InstallerError> {
+    match result {
+        Err(error) if recovering => {
+            tracing::warn!(?error, "ignoring error while recovering");
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Removes the file or, if it is in use on Windows, schedules it to be
+/// removed when the computer restarts.
+///
+/// Returns whether the removal was scheduled.
+fn remove_file_or_schedule(path: &Path) -> Result<bool, InstallerError> {
+    let path = &crate::os::extended_length_path(path);
+    let result = crate::os::retry_sharing_violation(|| Ok(std::fs::remove_file(path)?));
+
+    match result {
+        Ok(()) => Ok(false),
+        #[cfg(windows)]
+        Err(error) if crate::os::is_file_in_use(&error) => {
+            tracing::warn!(?path, "file in use, removing it on restart");
+
+            crate::os::windows::move_file_on_reboot(path, None).map_err(|move_error| {
+                tracing::warn!(?move_error, "could not schedule file removal");
+                error
+            })?;
+
+            Ok(true)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Deletes the executable even if it is the one currently running.
+///
+/// Returns `false` if the executable is running and cannot be deleted.
+#[cfg(feature = "self-replace")]
+fn delete_executable(path: &Path) -> Result<bool, InstallerError> {
+    self_replace::self_delete_at(path)?;
+    Ok(true)
+}
+
+/// Deletes the executable even if it is the one currently running.
+///
+/// Returns `false` if the executable is running and cannot be deleted.
+#[cfg(not(feature = "self-replace"))]
+fn delete_executable(path: &Path) -> Result<bool, InstallerError> {
+    // Unix allows unlinking a running executable, but Windows does not.
+    if cfg!(windows) {
+        let current_exe = std::env::current_exe()?.canonicalize()?;
+
+        if path.canonicalize()? == current_exe {
+            return Ok(false);
+        }
+    }
+
+    std::fs::remove_file(path)?;
+    Ok(true)
+}
@@ -0,0 +1,177 @@
+//! Archiving the user's settings and data files before uninstalling.
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{error::InstallerError, manifest::AppId};
+
+/// Returns the user's home directory where backups are stored by default.
+pub(super) fn default_backup_dir() -> Result<PathBuf, InstallerError> {
+    let name = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+
+    Ok(PathBuf::from(crate::os::env_var(name)?))
+}
+
+/// Returns a file name like `my-app-backup-20240102-030405.tar`.
+pub(super) fn archive_name(app_id: &AppId, time: SystemTime) -> String {
+    format!(
+        "{}-backup-{}.tar",
+        app_id.plain_id(),
+        format_timestamp(time)
+    )
+}
+
+/// Writes the contents of the directories to a tar archive.
+///
+/// Each directory is stored under its own name. Paths in `exclude` are
+/// skipped and symbolic links are stored instead of followed.
+pub(super) fn write_archive(
+    archive_path: &Path,
+    dirs: &[&Path],
+    exclude: &[&Path],
+) -> std::io::Result<()> {
+    let archive_dir = archive_path.parent().unwrap_or(Path::new(""));
+    std::fs::create_dir_all(archive_dir)?;
+
+    // Written beside the destination so an incomplete archive is never left
+    let temp_file = tempfile::Builder::new()
+        .prefix(".takecrate-backup")
+        .tempfile_in(archive_dir)?;
+
+    let mut builder = tar::Builder::new(temp_file.as_file());
+    builder.follow_symlinks(false);
+
+    for dir in dirs {
+        let name = PathBuf::from(dir.file_name().unwrap_or_default());
+        append_dir(&mut builder, dir, &name, exclude)?;
+    }
+
+    builder.into_inner()?.sync_all()?;
+    temp_file.persist(archive_path)?;
+
+    Ok(())
+}
+
+fn append_dir<W: std::io::Write>(
+    builder: &mut tar::Builder<W>,
+    dir: &Path,
+    name: &Path,
+    exclude: &[&Path],
+) -> std::io::Result<()> {
+    builder.append_dir(name, dir)?;
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let entry_name = name.join(entry.file_name());
+
+        if exclude.contains(&path.as_path()) {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            append_dir(builder, &path, &entry_name, exclude)?;
+        } else {
+            tracing::debug!(?path, "adding file to backup");
+            builder.append_path_with_name(&path, &entry_name)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Formats the time as `YYYYMMDD-HHMMSS` in UTC.
+fn format_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let secs_of_day = secs % 86400;
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+/// Converts days since the Unix epoch to a proleptic Gregorian date.
+///
+/// Algorithm from <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "19700101-000000");
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            "20231114-221320"
+        );
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH + Duration::from_secs(951_825_600)),
+            "20000229-120000"
+        );
+    }
+
+    #[test]
+    fn test_write_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().join("my-app");
+        std::fs::create_dir_all(data_dir.join("cache")).unwrap();
+        std::fs::create_dir_all(data_dir.join("bin")).unwrap();
+        std::fs::write(data_dir.join("settings.txt"), b"settings").unwrap();
+        std::fs::write(data_dir.join("cache").join("data.bin"), b"data").unwrap();
+        std::fs::write(data_dir.join("bin").join("my-app"), b"exe").unwrap();
+
+        let archive_path = dir.path().join("backup").join("backup.tar");
+        write_archive(&archive_path, &[&data_dir], &[&data_dir.join("bin")]).unwrap();
+
+        let mut archive = tar::Archive::new(std::fs::File::open(&archive_path).unwrap());
+        let mut names = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().into_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+
+        assert_eq!(
+            names,
+            [
+                PathBuf::from("my-app"),
+                PathBuf::from("my-app/cache"),
+                PathBuf::from("my-app/cache/data.bin"),
+                PathBuf::from("my-app/settings.txt"),
+            ]
+        );
+        assert_eq!(
+            std::fs::read_dir(archive_path.parent().unwrap())
+                .unwrap()
+                .count(),
+            1
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! Checking the integrity of an installation.
+//!
+//! See [`verify()`](crate::verify()) and [`verify_manifest()`].
+use std::path::{Path, PathBuf};
+
+use crate::{
+    error::{AddContext, InstallerError},
+    manifest::DiskManifest,
+};
+
+/// Result of checking an installation against its disk manifest.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct VerifyReport {
+    /// Path of the disk manifest that was checked.
+    pub manifest_path: PathBuf,
+    /// Items recorded in the disk manifest that no longer exist.
+    pub missing: Vec<VerifyItem>,
+    /// Items that exist but differ from when they were installed.
+    pub modified: Vec<VerifyItem>,
+    /// Files and directories in the application's own directories that
+    /// are not recorded in the disk manifest.
+    ///
+    /// Shared directories, such as `~/.local/bin`, and the data and
+    /// configuration directories are not checked since they are expected
+    /// to contain other files.
+    pub extra: Vec<PathBuf>,
+}
+
+impl VerifyReport {
+    /// Returns whether nothing is missing or modified.
+    ///
+    /// Extra files do not affect the installed application and are not
+    /// considered.
+    pub fn is_intact(&self) -> bool {
+        self.missing.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// An item recorded in the disk manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VerifyItem {
+    /// An installed file.
+    File(PathBuf),
+    /// An installed directory.
+    Dir(PathBuf),
+    /// A symbolic link to a command.
+    Symlink(PathBuf),
+    /// The directory in the search path (PATH).
+    ///
+    /// The profile is the shell or PowerShell profile that was modified,
+    /// if any.
+    SearchPath {
+        /// The directory.
+        dir: PathBuf,
+        /// The modified profile.
+        profile: Option<PathBuf>,
+    },
+    /// An App Paths registry entry by its executable filename.
+    AppPath(String),
+    /// The entry in the OS's list of installed applications.
+    UninstallEntry,
+    /// A shortcut (`.lnk`) file.
+    Shortcut(PathBuf),
+    /// A link in the app execution alias directory.
+    ExecutionAlias(PathBuf),
+}
+
+/// Checks the installation described by the disk manifest.
+///
+/// Every installed file is read and compared with the checksum recorded
+/// when installing, including the digest if its algorithm is the current
+/// [`Hasher`](crate::os::Hasher) or a built-in one.
+pub fn verify_manifest(manifest: &DiskManifest) -> Result<VerifyReport, InstallerError> {
+    let mut report = VerifyReport {
+        manifest_path: manifest.manifest_path.clone(),
+        ..Default::default()
+    };
+
+    verify_files(manifest, &mut report)?;
+    verify_dirs(manifest, &mut report)?;
+    verify_search_path(manifest, &mut report)?;
+    #[cfg(windows)]
+    verify_registry(manifest, &mut report);
+
+    tracing::info!(
+        missing = report.missing.len(),
+        modified = report.modified.len(),
+        extra = report.extra.len(),
+        "verified installation"
+    );
+
+    Ok(report)
+}
+
+fn verify_files(manifest: &DiskManifest, report: &mut VerifyReport) -> Result<(), InstallerError> {
+    for entry in &manifest.files {
+        match entry.current_checksum() {
+            Ok(checksum) => {
+                if checksum != entry.checksum() {
+                    tracing::debug!(path = ?entry.path, "file modified");
+                    report.modified.push(VerifyItem::File(entry.path.clone()));
+                }
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                tracing::debug!(path = ?entry.path, "file missing");
+                report.missing.push(VerifyItem::File(entry.path.clone()));
+            }
+            Err(error) => {
+                return Err(InstallerError::from(error)
+                    .with_context(format!("could not read file {:?}", entry.path)));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn verify_dirs(manifest: &DiskManifest, report: &mut VerifyReport) -> Result<(), InstallerError> {
+    for entry in &manifest.dirs {
+        if !entry.path.is_dir() {
+            report.missing.push(VerifyItem::Dir(entry.path.clone()));
+            continue;
+        }
+
+        if entry.preserve || is_user_data_dir(manifest, &entry.path) {
+            continue;
+        }
+
+        let dir_entries = std::fs::read_dir(&entry.path)
+            .with_contextc(|_e| format!("could not read directory {:?}", entry.path))?;
+
+        for dir_entry in dir_entries {
+            let path = dir_entry?.path();
+
+            if manifest.file_by_path(&path).is_none()
+                && !manifest.dirs.iter().any(|item| item.path == path)
+            {
+                tracing::debug!(?path, "extra file");
+                report.extra.push(path);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn is_user_data_dir(manifest: &DiskManifest, path: &Path) -> bool {
+    let paths = &manifest.app_paths;
+
+    path != paths.executable && (path == paths.data || path == paths.configuration)
+}
+
+fn verify_search_path(
+    manifest: &DiskManifest,
+    report: &mut VerifyReport,
+) -> Result<(), InstallerError> {
+    #[cfg(windows)]
+    {
+        if let Some(exe_dir) = &manifest.search_path {
+            if manifest.powershell_profile_paths.is_empty()
+                && !crate::os::windows::is_in_path_env_var(
+                    manifest.access_scope,
+                    exe_dir.as_os_str(),
+                )?
+            {
+                report.missing.push(VerifyItem::SearchPath {
+                    dir: exe_dir.clone(),
+                    profile: None,
+                });
+            }
+
+            for profile in &manifest.powershell_profile_paths {
+                if !crate::os::windows::is_in_powershell_profile(profile, exe_dir.as_os_str())? {
+                    report.missing.push(VerifyItem::SearchPath {
+                        dir: exe_dir.clone(),
+                        profile: Some(profile.clone()),
+                    });
+                }
+            }
+        }
+
+        if let Some(dir) = &manifest.shims_dir {
+            if !crate::os::windows::is_in_path_env_var(manifest.access_scope, dir.as_os_str())? {
+                report.missing.push(VerifyItem::SearchPath {
+                    dir: dir.clone(),
+                    profile: None,
+                });
+            }
+        }
+
+        for link_path in &manifest.execution_aliases {
+            match crate::os::file_checksum(link_path) {
+                Ok(checksum) => {
+                    if manifest.files_by_checksum(&checksum).is_empty() {
+                        report
+                            .modified
+                            .push(VerifyItem::ExecutionAlias(link_path.clone()));
+                    }
+                }
+                Err(_) => {
+                    report
+                        .missing
+                        .push(VerifyItem::ExecutionAlias(link_path.clone()));
+                }
+            }
+        }
+    }
+    #[cfg(unix)]
+    {
+        if let (Some(exe_dir), Some(profile)) =
+            (&manifest.search_path, &manifest.shell_profile_path)
+        {
+            if !crate::os::unix::is_in_path_env_var(exe_dir.as_os_str(), profile)? {
+                report.missing.push(VerifyItem::SearchPath {
+                    dir: exe_dir.clone(),
+                    profile: Some(profile.clone()),
+                });
+            }
+        }
+
+        for link_path in &manifest.symlinks {
+            match std::fs::read_link(link_path) {
+                Ok(target) => {
+                    if manifest.file_by_path(&target).is_none() {
+                        report.modified.push(VerifyItem::Symlink(link_path.clone()));
+                    }
+                }
+                Err(_) => {
+                    report.missing.push(VerifyItem::Symlink(link_path.clone()));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn verify_registry(manifest: &DiskManifest, report: &mut VerifyReport) {
+    let exe_names = manifest
+        .app_path_exe_name
+        .iter()
+        .chain(&manifest.command_app_path_exe_names);
+
+    for exe_name in exe_names {
+        if !crate::os::windows::has_app_path(manifest.access_scope, exe_name) {
+            report.missing.push(VerifyItem::AppPath(exe_name.clone()));
+        }
+    }
+
+    if !manifest.interactive_uninstall_args.is_empty()
+        && !crate::os::windows::has_uninstall_entry(manifest.access_scope, &manifest.app_id)
+    {
+        report.missing.push(VerifyItem::UninstallEntry);
+    }
+
+    for path in &manifest.shortcuts {
+        if !path.exists() {
+            report.missing.push(VerifyItem::Shortcut(path.clone()));
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Sandbox harness for running full installer cycles in a temporary directory.
+//!
+//! The sandbox redirects the environment variables used to locate the user's
+//! home, configuration, and program directories so installs don't touch the
+//! real user account. On Windows, `HKEY_CURRENT_USER` is also redirected to a
+//! temporary key for the process.
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use takecrate::{
+    inst::{PackageFileEntry, PackageManifest},
+    manifest::{AppId, DiskManifest, FileType},
+};
+use tempfile::TempDir;
+
+/// Environment variables are process wide, so only one sandbox may be active.
+static SANDBOX_LOCK: Mutex<()> = Mutex::new(());
+
+/// A temporary directory standing in for the user's machine.
+pub struct Sandbox {
+    root: TempDir,
+    saved_env: Vec<(&'static str, Option<OsString>)>,
+    #[cfg(windows)]
+    _registry: RegistryOverride,
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl Sandbox {
+    /// Creates the sandbox and redirects the environment into it.
+    pub fn new() -> Self {
+        let guard = SANDBOX_LOCK
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        let root = tempfile::tempdir().unwrap();
+
+        let home = root.path().join("home");
+        let local_app_data = home.join("AppData").join("Local");
+        let env = [
+            ("HOME", home.clone()),
+            ("XDG_CONFIG_HOME", home.join(".config")),
+            ("XDG_DATA_HOME", home.join(".local").join("share")),
+            ("XDG_STATE_HOME", home.join(".local").join("state")),
+            ("USERPROFILE", home.clone()),
+            ("LOCALAPPDATA", local_app_data.clone()),
+            ("LocalAppData", local_app_data),
+            ("APPDATA", home.join("AppData").join("Roaming")),
+            ("ProgramData", root.path().join("ProgramData")),
+            ("PROGRAMFILES", root.path().join("Program Files")),
+        ];
+
+        let mut saved_env = Vec::new();
+
+        for (key, value) in env {
+            std::fs::create_dir_all(&value).unwrap();
+            saved_env.push((key, std::env::var_os(key)));
+            std::env::set_var(key, value);
+        }
+
+        saved_env.push(("SHELL", std::env::var_os("SHELL")));
+        std::env::set_var("SHELL", "/bin/sh");
+
+        for key in ["XDG_BIN_HOME", "TAKECRATE_STATE_DIR"] {
+            saved_env.push((key, std::env::var_os(key)));
+            std::env::remove_var(key);
+        }
+
+        // Give the shell profile detection a deterministic target.
+        std::fs::write(home.join(".profile"), "# sandbox profile\n").unwrap();
+        std::fs::create_dir_all(root.path().join("source")).unwrap();
+
+        Self {
+            root,
+            saved_env,
+            #[cfg(windows)]
+            _registry: RegistryOverride::new(),
+            _guard: guard,
+        }
+    }
+
+    /// Returns the root directory of the sandbox.
+    pub fn path(&self) -> &Path {
+        self.root.path()
+    }
+
+    /// Returns the fake home directory.
+    pub fn home(&self) -> PathBuf {
+        self.path().join("home")
+    }
+
+    /// Returns the directory containing the package files.
+    pub fn source_dir(&self) -> PathBuf {
+        self.path().join("source")
+    }
+
+    /// Creates a package with a copy of the test binary and the given data files.
+    pub fn make_package(&self, app_id: &AppId, data_files: &[(&str, &[u8])]) -> PackageManifest {
+        let exe_name = takecrate::os::current_exe_name().unwrap();
+        std::fs::copy(
+            takecrate::os::current_exe().unwrap(),
+            self.source_dir().join(&exe_name),
+        )
+        .unwrap();
+
+        let mut manifest = PackageManifest::new(app_id);
+        manifest.files.push(
+            PackageFileEntry::new_main_exe(&exe_name, &exe_name, FileType::Executable).unwrap(),
+        );
+
+        for (name, contents) in data_files {
+            std::fs::write(self.source_dir().join(name), contents).unwrap();
+            manifest = manifest.with_file_entry(name, FileType::Data).unwrap();
+        }
+
+        manifest
+    }
+
+    /// Returns all files in the sandbox except for the package source
+    /// directory and the shell profile.
+    pub fn installed_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let mut pending = vec![self.path().to_path_buf()];
+
+        while let Some(dir) = pending.pop() {
+            for entry in std::fs::read_dir(&dir).unwrap() {
+                let path = entry.unwrap().path();
+
+                if path == self.source_dir() || path == self.home().join(".profile") {
+                    continue;
+                } else if path.is_dir() {
+                    pending.push(path);
+                } else {
+                    files.push(path);
+                }
+            }
+        }
+
+        files.sort();
+        files
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        for (key, value) in self.saved_env.drain(..) {
+            match value {
+                Some(value) => std::env::set_var(key, value),
+                None => std::env::remove_var(key),
+            }
+        }
+    }
+}
+
+/// Temporary key standing in for `HKEY_CURRENT_USER`.
+///
+/// The override applies to the whole process, so the user's real Path
+/// and uninstall entries are left alone. The key is deleted when dropped.
+#[cfg(windows)]
+struct RegistryOverride {
+    key_path: windows::core::HSTRING,
+}
+
+#[cfg(windows)]
+impl RegistryOverride {
+    const PARENT_KEY_PATH: &'static str = r"Software\takecrate-tests";
+
+    fn new() -> Self {
+        use windows::Win32::System::Registry::{
+            RegCloseKey, RegCreateKeyExW, RegOverridePredefKey, HKEY, HKEY_CURRENT_USER,
+            KEY_ALL_ACCESS, REG_OPTION_NON_VOLATILE,
+        };
+
+        let key_path = windows::core::HSTRING::from(format!(
+            r"{}\sandbox-{}",
+            Self::PARENT_KEY_PATH,
+            std::process::id()
+        ));
+        let mut hkey = HKEY::default();
+
+        // SAFETY: The key path and handle outlive the calls. The system keeps
+        // its own handle after the override, so ours can be closed.
+        unsafe {
+            RegCreateKeyExW(
+                HKEY_CURRENT_USER,
+                &key_path,
+                0,
+                None,
+                REG_OPTION_NON_VOLATILE,
+                KEY_ALL_ACCESS,
+                None,
+                &mut hkey,
+                None,
+            )
+            .ok()
+            .unwrap();
+            RegOverridePredefKey(HKEY_CURRENT_USER, hkey).ok().unwrap();
+            let _ = RegCloseKey(hkey);
+        }
+
+        Self { key_path }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for RegistryOverride {
+    fn drop(&mut self) {
+        use windows::Win32::System::Registry::{
+            RegDeleteKeyW, RegDeleteTreeW, RegOverridePredefKey, HKEY, HKEY_CURRENT_USER,
+        };
+
+        // SAFETY: The key paths outlive the calls.
+        unsafe {
+            let _ = RegOverridePredefKey(HKEY_CURRENT_USER, HKEY::default());
+            let _ = RegDeleteTreeW(HKEY_CURRENT_USER, &self.key_path);
+            // Fails if another test process is still using its key
+            let _ = RegDeleteKeyW(
+                HKEY_CURRENT_USER,
+                &windows::core::HSTRING::from(Self::PARENT_KEY_PATH),
+            );
+        }
+    }
+}
+
+/// Checks the files listed in the disk manifest exist with the recorded checksums.
+pub fn verify_installed_files(manifest: &DiskManifest) {
+    for entry in &manifest.files {
+        let checksum = takecrate::os::file_checksum(&entry.path).unwrap();
+
+        assert_eq!(checksum.len, entry.len, "{:?}", entry.path);
+        assert_eq!(checksum.crc32c, entry.crc32c, "{:?}", entry.path);
+    }
+
+    for entry in &manifest.dirs {
+        assert!(entry.path.is_dir(), "{:?}", entry.path);
+    }
+}
+
+/// Returns whether the file contains the given text.
+#[cfg(unix)]
+pub fn file_contains<P: AsRef<Path>, S: AsRef<std::ffi::OsStr>>(path: P, text: S) -> bool {
+    let contents = std::fs::read_to_string(path).unwrap_or_default();
+    contents.contains(text.as_ref().to_str().unwrap())
+}
@@ -1,3 +1,4 @@
+//! Tests installing to a directory and uninstalling it.
 use takecrate::{
     error::InstallerErrorKind,
     inst::{InstallConfig, PackageManifest},
@@ -0,0 +1,1394 @@
+//! Tests full install, verify, modify, and uninstall cycles in a sandbox.
+mod common;
+
+use common::Sandbox;
+use takecrate::{
+    error::InstallerErrorKind,
+    inst::{InstallConfig, InstallStep, Installer, SourceStaging},
+    manifest::{AppId, AppVersion, FileType, JournalOperation, ManifestIntegrity},
+    os::AccessScope,
+    path::AppPathPrefix,
+    uninst::Uninstaller,
+    verify::VerifyItem,
+};
+
+#[test_log::test]
+fn test_user_cycle() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_user_cycle").unwrap();
+    let package_manifest = sandbox
+        .make_package(
+            &app_id,
+            &[("readme.txt", b"hello"), ("settings.txt", b"defaults")],
+        )
+        .with_env_var("TAKECRATE_TESTS_SANDBOX_HOME", "{data_dir}");
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    // Install
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    assert!(disk_manifest.manifest_path.starts_with(sandbox.home()));
+    assert!(disk_manifest.manifest_path.is_file());
+    assert_eq!(disk_manifest.access_scope, AccessScope::User);
+    assert!(disk_manifest.installed_by.is_none());
+
+    // Verify
+    common::verify_installed_files(&disk_manifest);
+
+    for entry in &disk_manifest.files {
+        assert!(entry.path.starts_with(sandbox.home()), "{:?}", entry.path);
+    }
+
+    let receipt = takecrate::receipt(&app_id).unwrap();
+    let receipt_path = takecrate::manifest::receipt_path(&app_id, AccessScope::User).unwrap();
+    assert_eq!(receipt.schema_version, 1);
+    assert_eq!(receipt.app_id, app_id.namespaced_id());
+    assert_eq!(receipt.access_scope, AccessScope::User);
+    assert_eq!(receipt.manifest_path, disk_manifest.manifest_path);
+    assert_eq!(
+        receipt.executable,
+        disk_manifest.main_executable().unwrap().path
+    );
+    assert_eq!(receipt.data_dir, disk_manifest.app_paths.data);
+    assert!(receipt_path.is_file());
+
+    let search_path = disk_manifest.search_path.clone().unwrap();
+    assert_eq!(search_path, disk_manifest.app_paths.executable);
+
+    #[cfg(unix)]
+    {
+        let profile = disk_manifest.shell_profile_path.clone().unwrap();
+        assert_eq!(profile, sandbox.home().join(".profile"));
+        assert!(common::file_contains(&profile, "$HOME/.local/bin"));
+        assert!(common::file_contains(
+            &profile,
+            format!(
+                "export TAKECRATE_TESTS_SANDBOX_HOME='{}'",
+                disk_manifest.app_paths.data.display()
+            )
+        ));
+    }
+
+    // Modify: the user edits an installed file and adds their own file
+    let data_dir = &disk_manifest.app_paths.data;
+    let edited_file = disk_manifest
+        .files
+        .iter()
+        .find(|entry| entry.file_type == FileType::Data && entry.path.ends_with("settings.txt"))
+        .unwrap()
+        .path
+        .clone();
+    std::fs::write(&edited_file, b"user settings").unwrap();
+    std::fs::write(data_dir.join("notes.txt"), b"user notes").unwrap();
+
+    // Uninstall
+    takecrate::uninstall(&app_id).unwrap();
+
+    assert!(!disk_manifest.manifest_path.exists());
+    assert!(!receipt_path.exists());
+    assert!(!disk_manifest.main_executable().unwrap().path.exists());
+    assert!(!data_dir.join("readme.txt").exists());
+    assert_eq!(std::fs::read(&edited_file).unwrap(), b"user settings");
+    assert!(data_dir.join("notes.txt").is_file());
+
+    #[cfg(unix)]
+    {
+        let profile = sandbox.home().join(".profile");
+        assert!(!common::file_contains(&profile, "$HOME/.local/bin"));
+        assert!(!common::file_contains(
+            &profile,
+            "TAKECRATE_TESTS_SANDBOX_HOME"
+        ));
+    }
+
+    let remaining = sandbox.installed_files();
+    assert_eq!(
+        remaining,
+        vec![data_dir.join("notes.txt"), edited_file.clone()]
+    );
+
+    let result = takecrate::uninstall(&app_id);
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        InstallerErrorKind::NotInstalled
+    ));
+}
+
+#[test_log::test]
+fn test_user_reinstall_cycle() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_reinstall_cycle").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[("readme.txt", b"hello")]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+    config.destination = AppPathPrefix::SingleDir(sandbox.path().join("my_app"));
+    config.modify_os_search_path = false;
+
+    for _ in 0..2 {
+        takecrate::install(&package_manifest, &config).unwrap();
+
+        let disk_manifest = takecrate::manifest(&app_id).unwrap();
+        common::verify_installed_files(&disk_manifest);
+        assert!(disk_manifest.search_path.is_none());
+
+        takecrate::uninstall(&app_id).unwrap();
+        assert!(sandbox.installed_files().is_empty());
+    }
+}
+
+#[test_log::test]
+fn test_user_long_path_cycle() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_long_path_cycle").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[("readme.txt", b"hello")]);
+
+    // Longer than MAX_PATH on Windows
+    let mut prefix = sandbox.path().to_path_buf();
+    for index in 0..12 {
+        prefix.push(format!("nested_directory_{:02}_{}", index, "x".repeat(16)));
+    }
+    assert!(prefix.as_os_str().len() > 260);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+    config.destination = AppPathPrefix::SingleDir(prefix.clone());
+    config.modify_os_search_path = false;
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    common::verify_installed_files(&disk_manifest);
+    assert!(disk_manifest
+        .files
+        .iter()
+        .any(|entry| entry.path.starts_with(&prefix)));
+    // Paths are recorded in the normal form
+    assert!(!disk_manifest
+        .files
+        .iter()
+        .any(|entry| entry.path.to_string_lossy().starts_with(r"\\?\")));
+
+    takecrate::uninstall(&app_id).unwrap();
+    assert!(sandbox.installed_files().is_empty());
+}
+
+#[test_log::test]
+#[ignore = "writes to system directories; run on a disposable machine with administrator privileges"]
+fn test_system_cycle() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_system_cycle").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[("readme.txt", b"hello")]);
+
+    let mut config = InstallConfig::new_system().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    if cfg!(unix) {
+        // Modifying the system search path is not supported on Unix
+        config.destination = AppPathPrefix::CustomUnix(sandbox.path().join("usr"));
+        config.modify_os_search_path = false;
+    }
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    assert_eq!(disk_manifest.access_scope, AccessScope::System);
+    assert!(!disk_manifest
+        .installed_by
+        .as_ref()
+        .unwrap()
+        .username
+        .is_empty());
+    common::verify_installed_files(&disk_manifest);
+
+    takecrate::uninstall(&app_id).unwrap();
+
+    assert!(!disk_manifest.manifest_path.exists());
+    assert!(sandbox.installed_files().is_empty());
+}
+
+#[test_log::test]
+fn test_user_verify_cycle() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_verify_cycle").unwrap();
+    let package_manifest = sandbox.make_package(
+        &app_id,
+        &[("readme.txt", b"hello"), ("settings.txt", b"defaults")],
+    );
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+    config.destination = AppPathPrefix::SingleDir(sandbox.path().join("my_app"));
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let report = takecrate::verify(&app_id).unwrap();
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    assert!(report.is_intact(), "{:?}", report);
+    assert!(report.extra.is_empty(), "{:?}", report);
+    assert_eq!(report.manifest_path, disk_manifest.manifest_path);
+
+    // Corrupt the installation
+    let exe_dir = &disk_manifest.app_paths.executable;
+    let data_dir = &disk_manifest.app_paths.data;
+    std::fs::write(data_dir.join("settings.txt"), b"DEFAULTS").unwrap();
+    std::fs::remove_file(data_dir.join("readme.txt")).unwrap();
+    std::fs::write(data_dir.join("notes.txt"), b"user notes").unwrap();
+    std::fs::write(exe_dir.join("stray.txt"), b"stray").unwrap();
+
+    #[cfg(unix)]
+    {
+        let profile = disk_manifest.shell_profile_path.clone().unwrap();
+        std::fs::write(&profile, "").unwrap();
+    }
+
+    let report = takecrate::verify(&app_id).unwrap();
+    assert!(!report.is_intact());
+    assert!(report
+        .missing
+        .contains(&VerifyItem::File(data_dir.join("readme.txt"))));
+    assert_eq!(
+        report.modified,
+        [VerifyItem::File(data_dir.join("settings.txt"))]
+    );
+    assert_eq!(report.extra, [exe_dir.join("stray.txt")]);
+
+    #[cfg(unix)]
+    assert!(report.missing.contains(&VerifyItem::SearchPath {
+        dir: exe_dir.clone(),
+        profile: disk_manifest.shell_profile_path.clone(),
+    }));
+
+    takecrate::uninstall(&app_id).unwrap();
+}
+
+#[test_log::test]
+fn test_user_repair_cycle() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_repair_cycle").unwrap();
+    let package_manifest = sandbox.make_package(
+        &app_id,
+        &[("readme.txt", b"hello"), ("settings.txt", b"defaults")],
+    );
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+    config.destination = AppPathPrefix::SingleDir(sandbox.path().join("my_app"));
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    let exe_path = disk_manifest.main_executable().unwrap().path.clone();
+    let data_dir = &disk_manifest.app_paths.data;
+
+    // Damage the installation
+    std::fs::write(&exe_path, b"corrupted").unwrap();
+    std::fs::remove_file(data_dir.join("readme.txt")).unwrap();
+    std::fs::write(data_dir.join("settings.txt"), b"user settings").unwrap();
+
+    #[cfg(unix)]
+    {
+        let profile = disk_manifest.shell_profile_path.clone().unwrap();
+        std::fs::write(&profile, "").unwrap();
+    }
+
+    let report = Installer::new(&package_manifest)
+        .repair(&sandbox.source_dir())
+        .unwrap();
+
+    // The user's edits are kept
+    assert!(report.missing.is_empty(), "{:?}", report);
+    assert_eq!(
+        report.modified,
+        [VerifyItem::File(data_dir.join("settings.txt"))]
+    );
+    assert_eq!(
+        std::fs::read(data_dir.join("readme.txt")).unwrap(),
+        b"hello"
+    );
+    assert_eq!(
+        std::fs::read(data_dir.join("settings.txt")).unwrap(),
+        b"user settings"
+    );
+    assert_eq!(
+        std::fs::read(&exe_path).unwrap(),
+        std::fs::read(
+            sandbox
+                .source_dir()
+                .join(takecrate::os::current_exe_name().unwrap())
+        )
+        .unwrap()
+    );
+
+    #[cfg(unix)]
+    assert!(common::file_contains(
+        disk_manifest.shell_profile_path.as_ref().unwrap(),
+        "my_app"
+    ));
+
+    takecrate::uninstall(&app_id).unwrap();
+}
+
+#[test_log::test]
+fn test_list_installed() {
+    let sandbox = Sandbox::new();
+    let app_ids = [
+        AppId::new("takecrate.tests.takecrate_tests_sandbox_list_b").unwrap(),
+        AppId::new("takecrate.tests.takecrate_tests_sandbox_list_a").unwrap(),
+    ];
+
+    assert!(!takecrate::is_installed(&app_ids[0]).unwrap());
+    assert!(takecrate::installed_version(&app_ids[0]).unwrap().is_none());
+
+    for (index, app_id) in app_ids.iter().enumerate() {
+        let mut package_manifest = sandbox.make_package(app_id, &[]);
+        package_manifest.app_metadata.display_version = format!("1.{}", index);
+
+        let mut config = InstallConfig::new_user().unwrap();
+        config.source_dir = sandbox.source_dir();
+        config.destination = AppPathPrefix::SingleDir(sandbox.path().join(app_id.plain_id()));
+        config.modify_os_search_path = false;
+
+        takecrate::install(&package_manifest, &config).unwrap();
+    }
+
+    let manifest_path = takecrate::manifest::manifest_path(&app_ids[0], AccessScope::User).unwrap();
+
+    let installed = takecrate::list_installed().unwrap();
+    let user_apps = installed
+        .iter()
+        .filter(|app| app.access_scope == AccessScope::User)
+        .map(|app| (app.app_id.namespaced_id(), app.app_version.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        user_apps,
+        [
+            ("takecrate.tests.takecrate_tests_sandbox_list_a", "1.1"),
+            ("takecrate.tests.takecrate_tests_sandbox_list_b", "1.0"),
+        ]
+    );
+    assert_eq!(installed[0].manifest_path.parent(), manifest_path.parent());
+
+    assert!(takecrate::is_installed(&app_ids[0]).unwrap());
+    let installed_app = takecrate::installed_version(&app_ids[0]).unwrap().unwrap();
+    assert_eq!(installed_app.access_scope, AccessScope::User);
+    assert_eq!(installed_app.app_version, "1.0");
+
+    for app_id in &app_ids {
+        takecrate::uninstall(app_id).unwrap();
+    }
+
+    assert!(!takecrate::is_installed(&app_ids[0]).unwrap());
+    assert!(takecrate::list_installed()
+        .unwrap()
+        .iter()
+        .all(|app| app.access_scope != AccessScope::User));
+}
+
+#[test_log::test]
+fn test_user_purge_cycle() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_purge_cycle").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[("settings.txt", b"defaults")]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    // The user edits an installed file and the application writes its data
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    let data_dir = &disk_manifest.app_paths.data;
+    std::fs::write(data_dir.join("settings.txt"), b"user settings").unwrap();
+    std::fs::create_dir(data_dir.join("cache")).unwrap();
+    std::fs::write(data_dir.join("cache").join("data.bin"), b"cached").unwrap();
+
+    Uninstaller::new(&app_id).with_purge(true).run().unwrap();
+
+    assert!(!data_dir.exists());
+    assert!(sandbox.installed_files().is_empty());
+}
+
+#[test_log::test]
+fn test_user_backup_cycle() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_backup_cycle").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[("settings.txt", b"defaults")]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    let data_dir = &disk_manifest.app_paths.data;
+    std::fs::write(data_dir.join("settings.txt"), b"user settings").unwrap();
+
+    let backup_dir = sandbox.path().join("backups");
+    let mut uninstaller = Uninstaller::new(&app_id)
+        .with_purge(true)
+        .with_backup_user_data(true)
+        .with_backup_dir(&backup_dir);
+    uninstaller.run().unwrap();
+
+    let backup_path = uninstaller.backup_path().unwrap();
+    assert!(backup_path.starts_with(&backup_dir));
+    assert!(!data_dir.exists());
+    assert_eq!(sandbox.installed_files(), [backup_path]);
+}
+
+#[cfg(unix)]
+#[test_log::test]
+fn test_user_trash_cycle() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_trash_cycle").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[("settings.txt", b"defaults")]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    let data_dir = &disk_manifest.app_paths.data;
+    std::fs::write(data_dir.join("notes.txt"), b"user notes").unwrap();
+
+    Uninstaller::new(&app_id)
+        .with_purge(true)
+        .with_trash(true)
+        .run()
+        .unwrap();
+
+    assert!(!data_dir.exists());
+
+    let trash_files_dir = if cfg!(target_os = "macos") {
+        sandbox.home().join(".Trash")
+    } else {
+        sandbox.home().join(".local/share/Trash/files")
+    };
+    assert_eq!(
+        std::fs::read(trash_files_dir.join("settings.txt")).unwrap(),
+        b"defaults"
+    );
+    assert_eq!(
+        std::fs::read(trash_files_dir.join("notes.txt")).unwrap(),
+        b"user notes"
+    );
+
+    for path in sandbox.installed_files() {
+        assert!(
+            path.starts_with(sandbox.home().join(".Trash"))
+                || path.starts_with(sandbox.home().join(".local/share/Trash")),
+            "{:?}",
+            path
+        );
+    }
+}
+
+#[test_log::test]
+fn test_user_keep_user_data_cycle() {
+    let sandbox = Sandbox::new();
+    let app_id =
+        AppId::new("takecrate.tests.takecrate_tests_sandbox_keep_user_data_cycle").unwrap();
+    let package_manifest = sandbox.make_package(
+        &app_id,
+        &[("settings.txt", b"defaults"), ("notes.txt", b"notes")],
+    );
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    let data_dir = disk_manifest.app_paths.data.clone();
+    let exe_path = disk_manifest.main_executable().unwrap().path.clone();
+    std::fs::write(data_dir.join("settings.txt"), b"user settings").unwrap();
+
+    let mut uninstaller = Uninstaller::new(&app_id).with_keep_user_data(true);
+    uninstaller.run().unwrap();
+
+    assert_eq!(uninstaller.kept_user_data(), [data_dir.as_path()]);
+    assert!(!exe_path.exists());
+    assert!(!disk_manifest.manifest_path.exists());
+    assert!(data_dir.join("notes.txt").exists());
+
+    let kept = takecrate::manifest::kept_user_data(&app_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(kept.dirs[0].path, data_dir);
+    assert_eq!(kept.files.len(), 2);
+
+    // Installing again keeps the user's modified file
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    assert!(exe_path.exists());
+    assert_eq!(
+        std::fs::read(data_dir.join("settings.txt")).unwrap(),
+        b"user settings"
+    );
+    assert!(takecrate::manifest::kept_user_data(&app_id)
+        .unwrap()
+        .is_none());
+
+    takecrate::uninstall(&app_id).unwrap();
+
+    assert_eq!(sandbox.installed_files(), [data_dir.join("settings.txt")]);
+}
+
+#[test_log::test]
+fn test_user_overwrite_main_executable_cycle() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_overwrite_cycle").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+    config.destination = AppPathPrefix::SingleDir(sandbox.path().join("my_app"));
+    config.modify_os_search_path = false;
+    config.overwrite_main_executable = true;
+
+    takecrate::install(&package_manifest, &config).unwrap();
+    let old_manifest = takecrate::manifest(&app_id).unwrap();
+
+    // Simulate a new version of the binary
+    let exe_name = takecrate::os::current_exe_name().unwrap();
+    let mut contents = std::fs::read(sandbox.source_dir().join(&exe_name)).unwrap();
+    contents.extend_from_slice(b"new version");
+    std::fs::write(sandbox.source_dir().join(&exe_name), contents).unwrap();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    common::verify_installed_files(&disk_manifest);
+    assert_ne!(
+        old_manifest.main_executable().unwrap().crc32c,
+        disk_manifest.main_executable().unwrap().crc32c
+    );
+
+    let exe_path = &disk_manifest.main_executable().unwrap().path;
+    let mut uninstaller = Uninstaller::new(&app_id).with_keep_main_executable(true);
+    uninstaller.run().unwrap();
+
+    assert_eq!(uninstaller.kept_main_executable(), Some(exe_path.as_path()));
+    assert!(exe_path.is_file());
+    assert!(!disk_manifest.manifest_path.exists());
+}
+
+#[cfg(unix)]
+#[test_log::test]
+fn test_user_nushell_cycle() {
+    let sandbox = Sandbox::new();
+    std::env::set_var("SHELL", "/usr/bin/nu");
+
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_nushell_cycle").unwrap();
+    let package_manifest = sandbox
+        .make_package(&app_id, &[])
+        .with_env_var("TAKECRATE_TESTS_SANDBOX_NU", "{data_dir}");
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    let profile = disk_manifest.shell_profile_path.clone().unwrap();
+    let bin_dir = disk_manifest.app_paths.executable.display().to_string();
+    assert_eq!(
+        profile,
+        sandbox
+            .home()
+            .join(".config")
+            .join("nushell")
+            .join("env.nu")
+    );
+    assert!(common::file_contains(
+        &profile,
+        format!("prepend '{}'", bin_dir)
+    ));
+    assert!(common::file_contains(
+        &profile,
+        "$env.TAKECRATE_TESTS_SANDBOX_NU = r#'"
+    ));
+    assert!(!common::file_contains(
+        sandbox.home().join(".profile"),
+        &bin_dir
+    ));
+
+    takecrate::uninstall(&app_id).unwrap();
+
+    assert!(!common::file_contains(&profile, &bin_dir));
+    assert!(!common::file_contains(
+        &profile,
+        "TAKECRATE_TESTS_SANDBOX_NU"
+    ));
+}
+
+#[cfg(unix)]
+#[test_log::test]
+fn test_user_symlink_cycle() {
+    use takecrate::inst::SearchPathStrategy;
+
+    let sandbox = Sandbox::new();
+
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_symlink_cycle").unwrap();
+    std::fs::write(sandbox.source_dir().join("my-tool"), b"#!/bin/sh\n").unwrap();
+    let package_manifest = sandbox
+        .make_package(&app_id, &[])
+        .with_command_entry("my-tool")
+        .unwrap();
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+    config.destination = AppPathPrefix::SingleDir(sandbox.path().join("opt").join("app"));
+    config.search_path_strategy = SearchPathStrategy::Symlink;
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    let exe_path = disk_manifest.main_executable().unwrap().path.clone();
+    let link_dir = sandbox.home().join(".local").join("bin");
+    assert!(disk_manifest.search_path.is_none());
+    assert_eq!(disk_manifest.symlinks.len(), 2);
+    assert_eq!(
+        std::fs::read_link(link_dir.join(exe_path.file_name().unwrap())).unwrap(),
+        exe_path
+    );
+    assert_eq!(
+        std::fs::read_link(link_dir.join("my-tool")).unwrap(),
+        exe_path.with_file_name("my-tool")
+    );
+
+    takecrate::uninstall(&app_id).unwrap();
+
+    for link_path in &disk_manifest.symlinks {
+        assert!(std::fs::symlink_metadata(link_path).is_err());
+    }
+}
+
+#[cfg(unix)]
+#[test_log::test]
+fn test_user_alias_cycle() {
+    let sandbox = Sandbox::new();
+
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_alias_cycle").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[]).with_alias("ma");
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    let exe_path = disk_manifest.main_executable().unwrap().path.clone();
+    let link_path = exe_path.with_file_name("ma");
+    assert_eq!(disk_manifest.symlinks, vec![link_path.clone()]);
+    assert_eq!(std::fs::read_link(&link_path).unwrap(), exe_path);
+
+    takecrate::uninstall(&app_id).unwrap();
+
+    assert!(std::fs::symlink_metadata(&link_path).is_err());
+}
+
+#[test]
+fn test_invalid_alias() {
+    let sandbox = Sandbox::new();
+
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_invalid_alias").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[]);
+
+    assert!(package_manifest
+        .clone()
+        .with_alias("ma")
+        .verify(sandbox.source_dir())
+        .is_ok());
+    assert!(package_manifest
+        .clone()
+        .with_alias("../ma")
+        .verify(sandbox.source_dir())
+        .is_err());
+    assert!(package_manifest
+        .clone()
+        .with_alias("")
+        .verify(sandbox.source_dir())
+        .is_err());
+    assert!(package_manifest
+        .with_alias("ma")
+        .with_alias("ma")
+        .verify(sandbox.source_dir())
+        .is_err());
+}
+
+#[cfg(unix)]
+#[test_log::test]
+fn test_user_hardlink_cycle() {
+    use std::os::unix::fs::MetadataExt;
+
+    let sandbox = Sandbox::new();
+
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_hardlink_cycle").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[("readme.txt", b"hello")]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+    config.hardlink_files = true;
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    common::verify_installed_files(&disk_manifest);
+
+    let source_path = sandbox.source_dir().join("readme.txt");
+    let installed_path = disk_manifest.app_paths.data.join("readme.txt");
+    assert_eq!(
+        std::fs::metadata(&source_path).unwrap().ino(),
+        std::fs::metadata(&installed_path).unwrap().ino()
+    );
+
+    takecrate::uninstall(&app_id).unwrap();
+
+    assert!(!installed_path.exists());
+    assert!(source_path.exists());
+}
+
+#[test_log::test]
+fn test_user_refresh_os_metadata() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_refresh").unwrap();
+    let mut package_manifest = sandbox.make_package(&app_id, &[("readme.txt", b"hello")]);
+    package_manifest.app_metadata.display_version = "1.0.0".to_string();
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    // Simulate an in-place update while the user edited a data file
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    let exe_path = disk_manifest.main_executable().unwrap().path.clone();
+    let mut exe_contents = std::fs::read(&exe_path).unwrap();
+    exe_contents.extend_from_slice(b"updated");
+    std::fs::write(&exe_path, exe_contents).unwrap();
+    let readme_path = disk_manifest.app_paths.data.join("readme.txt");
+    std::fs::write(&readme_path, b"hello world").unwrap();
+    package_manifest.app_metadata.display_version = "1.1.0".to_string();
+
+    takecrate::refresh_os_metadata(&package_manifest).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    assert_eq!(disk_manifest.app_version, "1.1.0");
+    assert_eq!(takecrate::receipt(&app_id).unwrap().app_version, "1.1.0");
+
+    for entry in &disk_manifest.files {
+        let checksum = takecrate::os::file_checksum(&entry.path).unwrap();
+        assert_eq!(
+            checksum.crc32c == entry.crc32c,
+            entry.path != readme_path,
+            "{:?}",
+            entry.path
+        );
+    }
+
+    // The modified data file is kept
+    takecrate::uninstall(&app_id).unwrap();
+    assert!(!exe_path.exists());
+    assert!(readme_path.exists());
+}
+
+/// Writes a journal as if the operation was interrupted.
+fn write_stale_journal(disk_manifest: &takecrate::manifest::DiskManifest, operation: &str) {
+    use hmac::{Hmac, Mac};
+
+    let mut manifest = Vec::new();
+    disk_manifest.to_writer(&mut manifest).unwrap();
+    let contents = format!(
+        "(operation: {}, manifest_path: {:?}, manifest: {})",
+        operation,
+        disk_manifest.manifest_path,
+        String::from_utf8(manifest).unwrap()
+    );
+    let mut mac =
+        Hmac::<sha2::Sha256>::new_from_slice(disk_manifest.app_id.uuid().as_bytes()).unwrap();
+    mac.update(contents.as_bytes());
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let contents = format!("{}\n// integrity: {}\n", contents, digest);
+    let path = disk_manifest.manifest_path.with_file_name(format!(
+        "takecrate-journal__{}.ron",
+        disk_manifest.app_id.namespaced_id()
+    ));
+    std::fs::write(path, contents).unwrap();
+}
+
+#[test_log::test]
+fn test_user_interrupted_install() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_interrupted_install").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[("readme.txt", b"hello")]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    // Resume
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    let readme_path = disk_manifest.app_paths.data.join("readme.txt");
+    write_stale_journal(&disk_manifest, "Install");
+    std::fs::remove_file(&readme_path).unwrap();
+
+    let operation = takecrate::manifest::interrupted_operation(&app_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(operation.operation, JournalOperation::Install);
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    assert!(takecrate::manifest::interrupted_operation(&app_id)
+        .unwrap()
+        .is_none());
+    common::verify_installed_files(&takecrate::manifest(&app_id).unwrap());
+
+    // Roll back
+    write_stale_journal(&disk_manifest, "Install");
+    std::fs::remove_file(&readme_path).unwrap();
+
+    Installer::new(&package_manifest)
+        .roll_back_interrupted()
+        .unwrap();
+
+    assert!(sandbox.installed_files().is_empty());
+    assert!(takecrate::manifest::interrupted_operation(&app_id)
+        .unwrap()
+        .is_none());
+}
+
+#[test_log::test]
+fn test_user_interrupted_uninstall() {
+    let sandbox = Sandbox::new();
+    let app_id =
+        AppId::new("takecrate.tests.takecrate_tests_sandbox_interrupted_uninstall").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[("readme.txt", b"hello")]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    // Interrupted after the disk manifest was removed
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    write_stale_journal(&disk_manifest, "Uninstall");
+    std::fs::remove_file(&disk_manifest.manifest_path).unwrap();
+
+    let operation = takecrate::manifest::interrupted_operation(&app_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(operation.operation, JournalOperation::Uninstall);
+
+    takecrate::uninstall(&app_id).unwrap();
+    assert!(sandbox.installed_files().is_empty());
+
+    // Interrupted again, but installing completes the uninstallation first
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    write_stale_journal(&disk_manifest, "Uninstall");
+    std::fs::remove_file(&disk_manifest.manifest_path).unwrap();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+    common::verify_installed_files(&takecrate::manifest(&app_id).unwrap());
+
+    takecrate::uninstall(&app_id).unwrap();
+    assert!(sandbox.installed_files().is_empty());
+}
+
+/// Returns whether the key exists in the 64-bit or 32-bit registry view.
+#[cfg(windows)]
+fn registry_key_exists(path: &str, view_64: bool) -> bool {
+    use windows::{
+        core::HSTRING,
+        Win32::System::Registry::{
+            RegCloseKey, RegOpenKeyExW, HKEY, HKEY_LOCAL_MACHINE, KEY_READ, KEY_WOW64_32KEY,
+            KEY_WOW64_64KEY,
+        },
+    };
+
+    let view = if view_64 {
+        KEY_WOW64_64KEY
+    } else {
+        KEY_WOW64_32KEY
+    };
+    let path = HSTRING::from(path);
+    let mut handle = HKEY::default();
+
+    // SAFETY: The path outlives the call and the handle is closed.
+    unsafe {
+        let result = RegOpenKeyExW(HKEY_LOCAL_MACHINE, &path, 0, KEY_READ | view, &mut handle);
+
+        if result.is_ok() {
+            let _ = RegCloseKey(handle);
+        }
+
+        result.is_ok()
+    }
+}
+
+/// Checks the entries are in the 64-bit registry view for any build
+/// target, such as x86 and ARM64.
+#[cfg(windows)]
+#[test_log::test]
+#[ignore = "writes to system directories; run on a disposable machine with administrator privileges"]
+fn test_system_registry_view() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_registry_view").unwrap();
+    let package_manifest = sandbox
+        .make_package(&app_id, &[])
+        .with_interactive_uninstall_args(&["uninstall"]);
+
+    let mut config = InstallConfig::new_system().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    let exe_name = disk_manifest.app_path_exe_name.clone().unwrap();
+    let app_path_key = format!(
+        r"SOFTWARE\Microsoft\Windows\CurrentVersion\App Paths\{}",
+        exe_name
+    );
+    let uninstall_key = format!(
+        r"Software\Microsoft\Windows\CurrentVersion\Uninstall\{}",
+        app_id.uuid()
+    );
+    // Whether the OS has a separate 32-bit view
+    let is_64_bit_os =
+        cfg!(target_pointer_width = "64") || std::env::var_os("PROCESSOR_ARCHITEW6432").is_some();
+
+    for key in [&app_path_key, &uninstall_key] {
+        assert!(registry_key_exists(key, true), "{}", key);
+
+        if is_64_bit_os {
+            assert!(!registry_key_exists(key, false), "{}", key);
+        }
+    }
+
+    takecrate::uninstall(&app_id).unwrap();
+
+    for key in [&app_path_key, &uninstall_key] {
+        assert!(!registry_key_exists(key, true), "{}", key);
+    }
+}
+
+#[test_log::test]
+fn test_user_staged_install() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_staged").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[("readme.txt", b"hello")]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+    config.source_staging = SourceStaging::Always;
+    config.hardlink_files = true;
+
+    let mut installer = Installer::new(&package_manifest);
+    installer.run(&config).unwrap();
+
+    let report = installer.report().unwrap();
+    assert!(report.step_duration(InstallStep::Stage).is_some());
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    common::verify_installed_files(&disk_manifest);
+
+    // Installed from the staged copies which are removed afterwards
+    let readme_path = disk_manifest.app_paths.data.join("readme.txt");
+    assert_eq!(std::fs::read(&readme_path).unwrap(), b"hello");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        assert_eq!(readme_path.metadata().unwrap().nlink(), 1);
+    }
+
+    takecrate::uninstall(&app_id).unwrap();
+}
+
+#[test_log::test]
+fn test_user_uninstall_with_scope() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_uninstall_scope").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest_in_scope(&app_id, AccessScope::User).unwrap();
+    assert_eq!(disk_manifest.access_scope, AccessScope::User);
+    let result = takecrate::manifest_in_scope(&app_id, AccessScope::System);
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        InstallerErrorKind::DiskManifestNotFound
+    ));
+    let manifests = takecrate::manifests(&app_id).unwrap();
+    assert_eq!(manifests.len(), 1);
+    assert_eq!(manifests[0].manifest_path, disk_manifest.manifest_path);
+
+    let result = takecrate::uninstall_with_scope(&app_id, AccessScope::System);
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        InstallerErrorKind::NotInstalled
+    ));
+    assert!(takecrate::is_installed(&app_id).unwrap());
+
+    takecrate::uninstall_with_scope(&app_id, AccessScope::User).unwrap();
+    assert!(!takecrate::is_installed(&app_id).unwrap());
+}
+
+#[test_log::test]
+fn test_user_architecture_downgrade() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_arch_downgrade").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    // Installed as a universal binary
+    let mut disk_manifest = takecrate::manifest(&app_id).unwrap();
+    disk_manifest.main_executable_architectures = vec!["x86_64".to_string(), "arm64".to_string()];
+    let mut contents = Vec::new();
+    disk_manifest.to_writer(&mut contents).unwrap();
+    std::fs::write(&disk_manifest.manifest_path, contents).unwrap();
+
+    let result = takecrate::install(&package_manifest, &config);
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        InstallerErrorKind::ArchitectureDowngrade
+    ));
+    assert_eq!(
+        takecrate::manifest(&app_id)
+            .unwrap()
+            .main_executable_architectures,
+        ["x86_64", "arm64"]
+    );
+
+    config.allow_architecture_downgrade = true;
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    takecrate::uninstall(&app_id).unwrap();
+}
+
+#[test_log::test]
+fn test_user_uninstall_feedback_hook() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_feedback").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let exe_path = takecrate::manifest(&app_id)
+        .unwrap()
+        .main_executable()
+        .unwrap()
+        .path
+        .clone();
+    let calls = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let hook_calls = calls.clone();
+
+    Uninstaller::new(&app_id)
+        .with_feedback_hook(move |feedback| {
+            // Called after the files are removed
+            hook_calls.borrow_mut().push((
+                feedback.manifest.app_id.namespaced_id().to_string(),
+                feedback.interactive,
+                feedback.manifest.main_executable().unwrap().path.exists(),
+            ));
+            Some("feedback".to_string())
+        })
+        .run()
+        .unwrap();
+
+    assert!(!exe_path.exists());
+    assert_eq!(
+        *calls.borrow(),
+        [(app_id.namespaced_id().to_string(), false, false)]
+    );
+}
+
+#[test_log::test]
+fn test_user_upgrade() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_upgrade").unwrap();
+    let mut package_manifest = sandbox.make_package(
+        &app_id,
+        &[("readme.txt", b"version 1"), ("settings.txt", b"defaults")],
+    );
+    package_manifest.app_metadata.display_version = "1.0".to_string();
+
+    let prefix = sandbox.path().join("custom");
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+    config.destination = AppPathPrefix::SingleDir(prefix.clone());
+    config.modify_os_search_path = false;
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let data_dir = takecrate::manifest(&app_id).unwrap().app_paths.data;
+    std::fs::write(data_dir.join("settings.txt"), b"user settings").unwrap();
+
+    let mut package_manifest = sandbox.make_package(
+        &app_id,
+        &[("readme.txt", b"version 2"), ("settings.txt", b"defaults")],
+    );
+    package_manifest.app_metadata.display_version = "2.0".to_string();
+
+    Installer::new(&package_manifest)
+        .upgrade(&sandbox.source_dir())
+        .unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    assert_eq!(disk_manifest.app_version, "2.0");
+    assert_eq!(disk_manifest.access_scope, AccessScope::User);
+    assert_eq!(
+        disk_manifest.app_paths.prefix,
+        AppPathPrefix::SingleDir(prefix)
+    );
+    assert!(disk_manifest.search_path.is_none());
+    assert_eq!(
+        std::fs::read(data_dir.join("readme.txt")).unwrap(),
+        b"version 2"
+    );
+    assert_eq!(
+        std::fs::read(data_dir.join("settings.txt")).unwrap(),
+        b"user settings"
+    );
+
+    takecrate::uninstall(&app_id).unwrap();
+
+    let result = Installer::new(&package_manifest).upgrade(&sandbox.source_dir());
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        InstallerErrorKind::NotInstalled
+    ));
+}
+
+#[test_log::test]
+fn test_user_downgrade() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_downgrade").unwrap();
+    let mut package_manifest = sandbox.make_package(&app_id, &[]);
+    package_manifest.app_metadata.display_version = "2.0 (build 5)".to_string();
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    assert_eq!(disk_manifest.version, AppVersion::parse("2.0"));
+
+    package_manifest.app_metadata.display_version = "1.9.1".to_string();
+
+    let result = takecrate::install(&package_manifest, &config);
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        InstallerErrorKind::Downgrade
+    ));
+    assert_eq!(
+        takecrate::manifest(&app_id).unwrap().version,
+        disk_manifest.version
+    );
+
+    config.allow_downgrade = true;
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    assert_eq!(disk_manifest.app_version, "1.9.1");
+    assert_eq!(disk_manifest.version, AppVersion::new(&[1, 9, 1]));
+
+    takecrate::uninstall(&app_id).unwrap();
+}
+
+#[test_log::test]
+fn test_user_tampered_manifest() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_tampered").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[("readme.txt", b"hello")]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    assert_eq!(disk_manifest.integrity, ManifestIntegrity::Valid);
+
+    // Point an entry at a file that isn't part of the installation
+    let victim_path = sandbox.home().join("important.txt");
+    std::fs::write(&victim_path, b"hello").unwrap();
+    let readme_path = disk_manifest
+        .files
+        .iter()
+        .find(|entry| entry.path.ends_with("readme.txt"))
+        .unwrap()
+        .path
+        .clone();
+    let contents = std::fs::read_to_string(&disk_manifest.manifest_path)
+        .unwrap()
+        .replace(&format!("{:?}", readme_path), &format!("{:?}", victim_path));
+    std::fs::write(&disk_manifest.manifest_path, contents).unwrap();
+
+    let result = takecrate::uninstall(&app_id);
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        InstallerErrorKind::TamperedDiskManifest
+    ));
+    assert!(victim_path.exists());
+    assert!(readme_path.exists());
+
+    let result = Installer::new(&package_manifest).repair(&sandbox.source_dir());
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        InstallerErrorKind::TamperedDiskManifest
+    ));
+
+    let result = takecrate::refresh_os_metadata(&package_manifest);
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        InstallerErrorKind::TamperedDiskManifest
+    ));
+    assert_eq!(std::fs::read(&victim_path).unwrap(), b"hello");
+
+    // Rewriting the manifest with this crate is trusted again
+    let mut disk_manifest = takecrate::manifest(&app_id).unwrap();
+    assert_eq!(disk_manifest.integrity, ManifestIntegrity::Invalid);
+    disk_manifest
+        .files
+        .iter_mut()
+        .find(|entry| entry.path == victim_path)
+        .unwrap()
+        .path = readme_path;
+    let mut contents = Vec::new();
+    disk_manifest.to_writer(&mut contents).unwrap();
+    std::fs::write(&disk_manifest.manifest_path, contents).unwrap();
+
+    takecrate::uninstall(&app_id).unwrap();
+    assert!(victim_path.exists());
+}
+
+#[test_log::test]
+fn test_user_provenance() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_provenance").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+    config.modify_os_search_path = false;
+    config.desktop_shortcut = true;
+
+    let start = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let disk_manifest = takecrate::manifest(&app_id).unwrap();
+    assert!(disk_manifest.installed_at.unwrap() >= start);
+    assert_eq!(disk_manifest.installer_version, env!("CARGO_PKG_VERSION"));
+
+    let options = disk_manifest.install_options.as_ref().unwrap();
+    assert!(!options.modify_os_search_path);
+    assert!(options.desktop_shortcut);
+
+    let reused_config = InstallConfig::from_manifest(&disk_manifest).unwrap();
+    assert!(!reused_config.modify_os_search_path);
+    assert!(reused_config.desktop_shortcut);
+
+    takecrate::uninstall(&app_id).unwrap();
+}
+
+#[test_log::test]
+fn test_user_state_dir() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_state_dir").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[("readme.txt", b"hello")]);
+    let state_dir = sandbox.path().join("state");
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+    config.state_dir = Some(state_dir.clone());
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let manifest_path = state_dir
+        .join("user")
+        .join(takecrate::manifest::manifest_file_name(&app_id));
+    assert!(manifest_path.is_file());
+    assert!(matches!(
+        takecrate::manifest(&app_id).unwrap_err().kind(),
+        InstallerErrorKind::DiskManifestNotFound
+    ));
+
+    let disk_manifest = takecrate::manifest::DiskManifest::load(&manifest_path).unwrap();
+    let reused_config = InstallConfig::from_manifest(&disk_manifest).unwrap();
+    assert_eq!(reused_config.state_dir.as_ref(), Some(&state_dir));
+
+    Uninstaller::new(&app_id)
+        .with_state_dir(&state_dir)
+        .run()
+        .unwrap();
+
+    assert!(!manifest_path.exists());
+    assert!(sandbox.installed_files().is_empty());
+}
+
+#[cfg(unix)]
+#[test_log::test]
+fn test_user_xdg_state_home() {
+    let sandbox = Sandbox::new();
+    let app_id = AppId::new("takecrate.tests.takecrate_tests_sandbox_xdg_state").unwrap();
+    let package_manifest = sandbox.make_package(&app_id, &[]);
+
+    let mut config = InstallConfig::new_user().unwrap();
+    config.source_dir = sandbox.source_dir();
+
+    takecrate::install(&package_manifest, &config).unwrap();
+
+    let manifest_name = takecrate::manifest::manifest_file_name(&app_id);
+    let manifest_path = sandbox
+        .home()
+        .join(".local/state/io.crates.takecrate")
+        .join(&manifest_name);
+    assert_eq!(
+        takecrate::manifest(&app_id).unwrap().manifest_path,
+        manifest_path
+    );
+
+    // Installations by previous versions are still found
+    let legacy_dir = sandbox.home().join(".config/io.crates.takecrate");
+    std::fs::create_dir_all(&legacy_dir).unwrap();
+    std::fs::rename(&manifest_path, legacy_dir.join(&manifest_name)).unwrap();
+
+    assert_eq!(
+        takecrate::manifest(&app_id).unwrap().manifest_path,
+        legacy_dir.join(&manifest_name)
+    );
+
+    takecrate::uninstall(&app_id).unwrap();
+    assert!(!legacy_dir.join(&manifest_name).exists());
+}
@@ -1,7 +1,11 @@
 use core::str;
-use std::{io::Write, path::PathBuf};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use serde_json::Value;
 
 #[derive(Debug, Parser)]
@@ -19,6 +23,41 @@ enum Command {
         #[arg(long, short, default_value = "")]
         program_args: String,
     },
+    /// Compare Criterion benchmark results against a saved baseline
+    CheckBenchmarks {
+        /// Name of the baseline saved with `--save-baseline`
+        #[arg(long, default_value = "base")]
+        baseline: String,
+        /// Maximum allowed slowdown in percent
+        #[arg(long, default_value_t = 10.0)]
+        threshold: f64,
+    },
+    /// Bundle a built binary and its data files into a distributable archive
+    ///
+    /// The binary is renamed with the `_installer` suffix so it starts the
+    /// installer when run.
+    Package {
+        /// Path of the built binary
+        binary: PathBuf,
+        /// Additional file placed beside the binary, as `SOURCE[=ARCHIVE_PATH]`
+        #[arg(long = "file", short)]
+        files: Vec<String>,
+        /// Archive format [default: zip for .exe binaries, otherwise tar-gz]
+        #[arg(long, value_enum)]
+        format: Option<ArchiveFormat>,
+        /// Name of the archive and its top-level directory [default: binary name]
+        #[arg(long)]
+        name: Option<String>,
+        /// Directory where the archive is written
+        #[arg(long, short, default_value = "dist")]
+        output_dir: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ArchiveFormat {
+    Zip,
+    TarGz,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -29,6 +68,17 @@ fn main() -> anyhow::Result<()> {
             cargo_args,
             program_args,
         } => run_example_installer(cargo_args, program_args),
+        Command::CheckBenchmarks {
+            baseline,
+            threshold,
+        } => check_benchmarks(&baseline, threshold),
+        Command::Package {
+            binary,
+            files,
+            format,
+            name,
+            output_dir,
+        } => package(&binary, &files, format, name, &output_dir),
     }
 }
 
@@ -88,3 +138,214 @@ fn run_example_installer(cargo_args: String, program_args: String) -> anyhow::Re
 
     Ok(())
 }
+
+fn check_benchmarks(baseline: &str, threshold: f64) -> anyhow::Result<()> {
+    let target_dir = match std::env::var_os("CARGO_TARGET_DIR") {
+        Some(value) => PathBuf::from(value),
+        None => PathBuf::from(std::env::var("CARGO_MANIFEST_DIR")?).join("../target"),
+    };
+    let criterion_dir = target_dir.join("criterion");
+
+    let mut results = Vec::new();
+    find_benchmark_results(&criterion_dir, baseline, &mut results)?;
+
+    anyhow::ensure!(
+        !results.is_empty(),
+        "no benchmark results for baseline {:?} in {:?}",
+        baseline,
+        criterion_dir
+    );
+
+    let mut regressed = false;
+
+    for (name, base_mean, new_mean) in results {
+        let change = (new_mean - base_mean) / base_mean * 100.0;
+        let status = if change > threshold {
+            regressed = true;
+            "REGRESSED"
+        } else {
+            "ok"
+        };
+
+        println!("{status:>9} {change:>+8.2}% {name}");
+    }
+
+    anyhow::ensure!(!regressed, "benchmarks regressed more than {threshold}%");
+
+    Ok(())
+}
+
+fn find_benchmark_results(
+    dir: &Path,
+    baseline: &str,
+    results: &mut Vec<(String, f64, f64)>,
+) -> anyhow::Result<()> {
+    let base_path = dir.join(baseline).join("estimates.json");
+    let new_path = dir.join("new").join("estimates.json");
+
+    if base_path.is_file() && new_path.is_file() {
+        let name = dir
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        results.push((name, read_mean(&base_path)?, read_mean(&new_path)?));
+    }
+
+    if dir.is_dir() {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                find_benchmark_results(&path, baseline, results)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_mean(path: &Path) -> anyhow::Result<f64> {
+    let value = serde_json::from_slice::<Value>(&std::fs::read(path)?)?;
+
+    value
+        .pointer("/mean/point_estimate")
+        .and_then(|value| value.as_f64())
+        .ok_or_else(|| anyhow::anyhow!("missing mean estimate in {:?}", path))
+}
+
+fn package(
+    binary: &Path,
+    files: &[String],
+    format: Option<ArchiveFormat>,
+    name: Option<String>,
+    output_dir: &Path,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(binary.is_file(), "binary {:?} not found", binary);
+
+    let stem = binary
+        .file_stem()
+        .ok_or_else(|| anyhow::anyhow!("binary {:?} has no file name", binary))?
+        .to_string_lossy()
+        .to_string();
+    let is_exe = binary
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"));
+    let format = format.unwrap_or(if is_exe {
+        ArchiveFormat::Zip
+    } else {
+        ArchiveFormat::TarGz
+    });
+    let name = name.unwrap_or_else(|| stem.clone());
+
+    let installer_stem = if stem.ends_with("_installer") {
+        stem
+    } else {
+        format!("{stem}_installer")
+    };
+    let installer_name = if is_exe {
+        format!("{installer_stem}.exe")
+    } else {
+        installer_stem
+    };
+
+    let mut entries = vec![(binary.to_path_buf(), installer_name, true)];
+
+    for file in files {
+        let (source, archive_path) = match file.split_once('=') {
+            Some((source, archive_path)) => (PathBuf::from(source), archive_path.to_string()),
+            None => {
+                let source = PathBuf::from(file);
+                let archive_path = source
+                    .file_name()
+                    .ok_or_else(|| anyhow::anyhow!("file {:?} has no file name", source))?
+                    .to_string_lossy()
+                    .to_string();
+                (source, archive_path)
+            }
+        };
+
+        anyhow::ensure!(source.is_file(), "file {:?} not found", source);
+        anyhow::ensure!(
+            !archive_path.is_empty()
+                && !archive_path.starts_with('/')
+                && !archive_path.split(['/', '\\']).any(|part| part == ".."),
+            "invalid archive path {:?}",
+            archive_path
+        );
+
+        entries.push((source, archive_path.replace('\\', "/"), false));
+    }
+
+    std::fs::create_dir_all(output_dir)?;
+
+    let archive_path = match format {
+        ArchiveFormat::Zip => output_dir.join(format!("{name}.zip")),
+        ArchiveFormat::TarGz => output_dir.join(format!("{name}.tar.gz")),
+    };
+    let file = File::create(&archive_path)?;
+
+    match format {
+        ArchiveFormat::Zip => write_zip(file, &name, &entries)?,
+        ArchiveFormat::TarGz => write_tar_gz(file, &name, &entries)?,
+    }
+
+    println!("{}", archive_path.display());
+
+    Ok(())
+}
+
+fn write_zip(
+    file: File,
+    dir_name: &str,
+    entries: &[(PathBuf, String, bool)],
+) -> anyhow::Result<()> {
+    let mut writer = zip::ZipWriter::new(file);
+
+    for (source, archive_path, executable) in entries {
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .unix_permissions(if *executable { 0o755 } else { 0o644 });
+
+        writer.start_file(format!("{dir_name}/{archive_path}"), options)?;
+        std::io::copy(&mut File::open(source)?, &mut writer)?;
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+fn write_tar_gz(
+    file: File,
+    dir_name: &str,
+    entries: &[(PathBuf, String, bool)],
+) -> anyhow::Result<()> {
+    let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    for (source, archive_path, executable) in entries {
+        let metadata = std::fs::metadata(source)?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        // Set the mode explicitly so archives built on Windows are still executable
+        let mut header = tar::Header::new_gnu();
+        header.set_size(metadata.len());
+        header.set_mode(if *executable { 0o755 } else { 0o644 });
+        header.set_mtime(mtime);
+
+        builder.append_data(
+            &mut header,
+            format!("{dir_name}/{archive_path}"),
+            File::open(source)?,
+        )?;
+    }
+
+    builder.into_inner()?.finish()?;
+
+    Ok(())
+}